@@ -0,0 +1,812 @@
+use std::path::PathBuf;
+
+use structopt::StructOpt;
+
+// the options are parsed once, the size of the biggest subcommand does not matter
+#[allow(clippy::large_enum_variant)]
+#[derive(StructOpt, Debug)]
+#[structopt(author, about)]
+pub enum Opt {
+    /// Encodes data into the image
+    #[structopt(name = "encode")]
+    Encode {
+        /// Original image file. If it is `-` then a PNG, BMP, TIFF, WebP or Netpbm image is read from the stdin
+        #[structopt(parse(from_os_str))]
+        image: PathBuf,
+        /// File with the data to be encoded. If it is `-` then the data is read from the stdin,
+        /// and if it is a directory then all the files in it are bundled together, see `decode --extract-to`
+        #[structopt(parse(from_os_str))]
+        data: PathBuf,
+        /// Resulting image with the data hidden in it. If it is `-` and the image comes from the stdin
+        /// then the result is written to the stdout
+        #[structopt(parse(from_os_str))]
+        result: PathBuf,
+        /// Replace the destination file if it already exists
+        #[structopt(short = "f", long = "force")]
+        force: bool,
+        #[structopt(flatten)]
+        flags: EncodeFlags,
+        /// Store the name, the permissions, the modification time and the MIME type of the data file along with it,
+        /// so that decoding into a directory restores the file as it was
+        #[structopt(long = "metadata")]
+        metadata: bool,
+        /// Compress the data before hiding it, as `zstd`, `deflate` or `gzip`, optionally followed by `:<level>`,
+        /// from 1 to 22 for zstd and from 0 to 9 for the other ones. The codec is recorded in the image,
+        /// so decoding decompresses the data without being told to
+        #[structopt(long = "compress")]
+        compress: Option<String>,
+        /// Store the data as the entry with this name in a table of contents, so that the image can hold
+        /// several named payloads, each decoded with `decode --name`
+        #[structopt(long = "name")]
+        name: Option<String>,
+        /// XOR the data with the next unused bytes of this one-time pad file. The used part of the pad
+        /// is recorded in a file next to it with an additional `.used` extension and is never used again
+        #[structopt(long = "otp", parse(from_os_str))]
+        otp: Option<PathBuf>,
+        /// Encrypt the data with a key derived from this password, so that it cannot be read without it
+        #[structopt(long = "password")]
+        password: Option<String>,
+        /// Encrypt the data with the password stored in this keyring entry, see `key keyring`
+        #[structopt(long = "keyring-entry", conflicts_with = "password")]
+        keyring_entry: Option<String>,
+        /// Encrypt the data to this X25519 public key file, see `key gen x25519`, so that only the owner
+        /// of its secret key can read it. Can be given many times, for any one of the recipients to read it
+        #[structopt(long = "recipient", parse(from_os_str), number_of_values = 1)]
+        recipients: Vec<PathBuf>,
+        /// Sign the data itself with this Ed25519 secret key file, see `key gen ed25519`,
+        /// so that whoever decodes it can tell who it came from
+        #[structopt(long = "sign-data", parse(from_os_str))]
+        sign_data: Option<PathBuf>,
+        /// Hide this file too, in the capacity left after the data, which then serves as a decoy.
+        /// Nothing tells that it is there without its own `--hidden-password`
+        #[structopt(long = "hidden-data", parse(from_os_str), requires = "hidden-password")]
+        hidden_data: Option<PathBuf>,
+        /// Password of the `--hidden-data`, which should not be the same as the `--password` of the decoy
+        #[structopt(long = "hidden-password", requires = "hidden-data")]
+        hidden_password: Option<String>,
+        /// Write the resulting image in this format instead of the format of the original one,
+        /// which has to be an image that the data is hidden in the pixels of
+        #[structopt(long = "format", possible_values = &["png", "bmp", "tiff", "pnm", "qoi"])]
+        format: Option<String>,
+        /// Print the PSNR, the SSIM and the mean absolute error of the resulting image against the original one,
+        /// and how much of the capacity the data took, when both of them are files
+        #[structopt(long = "quality")]
+        quality: bool,
+        /// Seal the data for the P-256 key of a smartcard, given as a PEM public key file, see `key card`,
+        /// so that decoding it needs the card plugged in
+        #[structopt(long = "card-key", parse(from_os_str))]
+        card_key: Option<PathBuf>,
+    },
+    /// Adds one more named payload to the data already hidden in the image, changing only the pixels that hold it
+    /// and the table of contents. The rest of the settings have to be the ones the image was encoded with
+    #[structopt(name = "append")]
+    Append {
+        /// Image file with hidden data
+        #[structopt(parse(from_os_str))]
+        encoded: PathBuf,
+        /// File with the data to be appended. If it is `-` then the data is read from the stdin
+        #[structopt(parse(from_os_str))]
+        data: PathBuf,
+        /// Resulting image. If not supplied then the image itself is changed
+        #[structopt(parse(from_os_str))]
+        result: Option<PathBuf>,
+        /// Name of the new entry, see `encode --name`
+        #[structopt(long = "name")]
+        name: String,
+        /// Replace the destination file if it already exists
+        #[structopt(short = "f", long = "force")]
+        force: bool,
+        #[structopt(flatten)]
+        flags: EncodeFlags,
+        #[structopt(flatten)]
+        layers: LayerFlags,
+    },
+    /// Removes the named payload from the image, overwriting its bytes with random ones
+    /// and changing only the pixels that held it and the table of contents
+    #[structopt(name = "rm")]
+    Rm {
+        /// Image file with hidden data
+        #[structopt(parse(from_os_str))]
+        encoded: PathBuf,
+        /// Resulting image. If not supplied then the image itself is changed
+        #[structopt(parse(from_os_str))]
+        result: Option<PathBuf>,
+        /// Name of the entry to remove, an empty one for the data encoded without `--name`
+        #[structopt(long = "name")]
+        name: String,
+        /// Replace the destination file if it already exists
+        #[structopt(short = "f", long = "force")]
+        force: bool,
+        #[structopt(flatten)]
+        flags: EncodeFlags,
+    },
+    /// Swaps the named payload in the image for new data, in its place when it fits there
+    /// and after all the other payloads otherwise
+    #[structopt(name = "replace")]
+    Replace {
+        /// Image file with hidden data
+        #[structopt(parse(from_os_str))]
+        encoded: PathBuf,
+        /// File with the new data. If it is `-` then the data is read from the stdin
+        #[structopt(parse(from_os_str))]
+        data: PathBuf,
+        /// Resulting image. If not supplied then the image itself is changed
+        #[structopt(parse(from_os_str))]
+        result: Option<PathBuf>,
+        /// Name of the entry to replace, an empty one for the data encoded without `--name`
+        #[structopt(long = "name")]
+        name: String,
+        /// Replace the destination file if it already exists
+        #[structopt(short = "f", long = "force")]
+        force: bool,
+        #[structopt(flatten)]
+        flags: EncodeFlags,
+        #[structopt(flatten)]
+        layers: LayerFlags,
+    },
+    /// Hides an image, usually with some data already hidden in it, in another image, and that in the next one
+    /// if there are more, checking that every layer fits. Decode the result with `decode --depth`
+    #[structopt(name = "nest")]
+    Nest {
+        /// The innermost image, then the covers from the inner to the outer ones and the resulting image last
+        #[structopt(parse(from_os_str), min_values = 3, required = true)]
+        paths: Vec<PathBuf>,
+        /// Replace the destination file if it already exists
+        #[structopt(short = "f", long = "force")]
+        force: bool,
+        #[structopt(flatten)]
+        flags: EncodeFlags,
+    },
+    /// Encodes the data into the image in memory and checks that it is decoded back intact,
+    /// reporting how much of the capacity it takes and how much it distorts the image
+    #[structopt(name = "verify")]
+    Verify {
+        /// Original image file
+        #[structopt(parse(from_os_str))]
+        image: PathBuf,
+        /// File with the data to be encoded. If it is `-` then the data is read from the stdin
+        #[structopt(parse(from_os_str))]
+        data: PathBuf,
+        #[structopt(flatten)]
+        flags: EncodeFlags,
+    },
+    /// Decodes data that was hidden in the image
+    #[structopt(name = "decode")]
+    Decode {
+        /// Image file with hidden data. If it is `-` then a PNG, BMP, TIFF, WebP or Netpbm image is read from the stdin
+        #[structopt(parse(from_os_str))]
+        encoded: PathBuf,
+        /// File to store the extracted data. If not supplied then the data is printed to stdout.
+        /// If it is a directory and the data was encoded with `--metadata`, the file is restored in it under its original name
+        #[structopt(parse(from_os_str))]
+        data: Option<PathBuf>,
+        /// Unpack the directory that was encoded as the data into this one, with the paths of its files preserved
+        #[structopt(long = "extract-to", parse(from_os_str), conflicts_with = "data")]
+        extract_to: Option<PathBuf>,
+        /// Where to write the extracted data, same as the data file. If it is `auto` then the file is written
+        /// to the current directory, named after the original one or with the extension that fits the type of the data
+        #[structopt(long = "output", parse(from_os_str), conflicts_with_all = &["data", "extract-to"])]
+        output: Option<PathBuf>,
+        /// Print the data to the terminal even if it is binary, which is refused otherwise
+        #[structopt(long = "binary")]
+        binary: bool,
+        /// Replace the destination file if it already exists
+        #[structopt(short = "f", long = "force")]
+        force: bool,
+        /// Extract the entry with this name when the image holds several named payloads, see `encode --name`.
+        /// When not given, the first one is extracted
+        #[structopt(long = "name")]
+        name: Option<String>,
+        /// Look for the data stored with `encode --resync`, recovering what is left of it
+        #[structopt(long = "resync")]
+        resync: bool,
+        /// The image was encoded with `--sign`
+        #[structopt(long = "signed")]
+        signed: bool,
+        /// The method the data was embedded with, see `encode --method`
+        #[structopt(long = "method", default_value = "lsb", possible_values = &["lsb", "wet", "stc", "adaptive"])]
+        method: String,
+        /// The data was encoded with `--otp` using this pad file
+        #[structopt(long = "otp", parse(from_os_str))]
+        otp: Option<PathBuf>,
+        /// The data was encoded with `--password` using this password
+        #[structopt(long = "password")]
+        password: Option<String>,
+        /// The data was encoded with the password stored in this keyring entry
+        #[structopt(long = "keyring-entry", conflicts_with = "password")]
+        keyring_entry: Option<String>,
+        /// The data was encoded with `--recipient` for the public key of this X25519 secret key file
+        #[structopt(long = "identity", parse(from_os_str))]
+        identity: Option<PathBuf>,
+        /// The data was encoded with `--sign-data`, check its signature and report who signed it
+        #[structopt(long = "signed-data")]
+        signed_data: bool,
+        /// Require the data to be signed with the secret key of this Ed25519 public key file
+        #[structopt(long = "signer", parse(from_os_str))]
+        signer: Option<PathBuf>,
+        /// Extract the `--hidden-data` instead of the decoy, the `--password` being its `--hidden-password`
+        #[structopt(long = "hidden", requires = "password")]
+        hidden: bool,
+        /// Number of the images hidden in each other with `nest` to peel to get to the data
+        #[structopt(long = "depth", default_value = "1")]
+        depth: usize,
+        /// The image was encoded with `--use-alpha`, not needed with `--method wet`
+        #[structopt(long = "use-alpha")]
+        use_alpha: bool,
+        /// The image was encoded with `--skip-transparent`, not needed with `--method wet`
+        #[structopt(long = "skip-transparent")]
+        skip_transparent: bool,
+        /// The channels the data was hidden in, see `encode --channels`
+        #[structopt(long = "channels", default_value = "rgba")]
+        channels: String,
+        /// The mask image the data was encoded with, see `encode --mask`, not needed with `--method wet`
+        #[structopt(long = "mask", parse(from_os_str))]
+        mask: Option<PathBuf>,
+        /// The seed the data was whitened with, see `encode --whiten`
+        #[structopt(long = "whiten")]
+        whiten: Option<String>,
+        /// The seed the pixels were scattered with, see `encode --scatter`
+        #[structopt(long = "scatter")]
+        scatter: Option<String>,
+        /// The offset the data was encoded with, see `encode --offset`
+        #[structopt(long = "offset", default_value = "0")]
+        offset: usize,
+        /// The stride the data was encoded with, see `encode --stride`
+        #[structopt(long = "stride", default_value = "1")]
+        stride: usize,
+        /// The number of bits of each color byte the data was encoded with, see `encode --bits`.
+        /// When neither this nor `--wide-bits` is given, the bits are detected
+        #[structopt(long = "bits", possible_values = &["1", "2", "3", "4", "5", "6", "7", "8"])]
+        bits: Option<u8>,
+        /// The number of bits of each 16-bit sample the data was encoded with, see `encode --wide-bits`
+        #[structopt(long = "wide-bits", possible_values = &["1", "2", "3", "4", "5", "6", "7", "8"])]
+        wide_bits: Option<u8>,
+        /// The data was encoded with `--card-key` for the key of this application of the smartcard in the reader,
+        /// `piv` for the 9D slot or `openpgp` for the decryption key. The PIN that unlocks the key,
+        /// if the card asks for one, is taken from the `STEGANOGRAPHER_CARD_PIN` environment variable
+        #[structopt(long = "card", possible_values = &["piv", "openpgp"])]
+        card: Option<String>,
+    },
+    /// Extracts the data from every file under a directory, reporting the ones that contained nothing
+    #[structopt(name = "decode-all")]
+    DecodeAll {
+        /// Directory with the files, it is searched recursively
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+        /// Directory to store the extracted data in, under the names of the files without extensions
+        #[structopt(long = "out", parse(from_os_str))]
+        out: PathBuf,
+        /// Replace the extracted files if they already exist
+        #[structopt(short = "f", long = "force")]
+        force: bool,
+        /// Look for the data stored with `encode --resync`
+        #[structopt(long = "resync")]
+        resync: bool,
+        /// The files were encoded with `--sign`
+        #[structopt(long = "signed")]
+        signed: bool,
+        /// The method the data was embedded with, see `encode --method`
+        #[structopt(long = "method", default_value = "lsb", possible_values = &["lsb", "wet", "stc", "adaptive"])]
+        method: String,
+    },
+    /// Looks for a pattern in the data hidden in every PNG image among the given files and directories,
+    /// trying every method, and prints the images where it was found
+    #[structopt(name = "grep")]
+    Grep {
+        /// Text to look for, or the bytes in hex with `--hex`
+        pattern: String,
+        /// Image files or directories to search recursively
+        #[structopt(parse(from_os_str), required = true)]
+        paths: Vec<PathBuf>,
+        /// The pattern is given as hex bytes, such as `89504e47` for the PNG magic bytes
+        #[structopt(long = "hex")]
+        hex: bool,
+        /// Also try decrypting the data with this password, can be given many times
+        #[structopt(long = "password", number_of_values = 1)]
+        passwords: Vec<String>,
+        /// Also try decrypting the data with the password stored in this keyring entry, can be given many times
+        #[structopt(long = "keyring-entry", number_of_values = 1)]
+        keyring_entries: Vec<String>,
+    },
+    /// Checks that the image encoded with `--sign` was not altered since
+    #[structopt(name = "verify-image")]
+    VerifyImage {
+        /// Signed image file
+        #[structopt(parse(from_os_str))]
+        image: PathBuf,
+        /// Ed25519 public key file of the signer
+        #[structopt(parse(from_os_str))]
+        key: PathBuf,
+        /// The image was encoded with `--use-alpha`
+        #[structopt(long = "use-alpha")]
+        use_alpha: bool,
+        /// The image was encoded with `--skip-transparent`
+        #[structopt(long = "skip-transparent")]
+        skip_transparent: bool,
+    },
+    /// Watches a directory and extracts the data from every new file that appears in it
+    #[structopt(name = "watch")]
+    Watch {
+        /// Directory to watch
+        #[structopt(long = "decode", parse(from_os_str))]
+        incoming: PathBuf,
+        /// Directory to store the extracted data in, under the names of the files without extensions
+        #[structopt(long = "out", parse(from_os_str))]
+        out: PathBuf,
+        /// Replace the extracted files if they already exist
+        #[structopt(short = "f", long = "force")]
+        force: bool,
+        /// Look for the data stored with `encode --resync`
+        #[structopt(long = "resync")]
+        resync: bool,
+        /// The images were encoded with `--sign`
+        #[structopt(long = "signed")]
+        signed: bool,
+    },
+    /// Starts an HTTP server with the `/encode`, `/decode` and `/capacity` endpoints
+    #[structopt(name = "serve")]
+    Serve {
+        /// Address to listen on
+        #[structopt(long = "listen", default_value = "127.0.0.1:8080")]
+        listen: String,
+    },
+    /// Acts as a git filter that stores the files hidden in a cover image, reading the stdin
+    /// and writing to the stdout, configured with `git config filter.<name>.clean` and `.smudge`
+    #[structopt(name = "filter")]
+    Filter {
+        /// Hide the file contents in the cover image, used as the `clean` filter
+        #[structopt(long = "clean", requires = "cover", conflicts_with = "smudge", required_unless = "smudge")]
+        clean: bool,
+        /// Extract the file contents from the image, used as the `smudge` filter
+        #[structopt(long = "smudge")]
+        smudge: bool,
+        /// Image to hide the file contents in
+        #[structopt(long = "cover", parse(from_os_str))]
+        cover: Option<PathBuf>,
+        /// How the data is embedded, see `encode --method`
+        #[structopt(long = "method", default_value = "lsb", possible_values = &["lsb", "wet", "stc", "adaptive"])]
+        method: String,
+    },
+    /// Opens a window where a cover image and a file can be dropped to be encoded,
+    /// showing the capacity and the quality of the result as the settings change
+    #[structopt(name = "gui")]
+    Gui,
+    /// Starts an interactive shell that keeps the cover image and the data to hide in memory between
+    /// the commands, such as `open`, `capacity`, `add`, `set` and `write`. See `help` in the shell
+    #[structopt(name = "shell")]
+    Shell {
+        /// Image file to open right away
+        #[structopt(parse(from_os_str))]
+        image: Option<PathBuf>,
+        #[structopt(flatten)]
+        flags: EncodeFlags,
+    },
+    /// Reports how many bytes of data the image can hold with each bits setting, less the header,
+    /// the length and the CRC32, with the channels and the pixels the other flags leave for the data
+    #[structopt(name = "capacity")]
+    Capacity {
+        /// Image file to hide the data in
+        #[structopt(parse(from_os_str))]
+        image: PathBuf,
+        /// Only report the capacity with this number of the least significant bits of each color byte
+        #[structopt(long = "bits", possible_values = &["1", "2", "3", "4", "5", "6", "7", "8"])]
+        bits: Option<u8>,
+        #[structopt(flatten)]
+        flags: EncodeFlags,
+    },
+    /// Reports the smallest image that can hold the given amount of data, the inverse of the capacity
+    #[structopt(name = "plan")]
+    Plan {
+        /// Size of the data in bytes
+        size: usize,
+        /// Number of color channels of the image, all of them used for the data
+        #[structopt(long = "channels", default_value = "3")]
+        channels: u8,
+        /// Number of the least significant bits of each color byte that are replaced with the data
+        #[structopt(long = "bits", default_value = "2", possible_values = &["1", "2", "3", "4", "5", "6", "7", "8"])]
+        bits: u8,
+        /// How the data is embedded, see `encode --method`
+        #[structopt(long = "method", default_value = "lsb", possible_values = &["lsb", "wet", "stc", "adaptive"])]
+        method: String,
+        /// The data is stored with `encode --resync`, in this many copies to recover from the damage
+        #[structopt(long = "resync")]
+        resync: Option<usize>,
+        /// The data is expected to compress to this fraction of its size before it is hidden
+        #[structopt(long = "compression", default_value = "1")]
+        compression: f64,
+    },
+    /// Checks which common transformations of the image the hidden data survives
+    #[structopt(name = "stress")]
+    Stress {
+        /// Image file with hidden data
+        #[structopt(parse(from_os_str))]
+        encoded: PathBuf,
+        /// The data was stored with `encode --resync`
+        #[structopt(long = "resync")]
+        resync: bool,
+    },
+    /// Compares capacity, distortion and robustness of every method and bits setting on the image
+    #[structopt(name = "survey")]
+    Survey {
+        /// Original image file
+        #[structopt(parse(from_os_str))]
+        image: PathBuf,
+        /// File with the data to be encoded. If it is `-` then the data is read from the stdin
+        #[structopt(parse(from_os_str))]
+        data: PathBuf,
+    },
+    /// Overwrites the least significant bits of the image, destroying whatever may be hidden in them
+    /// while the image looks the same, such as before publishing it
+    #[structopt(name = "wipe")]
+    Wipe {
+        /// Image file to wipe
+        #[structopt(parse(from_os_str))]
+        image: PathBuf,
+        /// File to store the wiped image
+        #[structopt(parse(from_os_str))]
+        result: PathBuf,
+        /// Replace the destination file if it already exists
+        #[structopt(short = "f", long = "force")]
+        force: bool,
+        /// Number of the least significant bits of each color byte that are overwritten
+        #[structopt(long = "bits", default_value = "2", possible_values = &["1", "2", "3", "4", "5", "6", "7", "8"])]
+        bits: u8,
+        /// Overwrite them with `random` bits or with the bits sampled from the statistics of the image's own
+        /// least significant bits (`modeled`)
+        #[structopt(long = "fill", default_value = "random", possible_values = &["modeled", "random"])]
+        fill: String,
+        /// Wipe the alpha channel too
+        #[structopt(long = "use-alpha")]
+        use_alpha: bool,
+    },
+    /// Wipes every image among the given files and directories, see `wipe`, dropping their metadata too,
+    /// and reports which of them had data hidden in them and which metadata was removed
+    #[structopt(name = "sanitize")]
+    Sanitize {
+        /// Images and directories with them, which are searched recursively
+        #[structopt(parse(from_os_str), required = true)]
+        paths: Vec<PathBuf>,
+        /// Directory to store the sanitized images in, under the same names
+        #[structopt(long = "out", parse(from_os_str))]
+        out: PathBuf,
+        /// Replace the sanitized images if they already exist
+        #[structopt(short = "f", long = "force")]
+        force: bool,
+        /// Number of the least significant bits of each color byte that are overwritten
+        #[structopt(long = "bits", default_value = "2", possible_values = &["1", "2", "3", "4", "5", "6", "7", "8"])]
+        bits: u8,
+        /// Overwrite them with `random` bits or with the bits sampled from the statistics of the image's own
+        /// least significant bits (`modeled`)
+        #[structopt(long = "fill", default_value = "random", possible_values = &["modeled", "random"])]
+        fill: String,
+        /// Wipe the alpha channel too
+        #[structopt(long = "use-alpha")]
+        use_alpha: bool,
+    },
+    /// Measures the entropy and the noise of each region of the image, telling the regions that are safe to hide
+    /// the data in from the dangerously flat ones, where the changed bits stand out
+    #[structopt(name = "regions")]
+    Regions {
+        /// Image file to analyze
+        #[structopt(parse(from_os_str))]
+        image: PathBuf,
+        /// Side of the square regions of the image, in pixels
+        #[structopt(long = "region", default_value = "32")]
+        region: usize,
+        /// File to store the map of the regions in, as a PNG image, red for the flat ones, yellow for the marginal ones
+        /// and green for the safe ones
+        #[structopt(long = "heat-map", parse(from_os_str))]
+        heat_map: Option<PathBuf>,
+        /// Replace the heat map file if it already exists
+        #[structopt(short = "f", long = "force")]
+        force: bool,
+        /// Analyze the alpha channel too
+        #[structopt(long = "use-alpha")]
+        use_alpha: bool,
+    },
+    /// Compares the original image with the one the data was hidden in, counting the samples of each channel
+    /// that differ in each of their bits, to check that nothing but the intended low bits was touched
+    #[structopt(name = "compare")]
+    Compare {
+        /// The original image file
+        #[structopt(parse(from_os_str))]
+        original: PathBuf,
+        /// The image file with the data hidden in it
+        #[structopt(parse(from_os_str))]
+        altered: PathBuf,
+        /// File to store the heat map of the pixels that differ in, as a PNG image, from yellow for the lowest bits
+        /// to red for the highest ones
+        #[structopt(long = "heat-map", parse(from_os_str))]
+        heat_map: Option<PathBuf>,
+        /// Replace the heat map file if it already exists
+        #[structopt(short = "f", long = "force")]
+        force: bool,
+    },
+    /// Writes one bit plane of one channel of the image as a black and white image, where the data hidden in that bit
+    /// stands out as noise against the shapes of the image
+    #[structopt(name = "bitplanes")]
+    BitPlanes {
+        /// Image file to take the plane from
+        #[structopt(parse(from_os_str))]
+        image: PathBuf,
+        /// The channel, one of `r`, `g`, `b`, `a` or `l`, followed by the bit, where 0 is the least significant one,
+        /// such as `b0`
+        #[structopt(long = "plane", default_value = "b0")]
+        plane: String,
+        /// File to store the plane in, as a PNG image
+        #[structopt(short = "o", long = "output", parse(from_os_str))]
+        output: PathBuf,
+        /// Replace the destination file if it already exists
+        #[structopt(short = "f", long = "force")]
+        force: bool,
+    },
+    /// Tells whether the image is likely to have some data hidden in its least significant bits by the chi-square attack,
+    /// the RS analysis and the sample pair analysis, printing the probability for each region of the image and the
+    /// estimated share of the bits of each channel that carry the data as well
+    #[structopt(name = "detect")]
+    Detect {
+        /// Image file to test
+        #[structopt(parse(from_os_str))]
+        image: PathBuf,
+        /// Side of the square regions of the image that are tested on their own, in pixels
+        #[structopt(long = "region", default_value = "64")]
+        region: usize,
+        /// Print the report as a single JSON object, for the scripts that scan images in bulk
+        #[structopt(long = "json")]
+        json: bool,
+    },
+    /// Reads just the header of the hidden data and reports what it says, without extracting the data
+    #[structopt(name = "inspect")]
+    Inspect {
+        /// Image file with hidden data
+        #[structopt(parse(from_os_str))]
+        image: PathBuf,
+        /// The image was encoded with `--sign`
+        #[structopt(long = "signed")]
+        signed: bool,
+        /// The image was encoded with `--use-alpha`
+        #[structopt(long = "use-alpha")]
+        use_alpha: bool,
+        /// The image was encoded with `--skip-transparent`
+        #[structopt(long = "skip-transparent")]
+        skip_transparent: bool,
+        /// The channels the data was hidden in, see `encode --channels`
+        #[structopt(long = "channels", default_value = "rgba")]
+        channels: String,
+        /// The mask image the data was encoded with, see `encode --mask`
+        #[structopt(long = "mask", parse(from_os_str))]
+        mask: Option<PathBuf>,
+        /// The seed the data was whitened with, see `encode --whiten`
+        #[structopt(long = "whiten")]
+        whiten: Option<String>,
+        /// The seed the pixels were scattered with, see `encode --scatter`
+        #[structopt(long = "scatter")]
+        scatter: Option<String>,
+        /// The offset the data was encoded with, see `encode --offset`
+        #[structopt(long = "offset", default_value = "0")]
+        offset: usize,
+        /// The stride the data was encoded with, see `encode --stride`
+        #[structopt(long = "stride", default_value = "1")]
+        stride: usize,
+    },
+    /// Lists the named payloads stored in the image with `encode --name`, with their sizes, the layers
+    /// they are sealed in and when they were stored
+    #[structopt(name = "ls")]
+    Ls {
+        /// Image file with hidden data
+        #[structopt(parse(from_os_str))]
+        image: PathBuf,
+        /// Print the entries as a JSON array
+        #[structopt(long = "json")]
+        json: bool,
+        /// The image was encoded with `--sign`
+        #[structopt(long = "signed")]
+        signed: bool,
+        /// The image was encoded with `--use-alpha`
+        #[structopt(long = "use-alpha")]
+        use_alpha: bool,
+        /// The image was encoded with `--skip-transparent`
+        #[structopt(long = "skip-transparent")]
+        skip_transparent: bool,
+        /// The channels the data was hidden in, see `encode --channels`
+        #[structopt(long = "channels", default_value = "rgba")]
+        channels: String,
+        /// The mask image the data was encoded with, see `encode --mask`
+        #[structopt(long = "mask", parse(from_os_str))]
+        mask: Option<PathBuf>,
+        /// The seed the data was whitened with, see `encode --whiten`
+        #[structopt(long = "whiten")]
+        whiten: Option<String>,
+        /// The seed the pixels were scattered with, see `encode --scatter`
+        #[structopt(long = "scatter")]
+        scatter: Option<String>,
+        /// The offset the data was encoded with, see `encode --offset`
+        #[structopt(long = "offset", default_value = "0")]
+        offset: usize,
+        /// The stride the data was encoded with, see `encode --stride`
+        #[structopt(long = "stride", default_value = "1")]
+        stride: usize,
+    },
+    /// Looks for the hidden data with every bits setting and combination of the channels,
+    /// reporting the settings of everything that is found intact, for when they are forgotten
+    #[structopt(name = "recover")]
+    Recover {
+        /// Image file with hidden data
+        #[structopt(parse(from_os_str))]
+        image: PathBuf,
+        /// Directory to store the data that is found in, one file for each set of the settings it is found with
+        #[structopt(parse(from_os_str))]
+        output: Option<PathBuf>,
+        /// The image was encoded with `--sign`
+        #[structopt(long = "signed")]
+        signed: bool,
+        /// The seed the data was whitened with, see `encode --whiten`
+        #[structopt(long = "whiten")]
+        whiten: Option<String>,
+    },
+    /// Manages the keys for encrypting and signing the hidden data
+    #[structopt(name = "key")]
+    Key(KeyOpt),
+}
+
+/// Layers the data is sealed in by the `append` and `replace` commands, each entry has its own
+#[derive(StructOpt, Debug)]
+pub struct LayerFlags {
+    /// XOR the data with the next unused bytes of this one-time pad file, see `encode --otp`
+    #[structopt(long = "otp", parse(from_os_str))]
+    pub otp: Option<PathBuf>,
+    /// Encrypt the data with a key derived from this password
+    #[structopt(long = "password")]
+    pub password: Option<String>,
+    /// Encrypt the data with the password stored in this keyring entry, see `key keyring`
+    #[structopt(long = "keyring-entry", conflicts_with = "password")]
+    pub keyring_entry: Option<String>,
+    /// Encrypt the data to this X25519 public key file, see `encode --recipient`
+    #[structopt(long = "recipient", parse(from_os_str), number_of_values = 1)]
+    pub recipients: Vec<PathBuf>,
+    /// Sign the data itself with this Ed25519 secret key file, see `encode --sign-data`
+    #[structopt(long = "sign-data", parse(from_os_str))]
+    pub sign_data: Option<PathBuf>,
+    /// Compress the data before hiding it, see `encode --compress`
+    #[structopt(long = "compress")]
+    pub compress: Option<String>,
+    /// Seal the data for the P-256 key of a smartcard, see `encode --card-key`
+    #[structopt(long = "card-key", parse(from_os_str))]
+    pub card_key: Option<PathBuf>,
+}
+
+/// Settings of the encoding shared by the `encode` and `verify` commands
+#[derive(StructOpt, Debug)]
+pub struct EncodeFlags {
+    /// Spread consecutive payload bits this far apart in the image,
+    /// so that damage to a region of the image is easier to recover from
+    #[structopt(long = "interleave", default_value = "1")]
+    pub interleave: u16,
+    /// Order in which the pixels are visited: row by row (`raster`), row by row in alternating
+    /// directions (`boustrophedon`) or along the Hilbert curve (`hilbert`)
+    #[structopt(long = "traversal", default_value = "raster", possible_values = &["raster", "boustrophedon", "hilbert"])]
+    pub traversal: String,
+    /// Visit the pixels in a pseudo-random order derived from this seed instead, spreading the data
+    /// over the whole image. The data has to be decoded with the same seed
+    #[structopt(long = "scatter")]
+    pub scatter: Option<String>,
+    /// Leave this many of the first bytes of the pixels alone, such as to keep away from the top border.
+    /// The data has to be decoded with the same offset
+    #[structopt(long = "offset", default_value = "0")]
+    pub offset: usize,
+    /// Hide the data only in every K-th byte of the pixels after the offset, so that the payloads with
+    /// different offsets live in separate lanes. The data has to be decoded with the same stride
+    #[structopt(long = "stride", default_value = "1")]
+    pub stride: usize,
+    /// Hide this many bits of the data in each block of 2^N-1 bits of the image by changing at most one of them,
+    /// which changes far fewer pixels when the data is small compared to the capacity. 1 is plain replacing
+    #[structopt(long = "matrix", default_value = "1", possible_values = &["1", "2", "3", "4"])]
+    pub matrix: u8,
+    /// Store the data only in the noisiest color channels of the image it fits in
+    #[structopt(long = "auto-channels")]
+    pub auto_channels: bool,
+    /// Store the data in small repeated segments that can still be found after the image is cropped
+    #[structopt(long = "resync")]
+    pub resync: bool,
+    /// Sign the resulting image with this Ed25519 secret key file, see `verify-image`
+    #[structopt(long = "sign", parse(from_os_str))]
+    pub sign: Option<PathBuf>,
+    /// How the data is embedded: `lsb` replaces the least significant bits,
+    /// `wet` uses the wet paper code that never touches saturated color values,
+    /// `stc` uses the syndrome-trellis code that prefers to change the textured regions
+    /// and `adaptive` replaces the least significant bits of the most textured regions first
+    #[structopt(long = "method", default_value = "lsb", possible_values = &["lsb", "wet", "stc", "adaptive"])]
+    pub method: String,
+    /// Grayscale image of the same dimensions where darker pixels are cheaper to change
+    /// and the white ones are never changed, used by the `wet` and `stc` methods
+    #[structopt(long = "cost-map", parse(from_os_str))]
+    pub cost_map: Option<PathBuf>,
+    /// Fail instead of converting the images with less than 8 bits per sample or with a palette
+    /// to the nearest pixel format the data can be hidden in
+    #[structopt(long = "no-convert")]
+    pub no_convert: bool,
+    /// What happens to the capacity that is left after the data with the `lsb` method:
+    /// `keep` the original bits, fill it with the bits sampled from the statistics of the image's own
+    /// least significant bits (`modeled`) or with cryptographically random ones (`random`),
+    /// which leaves the whole plane uniform when the data is encrypted or whitened
+    #[structopt(long = "fill", default_value = "keep", possible_values = &["keep", "modeled", "random"])]
+    pub fill: String,
+    /// Number of the least significant bits of each 16-bit sample, such as of the 16-bit PNG images,
+    /// replaced with the data instead of the usual 2. Even 8 of them change the sample by less than 0.4%.
+    /// The data has to be decoded with the same number
+    #[structopt(long = "wide-bits", possible_values = &["1", "2", "3", "4", "5", "6", "7", "8"])]
+    pub wide_bits: Option<u8>,
+    /// The zlib compression level of the resulting PNG image, from 0 to 9.
+    /// By default the level of the original PNG image is matched
+    #[structopt(long = "compression")]
+    pub compression: Option<u8>,
+    /// Hide the data in the alpha channel of the image too, which is left alone by default
+    /// so that its transparency never changes. The data has to be decoded with `--use-alpha` as well
+    #[structopt(long = "use-alpha")]
+    pub use_alpha: bool,
+    /// Leave the fully transparent pixels out, as changing them is easy to notice when they are all black.
+    /// This implies leaving the alpha channel alone, and the data has to be decoded with `--skip-transparent`,
+    /// unless `--method wet` just never changes them
+    #[structopt(long = "skip-transparent")]
+    pub skip_transparent: bool,
+    /// The channels that hold the data, any of `r`, `g`, `b` and `a`, or `l` for the gray one.
+    /// The blue channel alone is the least noticeable. The data has to be decoded with the same channels
+    #[structopt(long = "channels", default_value = "rgba")]
+    pub channels: String,
+    /// Grayscale image of the same dimensions where only the pixels that are not black hold the data,
+    /// such as to keep it out of the flat regions. The data has to be decoded with the same mask,
+    /// unless `--method wet` just never changes the other pixels
+    #[structopt(long = "mask", parse(from_os_str))]
+    pub mask: Option<PathBuf>,
+    /// XOR everything hidden, the length included, with a keystream derived from this seed,
+    /// so that the changed bits look uniformly random. With `--fill random` and `--password` nothing
+    /// tells the size of the data, or whether there is any. The data has to be decoded with the same seed
+    #[structopt(long = "whiten")]
+    pub whiten: Option<String>,
+    /// Make the same image, data and flags always give the very same bytes, so that the results can be hashed
+    /// and compared. What would be random, such as the `--fill` or the salt of the `--password`, is derived from
+    /// the inputs instead, so the same data with the same password tells that it is the same
+    #[structopt(long = "deterministic")]
+    pub deterministic: bool,
+    /// Read the written image back and check that the very same data is decoded from it,
+    /// failing when anything was lost on the way
+    #[structopt(long = "verify")]
+    pub verify: bool,
+    /// The filter applied to the rows of the resulting PNG image before they are compressed
+    #[structopt(long = "filter", default_value = "adaptive", possible_values = &["none", "sub", "up", "average", "paeth", "adaptive"])]
+    pub filter: String,
+}
+
+#[derive(StructOpt, Debug)]
+pub enum KeyOpt {
+    /// Generates a new key and prints its fingerprint
+    #[structopt(name = "gen")]
+    Gen {
+        /// Kind of the key: `x25519` keypair for encryption, `ed25519` keypair for signing
+        /// or a `symmetric` key
+        #[structopt(possible_values = &["x25519", "ed25519", "symmetric"])]
+        kind: String,
+        /// File to store the secret key. The public key of the keypairs is stored
+        /// in the same file with an additional `.pub` extension
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+        /// Replace the key files if they already exist
+        #[structopt(short = "f", long = "force")]
+        force: bool,
+    },
+    /// Stores a password read from the stdin in the keyring of the operating system,
+    /// so that it can be fetched with `--keyring-entry` instead of being typed in
+    #[structopt(name = "keyring")]
+    Keyring {
+        /// Name of the keyring entry
+        entry: String,
+        /// Remove the entry instead
+        #[structopt(long = "delete")]
+        delete: bool,
+    },
+    /// Prints the P-256 public key of the decryption key of the OpenPGP application of the smartcard
+    /// in the reader, for `encode --card-key`. The keys of the PIV application are exported
+    /// with the tools of the card instead, such as `ykman piv keys export 9d`
+    #[structopt(name = "card")]
+    Card,
+}
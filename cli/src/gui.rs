@@ -0,0 +1,269 @@
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+
+use steganographer_core::binary::Bits;
+use steganographer_core::*;
+
+/// Everything the projection depends on, so that it is only recomputed when some of it changes
+#[derive(Clone, PartialEq)]
+struct Settings {
+    cover: Option<PathBuf>,
+    payload: Option<PathBuf>,
+    embedding: Embedding,
+    bits: Bits,
+    traversal: Traversal,
+    interleave: u16,
+    filler: Filler,
+    password: String,
+}
+
+impl Settings {
+    fn options(&self) -> EncodeOptions {
+        EncodeOptions::new()
+            .embedding(self.embedding)
+            .bits(self.bits)
+            .traversal(self.traversal)
+            .interleave(self.interleave)
+            .filler(self.filler)
+    }
+
+    /// Reads the file to hide, encrypting it when there is a password
+    fn payload(&self, payload: &Path) -> Result<Vec<u8>, Error> {
+        let payload = fs::read(payload)?;
+        match self.password.is_empty() {
+            true => Ok(payload),
+            false => crypto::seal(&payload, &self.password),
+        }
+    }
+}
+
+/// What the encoded image is going to be like with the current settings
+struct Projection {
+    capacity: usize,
+    payload: usize,
+    psnr: Option<Result<f64, String>>,
+}
+
+struct App {
+    settings: Settings,
+    projected: Option<Settings>,
+    projection: Result<Projection, String>,
+    output: String,
+    replace: bool,
+    status: String,
+}
+
+fn project(settings: &Settings) -> Result<Projection, Error> {
+    let cover = match &settings.cover {
+        Some(cover) => fs::read(cover)?,
+        None => return Err(Error::UnsupportedCarrier("no cover image yet".into())),
+    };
+    let options = settings.options();
+    let capacity = image_capacity(&cover[..], &options)?;
+    let payload = match &settings.payload {
+        Some(payload) => settings.payload(payload)?,
+        None => return Ok(Projection { capacity, payload: 0, psnr: None }),
+    };
+    let psnr = if payload.len() > capacity {
+        None
+    } else {
+        let mut encoded = Vec::new();
+        Some(encode_image(&cover[..], &payload, &mut encoded, &options)
+            .and_then(|_| image_psnr(&cover[..], &encoded[..]))
+            .map_err(|e| e.to_string()))
+    };
+    Ok(Projection { capacity, payload: payload.len(), psnr })
+}
+
+fn is_png(path: &Path) -> bool {
+    let mut signature = [0; 8];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut signature))
+        .is_ok_and(|_| &signature == b"\x89PNG\r\n\x1a\n")
+}
+
+impl App {
+    fn new() -> Self {
+        let settings = Settings {
+            cover: None,
+            payload: None,
+            embedding: Embedding::default(),
+            bits: Bits::Two,
+            traversal: Traversal::default(),
+            interleave: 1,
+            filler: Filler::default(),
+            password: String::new(),
+        };
+        App {
+            settings,
+            projected: None,
+            projection: Err(String::new()),
+            output: String::new(),
+            replace: false,
+            status: String::new(),
+        }
+    }
+
+    fn drop_files(&mut self, ctx: &egui::Context) {
+        for file in ctx.input(|input| input.raw.dropped_files.clone()) {
+            let path = match file.path {
+                Some(path) => path,
+                None => continue,
+            };
+            if is_png(&path) && self.settings.cover.is_none() {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                self.output = path.with_file_name(format!("{}-encoded.png", stem)).display().to_string();
+                self.settings.cover = Some(path);
+            } else {
+                self.settings.payload = Some(path);
+            }
+        }
+    }
+
+    fn export(&self) -> Result<(), Error> {
+        let (cover, payload) = match (&self.settings.cover, &self.settings.payload) {
+            (Some(cover), Some(payload)) => (cover, payload),
+            _ => return Err(Error::UnsupportedCarrier("drop both a cover image and a file to hide first".into())),
+        };
+        let mut encoded = Vec::new();
+        encode_image(fs::File::open(cover)?, &self.settings.payload(payload)?, &mut encoded, &self.settings.options())?;
+        let mut output = OpenOptions::new();
+        if self.replace {
+            output.create(true).truncate(true);
+        } else {
+            output.create_new(true);
+        }
+        output.write(true).open(&self.output)?.write_all(&encoded)?;
+        Ok(())
+    }
+
+    fn settings_grid(&mut self, ui: &mut egui::Ui) {
+        let settings = &mut self.settings;
+        egui::Grid::new("settings").num_columns(2).show(ui, |ui| {
+            ui.label("Cover image");
+            ui.label(settings.cover.as_ref().map_or("drop a PNG image here".into(), |p| p.display().to_string()));
+            ui.end_row();
+
+            ui.label("File to hide");
+            ui.label(settings.payload.as_ref().map_or("drop any file here".into(), |p| p.display().to_string()));
+            ui.end_row();
+
+            ui.label("Password");
+            ui.add(egui::TextEdit::singleline(&mut settings.password).password(true).hint_text("not encrypted"));
+            ui.end_row();
+
+            ui.label("Method");
+            egui::ComboBox::from_id_salt("method")
+                .selected_text(format!("{:?}", settings.embedding))
+                .show_ui(ui, |ui| {
+                    for embedding in [Embedding::Lsb, Embedding::WetPaper, Embedding::Stc, Embedding::Adaptive] {
+                        ui.selectable_value(&mut settings.embedding, embedding, format!("{:?}", embedding));
+                    }
+                });
+            ui.end_row();
+
+            if matches!(settings.embedding, Embedding::Lsb | Embedding::Adaptive) {
+                ui.label("Bits per byte");
+                ui.horizontal(|ui| {
+                    for bits in Bits::ALL {
+                        ui.selectable_value(&mut settings.bits, bits, bits.to_string());
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Traversal");
+                egui::ComboBox::from_id_salt("traversal")
+                    .selected_text(format!("{:?}", settings.traversal))
+                    .show_ui(ui, |ui| {
+                        for traversal in [Traversal::Raster, Traversal::Boustrophedon, Traversal::Hilbert] {
+                            ui.selectable_value(&mut settings.traversal, traversal, format!("{:?}", traversal));
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Interleave");
+                ui.add(egui::DragValue::new(&mut settings.interleave).range(1..=u16::MAX));
+                ui.end_row();
+
+                ui.label("Unused capacity");
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut settings.filler, Filler::Keep, "keep");
+                    ui.selectable_value(&mut settings.filler, Filler::Modeled, "modeled chaff");
+                    ui.selectable_value(&mut settings.filler, Filler::Random, "random");
+                });
+                ui.end_row();
+            }
+        });
+    }
+
+    fn projection(&mut self, ui: &mut egui::Ui) {
+        if self.projected.as_ref() != Some(&self.settings) {
+            self.projection = project(&self.settings).map_err(|e| e.to_string());
+            self.projected = Some(self.settings.clone());
+        }
+        match &self.projection {
+            Ok(projection) => {
+                ui.label(format!("Capacity: {} bytes", projection.capacity));
+                if projection.payload > 0 {
+                    let used = projection.payload as f32 / projection.capacity.max(1) as f32;
+                    ui.add(egui::ProgressBar::new(used.min(1.0))
+                        .text(format!("{} bytes, {:.1}% of the capacity", projection.payload, used * 100.0)));
+                }
+                match &projection.psnr {
+                    Some(Ok(psnr)) => ui.label(format!("PSNR: {:.2} dB", psnr)),
+                    Some(Err(e)) => ui.colored_label(ui.visuals().error_fg_color, e),
+                    None if projection.payload > projection.capacity => {
+                        ui.colored_label(ui.visuals().error_fg_color, "The file does not fit into the image")
+                    },
+                    None => ui.label("PSNR: -"),
+                };
+            },
+            Err(e) => {
+                ui.label(e);
+            },
+        }
+    }
+}
+
+impl eframe::App for App {
+    fn update(&mut self, ctx: &egui::Context, _: &mut eframe::Frame) {
+        self.drop_files(ctx);
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Steganographer");
+            if ctx.input(|input| !input.raw.hovered_files.is_empty()) {
+                ui.label("Drop the cover image first, then the file to hide");
+            }
+            ui.separator();
+            self.settings_grid(ui);
+            ui.separator();
+            self.projection(ui);
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Save as");
+                ui.text_edit_singleline(&mut self.output);
+                ui.checkbox(&mut self.replace, "Replace");
+            });
+            if ui.button("Export").clicked() {
+                self.status = match self.export() {
+                    Ok(()) => format!("Saved to {}", self.output),
+                    Err(e) => e.to_string(),
+                };
+            }
+            ui.label(&self.status);
+        });
+    }
+}
+
+/// Opens the window where a cover image and a file to hide in it can be dropped,
+/// with the capacity and the quality of the result projected as the settings change.
+pub fn run() -> Result<(), Error> {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default().with_inner_size([520.0, 420.0]).with_drag_and_drop(true),
+        ..Default::default()
+    };
+    eframe::run_native("Steganographer", options, Box::new(|_| Ok(Box::new(App::new()))))
+        .map_err(|e| Error::Wrapped(e.to_string().into()))
+}
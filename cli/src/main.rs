@@ -0,0 +1,920 @@
+extern crate structopt;
+
+use std::fs::File;
+use std::path::Path;
+
+use structopt::StructOpt;
+
+use cli::{EncodeFlags, KeyOpt, LayerFlags, Opt};
+use steganographer_core::*;
+
+mod cli;
+#[cfg(feature = "gui")]
+mod gui;
+mod shell;
+
+fn parse_embedding(method: &str) -> Embedding {
+    match method {
+        "wet" => Embedding::WetPaper,
+        "stc" => Embedding::Stc,
+        "adaptive" => Embedding::Adaptive,
+        _ => Embedding::Lsb,
+    }
+}
+
+fn parse_traversal(traversal: &str) -> Traversal {
+    match traversal {
+        "boustrophedon" => Traversal::Boustrophedon,
+        "hilbert" => Traversal::Hilbert,
+        _ => Traversal::Raster,
+    }
+}
+
+fn parse_filler(fill: &str) -> Filler {
+    match fill {
+        "modeled" => Filler::Modeled,
+        "random" => Filler::Random,
+        _ => Filler::Keep,
+    }
+}
+
+fn parse_format(format: &str) -> OutputFormat {
+    match format {
+        "bmp" => OutputFormat::Bmp,
+        "tiff" => OutputFormat::Tiff,
+        "pnm" => OutputFormat::Pnm,
+        "qoi" => OutputFormat::Qoi,
+        _ => OutputFormat::Png,
+    }
+}
+
+fn parse_png_filter(filter: &str) -> PngFilter {
+    match filter {
+        "none" => PngFilter::None,
+        "sub" => PngFilter::Sub,
+        "up" => PngFilter::Up,
+        "average" => PngFilter::Average,
+        "paeth" => PngFilter::Paeth,
+        _ => PngFilter::Adaptive,
+    }
+}
+
+fn parse_channels(channels: &str) -> Result<u8, Error> {
+    channels.chars().try_fold(0, |mask, channel| Ok(mask | match channel {
+        'r' | 'l' => 1,
+        'g' => 2,
+        'b' => 4,
+        'a' => 8,
+        _ => return Err(Error::Wrapped(format!("`{}` is not one of the r, g, b, a and l channels", channel).into())),
+    }))
+}
+
+/// Parses the codec of the `--compress` option along with its optional level, such as `zstd:19` or `gzip:9`
+fn parse_compression(compress: &str) -> Result<compress::Compression, Error> {
+    let (codec, level) = match compress.split_once(':') {
+        Some((codec, level)) => (codec, Some(level)),
+        None => (compress, None),
+    };
+    let level = |range: std::ops::RangeInclusive<i32>| match level.map(str::parse) {
+        Some(Ok(level)) if range.contains(&level) => Ok(Some(level)),
+        Some(_) => Err(Error::Wrapped(format!("the level of {} is not a number from {} to {}", codec, range.start(), range.end()).into())),
+        None => Ok(None),
+    };
+    match codec {
+        "zstd" => Ok(level(1..=22)?.map_or(compress::Compression::DEFAULT_ZSTD, compress::Compression::Zstd)),
+        "deflate" => Ok(level(0..=9)?.map_or(compress::Compression::DEFAULT_DEFLATE, |level| compress::Compression::Deflate(level as u32))),
+        "gzip" => Ok(level(0..=9)?.map_or(compress::Compression::DEFAULT_GZIP, |level| compress::Compression::Gzip(level as u32))),
+        _ => Err(Error::Wrapped(format!("`{}` is not one of the zstd, deflate and gzip codecs", codec).into())),
+    }
+}
+
+/// Parses the bit plane of the `bitplanes` command, a channel letter followed by the bit, into their indices
+fn parse_plane(plane: &str) -> Result<(usize, u8), Error> {
+    let invalid = || Error::Wrapped(format!("`{}` is not a channel letter followed by a bit, such as `b0`", plane).into());
+    let mut chars = plane.chars();
+    let channel = chars.next().ok_or_else(invalid)?;
+    let bit = chars.as_str().parse().map_err(|_| invalid())?;
+    Ok((parse_channels(&channel.to_string())?.trailing_zeros() as usize, bit))
+}
+
+/// Letters of the channels of the mask, as `--channels` takes them
+fn channel_letters(mask: u8) -> String {
+    "rgba".chars().enumerate().filter(|&(i, _)| mask & 1 << i != 0).map(|(_, channel)| channel).collect()
+}
+
+/// The `decode` flags that select the channels of the mask, along with `--use-alpha` when it has the alpha channel
+fn channel_flags(mask: u8) -> String {
+    let channels = channel_letters(mask);
+    match mask & 8 != 0 {
+        true => format!("--channels {} --use-alpha", channels),
+        false => format!("--channels {}", channels),
+    }
+}
+
+/// The detection report as a JSON object, with the rates of each channel keyed by its letter
+fn detection_json(detection: &steganalysis::Detection) -> String {
+    let rates = |rates: &[steganalysis::ChannelRate]| rates.iter()
+        .map(|channel| format!("\"{}\":{:.4}", channel_letters(1 << channel.channel), channel.rate))
+        .collect::<Vec<_>>()
+        .join(",");
+    let regions = detection.regions.iter()
+        .map(|region| format!(
+            "{{\"x\":{},\"y\":{},\"width\":{},\"height\":{},\"probability\":{:.4}}}",
+            region.x, region.y, region.width, region.height, region.probability,
+        ))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!(
+        "{{\"suspicious\":{},\"confidence\":{:.4},\"positives\":{},\"chi_square\":{{\"probability\":{:.4},\"regions\":[{}]}},\"rs\":{{{}}},\"spa\":{{{}}}}}",
+        detection.is_suspicious(), detection.confidence(), detection.positives(),
+        detection.probability, regions, rates(&detection.rs_rates), rates(&detection.spa_rates),
+    )
+}
+
+/// Names of the layers the header flags say the data is sealed in
+fn layer_names(flags: u8) -> Vec<&'static str> {
+    let layers = [
+        (container::FLAG_PASSWORD, "password"),
+        (container::FLAG_RECIPIENTS, "recipients"),
+        (container::FLAG_ONE_TIME_PAD, "one-time pad"),
+        (container::FLAG_SIGNED_DATA, "signed"),
+        (container::FLAG_ENTRIES, "named entries"),
+        (container::FLAG_CARD, "smartcard"),
+    ];
+    let mut names = layers.iter().filter(|&&(flag, _)| flags & flag != 0).map(|&(_, name)| name).collect::<Vec<_>>();
+    match (flags & container::FLAG_CODEC) >> container::FLAG_CODEC.trailing_zeros() {
+        compress::NONE => {},
+        compress::ZSTD => names.push("zstd"),
+        compress::DEFLATE => names.push("deflate"),
+        compress::GZIP => names.push("gzip"),
+        _ => names.push("unknown compression"),
+    }
+    names
+}
+
+/// The string as a JSON string literal
+fn json_string(string: &str) -> String {
+    let mut json = String::with_capacity(string.len() + 2);
+    json.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// The entries listed by `ls` as a JSON array, with the names of their layers and the timestamps in seconds
+fn entries_json(entries: &[toc::Entry]) -> String {
+    let entries = entries.iter()
+        .map(|entry| format!(
+            "{{\"name\":{},\"size\":{},\"layers\":[{}],\"timestamp\":{}}}",
+            json_string(&entry.name), entry.len,
+            layer_names(entry.flags).iter().map(|layer| json_string(layer)).collect::<Vec<_>>().join(","),
+            entry.timestamp,
+        ))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("[{}]", entries)
+}
+
+/// The seconds since the Unix epoch as the UTC date and time, or a dash for 0, which means that the time is not known
+fn utc_time(seconds: u64) -> String {
+    if seconds == 0 {
+        return "-".into();
+    }
+    // the days to the civil date conversion from http://howardhinnant.github.io/date_algorithms.html
+    let days = (seconds / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    let time = seconds % 86400;
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
+
+fn encode_options(flags: &EncodeFlags) -> Result<EncodeOptions, Error> {
+    let mut options = EncodeOptions::new()
+        .interleave(flags.interleave)
+        .traversal(parse_traversal(&flags.traversal))
+        .offset(flags.offset)
+        .stride(flags.stride)
+        .matrix(flags.matrix)
+        .auto_channels(flags.auto_channels)
+        .resync(flags.resync)
+        .embedding(parse_embedding(&flags.method))
+        .convert(!flags.no_convert)
+        .filler(parse_filler(&flags.fill))
+        .png_filter(parse_png_filter(&flags.filter))
+        .skip_alpha(!flags.use_alpha)
+        .skip_transparent(flags.skip_transparent)
+        .channels(parse_channels(&flags.channels)?)
+        .deterministic(flags.deterministic)
+        .verify(flags.verify);
+    if let Some(sign) = &flags.sign {
+        options = options.signing_key(sign);
+    }
+    if let Some(cost_map) = &flags.cost_map {
+        options = options.cost_map(cost_map);
+    }
+    if let Some(mask) = &flags.mask {
+        options = options.mask(mask);
+    }
+    if let Some(seed) = &flags.whiten {
+        options = options.whiten(seed.as_str());
+    }
+    if let Some(seed) = &flags.scatter {
+        options = options.scatter(seed);
+    }
+    if let Some(compression) = flags.compression {
+        options = options.compression(compression);
+    }
+    if let Some(wide_bits) = flags.wide_bits {
+        options = options.wide_bits(std::convert::TryFrom::try_from(wide_bits)?);
+    }
+    Ok(options)
+}
+
+/// Adds the layers the data is sealed in to the options
+fn layer_options(mut options: EncodeOptions, layers: LayerFlags) -> Result<EncodeOptions, Error> {
+    if let Some(otp) = layers.otp {
+        options = options.otp_pad(otp);
+    }
+    if let Some(password) = password_or_entry(layers.password, layers.keyring_entry)? {
+        options = options.password(password);
+    }
+    for recipient in layers.recipients {
+        options = options.recipient(recipient);
+    }
+    if let Some(key) = layers.sign_data {
+        options = options.data_signing_key(key);
+    }
+    if let Some(compress) = layers.compress {
+        options = options.compress(parse_compression(&compress)?);
+    }
+    if let Some(key) = layers.card_key {
+        options = options.card_key(key);
+    }
+    Ok(options)
+}
+
+fn open_result(path: &Path, force: bool) -> Result<File, Error> {
+    Ok(std::fs::OpenOptions::new().write(true).truncate(true).create(true).create_new(!force).open(path)?)
+}
+
+fn parse_hex(hex: &str) -> Result<Vec<u8>, Error> {
+    let digits = hex.bytes()
+        .filter(|c| !c.is_ascii_whitespace())
+        .map(|c| (c as char).to_digit(16).map(|d| d as u8))
+        .collect::<Option<Vec<_>>>()
+        .filter(|digits| digits.len() % 2 == 0)
+        .ok_or_else(|| Error::Wrapped(format!("`{}` is not a sequence of hex bytes", hex).into()))?;
+    Ok(digits.chunks(2).map(|pair| pair[0] << 4 | pair[1]).collect())
+}
+
+fn parse_applet(applet: &str) -> Applet {
+    match applet {
+        "piv" => Applet::Piv,
+        "openpgp" => Applet::OpenPgp,
+        _ => unreachable!("structopt only lets the possible values of --card through, not {}", applet),
+    }
+}
+
+fn main() -> Result<(), Error> {
+    match Opt::from_args() {
+        Opt::Encode { image, data, result, force, flags, metadata, compress, name, otp, password, keyring_entry, recipients, sign_data, hidden_data, hidden_password, format, quality, card_key } => {
+            let mut options = encode_options(&flags)?.replace(force).metadata(metadata);
+            if data.as_os_str() != "-" {
+                options = options.data(data);
+            }
+            if let Some(key) = card_key {
+                options = options.card_key(key);
+            }
+            if let Some(compress) = compress {
+                options = options.compress(parse_compression(&compress)?);
+            }
+            if let Some(name) = name {
+                options = options.name(name);
+            }
+            if let Some(otp) = otp {
+                options = options.otp_pad(otp);
+            }
+            if let Some(password) = password_or_entry(password, keyring_entry)? {
+                options = options.password(password);
+            }
+            for recipient in recipients {
+                options = options.recipient(recipient);
+            }
+            if let Some(key) = sign_data {
+                options = options.data_signing_key(key);
+            }
+            if let (Some(data), Some(password)) = (hidden_data, hidden_password) {
+                options = options.hidden_volume(data, password);
+            }
+            if let Some(format) = format {
+                options = options.format(parse_format(&format));
+            }
+            if image.as_os_str() == "-" {
+                // the conversion notice would need the stdin read twice, the refusal still works
+                let stdin = std::io::stdin();
+                return match result.as_os_str() == "-" {
+                    true => encode_stream(stdin.lock(), std::io::stdout(), &options),
+                    false => encode_stream(stdin.lock(), open_result(&result, force)?, &options),
+                };
+            }
+            if !flags.no_convert {
+                if let Some(conversion) = image_conversion(File::open(&image)?)? {
+                    eprintln!("Notice: the image is converted from {}, pass --no-convert to refuse", conversion);
+                }
+            }
+            if !quality || result.as_os_str() == "-" {
+                return encode_into_file(image, result, &options);
+            }
+            encode_into_file(image.clone(), result.clone(), &options)?;
+            let quality = image_quality(File::open(image)?, File::open(result)?, &options)?;
+            println!("PSNR: {:.2} dB", quality.psnr);
+            println!("SSIM: {:.4}", quality.ssim);
+            println!("Mean absolute error: {:.4}", quality.mean_absolute_error);
+            match quality.used {
+                Some(used) => println!("Capacity used: {} of {} bytes ({:.1}%)", used, quality.capacity,
+                                       used as f64 / quality.capacity.max(1) as f64 * 100.0),
+                None => println!("Capacity: {} bytes", quality.capacity),
+            }
+            Ok(())
+        },
+        Opt::Append { encoded, data, result, name, force, flags, layers } => {
+            let mut options = layer_options(encode_options(&flags)?, layers)?.name(name).replace(force || result.is_none());
+            if data.as_os_str() != "-" {
+                options = options.data(data);
+            }
+            let result = result.unwrap_or_else(|| encoded.clone());
+            append_into_file(encoded, result, &options)
+        },
+        Opt::Rm { encoded, result, name, force, flags } => {
+            let options = encode_options(&flags)?.name(name).replace(force || result.is_none());
+            let result = result.unwrap_or_else(|| encoded.clone());
+            remove_from_file(encoded, result, &options)
+        },
+        Opt::Replace { encoded, data, result, name, force, flags, layers } => {
+            let mut options = layer_options(encode_options(&flags)?, layers)?.name(name).replace(force || result.is_none());
+            if data.as_os_str() != "-" {
+                options = options.data(data);
+            }
+            let result = result.unwrap_or_else(|| encoded.clone());
+            replace_in_file(encoded, result, &options)
+        },
+        Opt::Nest { mut paths, force, flags } => {
+            let result = paths.pop().expect("structopt requires at least 3 paths");
+            let inner = paths.remove(0);
+            nest_images(inner, &paths, result, &encode_options(&flags)?.replace(force))
+        },
+        Opt::Verify { image, data, flags } => {
+            let mut options = encode_options(&flags)?;
+            if data.as_os_str() != "-" {
+                options = options.data(data);
+            }
+            let roundtrip = roundtrip_image(image, &options)?;
+            println!("Capacity used: {} of {} bytes ({:.1}%)", roundtrip.payload, roundtrip.capacity,
+                     roundtrip.payload as f64 / roundtrip.capacity.max(1) as f64 * 100.0);
+            println!("PSNR: {:.2} dB", roundtrip.psnr);
+            if !roundtrip.is_intact() {
+                println!("The data was not recovered intact, {} of its bits are wrong", roundtrip.wrong_bits);
+                std::process::exit(1);
+            }
+            println!("The data was recovered intact");
+            Ok(())
+        },
+        Opt::Decode { encoded, data, extract_to, output, binary, force, name, resync, signed, method, otp, password, keyring_entry, identity, signed_data, signer, hidden, depth, use_alpha, skip_transparent, channels, mask, whiten, scatter, offset, stride, bits, wide_bits, card } => {
+            let mut options = DecodeOptions::new()
+                .offset(offset)
+                .stride(stride)
+                .replace(force)
+                .binary_to_terminal(binary)
+                .resync(resync)
+                .signed(signed)
+                .embedding(parse_embedding(&method))
+                .signed_data(signed_data)
+                .hidden_volume(hidden)
+                .depth(depth)
+                .skip_alpha(!use_alpha)
+                .skip_transparent(skip_transparent)
+                .channels(parse_channels(&channels)?);
+            if let Some(name) = name {
+                options = options.name(name);
+            }
+            if let Some(otp) = otp {
+                options = options.otp_pad(otp);
+            }
+            if let Some(password) = password_or_entry(password, keyring_entry)? {
+                options = options.password(password);
+            }
+            if let Some(identity) = identity {
+                options = options.identity(identity);
+            }
+            if let Some(signer) = signer {
+                options = options.signer(signer);
+            }
+            if let Some(mask) = mask {
+                options = options.mask(mask);
+            }
+            if let Some(seed) = whiten {
+                options = options.whiten(seed);
+            }
+            if let Some(seed) = scatter {
+                options = options.scatter(&seed);
+            }
+            if let Some(bits) = bits {
+                options = options.bits(std::convert::TryFrom::try_from(bits)?);
+            }
+            if let Some(wide_bits) = wide_bits {
+                options = options.wide_bits(std::convert::TryFrom::try_from(wide_bits)?);
+            }
+            if let (None, None) = (bits, wide_bits) {
+                options = options.detect_bits(true);
+            }
+            if let Some(data) = data {
+                options = options.output(data);
+            }
+            if let Some(card) = card {
+                options = options.card(parse_applet(&card));
+                if let Ok(pin) = std::env::var("STEGANOGRAPHER_CARD_PIN") {
+                    options = options.card_pin(pin);
+                }
+            }
+            match output {
+                Some(output) if output.as_os_str() == "auto" => options = options.auto_output(true),
+                Some(output) => options = options.output(output),
+                None => {},
+            }
+            if let Some(dir) = extract_to {
+                options = options.extract_to(dir);
+            }
+            let report = match encoded.as_os_str() == "-" {
+                true => decode_stream(std::io::stdin().lock(), &options)?,
+                false => decode_from_file(encoded, &options)?,
+            };
+            if report.damaged > 0 {
+                eprintln!("Found {} damaged copies of the parts of the data", report.damaged);
+            }
+            for range in report.corrected {
+                eprintln!("Bytes {}..{} of the data were damaged and are repaired", range.start, range.end);
+            }
+            for range in report.missing {
+                eprintln!("Bytes {}..{} of the data were lost and are replaced with zeroes", range.start, range.end);
+            }
+            if let Some(signer) = report.signer {
+                eprintln!("Data signed by {}", signer);
+            }
+            if let Some(name) = report.metadata.and_then(|metadata| metadata.filename) {
+                eprintln!("Data file name: {}", name);
+            }
+            Ok(())
+        },
+        Opt::DecodeAll { path, out, force, resync, signed, method } => {
+            let options = DecodeOptions::new()
+                .replace(force)
+                .resync(resync)
+                .signed(signed)
+                .embedding(parse_embedding(&method));
+            let extractions = bulk::decode_all(&path, &out, &options)?;
+            let mut empty = Vec::new();
+            for extraction in &extractions {
+                match &extraction.result {
+                    Ok(output) => println!("{}: extracted to {}", extraction.source.display(), output.display()),
+                    Err(e) => empty.push(format!("{}: {}", extraction.source.display(), e)),
+                }
+            }
+            println!("{} of {} files contained data", extractions.len() - empty.len(), extractions.len());
+            if !empty.is_empty() {
+                println!("Nothing was extracted from:");
+                for line in empty {
+                    println!("  {}", line);
+                }
+            }
+            Ok(())
+        },
+        Opt::Grep { pattern, paths, hex, mut passwords, keyring_entries } => {
+            let pattern = match hex {
+                true => parse_hex(&pattern)?,
+                false => pattern.into_bytes(),
+            };
+            for entry in &keyring_entries {
+                passwords.push(fetch_password(entry)?);
+            }
+            let methods = [
+                ("lsb", DecodeOptions::new()),
+                ("lsb --resync", DecodeOptions::new().resync(true)),
+                ("wet", DecodeOptions::new().embedding(Embedding::WetPaper)),
+                ("stc", DecodeOptions::new().embedding(Embedding::Stc)),
+                ("adaptive", DecodeOptions::new().embedding(Embedding::Adaptive)),
+            ];
+            // every method without a password first, then with each of them, never printing the passwords themselves
+            let mut attempts = methods.iter().map(|(name, options)| (name.to_string(), options.clone())).collect::<Vec<_>>();
+            for (i, password) in passwords.iter().enumerate() {
+                attempts.extend(methods.iter().map(|(name, options)| {
+                    (format!("{} with password {}", name, i + 1), options.clone().password(password.as_str()))
+                }));
+            }
+            let options = attempts.iter().map(|(_, options)| options.clone()).collect::<Vec<_>>();
+            for found in bulk::grep(&paths, &pattern, &options)? {
+                let offsets = found.offsets.iter().map(|offset| offset.to_string()).collect::<Vec<_>>();
+                println!("{}: {} at {}", found.source.display(), attempts[found.attempt].0, offsets.join(", "));
+            }
+            Ok(())
+        },
+        Opt::VerifyImage { image, key, use_alpha, skip_transparent } => {
+            let options = DecodeOptions::new().skip_alpha(!use_alpha).skip_transparent(skip_transparent);
+            println!("Signature is valid, signed by {}", verify_image(image, key, &options)?);
+            Ok(())
+        },
+        Opt::Wipe { image, result, force, bits, fill, use_alpha } => {
+            let options = EncodeOptions::new()
+                .replace(force)
+                .bits(std::convert::TryFrom::try_from(bits)?)
+                .filler(parse_filler(&fill))
+                .skip_alpha(!use_alpha);
+            wipe_file(image, result, &options)
+        },
+        Opt::Sanitize { paths, out, force, bits, fill, use_alpha } => {
+            let options = EncodeOptions::new()
+                .replace(force)
+                .bits(std::convert::TryFrom::try_from(bits)?)
+                .filler(parse_filler(&fill))
+                .skip_alpha(!use_alpha);
+            let sanitized = bulk::sanitize(&paths, &out, &options)?;
+            let mut failed = Vec::new();
+            for image in &sanitized {
+                let removed = match &image.result {
+                    Ok(removed) => removed,
+                    Err(e) => {
+                        failed.push(format!("{}: {}", image.source.display(), e));
+                        continue;
+                    },
+                };
+                let mut found = Vec::new();
+                if removed.hidden_data {
+                    found.push("hidden data".to_string());
+                }
+                if !removed.chunks.is_empty() {
+                    found.push(format!("metadata ({})", removed.chunks.join(", ")));
+                }
+                let found = if found.is_empty() { "nothing suspicious".to_string() } else { found.join(" and ") };
+                println!("{}: {} removed, written to {}", image.source.display(), found, removed.output.display());
+            }
+            println!("{} of {} files were sanitized", sanitized.len() - failed.len(), sanitized.len());
+            if !failed.is_empty() {
+                println!("Could not sanitize:");
+                for line in failed {
+                    println!("  {}", line);
+                }
+            }
+            Ok(())
+        },
+        Opt::Regions { image, region, heat_map, force, use_alpha } => {
+            let image = std::fs::read(image)?;
+            let options = EncodeOptions::new().skip_alpha(!use_alpha);
+            let regions = image_regions(&image[..], &options, region)?;
+            let mut rows = regions.iter().peekable();
+            while let Some(first) = rows.peek().copied() {
+                let row = std::iter::from_fn(|| rows.next_if(|region| region.y == first.y))
+                    .map(|region| match region.suitability() {
+                        noise::Suitability::Flat => '.',
+                        noise::Suitability::Marginal => '+',
+                        noise::Suitability::Safe => '#',
+                    })
+                    .collect::<String>();
+                println!("{}", row);
+            }
+            for (suitability, name) in &[(noise::Suitability::Safe, "Safe (#)"), (noise::Suitability::Marginal, "Marginal (+)"), (noise::Suitability::Flat, "Flat (.)")] {
+                let count = regions.iter().filter(|region| region.suitability() == *suitability).count();
+                println!("{}: {} of {} regions ({:.1}%)", name, count, regions.len(), count as f64 / regions.len().max(1) as f64 * 100.0);
+            }
+            let mean = |f: fn(&noise::RegionNoise) -> f64| regions.iter().map(f).sum::<f64>() / regions.len().max(1) as f64;
+            println!("Mean entropy: {:.2} bits, mean variance: {:.1}, mean noise: {:.2}",
+                     mean(|region| region.entropy), mean(|region| region.variance), mean(|region| region.noise));
+            if let Some(heat_map) = heat_map {
+                let mut map = Vec::new();
+                suitability_map_image(&image[..], &options, region, &mut map)?;
+                std::io::Write::write_all(&mut open_result(&heat_map, force)?, &map)?;
+            }
+            Ok(())
+        },
+        Opt::Compare { original, altered, heat_map, force } => {
+            let (original, altered) = (std::fs::read(original)?, std::fs::read(altered)?);
+            let comparison = compare_images(&original[..], &altered[..])?;
+            println!("Changed pixels: {} of {}", comparison.changed_pixels, comparison.pixels);
+            for channel in &comparison.channels {
+                let planes = channel.changed_planes().iter().map(|bit| bit.to_string()).collect::<Vec<_>>();
+                match planes.is_empty() {
+                    true => println!("{}: unchanged", channel_letters(1 << channel.channel)),
+                    false => println!("{}: {} samples changed, in bits {}", channel_letters(1 << channel.channel), channel.changed, planes.join(", ")),
+                }
+                for bit in channel.changed_planes() {
+                    println!("  bit {}: {}", bit, channel.bits[bit]);
+                }
+            }
+            if let Some(heat_map) = heat_map {
+                let mut map = Vec::new();
+                heat_map_image(&original[..], &altered[..], &mut map)?;
+                std::io::Write::write_all(&mut open_result(&heat_map, force)?, &map)?;
+            }
+            Ok(())
+        },
+        Opt::BitPlanes { image, plane, output, force } => {
+            let (channel, bit) = parse_plane(&plane)?;
+            bit_plane_file(image, channel, bit, output, force)
+        },
+        Opt::Detect { image, region, json } => {
+            let detection = detect_image(File::open(image)?, region)?;
+            if json {
+                println!("{}", detection_json(&detection));
+                return Ok(());
+            }
+            let mut rows = detection.regions.iter().peekable();
+            while let Some(first) = rows.peek().copied() {
+                let row = std::iter::from_fn(|| rows.next_if(|region| region.y == first.y))
+                    .map(|region| format!("{:>4.0}%", region.probability * 100.0))
+                    .collect::<String>();
+                println!("{}", row);
+            }
+            println!("Chi-square probability of hidden data: {:.1}%", detection.probability * 100.0);
+            for (name, rates) in &[("RS", &detection.rs_rates), ("SPA", &detection.spa_rates)] {
+                for channel in rates.iter() {
+                    println!("{} embedding rate of {}: {:.1}%", name, channel_letters(1 << channel.channel), channel.rate * 100.0);
+                }
+            }
+            println!("Confidence: {:.1}%, {} of 3 detectors positive", detection.confidence() * 100.0, detection.positives());
+            match detection.is_suspicious() {
+                true => println!("Verdict: the image is likely to have data hidden in it"),
+                false => println!("Verdict: nothing was detected"),
+            }
+            Ok(())
+        },
+        Opt::Inspect { image, signed, use_alpha, skip_transparent, channels, mask, whiten, scatter, offset, stride } => {
+            let mut options = DecodeOptions::new()
+                .offset(offset)
+                .stride(stride)
+                .signed(signed)
+                .skip_alpha(!use_alpha)
+                .skip_transparent(skip_transparent)
+                .channels(parse_channels(&channels)?);
+            if let Some(mask) = mask {
+                options = options.mask(mask);
+            }
+            if let Some(seed) = whiten {
+                options = options.whiten(seed);
+            }
+            if let Some(seed) = scatter {
+                options = options.scatter(&seed);
+            }
+            let inspection = match inspect_image(File::open(image)?, &options) {
+                Ok(inspection) => inspection,
+                Err(Error::UnsupportedCarrier(reason)) => {
+                    println!("No hidden data found: {}", reason);
+                    std::process::exit(1);
+                },
+                Err(e) => return Err(e),
+            };
+            let header = inspection.header;
+            let layers = layer_names(header.flags);
+            println!("Format version: {}", header.version);
+            println!("Bits: {}", u8::from(inspection.bits));
+            println!("Length: {} bytes, the image holds up to {}{}", inspection.length, inspection.capacity,
+                     if inspection.is_plausible() { "" } else { ", so the header is damaged" });
+            println!("Layers: {}", if layers.is_empty() { "none".into() } else { layers.join(", ") });
+            match header.traversal {
+                Traversal::Scattered(_) => println!("Traversal: scattered"),
+                traversal => println!("Traversal: {}", format!("{:?}", traversal).to_lowercase()),
+            }
+            println!("Interleave: {}", header.interleave);
+            if header.matrix > 1 {
+                println!("Matrix: {} bits of the data in each {} of the image", header.matrix, matrix::block_size(header.matrix));
+            }
+            if header.channels != 0 {
+                println!("Channels: {}", channel_letters(header.channels));
+            }
+            Ok(())
+        },
+        Opt::Ls { image, json, signed, use_alpha, skip_transparent, channels, mask, whiten, scatter, offset, stride } => {
+            let mut options = DecodeOptions::new()
+                .offset(offset)
+                .stride(stride)
+                .signed(signed)
+                .detect_bits(true)
+                .skip_alpha(!use_alpha)
+                .skip_transparent(skip_transparent)
+                .channels(parse_channels(&channels)?);
+            if let Some(mask) = mask {
+                options = options.mask(mask);
+            }
+            if let Some(seed) = whiten {
+                options = options.whiten(seed);
+            }
+            if let Some(seed) = scatter {
+                options = options.scatter(&seed);
+            }
+            let entries = list_entries(image, &options)?;
+            if json {
+                println!("{}", entries_json(&entries));
+                return Ok(());
+            }
+            let width = entries.iter().map(|entry| entry.name.chars().count()).max().unwrap_or(0).max(4);
+            println!("{:width$}  {:>10}  {:19}  LAYERS", "NAME", "SIZE", "STORED", width = width);
+            for entry in &entries {
+                let layers = layer_names(entry.flags);
+                println!("{:width$}  {:>10}  {:19}  {}", entry.name, entry.len, utc_time(entry.timestamp),
+                         if layers.is_empty() { "none".into() } else { layers.join(", ") }, width = width);
+            }
+            Ok(())
+        },
+        Opt::Recover { image, output, signed, whiten } => {
+            let mut options = DecodeOptions::new().signed(signed);
+            if let Some(seed) = whiten {
+                options = options.whiten(seed);
+            }
+            let found = recover_image(File::open(image)?, &options)?;
+            if found.is_empty() {
+                return Err(Error::UnsupportedCarrier("no intact data was found with any of the settings".into()));
+            }
+            if let Some(output) = &output {
+                std::fs::create_dir_all(output)?;
+            }
+            for (index, recovered) in found.iter().enumerate() {
+                let transparent = if recovered.skip_transparent { " --skip-transparent" } else { "" };
+                let layers = layer_names(recovered.flags);
+                let layers = if layers.is_empty() { String::new() } else { format!(", sealed with {}", layers.join(", ")) };
+                println!("--bits {} {}{}: {} bytes{}", u8::from(recovered.bits), channel_flags(recovered.channels), transparent,
+                         recovered.data.len(), layers);
+                if let Some(output) = &output {
+                    std::fs::write(output.join(format!("recovered-{}.bin", index + 1)), &recovered.data)?;
+                }
+            }
+            Ok(())
+        },
+        Opt::Survey { image, data } => {
+            let mut options = EncodeOptions::new();
+            if data.as_os_str() != "-" {
+                options = options.data(data);
+            }
+            let attacks = stress::Attack::battery();
+            println!("{:<16} {:<6} {:>10} {:>8}  survived", "method", "bits", "capacity", "PSNR");
+            for row in survey_image(image, &options)? {
+                let psnr = row.psnr.map_or("-".into(), |psnr| format!("{:.2}", psnr));
+                let survived = row.survived.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+                println!("{:<16} {:<6} {:>10} {:>8}  {}/{} {}", row.method.to_string(), u8::from(row.bits), row.capacity,
+                         psnr, survived.len(), attacks.len(), survived.join(", "));
+            }
+            Ok(())
+        },
+        Opt::Capacity { image, bits, flags } => {
+            let image = std::fs::read(image)?;
+            let options = encode_options(&flags)?;
+            let settings = match bits {
+                Some(bits) => vec![std::convert::TryFrom::try_from(bits)?],
+                None => binary::Bits::ALL.to_vec(),
+            };
+            println!("{:<6} {:>10}", "bits", "capacity");
+            for bits in settings {
+                println!("{:<6} {:>10}", u8::from(bits), image_capacity(&image[..], &options.clone().bits(bits))?);
+            }
+            Ok(())
+        },
+        Opt::Plan { size, channels, bits, method, resync, compression } => {
+            let options = EncodeOptions::new()
+                .bits(std::convert::TryFrom::try_from(bits)?)
+                .embedding(parse_embedding(&method))
+                .resync(resync.is_some());
+            let size = (size as f64 * compression).ceil() as usize;
+            let pixels = plan_pixels(size, binary::Layout::new(channels, 1, false), &options)
+                .ok_or_else(|| Error::UnsupportedCarrier("no image of a sane size can hold that much".into()))?
+                * resync.unwrap_or(1).max(1);
+            println!("At least {} pixels are needed for {} bytes, such as:", pixels, size);
+            for &(a, b) in &[(1, 1), (4, 3), (3, 2), (16, 9)] {
+                let (width, height) = plan_dimensions(pixels, (a, b));
+                println!("  {}x{} ({}:{})", width, height, a, b);
+            }
+            Ok(())
+        },
+        Opt::Stress { encoded, resync } => {
+            for (attack, outcome) in stress_test_image(encoded, &DecodeOptions::new().resync(resync))? {
+                println!("{:<20} {}", attack.to_string(), outcome);
+            }
+            Ok(())
+        },
+        Opt::Key(KeyOpt::Gen { kind, output, force }) => {
+            let key = generate_key_files(kind.parse()?, output, force)?;
+            println!("{}", key.fingerprint());
+            Ok(())
+        },
+        Opt::Key(KeyOpt::Keyring { entry, delete }) => keyring_entry(&entry, delete),
+        Opt::Key(KeyOpt::Card) => {
+            print!("{}", card_public_key()?);
+            Ok(())
+        },
+        Opt::Watch { incoming, out, force, resync, signed } => {
+            let options = DecodeOptions::new()
+                .replace(force)
+                .resync(resync)
+                .signed(signed);
+            watch_directory(&incoming, &out, &options)
+        },
+        Opt::Filter { clean, smudge, cover, method } => {
+            let (stdin, stdout) = (std::io::stdin(), std::io::stdout());
+            if smudge {
+                return filter::smudge(stdin.lock(), stdout.lock(), &DecodeOptions::new().embedding(parse_embedding(&method)));
+            }
+            debug_assert!(clean, "structopt requires either --clean or --smudge");
+            let cover = cover.expect("structopt requires the cover for --clean");
+            filter::clean(&cover, stdin.lock(), stdout.lock(), &EncodeOptions::new().embedding(parse_embedding(&method)))
+        },
+        Opt::Serve { listen } => serve_http(&listen),
+        Opt::Gui => run_gui(),
+        Opt::Shell { image, flags } => shell::run(image, encode_options(&flags)?),
+    }
+}
+
+#[cfg(feature = "gui")]
+fn run_gui() -> Result<(), Error> {
+    gui::run()
+}
+
+#[cfg(not(feature = "gui"))]
+fn run_gui() -> Result<(), Error> {
+    Err(Error::MissingFeature("gui"))
+}
+
+#[cfg(feature = "serve")]
+fn serve_http(address: &str) -> Result<(), Error> {
+    eprintln!("Listening on http://{}", address);
+    serve::serve(address, |url, status| eprintln!("{} {}", status, url))
+}
+
+#[cfg(not(feature = "serve"))]
+fn serve_http(_: &str) -> Result<(), Error> {
+    Err(Error::MissingFeature("serve"))
+}
+
+#[cfg(feature = "watch")]
+fn watch_directory(incoming: &Path, out: &Path, options: &DecodeOptions) -> Result<(), Error> {
+    watch::watch(incoming, out, options, |extraction| match extraction.result {
+        Ok(output) => println!("{}: extracted to {}", extraction.source.display(), output.display()),
+        Err(e) => println!("{}: {}", extraction.source.display(), e),
+    })
+}
+
+#[cfg(not(feature = "watch"))]
+fn watch_directory(_: &Path, _: &Path, _: &DecodeOptions) -> Result<(), Error> {
+    Err(Error::MissingFeature("watch"))
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_entry(entry: &str, delete: bool) -> Result<(), Error> {
+    if delete {
+        return keychain::delete_password(entry);
+    }
+    let mut password = String::new();
+    std::io::stdin().read_line(&mut password)?;
+    keychain::store_password(entry, password.trim_end_matches(&['\r', '\n'][..]))
+}
+
+#[cfg(not(feature = "keyring"))]
+fn keyring_entry(_: &str, _: bool) -> Result<(), Error> {
+    Err(Error::MissingFeature("keyring"))
+}
+
+#[cfg(feature = "smartcard")]
+fn card_public_key() -> Result<String, Error> {
+    let key = smartcard::public_key(&mut smartcard::Pcsc::connect()?, Applet::OpenPgp)?;
+    Ok(smartcard::format_public_key(&key))
+}
+
+#[cfg(not(feature = "smartcard"))]
+fn card_public_key() -> Result<String, Error> {
+    Err(Error::MissingFeature("smartcard"))
+}
+
+#[cfg(feature = "keyring")]
+fn fetch_password(entry: &str) -> Result<String, Error> {
+    keychain::fetch_password(entry)
+}
+
+#[cfg(not(feature = "keyring"))]
+fn fetch_password(_: &str) -> Result<String, Error> {
+    Err(Error::MissingFeature("keyring"))
+}
+
+/// The password given on the command line, or the one stored in the keyring entry
+fn password_or_entry(password: Option<String>, keyring_entry: Option<String>) -> Result<Option<String>, Error> {
+    match keyring_entry {
+        Some(entry) => fetch_password(&entry).map(Some),
+        None => Ok(password),
+    }
+}
@@ -0,0 +1,165 @@
+use std::convert::TryFrom;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use steganographer_core::binary::Bits;
+use steganographer_core::*;
+
+use crate::{parse_channels, parse_embedding, parse_filler, parse_traversal};
+
+const HELP: &str = "\
+open <image>            load a PNG, BMP, TIFF or WebP image as the cover
+capacity                how many bytes the cover can hold with the current settings
+add <file>              stage the file as the data to hide, replacing what was staged before
+set <setting> <value>   change the method, bits, traversal, interleave, fill or channels
+password [password]     encrypt the data with the password when writing and decrypt it when extracting,
+                        or stop doing that when no password is given
+write <image> [-f]      hide the staged data in the cover and save the result
+extract [file] [-f]     reveal the data hidden in the cover, to the file or to the terminal
+status                  show the cover, the staged data and the settings
+help                    show this list
+exit                    leave the shell";
+
+/// Everything the shell keeps in memory between the commands
+struct Shell {
+    cover: Option<(PathBuf, Vec<u8>)>,
+    staged: Option<(PathBuf, Vec<u8>)>,
+    options: EncodeOptions,
+    password: Option<String>,
+}
+
+fn usage(usage: &str) -> Error {
+    Error::Wrapped(format!("usage: {}", usage).into())
+}
+
+fn open_output(path: &str, force: bool) -> Result<fs::File, Error> {
+    Ok(OpenOptions::new().write(true).truncate(true).create(true).create_new(!force).open(path)?)
+}
+
+impl Shell {
+    fn cover(&self) -> Result<&[u8], Error> {
+        self.cover.as_ref()
+            .map(|(_, cover)| &cover[..])
+            .ok_or_else(|| Error::UnsupportedCarrier("no image is open, use `open <image>` first".into()))
+    }
+
+    fn set(&mut self, setting: &str, value: &str) -> Result<(), Error> {
+        let options = self.options.clone();
+        self.options = match setting {
+            "method" => options.embedding(parse_embedding(value)),
+            "bits" => options.bits(value.parse::<u8>().map_err(|_| usage("set bits <1-8>")).and_then(Bits::try_from)?),
+            "traversal" => options.traversal(parse_traversal(value)),
+            "interleave" => options.interleave(value.parse().map_err(|_| usage("set interleave <number>"))?),
+            "fill" => options.filler(parse_filler(value)),
+            "channels" => options.channels(parse_channels(value)?),
+            _ => return Err(usage("set <method|bits|traversal|interleave|fill|channels> <value>")),
+        };
+        Ok(())
+    }
+
+    fn run(&mut self, command: &str, args: &[&str]) -> Result<(), Error> {
+        let force = args.contains(&"-f");
+        let path = args.iter().find(|&&arg| arg != "-f").copied();
+        match (command, path) {
+            ("open", Some(path)) => {
+                let cover = fs::read(path)?;
+                println!("Capacity: {} bytes", image_capacity(&cover[..], &self.options)?);
+                self.cover = Some((path.into(), cover));
+            },
+            ("capacity", _) => {
+                let capacity = image_capacity(self.cover()?, &self.options)?;
+                match &self.staged {
+                    Some((_, data)) => {
+                        let size = data.len() + self.password.as_ref().map_or(0, |_| crypto::OVERHEAD);
+                        println!("Capacity: {} bytes, {} of them staged ({:.1}%)",
+                                 capacity, size, size as f64 / capacity.max(1) as f64 * 100.0)
+                    },
+                    None => println!("Capacity: {} bytes", capacity),
+                }
+            },
+            ("add", Some(path)) => {
+                let data = fs::read(path)?;
+                println!("Staged {} bytes", data.len());
+                self.staged = Some((path.into(), data));
+            },
+            ("password", _) => {
+                self.password = Some(args.join(" ")).filter(|password| !password.is_empty());
+                println!("{}", if self.password.is_some() { "The data is encrypted" } else { "The data is not encrypted" });
+            },
+            ("set", Some(setting)) => self.set(setting, args.get(1).ok_or_else(|| usage("set <setting> <value>"))?)?,
+            ("write", Some(path)) => {
+                let (_, data) = self.staged.as_ref()
+                    .ok_or_else(|| Error::UnsupportedCarrier("no data is staged, use `add <file>` first".into()))?;
+                let data = match &self.password {
+                    Some(password) => crypto::seal(data, password)?,
+                    None => data.clone(),
+                };
+                let mut encoded = Vec::new();
+                encode_image(self.cover()?, &data, &mut encoded, &self.options)?;
+                open_output(path, force)?.write_all(&encoded)?;
+                println!("Saved to {}", path);
+            },
+            ("extract", path) => {
+                let mut options = self.options.decoding();
+                if let Some(password) = &self.password {
+                    options = options.password(password.as_str());
+                }
+                // the decrypted data is wiped as soon as it is written out
+                with_decoded_image(self.cover()?, &options, |data, _| -> Result<(), Error> {
+                    match path {
+                        Some(path) => open_output(path, force)?.write_all(data)?,
+                        None => println!("{}", String::from_utf8_lossy(data)),
+                    }
+                    Ok(())
+                })??;
+            },
+            ("status", _) => {
+                let describe = |file: &Option<(PathBuf, Vec<u8>)>| file.as_ref()
+                    .map_or("-".into(), |(path, data)| format!("{} ({} bytes)", path.display(), data.len()));
+                println!("Cover: {}", describe(&self.cover));
+                println!("Staged: {}", describe(&self.staged));
+                println!("Password: {}", if self.password.is_some() { "set" } else { "-" });
+                println!("Settings: {:?}", self.options);
+            },
+            ("help", _) => println!("{}", HELP),
+            ("open", None) => return Err(usage("open <image>")),
+            ("add", None) => return Err(usage("add <file>")),
+            ("set", None) => return Err(usage("set <setting> <value>")),
+            ("write", None) => return Err(usage("write <image> [-f]")),
+            _ => return Err(Error::Wrapped(format!("unknown command `{}`, see `help`", command).into())),
+        }
+        Ok(())
+    }
+}
+
+/// Reads the commands from the stdin until `exit` or its end, keeping the cover image,
+/// the staged data and the settings in memory between them.
+/// Errors of the commands are printed and do not end the shell.
+pub fn run(image: Option<PathBuf>, options: EncodeOptions) -> Result<(), Error> {
+    let mut shell = Shell { cover: None, staged: None, options, password: None };
+    if let Some(image) = image {
+        shell.run("open", &[&image.to_string_lossy()])?;
+    }
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("steganographer> ");
+        io::stdout().flush()?;
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some("exit") | Some("quit") => break,
+            Some(command) => command,
+            None => continue,
+        };
+        if let Err(e) = shell.run(command, &words.collect::<Vec<_>>()) {
+            eprintln!("{}", e);
+        }
+    }
+    println!();
+    Ok(())
+}
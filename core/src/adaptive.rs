@@ -0,0 +1,125 @@
+use std::cmp::Reverse;
+use std::io;
+
+use crate::binary::{pack_bits, unpack_bits, Bits, Layout};
+use crate::Error;
+
+/// Bytes of the payload length in front of it
+const LENGTH_BYTES: usize = 4;
+
+fn eof() -> Error {
+    io::Error::from(io::ErrorKind::UnexpectedEof).into()
+}
+
+/// Returns how textured the neighbourhood of each of the carrier bytes is, the sum of the absolute differences
+/// from the same sample of the four neighbouring pixels.
+///
+/// The `bits` least significant bits of every byte are left out, so hiding data in those bits
+/// does not change the texture, and the decoder gets exactly the same one from the altered carrier.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::adaptive::texture;
+/// # use steganographer_core::binary::{Bits, Layout};
+/// let carrier = [10, 10, 10, 10, 10, 90];
+/// let layout = Layout::default().width(3);
+/// assert_eq!(texture(&carrier, layout, Bits::One), [0, 0, 40, 0, 40, 80]);
+///
+/// // the least significant bits do not matter
+/// let altered = [11, 10, 11, 11, 10, 91];
+/// assert_eq!(texture(&altered, layout, Bits::One), texture(&carrier, layout, Bits::One));
+/// ```
+pub fn texture(carrier: &[u8], layout: Layout, bits: Bits) -> Vec<u32> {
+    let size = layout.pixel_size();
+    let row = layout.pixels_per_row().unwrap_or(carrier.len() / size).max(1) * size;
+    let high = |i: usize| carrier[i].checked_shr(bits.count() as u32).unwrap_or(0) as i32;
+    (0..carrier.len())
+        .map(|i| {
+            let column = i % row;
+            let neighbours = [
+                i.checked_sub(size).filter(|_| column >= size),
+                Some(i + size).filter(|_| column + size < row),
+                i.checked_sub(row),
+                Some(i + row),
+            ];
+            neighbours.iter()
+                .flatten()
+                .filter(|&&j| j < carrier.len())
+                .map(|&j| (high(i) - high(j)).unsigned_abs())
+                .sum()
+        })
+        .collect()
+}
+
+/// Returns the usable carrier bytes from the most [textured](fn.texture.html) to the flattest,
+/// which is the order they are filled with the data in, the ones with the same texture in the order of the carrier.
+pub fn order(carrier: &[u8], layout: Layout, bits: Bits) -> Vec<usize> {
+    let texture = texture(carrier, layout, bits);
+    let mut positions = (0..carrier.len()).filter(|&i| layout.is_usable(i)).collect::<Vec<_>>();
+    positions.sort_by_key(|&i| Reverse(texture[i]));
+    positions
+}
+
+/// How many bytes of payload `len` carrier bytes can hold, whatever their texture
+pub fn capacity(len: usize, layout: Layout, bits: Bits) -> usize {
+    layout.capacity(len, bits).saturating_sub(LENGTH_BYTES)
+}
+
+/// Hides a slice of bytes along with its length in the least significant bits of the carrier bytes in place,
+/// filling the most textured regions of the carrier first, see [`order`](fn.order.html).
+///
+/// The changes in the noisy and the edge regions are much harder to see and to detect than the ones in the flat
+/// regions, which are only used when the payload does not fit elsewhere.
+///
+/// # Errors
+/// An `UnexpectedEof` IO error when the payload does not fit into the carrier.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::adaptive::{hide_adaptive, reveal_adaptive};
+/// # use steganographer_core::binary::{Bits, Layout};
+/// // the left half of every row is flat, the right one is noisy
+/// let mut carrier = (0..64 * 64).map(|i| if i % 64 < 32 { 128 } else { (i * 97 % 251) as u8 }).collect::<Vec<_>>();
+/// let original = carrier.clone();
+/// let layout = Layout::default().width(64);
+///
+/// hide_adaptive(b"only in the noise", &mut carrier, layout, Bits::Two).unwrap();
+///
+/// let changed = (0..carrier.len()).filter(|&i| carrier[i] != original[i]).collect::<Vec<_>>();
+/// assert!(!changed.is_empty() && changed.iter().all(|i| i % 64 >= 32));
+/// assert_eq!(reveal_adaptive(&carrier, layout, Bits::Two).unwrap(), b"only in the noise");
+/// ```
+pub fn hide_adaptive(payload: &[u8], carrier: &mut [u8], layout: Layout, bits: Bits) -> Result<(), Error> {
+    let positions = order(carrier, layout, bits);
+    let framed = (payload.len() as u32).to_be_bytes().iter().chain(payload).copied().collect::<Vec<_>>();
+    let needed = bits.carrier_len(framed.len());
+    if needed > positions.len() {
+        return Err(eof());
+    }
+    let mut bytes = positions[..needed].iter().map(|&i| carrier[i]).collect::<Vec<_>>();
+    pack_bits(&framed, &mut bytes, bits);
+    positions.iter().zip(bytes).for_each(|(&i, byte)| carrier[i] = byte);
+    Ok(())
+}
+
+/// Reveals a slice of bytes previously hidden by the [`hide_adaptive`](fn.hide_adaptive.html) function.
+///
+/// # Errors
+/// An `UnexpectedEof` IO error when the length stored in the carrier is greater than its capacity.
+pub fn reveal_adaptive(carrier: &[u8], layout: Layout, bits: Bits) -> Result<Vec<u8>, Error> {
+    let bytes = order(carrier, layout, bits).into_iter().map(|i| carrier[i]).collect::<Vec<_>>();
+    if bits.carrier_len(LENGTH_BYTES) > bytes.len() {
+        return Err(eof());
+    }
+    let mut length = [0; LENGTH_BYTES];
+    unpack_bits(&bytes, &mut length, bits);
+    let length = u32::from_be_bytes(length) as usize;
+    if bits.carrier_len(LENGTH_BYTES + length) > bytes.len() {
+        return Err(eof());
+    }
+    let mut framed = vec![0; LENGTH_BYTES + length];
+    unpack_bits(&bytes, &mut framed, bits);
+    Ok(framed.split_off(LENGTH_BYTES))
+}
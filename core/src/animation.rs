@@ -0,0 +1,197 @@
+use gif::{DecodeOptions, Encoder, Frame, Repeat};
+
+use crate::Error;
+
+/// Size of the length of the payload part that starts the bits of every frame
+pub const FRAME_HEADER: usize = 4;
+
+/// An animation decoded down to the palette indices of its frames
+struct Animation {
+    width: u16,
+    height: u16,
+    repeat: Repeat,
+    palette: Option<Vec<u8>>,
+    frames: Vec<Frame<'static>>,
+}
+
+fn read_gif(gif: &[u8]) -> Result<Animation, Error> {
+    let mut decoder = DecodeOptions::new().read_info(gif)?;
+    let mut frames = Vec::new();
+    while let Some(frame) = decoder.read_next_frame()? {
+        frames.push(frame.clone());
+    }
+    Ok(Animation {
+        width: decoder.width(),
+        height: decoder.height(),
+        repeat: decoder.repeat(),
+        palette: decoder.global_palette().map(<[u8]>::to_vec),
+        frames,
+    })
+}
+
+fn luminance(color: &[u8]) -> u32 {
+    299 * color[0] as u32 + 587 * color[1] as u32 + 114 * color[2] as u32
+}
+
+/// Sorts the colors of the palette by their luminance, so that the colors that only differ
+/// in the lowest bit of their index look almost the same, and returns where every old index went.
+///
+/// The palette is padded to an even number of colors with a copy of its last one,
+/// so that every color has such a pair.
+fn sort_palette(palette: &mut Vec<u8>) -> [u8; 256] {
+    if palette.len() / 3 % 2 == 1 && palette.len() < 256 * 3 {
+        palette.extend_from_within(palette.len() - 3..);
+    }
+    let mut order = (0..palette.len() / 3).collect::<Vec<_>>();
+    order.sort_by_key(|&i| luminance(&palette[i * 3..]));
+
+    let mut remap = [0; 256];
+    let original = std::mem::take(palette);
+    for (new, &old) in order.iter().enumerate() {
+        remap[old] = new as u8;
+        palette.extend_from_slice(&original[old * 3..old * 3 + 3]);
+    }
+    remap
+}
+
+fn remap_frame(frame: &mut Frame, remap: &[u8; 256]) {
+    frame.buffer.to_mut().iter_mut().for_each(|index| *index = remap[*index as usize]);
+    frame.transparent = frame.transparent.map(|index| remap[index as usize]);
+}
+
+/// Positions of the pixels of the frame that hold the bits, which are all but the transparent ones
+/// and the ones that would become transparent with the other lowest bit
+fn usable<'a>(frame: &'a Frame<'_>) -> impl Iterator<Item = usize> + 'a {
+    let transparent = frame.transparent.map(|index| index >> 1);
+    frame.buffer.iter()
+        .enumerate()
+        .filter(move |&(_, &index)| Some(index >> 1) != transparent)
+        .map(|(i, _)| i)
+}
+
+fn frame_capacity(frame: &Frame) -> usize {
+    (usable(frame).count() / 8).saturating_sub(FRAME_HEADER)
+}
+
+/// Splits the payload between the frames in proportion to their capacity,
+/// so that all of them are altered at about the same rate.
+pub(crate) fn distribute(len: usize, capacities: &[usize]) -> Vec<usize> {
+    let total = capacities.iter().sum::<usize>().max(1);
+    let mut parts = capacities.iter().map(|&c| (len as u128 * c as u128 / total as u128) as usize).collect::<Vec<_>>();
+    let mut left = len - parts.iter().sum::<usize>();
+    for (part, &capacity) in parts.iter_mut().zip(capacities) {
+        let more = left.min(capacity - *part);
+        *part += more;
+        left -= more;
+    }
+    parts
+}
+
+/// Total number of bytes that can be hidden in the frames of the GIF with [`hide_in_gif`](fn.hide_in_gif.html).
+///
+/// # Errors
+/// Wrapped decoding errors when `gif` is not a valid GIF file.
+pub fn gif_capacity(gif: &[u8]) -> Result<usize, Error> {
+    Ok(read_gif(gif)?.frames.iter().map(frame_capacity).sum())
+}
+
+/// Hides a slice of bytes in the lowest bits of the palette indices of all the frames of a GIF,
+/// every frame holding a part of it proportional to its size behind its own length,
+/// so that an animation can hold as many times more than a still image as it has frames,
+/// and none of the frames is altered much more than the others.
+///
+/// The palettes are sorted by the luminance of their colors first, so that the indices that only differ
+/// in their lowest bit point to similar colors, and the transparent pixels are left as they are.
+/// The delays, the disposal methods and the looping of the animation are all preserved.
+///
+/// # Errors
+/// [`InsufficientCapacity`](../enum.Error.html#variant.InsufficientCapacity) when the payload does not fit
+/// into the frames, wrapped decoding errors when `gif` is not a valid GIF file.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::animation::{gif_capacity, hide_in_gif, reveal_from_gif};
+/// let palette = [0, 0, 0, 255, 0, 0, 0, 255, 0, 0, 0, 255];
+/// let mut gif = Vec::new();
+/// let mut encoder = gif::Encoder::new(&mut gif, 32, 32, &palette).unwrap();
+/// for color in 0..3 {
+///     encoder.write_frame(&gif::Frame { width: 32, height: 32, buffer: vec![color; 1024].into(), ..Default::default() }).unwrap();
+/// }
+/// drop(encoder);
+///
+/// // 1024 pixels hold 128 bytes, 4 of which are the length of the frame's part
+/// assert_eq!(gif_capacity(&gif).unwrap(), 3 * 124);
+///
+/// let cloaked = hide_in_gif(&gif, &[42; 300]).unwrap();
+/// assert_eq!(reveal_from_gif(&cloaked).unwrap(), [42; 300]);
+/// assert!(hide_in_gif(&gif, &[42; 400]).is_err());
+/// ```
+///
+pub fn hide_in_gif(gif: &[u8], payload: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut animation = read_gif(gif)?;
+    let global_remap = animation.palette.as_mut().map(sort_palette);
+    for frame in &mut animation.frames {
+        let remap = match &mut frame.palette {
+            Some(palette) => sort_palette(palette),
+            None => global_remap.ok_or_else(|| Error::UnsupportedCarrier("a GIF frame has no palette".into()))?,
+        };
+        remap_frame(frame, &remap);
+        // the decoder already put the rows in their order
+        frame.interlaced = false;
+    }
+
+    let capacities = animation.frames.iter().map(frame_capacity).collect::<Vec<_>>();
+    let capacity = capacities.iter().sum::<usize>();
+    if payload.len() > capacity {
+        return Err(Error::InsufficientCapacity { needed: payload.len(), available: capacity });
+    }
+
+    let mut payload = payload;
+    for (frame, part) in animation.frames.iter_mut().zip(distribute(payload.len(), &capacities)) {
+        let (data, rest) = payload.split_at(part);
+        payload = rest;
+        let bytes = (part as u32).to_be_bytes().iter().chain(data).copied().collect::<Vec<_>>();
+        let bits = bytes.iter().flat_map(|byte| (0..8).rev().map(move |i| byte >> i & 1));
+        let positions = usable(frame).collect::<Vec<_>>();
+        let buffer = frame.buffer.to_mut();
+        for (position, bit) in positions.into_iter().zip(bits) {
+            buffer[position] = buffer[position] & !1 | bit;
+        }
+    }
+
+    let mut result = Vec::with_capacity(gif.len());
+    {
+        let mut encoder = Encoder::new(&mut result, animation.width, animation.height, animation.palette.as_deref().unwrap_or(&[]))?;
+        encoder.set_repeat(animation.repeat)?;
+        for frame in &animation.frames {
+            encoder.write_frame(frame)?;
+        }
+    }
+    Ok(result)
+}
+
+/// Reveals a slice of bytes previously hidden by the [`hide_in_gif`](fn.hide_in_gif.html) function,
+/// putting the parts from all the frames back together in their order.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when some frame holds
+/// a length that does not fit into it, which means there is no hidden data.
+pub fn reveal_from_gif(gif: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut payload = Vec::new();
+    for frame in read_gif(gif)?.frames {
+        let positions = usable(&frame).collect::<Vec<_>>();
+        let bytes = positions.chunks_exact(8)
+            .map(|byte| byte.iter().fold(0, |acc, &position| acc << 1 | frame.buffer[position] & 1))
+            .collect::<Vec<u8>>();
+        if bytes.len() < FRAME_HEADER {
+            continue;
+        }
+        let (len, data) = bytes.split_at(FRAME_HEADER);
+        let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        let part = data.get(..len)
+            .ok_or_else(|| Error::UnsupportedCarrier("there is no hidden data in the GIF".into()))?;
+        payload.extend_from_slice(part);
+    }
+    Ok(payload)
+}
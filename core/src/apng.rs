@@ -0,0 +1,262 @@
+use std::io::Cursor;
+
+use image::ColorType;
+use png::{BitDepth, Decoder, Encoder, FrameControl, Transformations};
+
+use crate::binary::{Bits, Endianness, Layout};
+use crate::container::{hide_payload, image_layout, payload_capacity, reveal_with, Conversion, Header};
+use crate::animation::distribute;
+use crate::chunks::copy_ancillary;
+use crate::{DecodeOptions, EncodeOptions, Embedding, Error};
+use crate::options::PngOutput;
+
+/// A frame of an animated PNG image decoded down to its pixels
+struct Frame {
+    /// `None` for the default image that is not a part of the animation
+    control: Option<FrameControl>,
+    width: u32,
+    pixels: Vec<u8>,
+}
+
+struct Animation {
+    width: u32,
+    height: u32,
+    color: png::ColorType,
+    depth: BitDepth,
+    plays: u32,
+    frames: Vec<Frame>,
+}
+
+/// Whether the PNG image has an animation control chunk, which comes before the first image data chunk.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::apng::is_animated;
+/// let mut png = Vec::new();
+/// image::png::PngEncoder::new(&mut png).encode(&[0; 12], 2, 2, image::ColorType::Rgb8).unwrap();
+///
+/// assert!(!is_animated(&png));
+/// ```
+pub fn is_animated(png: &[u8]) -> bool {
+    if !png.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return false;
+    }
+    let mut at = 8;
+    while let Some(chunk) = png.get(at..at + 8) {
+        match &chunk[4..] {
+            b"acTL" => return true,
+            b"IDAT" => return false,
+            _ => at += 12 + u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) as usize,
+        }
+    }
+    false
+}
+
+fn color_type(color: png::ColorType, depth: BitDepth) -> Option<ColorType> {
+    use png::ColorType::*;
+    Some(match (color, depth) {
+        (Grayscale, BitDepth::Eight) => ColorType::L8,
+        (GrayscaleAlpha, BitDepth::Eight) => ColorType::La8,
+        (Rgb, BitDepth::Eight) => ColorType::Rgb8,
+        (Rgba, BitDepth::Eight) => ColorType::Rgba8,
+        (Grayscale, BitDepth::Sixteen) => ColorType::L16,
+        (GrayscaleAlpha, BitDepth::Sixteen) => ColorType::La16,
+        (Rgb, BitDepth::Sixteen) => ColorType::Rgb16,
+        (Rgba, BitDepth::Sixteen) => ColorType::Rgba16,
+        _ => return None,
+    })
+}
+
+/// The PNG color type and bit depth of the pixels of the given format
+pub(crate) fn png_color(color_type: ColorType) -> Option<(png::ColorType, BitDepth)> {
+    use png::ColorType::*;
+    Some(match color_type {
+        ColorType::L8 => (Grayscale, BitDepth::Eight),
+        ColorType::La8 => (GrayscaleAlpha, BitDepth::Eight),
+        ColorType::Rgb8 => (Rgb, BitDepth::Eight),
+        ColorType::Rgba8 => (Rgba, BitDepth::Eight),
+        ColorType::L16 => (Grayscale, BitDepth::Sixteen),
+        ColorType::La16 => (GrayscaleAlpha, BitDepth::Sixteen),
+        ColorType::Rgb16 => (Rgb, BitDepth::Sixteen),
+        ColorType::Rgba16 => (Rgba, BitDepth::Sixteen),
+        _ => return None,
+    })
+}
+
+fn read_animation(png: &[u8]) -> Result<Animation, Error> {
+    let mut decoder = Decoder::new(Cursor::new(png));
+    // palettes and samples of less than 8 bits are expanded, the same as for the still images
+    decoder.set_transformations(Transformations::EXPAND);
+    let mut reader = decoder.read_info()?;
+    let info = reader.info();
+    let (width, height) = (info.width, info.height);
+    let plays = info.animation_control.map_or(0, |control| control.num_plays);
+    // the default image comes before the first control chunk when it is not a part of the animation
+    let separate = info.animation_control.is_some() && info.frame_control.is_none();
+    let count = info.animation_control.map_or(1, |control| control.num_frames as usize) + separate as usize;
+    let (color, depth) = reader.output_color_type();
+
+    let mut buffer = vec![0; reader.output_buffer_size().ok_or_else(|| Error::UnsupportedCarrier("the image is too large".into()))?];
+    let mut frames = Vec::with_capacity(count);
+    for index in 0..count {
+        let output = reader.next_frame(&mut buffer)?;
+        let control = reader.info().frame_control.filter(|_| !(separate && index == 0));
+        frames.push(Frame { control, width: output.width, pixels: buffer[..output.line_size * output.height as usize].to_vec() });
+    }
+    Ok(Animation { width, height, color, depth, plays, frames })
+}
+
+fn write_animation(animation: &Animation, output: &PngOutput) -> Result<Vec<u8>, Error> {
+    let mut result = Vec::new();
+    let mut encoder = Encoder::new(&mut result, animation.width, animation.height);
+    encoder.set_color(animation.color);
+    encoder.set_depth(animation.depth);
+    encoder.set_deflate_compression(output.compression);
+    encoder.set_filter(output.filter);
+    let animated = animation.frames.iter().filter(|frame| frame.control.is_some()).count() as u32;
+    encoder.set_animated(animated, animation.plays)?;
+    encoder.set_sep_def_img(animation.frames.first().is_some_and(|frame| frame.control.is_none()))?;
+    let mut writer = encoder.write_header()?;
+    for frame in &animation.frames {
+        if let Some(control) = &frame.control {
+            writer.reset_frame_position()?;
+            writer.set_frame_dimension(control.width, control.height)?;
+            writer.set_frame_position(control.x_offset, control.y_offset)?;
+            writer.set_frame_delay(control.delay_num, control.delay_den)?;
+            writer.set_dispose_op(control.dispose_op)?;
+            writer.set_blend_op(control.blend_op)?;
+        }
+        writer.write_image_data(&frame.pixels)?;
+    }
+    writer.finish()?;
+    Ok(result)
+}
+
+fn layout(animation: &Animation, frame: &Frame) -> Layout {
+    color_type(animation.color, animation.depth)
+        .map(image_layout)
+        .unwrap_or_default()
+        .endianness(Endianness::Big)
+        .width(frame.width as usize)
+}
+
+/// Hides a slice of bytes in all the frames of an animated PNG image, every frame that can hold
+/// at least a byte holding a part of it proportional to its size behind its own header,
+/// so that none of the frames is altered much more than the others.
+///
+/// Only the [`Lsb`](../enum.Embedding.html#variant.Lsb) embedding is supported, with its bits, interleave
+/// and traversal options. The timing, the placement, the disposal and the blending of the frames
+/// and the number of plays are all preserved together with the [ancillary chunks](../chunks/fn.copy_ancillary.html),
+/// such as the ICC profile, while the palettes and the samples of less than 8 bits are expanded like for the still images.
+///
+/// # Errors
+/// [`InsufficientCapacity`](../enum.Error.html#variant.InsufficientCapacity) when the payload does not fit into
+/// the frames, [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when some other embedding or a [mask](../struct.EncodeOptions.html#method.mask) is configured or when the image would have to be converted
+/// and the [`convert`](../struct.EncodeOptions.html#method.convert) option is off.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::apng::{hide_in_apng, reveal_from_apng};
+/// # use steganographer_core::EncodeOptions;
+/// let mut png = Vec::new();
+/// let mut encoder = png::Encoder::new(&mut png, 16, 16);
+/// encoder.set_color(png::ColorType::Rgb);
+/// encoder.set_animated(3, 0).unwrap();
+/// let mut writer = encoder.write_header().unwrap();
+/// for frame in 0..3 {
+///     writer.set_frame_delay(frame + 1, 10).unwrap();
+///     writer.write_image_data(&[frame as u8 * 80; 16 * 16 * 3]).unwrap();
+/// }
+/// writer.finish().unwrap();
+///
+/// let options = EncodeOptions::new();
+/// let cloaked = hide_in_apng(&png, &[42; 300], &options).unwrap();
+///
+/// assert_eq!(reveal_from_apng(&cloaked, &options.decoding()).unwrap(), [42; 300]);
+/// assert!(hide_in_apng(&png, &[42; 600], &options).is_err());
+///
+/// let mut reader = png::Decoder::new(std::io::Cursor::new(&cloaked)).read_info().unwrap();
+/// assert_eq!(reader.info().animation_control.unwrap().num_frames, 3);
+/// assert_eq!(reader.info().frame_control.unwrap().delay_num, 1);
+/// assert_eq!(reader.next_frame_info().unwrap().delay_num, 2);
+/// ```
+///
+pub fn hide_in_apng(png: &[u8], payload: &[u8], options: &EncodeOptions) -> Result<Vec<u8>, Error> {
+    if options.embedding != Embedding::Lsb || options.resync || options.matrix > 1 || options.signing_key.is_some() || options.hidden_volume.is_some() {
+        return Err(Error::UnsupportedCarrier("animated PNG images only support the plain lsb method".into()));
+    }
+    if options.mask.is_some() {
+        return Err(Error::UnsupportedCarrier("animated PNG images cannot be masked, as their frames differ in size".into()));
+    }
+    let mut animation = read_animation(png)?;
+    let conversion = color_type(animation.color, animation.depth).and_then(|color| Conversion::detect(png, color));
+    if !options.convert {
+        if let Some(conversion) = conversion {
+            return Err(Error::UnsupportedCarrier(format!("the image would have to be converted from {}", conversion)));
+        }
+    }
+
+    let capacities = animation.frames.iter()
+        .map(|frame| {
+            let layout = options.carrier_layout(layout(&animation, frame));
+            payload_capacity(frame.pixels.len(), options.carrier_bits(layout), layout)
+        })
+        .collect::<Vec<_>>();
+    let capacity = capacities.iter().sum::<usize>();
+    if payload.len() > capacity {
+        return Err(Error::InsufficientCapacity { needed: payload.len(), available: capacity });
+    }
+
+    let header = Header {
+        interleave: options.interleave,
+        traversal: options.traversal,
+        flags: options.header_flags(),
+        ..Header::default()
+    };
+    let parts = distribute(payload.len(), &capacities);
+    let mut payload = payload;
+    for index in 0..animation.frames.len() {
+        if capacities[index] == 0 {
+            continue;
+        }
+        let (part, rest) = payload.split_at(parts[index]);
+        payload = rest;
+        let layout = options.carrier_layout(layout(&animation, &animation.frames[index]));
+        hide_payload(part, &mut animation.frames[index].pixels, options.carrier_bits(layout), layout, &header)?;
+    }
+    Ok(copy_ancillary(png, &write_animation(&animation, &options.png_output(png))?, conversion.is_some()))
+}
+
+/// Reveals a slice of bytes previously hidden by the [`hide_in_apng`](fn.hide_in_apng.html) function,
+/// putting the parts from all the frames back together in their order.
+pub fn reveal_from_apng(png: &[u8], options: &DecodeOptions) -> Result<Vec<u8>, Error> {
+    Ok(reveal_apng_with_flags(png, options)?.0)
+}
+
+/// Same as [`reveal_from_apng`](fn.reveal_from_apng.html), along with the flags from the header of the first frame
+pub(crate) fn reveal_apng_with_flags(png: &[u8], options: &DecodeOptions) -> Result<(Vec<u8>, u8), Error> {
+    if options.hidden_volume {
+        return Err(Error::UnsupportedCarrier("animated PNG images cannot hold a hidden volume".into()));
+    }
+    let animation = read_animation(png)?;
+    let mut payload = Vec::new();
+    let mut flags = None;
+    for frame in &animation.frames {
+        let layout = options.carrier_layout(layout(&animation, frame));
+        let bits = options.carrier_bits(layout);
+        if payload_capacity(frame.pixels.len(), bits, layout) > 0 {
+            let (header, part) = reveal_with(&frame.pixels, bits, layout, None, options.scatter)?;
+            flags.get_or_insert(header.flags);
+            payload.extend(part);
+        }
+    }
+    Ok((payload, flags.unwrap_or(0)))
+}
+
+/// Total number of bytes that can be hidden in the frames of the animated PNG image with the given bits setting.
+pub fn apng_capacity(png: &[u8], bits: Bits) -> Result<usize, Error> {
+    let animation = read_animation(png)?;
+    Ok(animation.frames.iter().map(|frame| payload_capacity(frame.pixels.len(), bits, layout(&animation, frame))).sum())
+}
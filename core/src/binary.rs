@@ -0,0 +1,832 @@
+use fmt::{Debug, Display, Formatter};
+use io::{Read, Write};
+use std::{fmt, io};
+use std::convert::TryFrom;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::container::check_flags;
+use crate::Error;
+
+/// Size of the integrity tag that follows the payload hidden by [`hide_bytes`](fn.hide_bytes.html)
+pub const INTEGRITY_TAG_SIZE: usize = 8;
+
+/// First bytes of everything hidden by this crate, so that the decoder can tell right away
+/// whether there is anything to decode at all
+pub const MAGIC: [u8; 2] = *b"SG";
+
+/// Version of the format of the hidden data, which goes right after the [`MAGIC`](constant.MAGIC.html).
+///
+/// The decoders refuse the data of the newer versions instead of misreading it.
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Size of the [`MAGIC`](constant.MAGIC.html) and the [version](constant.FORMAT_VERSION.html)
+pub const MAGIC_SIZE: usize = MAGIC.len() + 1;
+
+/// Checks the [`MAGIC`](constant.MAGIC.html) and the [version](constant.FORMAT_VERSION.html) at the start of the hidden bytes,
+/// returning the version.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when there is no magic,
+/// which means that nothing was hidden, or when the version is newer than this one.
+pub fn check_magic(bytes: &[u8]) -> Result<u8, Error> {
+    if bytes.len() < MAGIC_SIZE || bytes[..MAGIC.len()] != MAGIC {
+        return Err(Error::UnsupportedCarrier("there is no hidden data in it".into()));
+    }
+    match bytes[MAGIC.len()] {
+        version @ 1..=FORMAT_VERSION => Ok(version),
+        version => Err(Error::UnsupportedCarrier(format!(
+            "the data is hidden in the format version {}, but only up to {} is supported", version, FORMAT_VERSION,
+        ))),
+    }
+}
+
+/// HMAC-SHA256 of the length and the payload, truncated to [`INTEGRITY_TAG_SIZE`](constant.INTEGRITY_TAG_SIZE.html).
+///
+/// The key is fixed, so this only tells the damaged data from the intact one,
+/// anyone can compute a tag for the data they altered on purpose.
+fn integrity_tag(length: u64, payload: &[u8]) -> [u8; INTEGRITY_TAG_SIZE] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(b"steganographer integrity").expect("HMAC takes keys of any size");
+    mac.update(&length.to_be_bytes());
+    mac.update(payload);
+    let mut tag = [0; INTEGRITY_TAG_SIZE];
+    tag.copy_from_slice(&mac.finalize().into_bytes()[..INTEGRITY_TAG_SIZE]);
+    tag
+}
+
+/// Hides a slice of bytes along with the [magic](constant.MAGIC.html), its length and an integrity tag behind bytes from `carrier`.
+///
+/// Returns a vector of [`bits.carrier_len(MAGIC_SIZE + 1 + 8 + payload.len() + INTEGRITY_TAG_SIZE)`](enum.Bits.html#method.carrier_len)
+/// bytes which have their least significant bits replaced by the `payload` data prefixed with the magic,
+/// the [version](constant.FORMAT_VERSION.html), the `flags` and its 64-bit length, and followed by the [tag](constant.INTEGRITY_TAG_SIZE.html)
+/// that lets [`reveal_bytes`](fn.reveal_bytes.html) tell whether it got the data back intact.
+///
+/// The magic, the version and the flags are the same as the ones the [header](../container/struct.Header.html)
+/// of the images starts with, and the flags tell the [layers](../container/constant.FLAG_PASSWORD.html)
+/// the payload is sealed in the same way.
+///
+/// `bits` determine how many least significant bits are replaced.
+///
+/// # Errors
+/// Only lower-level IO errors might occur, depending solely on supplied carrier.
+///
+/// Most common and obvious one is an `UnexpectedEof` when there are not enough carrier bytes.
+///
+/// # Examples
+///
+/// ```
+/// # use std::io::Cursor;
+/// # use steganographer_core::binary::{hide_bytes, Bits};
+///
+/// let mut carrier = Cursor::new([0b11100000; 48]);
+/// let cloaked = hide_bytes(&[5, 14, 7, 3], carrier, Bits::Four, 0).unwrap();
+///
+/// assert_eq!(cloaked.len(), 48);
+/// assert_eq!(&cloaked[..32], &[0b11100101, 0b11100011,   // 'S' \
+///                              0b11100100, 0b11100111,   // 'G' | magic
+///                              0b11100000, 0b11100001,   // 1   | version
+///                              0b11100000, 0b11100000,   // 0   / flags
+///                              0b11100000, 0b11100000,   // 0 \
+///                              0b11100000, 0b11100000,   // 0 |
+///                              0b11100000, 0b11100000,   // 0 |
+///                              0b11100000, 0b11100000,   // 0 |
+///                              0b11100000, 0b11100000,   // 0 | u64 number of bytes
+///                              0b11100000, 0b11100000,   // 0 |
+///                              0b11100000, 0b11100000,   // 0 |
+///                              0b11100000, 0b11100100,   // 4 /
+///                              0b11100000, 0b11100101,   // 5
+///                              0b11100000, 0b11101110,   // 14
+///                              0b11100000, 0b11100111,   // 7
+///                              0b11100000, 0b11100011]); // 3
+///                                                        // and 8 bytes of the tag
+/// ```
+///
+pub fn hide_bytes(payload: &[u8], carrier: impl Read, bits: Bits, flags: u8) -> Result<Vec<u8>, Error> {
+    let length = payload.len() as u64;
+    let mut result = Vec::with_capacity(bits.carrier_len(MAGIC_SIZE + 1 + 8 + payload.len() + INTEGRITY_TAG_SIZE));
+    let mut writer = SteganographWriter::new(carrier, &mut result).bits(bits);
+
+    writer.write_all(&MAGIC)?;
+    writer.write_u8(FORMAT_VERSION)?;
+    writer.write_u8(flags)?;
+    writer.write_u64::<BigEndian>(length)?;
+    writer.write_all(payload)?;
+    writer.write_all(&integrity_tag(length, payload))?;
+    writer.flush()?;
+    drop(writer);
+    Ok(result)
+}
+
+/// Reveals a slice of bytes previously hidden by the [`hide_bytes`](fn.hide_bytes.html) function, along with its flags.
+///
+/// Extracts the magic, the flags, 8 bytes of `length`, then `length` bytes and the integrity tag from the `reader` input, reading
+/// `bits.carrier_len(MAGIC_SIZE + 1 + 8 + length + INTEGRITY_TAG_SIZE)` bytes from it.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when there is no magic
+/// or the data is of a newer format, see [`check_magic`](fn.check_magic.html) and
+/// [`check_flags`](../container/fn.check_flags.html),
+/// [`IntegrityFailure`](../enum.Error.html#variant.IntegrityFailure) when the tag does not match the data,
+/// which means that it was damaged or that nothing was hidden in the first place.
+///
+/// Otherwise only lower-level IO errors might occur, depending solely on supplied reader.
+/// Most common and obvious one is an `UnexpectedEof` when the length extracted after the magic
+/// bytes is greater than the number of bytes that can be read from the `reader`.
+///
+/// # Examples
+///
+/// ```
+/// # use std::io::Cursor;
+/// # use steganographer_core::binary::{hide_bytes, reveal_bytes, Bits};
+/// # use steganographer_core::Error;
+/// # use steganographer_core::container::FLAG_PASSWORD;
+/// let mut cloaked = hide_bytes(&[5, 14, 7, 3], Cursor::new([0b11100000; 48]), Bits::Four, FLAG_PASSWORD).unwrap();
+///
+/// assert_eq!(reveal_bytes(Cursor::new(&cloaked), Bits::Four).unwrap(), (vec![5, 14, 7, 3], FLAG_PASSWORD));
+///
+/// // flip a bit of the data
+/// cloaked[25] ^= 1;
+/// assert!(matches!(reveal_bytes(Cursor::new(&cloaked), Bits::Four), Err(Error::IntegrityFailure)));
+///
+/// // there is nothing hidden in the untouched carrier
+/// assert!(matches!(reveal_bytes(Cursor::new([0b11100000; 48]), Bits::Four), Err(Error::UnsupportedCarrier(_))));
+/// ```
+///
+pub fn reveal_bytes(reader: impl Read, bits: Bits) -> Result<(Vec<u8>, u8), Error> {
+    let mut reader = SteganographReader::new(reader).bits(bits);
+    let mut magic = [0; MAGIC_SIZE];
+    reader.read_exact(&mut magic)?;
+    check_magic(&magic)?;
+    let flags = check_flags(reader.read_u8()?)?;
+    let length = reader.read_u64::<BigEndian>()?;
+    // the length is not trusted with the allocation until there is that much to read
+    let mut result = Vec::new();
+    if (&mut reader).take(length).read_to_end(&mut result)? as u64 != length {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    let mut tag = [0; INTEGRITY_TAG_SIZE];
+    reader.read_exact(&mut tag)?;
+    if tag != integrity_tag(length, &result) {
+        return Err(Error::IntegrityFailure);
+    }
+    Ok((result, flags))
+}
+
+/// Replaces the least significant bits of the carrier bytes with the bits of the payload,
+/// most significant bits first, using [`bits.carrier_len(payload.len())`](enum.Bits.html#method.carrier_len)
+/// carrier bytes.
+///
+/// When `bits` is not a divisor of 8, the bits of a payload byte span the carrier bytes,
+/// and the last carrier byte might only be filled partially, from the top of its least significant bits,
+/// keeping the rest of them as they were.
+///
+/// This is the core that everything else in this crate is built on.
+///
+/// # Panics
+/// When there are less carrier bytes than that.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::binary::{pack_bits, Bits};
+/// let mut carrier = [0b11110000; 4];
+/// pack_bits(&[0b10_01_11_00], &mut carrier, Bits::Two);
+///
+/// assert_eq!(carrier, [0b11110010, 0b11110001, 0b11110011, 0b11110000]);
+///
+/// let mut carrier = [0b11110000; 3];
+/// pack_bits(&[0b101_110_01], &mut carrier, Bits::Three);
+///
+/// assert_eq!(carrier, [0b11110101, 0b11110110, 0b11110010]);
+/// ```
+///
+pub fn pack_bits(payload: &[u8], carrier: &mut [u8], bits: Bits) {
+    pack_bits_at(payload, carrier, bits, 0)
+}
+
+/// Same as [`pack_bits`](fn.pack_bits.html), but the first `skip` of the least significant bits
+/// of the first carrier byte are already taken, so the payload starts right below them,
+/// using `(skip + 8 * payload.len())` divided by the number of bits and rounded up carrier bytes.
+///
+/// This is what lets the payload written in pieces continue in the carrier byte the previous piece
+/// only filled partially.
+///
+/// # Panics
+/// When there are less carrier bytes than that.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::binary::{pack_bits_at, Bits};
+/// let mut carrier = [0b11110010, 0b11110000, 0b11110000, 0b11110000, 0b11110001];
+/// pack_bits_at(&[0b1_00_11_10_0], &mut carrier, Bits::Two, 1);
+///
+/// assert_eq!(carrier, [0b11110011, 0b11110000, 0b11110011, 0b11110010, 0b11110001]);
+/// ```
+///
+pub fn pack_bits_at(payload: &[u8], carrier: &mut [u8], bits: Bits, skip: usize) {
+    let total = skip + payload.len() * 8;
+    let len = total.div_ceil(bits.count());
+    assert!(carrier.len() >= len, "carrier is too small for the payload");
+
+    for (i, carrier_byte) in carrier[..len].iter_mut().enumerate() {
+        let start = (i * bits.count()).max(skip);
+        let count = ((i + 1) * bits.count()).min(total) - start;
+        let filled = start - i * bits.count();
+        *carrier_byte = place_bits(*carrier_byte, take_bits(payload, start - skip, count), count, filled, bits);
+    }
+}
+
+/// Collects the least significant bits of the carrier bytes into the payload bytes,
+/// the reverse of [`pack_bits`](fn.pack_bits.html).
+///
+/// # Panics
+/// When there are less than [`bits.carrier_len(payload.len())`](enum.Bits.html#method.carrier_len) carrier bytes.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::binary::{unpack_bits, Bits};
+/// let mut payload = [0];
+/// unpack_bits(&[0b11110010, 0b11110001, 0b11110011, 0b11110000], &mut payload, Bits::Two);
+///
+/// assert_eq!(payload, [0b10_01_11_00]);
+///
+/// unpack_bits(&[0b11110101, 0b11110110, 0b11110010], &mut payload, Bits::Three);
+///
+/// assert_eq!(payload, [0b101_110_01]);
+/// ```
+///
+pub fn unpack_bits(carrier: &[u8], payload: &mut [u8], bits: Bits) {
+    unpack_bits_at(carrier, payload, bits, 0)
+}
+
+/// Same as [`unpack_bits`](fn.unpack_bits.html), but skips the first `skip` of the least significant bits
+/// of the first carrier byte, the reverse of [`pack_bits_at`](fn.pack_bits_at.html).
+///
+/// # Panics
+/// When there are less carrier bytes than the payload with the skipped bits takes.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::binary::{unpack_bits_at, Bits};
+/// let mut payload = [0];
+/// unpack_bits_at(&[0b11110011, 0b11110000, 0b11110011, 0b11110010, 0b11110001], &mut payload, Bits::Two, 1);
+///
+/// assert_eq!(payload, [0b1_00_11_10_0]);
+/// ```
+///
+pub fn unpack_bits_at(carrier: &[u8], payload: &mut [u8], bits: Bits, skip: usize) {
+    let len = (skip + payload.len() * 8).div_ceil(bits.count());
+    assert!(carrier.len() >= len, "carrier is too small for the payload");
+
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte = (skip + i * 8..skip + i * 8 + 8).fold(0, |acc, at| {
+            acc << 1 | carrier[at / bits.count()] >> (bits.count() - 1 - at % bits.count()) & 1
+        });
+    }
+}
+
+/// Takes `count` bits of the payload starting at the bit offset `at`, most significant bits first
+fn take_bits(payload: &[u8], at: usize, count: usize) -> u8 {
+    (at..at + count).fold(0, |acc, bit| acc << 1 | payload[bit / 8] >> (7 - bit % 8) & 1)
+}
+
+/// Puts `count` bits of `value` into the least significant bits of the carrier byte,
+/// right below the `filled` ones that are already there
+fn place_bits(carrier_byte: u8, value: u8, count: usize, filled: usize, bits: Bits) -> u8 {
+    let shift = bits.count() - filled - count;
+    let mask = (((1u16 << count) - 1) << shift) as u8;
+    carrier_byte & !mask | value << shift & mask
+}
+
+/// A wrapper over some reader that extracts bytes from appropriate least significant bits
+///
+/// The bits left over in the last carrier byte read, when `bits` is not a divisor of 8,
+/// start the next byte that is read.
+///
+/// # Examples
+///
+/// ```
+/// # use std::io::{Read, Cursor};
+/// # use steganographer_core::binary::{SteganographReader, Bits};
+///
+/// let mut cloaked = Cursor::new([224, 227, 225, 226, 224, 225, 225, 227, 224, 224, 225, 226, 225, 227, 227, 227]);
+/// let mut reader = SteganographReader::new(cloaked).bits(Bits::Two);
+/// let mut buf = vec![0; 4];
+///
+/// reader.read_exact(&mut buf).unwrap();
+///
+/// assert_eq!(&buf, &[54, 23, 6, 127]);
+/// ```
+///
+pub struct SteganographReader<T: Read> {
+    source: T,
+    bits: Bits,
+    layout: Layout,
+    position: usize,
+    /// The partially read carrier byte and how many of its bits are already read
+    partial: Option<(u8, usize)>,
+}
+
+impl<T: Read> SteganographReader<T> {
+    /// Creates an instance of [SteganographReader](struct.SteganographReader.html)
+    /// with 1 bit of hidden data per image color byte.
+    pub fn new(source: T) -> Self {
+        SteganographReader {
+            source,
+            bits: Bits::default(),
+            layout: Layout::default(),
+            position: 0,
+            partial: None,
+        }
+    }
+
+    /// Configures the reader to use a specified number of bits
+    /// of hidden data per image color byte.
+    pub fn bits(self, bits: Bits) -> Self {
+        SteganographReader { bits, ..self }
+    }
+
+    /// Configures the reader to only use the source bytes that are usable according to the layout.
+    pub fn layout(self, layout: Layout) -> Self {
+        SteganographReader { layout, ..self }
+    }
+
+    fn read_usable(&mut self, buffer: &mut [u8]) -> Result<(), io::Error> {
+        if self.layout.is_dense() {
+            return self.source.read_exact(buffer);
+        }
+        let mut byte = [0];
+        for b in buffer.iter_mut() {
+            loop {
+                self.source.read_exact(&mut byte)?;
+                self.position += 1;
+                if self.layout.is_usable(self.position - 1) {
+                    break;
+                }
+            }
+            *b = byte[0];
+        }
+        Ok(())
+    }
+}
+
+impl<T: Read> Read for SteganographReader<T> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        let (held, skip) = self.partial.map_or((None, 0), |(carrier_byte, read)| (Some(carrier_byte), read));
+        let total = skip + buf.len() * 8;
+        let mut carrier = held.into_iter().collect::<Vec<_>>();
+        let start = carrier.len();
+        carrier.resize(total.div_ceil(self.bits.count()), 0);
+        self.read_usable(&mut carrier[start..])?;
+
+        unpack_bits_at(&carrier, buf, self.bits, skip);
+        self.partial = match total % self.bits.count() {
+            0 => None,
+            read => carrier.last().map(|&carrier_byte| (carrier_byte, read)),
+        };
+        Ok(buf.len())
+    }
+}
+
+/// A wrapper over some reader and some writer hides bytes into least significant bits of data from
+/// the reader and then and writes them all into the writer.
+///
+/// When `bits` is not a divisor of 8, the last carrier byte altered by a write might only be filled partially,
+/// so it is held back for the next write to continue filling it.
+/// It is written to the destination once the writer is flushed or dropped, the same as with a `BufWriter`,
+/// and the writes after the flush start with the next carrier byte.
+///
+/// # Examples
+///
+/// ```
+/// # use std::io::{Write, Cursor};
+/// # use steganographer_core::binary::{SteganographWriter, Bits};
+///
+/// let mut carrier = Cursor::new([224; 16]);
+/// let mut result = Vec::new();
+/// let mut writer = SteganographWriter::new(carrier, &mut result).bits(Bits::Two);
+///
+/// writer.write_all(&[54, 23, 6, 127]).unwrap();
+/// drop(writer);
+///
+/// assert_eq!(&result, &[224, 227, 225, 226, 224, 225, 225, 227, 224, 224, 225, 226, 225, 227, 227, 227]);
+/// ```
+///
+pub struct SteganographWriter<R: Read, W: Write> {
+    carrier: R,
+    destination: W,
+    bits: Bits,
+    layout: Layout,
+    position: usize,
+    /// The partially filled carrier byte and how many of its bits are filled
+    partial: Option<(u8, usize)>,
+}
+
+impl<R: Read, W: Write> SteganographWriter<R, W> {
+    /// Creates an instance of [SteganographWriter](struct.SteganographWriter.html)
+    /// that expects 1 bit of hidden data per image color byte.
+    pub fn new(carrier: R, destination: W) -> SteganographWriter<R, W> {
+        SteganographWriter {
+            carrier,
+            destination,
+            bits: Bits::default(),
+            layout: Layout::default(),
+            position: 0,
+            partial: None,
+        }
+    }
+
+    /// Configures the writer to expect a specified number of bits
+    /// of hidden data per image color byte.
+    pub fn bits(mut self, bits: Bits) -> Self {
+        self.bits = bits;
+        self
+    }
+
+    /// Configures the writer to only alter the carrier bytes that are usable according to the layout,
+    /// the rest of them are written to the destination untouched.
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Reads carrier bytes until there are `count` usable ones among them,
+    /// returning all of the bytes read and the indices of the usable ones
+    fn read_usable(&mut self, count: usize) -> Result<(Vec<u8>, Vec<usize>), io::Error> {
+        if self.layout.is_dense() {
+            let mut raw = vec![0; count];
+            self.carrier.read_exact(&mut raw)?;
+            return Ok((raw, (0..count).collect()));
+        }
+        let mut raw = Vec::with_capacity(count);
+        let mut usable = Vec::with_capacity(count);
+        let mut byte = [0];
+        while usable.len() < count {
+            self.carrier.read_exact(&mut byte)?;
+            if self.layout.is_usable(self.position) {
+                usable.push(raw.len());
+            }
+            self.position += 1;
+            raw.push(byte[0]);
+        }
+        Ok((raw, usable))
+    }
+}
+
+impl<R: Read, W: Write> Write for SteganographWriter<R, W> {
+    fn write(&mut self, payload: &[u8]) -> Result<usize, io::Error> {
+        let (held, skip) = self.partial.map_or((None, 0), |(carrier_byte, filled)| (Some(carrier_byte), filled));
+        let total = skip + payload.len() * 8;
+        let (mut raw, mut usable) = self.read_usable(total.div_ceil(self.bits.count()) - held.iter().len())?;
+        if let Some(carrier_byte) = held {
+            raw.insert(0, carrier_byte);
+            usable = std::iter::once(0).chain(usable.into_iter().map(|i| i + 1)).collect();
+        }
+
+        let mut carrier = usable.iter().map(|&i| raw[i]).collect::<Vec<_>>();
+        pack_bits_at(payload, &mut carrier, self.bits, skip);
+        usable.iter().zip(carrier).for_each(|(&i, carrier_byte)| raw[i] = carrier_byte);
+        self.partial = match total % self.bits.count() {
+            0 => None,
+            // the last usable byte is the last one read
+            filled => raw.pop().map(|carrier_byte| (carrier_byte, filled)),
+        };
+        self.destination.write_all(&raw)?;
+        Ok(payload.len())
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        if let Some((carrier_byte, _)) = self.partial.take() {
+            self.destination.write_all(&[carrier_byte])?;
+        }
+        self.destination.flush()
+    }
+}
+
+impl<R: Read, W: Write> Drop for SteganographWriter<R, W> {
+    fn drop(&mut self) {
+        // same as BufWriter, there is nobody to report the errors to
+        let _ = self.flush();
+    }
+}
+
+/// A enum that represents the number of least significant bits to be replaced with the payload data.
+///
+/// When it is not a divisor of 8, the bits of the payload bytes span the carrier bytes.
+///
+/// # Examples
+///
+/// ```
+/// # use std::convert::TryFrom;
+/// # use std::io::Cursor;
+/// # use steganographer_core::binary::{hide_bytes, reveal_bytes, Bits};
+/// let bits = Bits::try_from(5).unwrap();
+/// let cloaked = hide_bytes(b"hi, world!", Cursor::new([0b10101010; 48]), bits, 0).unwrap();
+///
+/// // 30 bytes with the magic, the flags, the length and the tag take 240 bits, which is exactly 48 carrier bytes
+/// assert_eq!(cloaked.len(), 48);
+/// assert_eq!(reveal_bytes(Cursor::new(cloaked), bits).unwrap().0, b"hi, world!");
+/// assert!(Bits::try_from(9).is_err());
+/// ```
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum Bits {
+    /// Use only one least significant bit to store hidden data
+    #[default]
+    One = 1,
+    /// Use two least significant bits to store hidden data
+    Two = 2,
+    /// Use three least significant bits to store hidden data
+    Three = 3,
+    /// Use a whole least significant half of the byte to store hidden data
+    Four = 4,
+    /// Use five least significant bits to store hidden data
+    Five = 5,
+    /// Use six least significant bits to store hidden data
+    Six = 6,
+    /// Use seven least significant bits to store hidden data
+    Seven = 7,
+    /// Replace the carrier bytes with the hidden data altogether
+    Eight = 8,
+}
+
+impl Bits {
+    /// All of the possible values, from one bit to eight.
+    pub const ALL: [Bits; 8] =
+        [Bits::One, Bits::Two, Bits::Three, Bits::Four, Bits::Five, Bits::Six, Bits::Seven, Bits::Eight];
+
+    /// Returns the mask value where least significant bits are ones.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::binary::Bits;
+    /// assert_eq!(Bits::One.mask(), 0b00000001);
+    /// assert_eq!(Bits::Two.mask(), 0b00000011);
+    /// assert_eq!(Bits::Four.mask(), 0b00001111);
+    /// assert_eq!(Bits::Eight.mask(), 0b11111111);
+    /// ```
+    pub const fn mask(&self) -> u8 {
+        ((1u16 << *self as u8) - 1) as u8
+    }
+
+    /// Returns the number of least significant bits replaced in each byte.
+    pub const fn count(&self) -> usize {
+        *self as usize
+    }
+
+    /// Returns how many carrier bytes are needed to hide `len` bytes of data
+    /// given that this many least significant bits per byte are replaced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::binary::Bits;
+    /// assert_eq!(Bits::One.carrier_len(1), 8);
+    /// assert_eq!(Bits::Two.carrier_len(1), 4);
+    /// assert_eq!(Bits::Three.carrier_len(1), 3);
+    /// assert_eq!(Bits::Three.carrier_len(3), 8);
+    /// assert_eq!(Bits::Four.carrier_len(1), 2);
+    /// ```
+    pub const fn carrier_len(&self, len: usize) -> usize {
+        (len * 8).div_ceil(*self as usize)
+    }
+
+    /// Returns how many whole bytes of data can be hidden in `len` carrier bytes,
+    /// the reverse of [`carrier_len`](#method.carrier_len).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::binary::Bits;
+    /// assert_eq!(Bits::Two.payload_len(9), 2);
+    /// assert_eq!(Bits::Three.payload_len(8), 3);
+    /// ```
+    pub const fn payload_len(&self, len: usize) -> usize {
+        len * *self as usize / 8
+    }
+
+    /// Returns how many carrier bytes hide a whole number of data bytes, the step at which
+    /// the data bytes start at the beginning of a carrier byte again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::binary::Bits;
+    /// assert_eq!(Bits::Two.period(), 4);
+    /// assert_eq!(Bits::Six.period(), 4);
+    /// assert_eq!(Bits::Seven.period(), 8);
+    /// ```
+    pub const fn period(&self) -> usize {
+        8 >> (*self as u8).trailing_zeros()
+    }
+}
+
+impl TryFrom<u8> for Bits {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1..=8 => Ok(Bits::ALL[value as usize - 1]),
+            x => Err(Error::WrongBits(x))
+        }
+    }
+}
+
+impl From<Bits> for u8 {
+    fn from(bits: Bits) -> Self {
+        bits as u8
+    }
+}
+
+impl Debug for Bits {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        let name = ["One", "Two", "Three", "Four", "Five", "Six", "Seven", "Eight"][*self as usize - 1];
+        write!(f, "Bits::{}", name)
+    }
+}
+
+impl Display for Bits {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{} bits", *self as u8)
+    }
+}
+
+/// Byte order of the multi-byte samples of the carrier.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Endianness {
+    /// The least significant byte of each sample comes first
+    Little,
+    /// The most significant byte of each sample comes first
+    Big,
+}
+
+impl Endianness {
+    /// Byte order of the target platform, which is how decoded pixels are stored in memory.
+    pub const fn native() -> Self {
+        if cfg!(target_endian = "little") { Endianness::Little } else { Endianness::Big }
+    }
+}
+
+impl Default for Endianness {
+    fn default() -> Self {
+        Endianness::native()
+    }
+}
+
+/// Describes how the samples are laid out in the carrier bytes and which of them may be altered.
+///
+/// By default the carrier is treated as undifferentiated bytes, all of which are usable.
+/// For images, each pixel consists of `channels` samples of `sample_size` bytes each.
+/// Only the least significant byte of each multi-byte sample is used, so that 16-bit images
+/// or 24-bit audio have their low bits altered and not the high ones. The samples are expected
+/// in the native byte order, as this is how the decoded pixels are stored in memory,
+/// unless configured otherwise with [`endianness`](#method.endianness).
+///
+/// # Examples
+///
+/// ```
+/// # use std::io::{Write, Cursor};
+/// # use steganographer_core::binary::{SteganographWriter, Bits, Endianness, Layout};
+///
+/// let rgba = Layout::new(4, 1, true).skip_alpha();
+/// let mut result = Vec::new();
+/// let mut writer = SteganographWriter::new(Cursor::new([0; 16]), &mut result).bits(Bits::Two).layout(rgba);
+///
+/// writer.write_all(&[0b11111111, 0b11111111]).unwrap();
+/// drop(writer);
+///
+/// // the alpha bytes are left alone
+/// assert_eq!(&result, &[3, 3, 3, 0, 3, 3, 3, 0, 3, 3]);
+/// assert_eq!(rgba.usable_bytes(16), 12);
+///
+/// // big-endian 16-bit samples, like in the PNG files themselves
+/// let samples = Layout::new(1, 2, false).endianness(Endianness::Big);
+/// assert_eq!(samples.usable(&[0x12, 0x34, 0x56, 0x78]), [0x34, 0x78]);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Layout {
+    channels: u8,
+    sample_size: u8,
+    alpha: bool,
+    mask: u8,
+    endianness: Endianness,
+    width: usize,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout::new(1, 1, false)
+    }
+}
+
+impl Layout {
+    /// Creates a layout of pixels with `channels` samples of `sample_size` bytes each,
+    /// where the last channel is alpha if `alpha` is set. All of the channels are usable.
+    pub fn new(channels: u8, sample_size: u8, alpha: bool) -> Self {
+        let channels = channels.clamp(1, 8);
+        let mask = ((1u16 << channels) - 1) as u8;
+        Layout { channels, sample_size: sample_size.max(1), alpha, mask, endianness: Endianness::native(), width: 0 }
+    }
+
+    /// Configures the number of pixels in each row, for the things that care about their neighbourhood.
+    pub fn width(self, width: usize) -> Self {
+        Layout { width, ..self }
+    }
+
+    /// Number of pixels in each row, `None` when it was not configured.
+    pub fn pixels_per_row(&self) -> Option<usize> {
+        Some(self.width).filter(|&width| width > 0)
+    }
+
+    /// Configures the byte order of the samples.
+    pub fn endianness(self, endianness: Endianness) -> Self {
+        Layout { endianness, ..self }
+    }
+
+    /// Number of samples in each pixel.
+    pub fn channels(&self) -> u8 {
+        self.channels
+    }
+
+    /// Number of bytes in each sample.
+    pub fn sample_size(&self) -> u8 {
+        self.sample_size
+    }
+
+    /// Number of bytes in each pixel.
+    pub fn pixel_size(&self) -> usize {
+        self.channels as usize * self.sample_size as usize
+    }
+
+    /// Whether the last channel of each pixel is alpha.
+    pub fn has_alpha(&self) -> bool {
+        self.alpha
+    }
+
+    /// Bit mask of the usable channels, where the lowest bit is the first channel.
+    pub fn mask(&self) -> u8 {
+        self.mask
+    }
+
+    /// Excludes the alpha channel, if there is one, from the usable channels.
+    pub fn skip_alpha(self) -> Self {
+        if self.alpha {
+            Layout { mask: self.mask & !(1 << (self.channels - 1)), ..self }
+        } else {
+            self
+        }
+    }
+
+    /// Restricts the usable channels to the ones set in `mask`, where the lowest bit is the first channel.
+    pub fn only_channels(self, mask: u8) -> Self {
+        Layout { mask: self.mask & mask, ..self }
+    }
+
+    fn is_dense(&self) -> bool {
+        self.pixel_size() == 1 && self.mask == 1
+    }
+
+    /// Returns whether the carrier byte at the given index may be altered.
+    pub fn is_usable(&self, index: usize) -> bool {
+        let sample_size = self.sample_size as usize;
+        let low_byte = match self.endianness {
+            Endianness::Little => 0,
+            Endianness::Big => sample_size - 1,
+        };
+        let channel = index / sample_size % self.channels as usize;
+        index % sample_size == low_byte && self.mask >> channel & 1 == 1
+    }
+
+    /// Returns how many of the first `len` carrier bytes may be altered.
+    pub fn usable_bytes(&self, len: usize) -> usize {
+        let period = self.pixel_size();
+        let per_period = (0..period).filter(|&i| self.is_usable(i)).count();
+        len / period * per_period + (0..len % period).filter(|&i| self.is_usable(i)).count()
+    }
+
+    /// Returns how many of the first or the last of `len` carrier bytes, rounded up to whole pixels,
+    /// are needed to have at least `count` usable bytes among them.
+    ///
+    /// As the result is a whole number of pixels, the layout applies to those bytes on their own as well.
+    pub fn pixels_len(&self, len: usize, count: usize) -> usize {
+        let period = self.pixel_size();
+        let per_period = (0..period).filter(|&i| self.is_usable(i)).count().max(1);
+        let pixels = count.div_ceil(per_period);
+        (pixels * period).min(len / period * period)
+    }
+
+    /// Returns how many bytes can be hidden in `len` carrier bytes with the given bits setting.
+    pub fn capacity(&self, len: usize, bits: Bits) -> usize {
+        bits.payload_len(self.usable_bytes(len))
+    }
+
+    /// Collects the usable bytes of the carrier.
+    pub fn usable(&self, carrier: &[u8]) -> Vec<u8> {
+        carrier.iter().enumerate().filter(|&(i, _)| self.is_usable(i)).map(|(_, &b)| b).collect()
+    }
+}
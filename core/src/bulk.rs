@@ -0,0 +1,232 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::ImageFormat;
+
+use crate::{chunks, decode_from_file, inspect_image, output_format, wipe_file, with_decoded_image, DecodeOptions, EncodeOptions, Error, OutputFormat};
+
+/// What came out of one of the files the data was looked for in.
+#[derive(Debug)]
+pub struct Extraction {
+    /// The file the data was looked for in
+    pub source: PathBuf,
+    /// The file the data was extracted to, or why that did not work
+    pub result: Result<PathBuf, Error>,
+}
+
+/// Tries to extract the data from the `source` file into the `output` file, creating its directory if needed.
+pub fn extract(source: PathBuf, output: PathBuf, options: &DecodeOptions) -> Extraction {
+    let result = match output.parent() {
+        Some(parent) => fs::create_dir_all(parent).map_err(Error::from),
+        None => Ok(()),
+    };
+    let result = result
+        .and_then(|_| decode_from_file(source.clone(), &options.clone().output(&output)))
+        .map(|_| output);
+    Extraction { source, result }
+}
+
+/// A file with some hidden data that contains the pattern looked for by [`grep`](fn.grep.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Found {
+    /// The file with the hidden data
+    pub source: PathBuf,
+    /// Index of the decoding settings the data was extracted with, among the ones given to `grep`
+    pub attempt: usize,
+    /// Offsets of every occurrence of the pattern in the hidden data
+    pub offsets: Vec<usize>,
+}
+
+fn collect_files(dir: &Path, skip: Option<&Path>, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        if skip == Some(&path.canonicalize()?) {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            collect_files(&path, skip, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Tries to extract the data from every file under the `path` directory, or from the `path` file itself,
+/// writing the data to a file with the same name, but without the extension, in the same subdirectory
+/// of the `out` directory.
+///
+/// The files are visited in the order of their names and the `out` directory is skipped if it is under `path`.
+/// The files that contained no data, or were not carriers at all, have the reason in their
+/// [`result`](struct.Extraction.html#structfield.result).
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::{encode_image, DecodeOptions, EncodeOptions};
+/// # use steganographer_core::bulk::decode_all;
+/// # use image::{ColorType, ImageEncoder};
+/// # use image::codecs::png::PngEncoder;
+/// let dir = std::env::temp_dir().join("steganographer-decode-all");
+/// let _ = std::fs::remove_dir_all(&dir);
+/// std::fs::create_dir_all(dir.join("inbox/nested")).unwrap();
+/// let mut cover = Vec::new();
+/// PngEncoder::new(&mut cover).write_image(&[0; 32 * 32 * 3], 32, 32, ColorType::Rgb8).unwrap();
+/// for (name, payload) in [("inbox/first.png", "one"), ("inbox/nested/second.png", "two")] {
+///     let output = std::fs::File::create(dir.join(name)).unwrap();
+///     encode_image(&cover[..], payload.as_bytes(), output, &EncodeOptions::new()).unwrap();
+/// }
+/// std::fs::write(dir.join("inbox/notes.txt"), b"not an image").unwrap();
+///
+/// let extractions = decode_all(&dir.join("inbox"), &dir.join("out"), &DecodeOptions::new()).unwrap();
+/// let decoded = extractions.iter()
+///     .filter_map(|extraction| extraction.result.as_ref().ok())
+///     .map(|output| std::fs::read_to_string(output).unwrap())
+///     .collect::<Vec<_>>();
+/// assert_eq!(decoded, ["one", "two"]);
+/// assert_eq!(extractions.len(), 3);
+/// assert!(extractions[2].source.ends_with("notes.txt") && extractions[2].result.is_err());
+/// ```
+pub fn decode_all(path: &Path, out: &Path, options: &DecodeOptions) -> Result<Vec<Extraction>, Error> {
+    fs::create_dir_all(out)?;
+    let out = out.canonicalize()?;
+
+    let mut files = Vec::new();
+    if path.is_dir() {
+        collect_files(path, Some(&out), &mut files)?;
+    } else {
+        files.push(path.to_path_buf());
+    }
+
+    Ok(files.into_iter()
+        .map(|source| {
+            let relative = source.strip_prefix(path).unwrap_or(&source);
+            let output = out.join(relative.with_extension(""));
+            extract(source, output, options)
+        })
+        .collect())
+}
+
+/// Looks for the `pattern` in the data hidden in every PNG image among the `paths` and under them,
+/// trying each of the `attempts` settings in turn until one of them finds it.
+/// The data is decrypted first with the [password](../struct.DecodeOptions.html#method.password) of the attempt, if it has one.
+///
+/// The images are visited in the order of their names, the files that are not PNG images
+/// and the ones that have nothing hidden in them are skipped.
+pub fn grep(paths: &[PathBuf], pattern: &[u8], attempts: &[DecodeOptions]) -> Result<Vec<Found>, Error> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            collect_files(path, None, &mut files)?;
+        } else {
+            files.push(path.clone());
+        }
+    }
+
+    let mut found = Vec::new();
+    for source in files {
+        let image = fs::read(&source)?;
+        if !image.starts_with(b"\x89PNG") {
+            continue;
+        }
+        for (attempt, options) in attempts.iter().enumerate() {
+            let offsets = with_decoded_image(&image[..], options, |data, _| {
+                data.windows(pattern.len().max(1))
+                    .enumerate()
+                    .filter(|(_, window)| *window == pattern)
+                    .map(|(offset, _)| offset)
+                    .collect::<Vec<_>>()
+            });
+            let offsets = match offsets {
+                Ok(offsets) => offsets,
+                Err(_) => continue,
+            };
+            if !offsets.is_empty() {
+                found.push(Found { source, attempt, offsets });
+                break;
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// What [`sanitize`](fn.sanitize.html) did to one of the images.
+#[derive(Debug)]
+pub struct Sanitized {
+    /// The image that was sanitized
+    pub source: PathBuf,
+    /// What was removed from it, or why that did not work
+    pub result: Result<Removed, Error>,
+}
+
+/// What was removed from an image by [`sanitize`](fn.sanitize.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Removed {
+    /// The sanitized image
+    pub output: PathBuf,
+    /// Whether there was a [header](../fn.inspect_image.html) of the data hidden by this crate in the image
+    pub hidden_data: bool,
+    /// Types of the ancillary PNG chunks that were dropped, see [`ancillary_chunks`](../chunks/fn.ancillary_chunks.html)
+    pub chunks: Vec<String>,
+}
+
+/// Wipes the image into the output file, creating its directory if needed, and reports what was removed from it
+fn sanitize_file(source: &Path, output: PathBuf, options: &EncodeOptions) -> Result<Removed, Error> {
+    let image = fs::read(source)?;
+    let format = image::guess_format(&image)
+        .map_err(|_| Error::UnsupportedCarrier("the image format is not recognized".into()))?;
+    let written = output_format(format, options);
+    let output = match (format, written) {
+        (ImageFormat::Png, OutputFormat::Png) | (ImageFormat::Bmp, OutputFormat::Bmp) | (ImageFormat::Tiff, OutputFormat::Tiff) => output,
+        _ => output.with_extension(written.extension()),
+    };
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let hidden_data = inspect_image(&image[..], &DecodeOptions::new()).is_ok();
+    wipe_file(source.to_path_buf(), output.clone(), options)?;
+    Ok(Removed { output, hidden_data, chunks: chunks::ancillary_chunks(&image) })
+}
+
+/// [Wipes](../fn.wipe_image.html) every image among the `paths` and under them, writing it to the same
+/// subdirectory of the `out` directory, and reports for each of them whether it had some data hidden by this crate
+/// and which of its metadata chunks were dropped.
+///
+/// The least significant bits are overwritten with the [bits](../struct.EncodeOptions.html#method.bits) and the
+/// [filler](../struct.EncodeOptions.html#method.filler) of the options. The images are written in their own format
+/// when it can be written and as PNG otherwise, with the extension changed to match.
+/// The files are visited in the order of their names and the `out` directory is skipped if it is under
+/// one of the `paths`. The files that are not images have the reason in their
+/// [`result`](struct.Sanitized.html#structfield.result).
+pub fn sanitize(paths: &[PathBuf], out: &Path, options: &EncodeOptions) -> Result<Vec<Sanitized>, Error> {
+    fs::create_dir_all(out)?;
+    let out = out.canonicalize()?;
+
+    let mut sources = Vec::new();
+    for path in paths {
+        let mut files = Vec::new();
+        let root = match path.is_dir() {
+            true => {
+                collect_files(path, Some(&out), &mut files)?;
+                path.as_path()
+            },
+            false => {
+                files.push(path.clone());
+                path.parent().unwrap_or(path)
+            },
+        };
+        sources.extend(files.into_iter().map(|file| {
+            let relative = file.strip_prefix(root).unwrap_or(&file).to_path_buf();
+            (file, relative)
+        }));
+    }
+
+    Ok(sources.into_iter()
+        .map(|(source, relative)| {
+            let result = sanitize_file(&source, out.join(relative), options);
+            Sanitized { source, result }
+        })
+        .collect())
+}
@@ -0,0 +1,123 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::Error;
+
+/// Magic at the start of the bundles, so that they can be told apart from any other data
+pub const MAGIC: [u8; 4] = *b"SGB\x01";
+
+/// Kind of the bundle entries that are files
+const FILE: u8 = 0;
+
+/// Kind of the bundle entries that are directories, which are stored so that the empty ones are unpacked too
+const DIRECTORY: u8 = 1;
+
+/// Returns whether the data is a bundle made by [`pack`](fn.pack.html).
+pub fn is_bundle(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC)
+}
+
+/// Packs all of the files and directories in the directory, other than the symbolic links, into a single
+/// bundle with their paths relative to it, in the order of their names so that the same tree always makes the same bundle.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::bundle::{pack, unpack};
+/// let dir = std::env::temp_dir().join("steganographer-bundle");
+/// std::fs::create_dir_all(dir.join("notes/empty")).unwrap();
+/// std::fs::write(dir.join("notes/plans.txt"), b"the plans").unwrap();
+/// std::fs::write(dir.join("keys.txt"), b"hunter2").unwrap();
+///
+/// let bundle = pack(&dir).unwrap();
+/// let unpacked = std::env::temp_dir().join("steganographer-unbundled");
+/// let files = unpack(&bundle, &unpacked, true).unwrap();
+///
+/// assert_eq!(files.len(), 2);
+/// assert_eq!(std::fs::read(unpacked.join("notes/plans.txt")).unwrap(), b"the plans");
+/// assert!(unpacked.join("notes/empty").is_dir());
+/// ```
+pub fn pack(dir: impl AsRef<Path>) -> Result<Vec<u8>, Error> {
+    let mut bundle = MAGIC.to_vec();
+    pack_into(dir.as_ref(), "", &mut bundle)?;
+    Ok(bundle)
+}
+
+fn pack_into(dir: &Path, prefix: &str, bundle: &mut Vec<u8>) -> Result<(), Error> {
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name().into_string()
+            .map_err(|name| Error::UnsupportedCarrier(format!("the name of {:?} is not UTF-8", name)))?;
+        let path = format!("{}{}", prefix, name);
+        let kind = entry.file_type()?;
+        if kind.is_dir() {
+            write_entry(bundle, DIRECTORY, &path, &[])?;
+            pack_into(&entry.path(), &format!("{}/", path), bundle)?;
+        } else if kind.is_file() {
+            write_entry(bundle, FILE, &path, &fs::read(entry.path())?)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_entry(bundle: &mut Vec<u8>, kind: u8, path: &str, contents: &[u8]) -> Result<(), Error> {
+    bundle.write_u8(kind)?;
+    bundle.write_u16::<BigEndian>(path.len() as u16)?;
+    bundle.write_all(path.as_bytes())?;
+    bundle.write_u64::<BigEndian>(contents.len() as u64)?;
+    bundle.write_all(contents)?;
+    Ok(())
+}
+
+/// Unpacks the bundle made by [`pack`](fn.pack.html) into the directory, creating it when it does not exist,
+/// and returns the paths of the files written.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when the data is not a bundle,
+/// or when some of its paths lead out of the directory, in which case nothing is written at all,
+/// and an `AlreadyExists` IO error when some of the files exist and they are not to be replaced.
+pub fn unpack(bundle: &[u8], dir: impl AsRef<Path>, replace: bool) -> Result<Vec<PathBuf>, Error> {
+    if !is_bundle(bundle) {
+        return Err(Error::UnsupportedCarrier("the data is not a bundle of a directory".into()));
+    }
+    let mut from = &bundle[MAGIC.len()..];
+    let mut entries = Vec::new();
+    while !from.is_empty() {
+        let kind = from.read_u8()?;
+        if kind != FILE && kind != DIRECTORY {
+            return Err(Error::UnsupportedCarrier(format!("the bundle has an entry of an unknown kind {}", kind)));
+        }
+        let mut path = vec![0; from.read_u16::<BigEndian>()? as usize];
+        from.read_exact(&mut path)?;
+        let path = String::from_utf8(path).map_err(|_| Error::UnsupportedCarrier("a path in the bundle is not UTF-8".into()))?;
+        let len = from.read_u64::<BigEndian>()? as usize;
+        if len > from.len() {
+            return Err(Error::UnsupportedCarrier(format!("the bundle ends in the middle of {}", path)));
+        }
+        let safe = !path.is_empty() && Path::new(&path).components().all(|component| matches!(component, Component::Normal(_)));
+        if !safe {
+            return Err(Error::UnsupportedCarrier(format!("the path {:?} in the bundle leads out of its directory", path)));
+        }
+        entries.push((kind, dir.as_ref().join(path), &from[..len]));
+        from = &from[len..];
+    }
+    fs::create_dir_all(&dir)?;
+    let mut files = Vec::new();
+    for (kind, path, contents) in entries {
+        match kind {
+            DIRECTORY => fs::create_dir_all(&path)?,
+            _ => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::OpenOptions::new().write(true).truncate(true).create(true).create_new(!replace).open(&path)?.write_all(contents)?;
+                files.push(path);
+            },
+        }
+    }
+    Ok(files)
+}
@@ -0,0 +1,124 @@
+use rand_core::{CryptoRng, OsRng, RngCore};
+
+use crate::binary::{Bits, Layout};
+use crate::wetpaper::SplitMix;
+
+/// Side of the square regions of pixels that have their own statistics in the model
+pub const REGION_SIZE: usize = 32;
+
+/// What happens to the capacity of the carrier that is left after the payload.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Filler {
+    /// The carrier bytes keep their original bits
+    #[default]
+    Keep,
+    /// The bits are sampled from a model of the carrier's own least significant bits,
+    /// see [`modeled_chaff`](fn.modeled_chaff.html)
+    Modeled,
+    /// The bits are cryptographically random, see [`random_chaff`](fn.random_chaff.html)
+    Random,
+}
+
+/// Replaces the least significant bits of every usable carrier byte with values sampled from
+/// how often each of them occurs in the same channel of the same region of the carrier,
+/// given the bit right above them, which is left as it is.
+///
+/// Hiding the payload afterwards leaves the rest of the capacity filled with bits that have the same
+/// per-region bias and the same correlation with the next bit plane as the noise of the carrier,
+/// so there is no boundary between the altered and the original bits where the payload ends.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::binary::{Bits, Layout};
+/// # use steganographer_core::chaff::modeled_chaff;
+/// let layout = Layout::new(3, 1, false).width(4);
+/// let mut carrier = [0b0110; 48];
+///
+/// // every low value of the model is 0b10, so that is all that can be sampled
+/// modeled_chaff(&mut carrier, Bits::Two, layout);
+/// assert_eq!(carrier, [0b0110; 48]);
+///
+/// let mut carrier = (0..=255).collect::<Vec<u8>>();
+/// modeled_chaff(&mut carrier, Bits::Two, layout);
+/// assert!(carrier.iter().zip(0..=255).all(|(&chaff, original)| chaff >> 2 == original >> 2));
+/// ```
+///
+pub fn modeled_chaff(carrier: &mut [u8], bits: Bits, layout: Layout) {
+    modeled_chaff_with_rng(carrier, bits, layout, &mut OsRng)
+}
+
+/// Same as [`modeled_chaff`](fn.modeled_chaff.html), but the samples are seeded from the given generator
+pub(crate) fn modeled_chaff_with_rng(carrier: &mut [u8], bits: Bits, layout: Layout, rng: &mut impl RngCore) {
+    let pixel_size = layout.pixel_size();
+    let width = layout.pixels_per_row().unwrap_or(carrier.len() / pixel_size).max(1);
+    let regions_per_row = width.div_ceil(REGION_SIZE);
+    let channels = layout.channels() as usize;
+    let values = bits.mask() as usize + 1;
+
+    // the histogram of the low values for every region, channel and the value of the next bit
+    let context = |index: usize, byte: u8| {
+        let pixel = index / pixel_size;
+        let region = pixel / width / REGION_SIZE * regions_per_row + pixel % width / REGION_SIZE;
+        let channel = index / layout.sample_size() as usize % channels;
+        ((region * channels + channel) * 2 + (byte as usize >> bits.count() & 1)) * values
+    };
+    let mut histograms = Vec::new();
+    for (index, &byte) in carrier.iter().enumerate().filter(|&(i, _)| layout.is_usable(i)) {
+        let slot = context(index, byte) + (byte & bits.mask()) as usize;
+        if slot >= histograms.len() {
+            histograms.resize(slot + values, 0u32);
+        }
+        histograms[slot] += 1;
+    }
+
+    let mut random = SplitMix::new(rng.next_u64());
+    for (index, byte) in carrier.iter_mut().enumerate().filter(|&(i, _)| layout.is_usable(i)) {
+        let histogram = &histograms[context(index, *byte)..][..values];
+        let mut sample = random.next_u64() % histogram.iter().map(|&count| u64::from(count)).sum::<u64>();
+        let value = histogram.iter()
+            .position(|&count| {
+                let found = sample < u64::from(count);
+                sample = sample.saturating_sub(u64::from(count));
+                found
+            })
+            .unwrap_or(0);
+        *byte = *byte & !bits.mask() | value as u8;
+    }
+}
+
+/// Replaces the least significant bits of every usable carrier byte with cryptographically random ones.
+///
+/// Hiding a payload that looks random itself, such as an [encrypted](../crypto/index.html) or a
+/// [whitened](../whitening/index.html) one, afterwards leaves the whole plane uniformly random,
+/// so there is no boundary where the payload ends, and a carrier with a [hidden volume](../struct.EncodeOptions.html#method.hidden_volume)
+/// cannot be told from one without it.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::binary::{Bits, Layout};
+/// # use steganographer_core::chaff::random_chaff;
+/// let mut carrier = vec![0b1010_0000; 4096];
+/// random_chaff(&mut carrier, Bits::Two, Layout::new(4, 1, true).skip_alpha());
+///
+/// // the alpha channel and the high bits are left alone
+/// assert!(carrier.iter().all(|&byte| byte >> 2 == 0b10_1000));
+/// assert!(carrier.chunks(4).all(|pixel| pixel[3] == 0b1010_0000));
+/// assert!(carrier.iter().filter(|&&byte| byte & 0b11 == 0b11).count() > 500);
+/// ```
+///
+pub fn random_chaff(carrier: &mut [u8], bits: Bits, layout: Layout) {
+    random_chaff_with_rng(carrier, bits, layout, &mut OsRng)
+}
+
+/// Same as [`random_chaff`](fn.random_chaff.html), but the bits come from the given generator
+pub(crate) fn random_chaff_with_rng(carrier: &mut [u8], bits: Bits, layout: Layout, rng: &mut (impl RngCore + CryptoRng)) {
+    let mut random = vec![0; carrier.len()];
+    rng.fill_bytes(&mut random);
+    for (index, (byte, random)) in carrier.iter_mut().zip(random).enumerate() {
+        if layout.is_usable(index) {
+            *byte = *byte & !bits.mask() | random & bits.mask();
+        }
+    }
+}
@@ -0,0 +1,147 @@
+const SIGNATURE_SIZE: usize = 8;
+
+/// Chunks of the animation, which belong to the frames and are never copied to a still image
+const ANIMATION: [&[u8; 4]; 3] = [b"acTL", b"fcTL", b"fdAT"];
+
+/// Chunks that tell how the samples map to the colors, which stay true when the pixel format is converted,
+/// even though they are not marked safe to copy
+const COLOR_SPACE: [&[u8; 4]; 7] = [b"iCCP", b"sRGB", b"gAMA", b"cHRM", b"cICP", b"mDCV", b"cLLI"];
+
+/// Splits the PNG file into its chunks, each with its length, type, data and CRC,
+/// stopping at the first one that is truncated
+fn chunks(png: &[u8]) -> Vec<&[u8]> {
+    let mut chunks = Vec::new();
+    let mut at = SIGNATURE_SIZE;
+    while let Some(length) = png.get(at..at + 4) {
+        let end = at + 12 + u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize;
+        match png.get(at..end) {
+            Some(chunk) => chunks.push(chunk),
+            None => break,
+        }
+        at = end;
+    }
+    chunks
+}
+
+fn kind(chunk: &[u8]) -> &[u8] {
+    &chunk[4..8]
+}
+
+/// The lowercase first letter marks the ancillary chunks, the ones that the decoders may ignore
+fn is_ancillary(chunk: &[u8]) -> bool {
+    chunk[4] & 0x20 != 0
+}
+
+/// The lowercase last letter marks the chunks that stay valid whatever happens to the critical ones
+fn is_safe_to_copy(chunk: &[u8]) -> bool {
+    chunk[7] & 0x20 != 0
+}
+
+/// Copies the ancillary chunks of the `original` PNG image, such as the text, the physical dimensions or the gamma,
+/// into the `encoded` one that was written from its pixels, so that it does not look freshly stripped.
+///
+/// The chunks that came before the image data go right after the header and the rest go right before the end,
+/// the same as in the original. When the pixel format was [`converted`](../container/struct.Conversion.html),
+/// only the chunks that are marked safe to copy and the ones of the color space, such as the ICC profile,
+/// are kept, as the rest of them describe the original format.
+/// The chunks of the animation and the ones that the encoded image already has are never copied.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::chunks::copy_ancillary;
+/// let mut png = Vec::new();
+/// let mut encoder = png::Encoder::new(&mut png, 1, 1);
+/// encoder.add_text_chunk("Author".into(), "someone".into()).unwrap();
+/// encoder.set_pixel_dims(Some(png::PixelDimensions { xppu: 2835, yppu: 2835, unit: png::Unit::Meter }));
+/// encoder.set_source_gamma(png::ScaledFloat::new(1.0 / 2.2));
+/// encoder.write_header().unwrap().write_image_data(&[0]).unwrap();
+///
+/// let mut encoded = Vec::new();
+/// png::Encoder::new(&mut encoded, 1, 1).write_header().unwrap().write_image_data(&[1]).unwrap();
+///
+/// let copied = copy_ancillary(&png, &encoded, false);
+/// let reader = png::Decoder::new(std::io::Cursor::new(&copied)).read_info().unwrap();
+/// assert_eq!(reader.info().uncompressed_latin1_text[0].text, "someone");
+/// assert_eq!(reader.info().pixel_dims.unwrap().xppu, 2835);
+///
+/// let converted = copy_ancillary(&png, &encoded, true);
+/// let reader = png::Decoder::new(std::io::Cursor::new(&converted)).read_info().unwrap();
+/// assert_eq!(reader.info().gama_chunk, Some(png::ScaledFloat::new(1.0 / 2.2)));
+/// ```
+///
+pub fn copy_ancillary(original: &[u8], encoded: &[u8], converted: bool) -> Vec<u8> {
+    let target = chunks(encoded);
+    if target.len() < 2 {
+        return encoded.to_vec();
+    }
+    let copied = |chunk: &&[u8]| is_ancillary(chunk)
+        && (!converted || is_safe_to_copy(chunk) || COLOR_SPACE.iter().any(|color| kind(chunk) == &color[..]))
+        && !ANIMATION.iter().any(|animation| kind(chunk) == &animation[..])
+        && !target.iter().any(|existing| kind(existing) == kind(chunk));
+
+    let source = chunks(original);
+    let data = source.iter().position(|chunk| kind(chunk) == b"IDAT").unwrap_or(source.len());
+    let (before, after) = source.split_at(data);
+
+    let mut result = Vec::with_capacity(encoded.len() + original.len() / 16);
+    result.extend_from_slice(&encoded[..SIGNATURE_SIZE]);
+    result.extend_from_slice(target[0]);
+    before.iter().copied().filter(copied).for_each(|chunk| result.extend_from_slice(chunk));
+    let (end, rest) = target[1..].split_last().expect("there are at least two chunks");
+    rest.iter().for_each(|chunk| result.extend_from_slice(chunk));
+    after.iter().copied().filter(copied).for_each(|chunk| result.extend_from_slice(chunk));
+    result.extend_from_slice(end);
+    result
+}
+
+/// Lists the types of the ancillary chunks of the PNG image, such as `tEXt` or `eXIf`, in the order they come in,
+/// which is everything but the pixels that could be carrying some data.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::chunks::ancillary_chunks;
+/// let mut png = Vec::new();
+/// let mut encoder = png::Encoder::new(&mut png, 1, 1);
+/// encoder.add_text_chunk("Comment".into(), "nothing to see here".into()).unwrap();
+/// encoder.write_header().unwrap().write_image_data(&[0]).unwrap();
+///
+/// assert_eq!(ancillary_chunks(&png), ["tEXt"]);
+/// assert!(ancillary_chunks(b"GIF89a").is_empty());
+/// ```
+///
+pub fn ancillary_chunks(png: &[u8]) -> Vec<String> {
+    if !png.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Vec::new();
+    }
+    chunks(png).into_iter()
+        .filter(|chunk| is_ancillary(chunk))
+        .map(|chunk| String::from_utf8_lossy(kind(chunk)).into_owned())
+        .collect()
+}
+
+/// Tells the zlib compression level the PNG image was most likely written with, from the hint in the zlib header
+/// of its image data, which only tells apart the fastest, the fast, the default and the best levels.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::chunks::compression_level;
+/// let mut png = Vec::new();
+/// let mut encoder = png::Encoder::new(&mut png, 1, 1);
+/// encoder.set_deflate_compression(png::DeflateCompression::Level(9));
+/// encoder.write_header().unwrap().write_image_data(&[0]).unwrap();
+///
+/// assert_eq!(compression_level(&png), Some(9));
+/// assert_eq!(compression_level(b"GIF89a"), None);
+/// ```
+///
+pub fn compression_level(png: &[u8]) -> Option<u8> {
+    if !png.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return None;
+    }
+    let data = chunks(png).into_iter().find(|chunk| kind(chunk) == b"IDAT")?;
+    let flags = *data.get(9)?;
+    Some([1, 5, 6, 9][flags as usize >> 6])
+}
@@ -0,0 +1,126 @@
+use std::io::{Read, Write};
+
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+
+use crate::container::FLAG_CODEC;
+use crate::Error;
+
+/// Id of the codec of the payload that is not compressed at all
+pub const NONE: u8 = 0;
+
+/// Id of the [Zstandard](enum.Compression.html#variant.Zstd) codec
+pub const ZSTD: u8 = 1;
+
+/// Id of the [deflate](enum.Compression.html#variant.Deflate) codec
+pub const DEFLATE: u8 = 2;
+
+/// Id of the [gzip](enum.Compression.html#variant.Gzip) codec
+pub const GZIP: u8 = 3;
+
+/// A way of compressing the payload before it is sealed in any layers and hidden,
+/// which is recorded in the [flags](../container/constant.FLAG_CODEC.html) of the header,
+/// so that the decoder decompresses it without being told to.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::compress::{decompress, Compression};
+/// let text = "all work and no play makes jack a dull boy\n".repeat(50);
+/// let compressed = Compression::Zstd(19).compress(text.as_bytes()).unwrap();
+///
+/// assert!(compressed.len() * 10 < text.len());
+/// assert_eq!(decompress(Compression::Zstd(19).flags(), &compressed).unwrap(), text.as_bytes());
+/// assert_eq!(decompress(0, b"as it is").unwrap(), b"as it is");
+///
+/// let gzipped = Compression::DEFAULT_GZIP.compress(text.as_bytes()).unwrap();
+/// assert!(gzipped.starts_with(&[0x1f, 0x8b]));
+/// assert_eq!(decompress(Compression::DEFAULT_GZIP.flags(), &gzipped).unwrap(), text.as_bytes());
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Compression {
+    /// The Zstandard compression with the given level, from 1 to 22, where the higher ones
+    /// compress better and take more time
+    Zstd(i32),
+    /// The deflate compression in the zlib format with the given level, from 0 for none to 9 for the best one,
+    /// which can be decompressed by anything that has zlib
+    Deflate(u32),
+    /// The deflate compression in the gzip format with the given level, from 0 for none to 9 for the best one,
+    /// so that the data can also be decompressed with `gunzip`
+    Gzip(u32),
+}
+
+impl Compression {
+    /// The Zstandard compression with its default level
+    pub const DEFAULT_ZSTD: Compression = Compression::Zstd(3);
+
+    /// The deflate compression with its default level
+    pub const DEFAULT_DEFLATE: Compression = Compression::Deflate(6);
+
+    /// The gzip compression with its default level
+    pub const DEFAULT_GZIP: Compression = Compression::Gzip(6);
+
+    /// The id of the codec
+    pub fn id(self) -> u8 {
+        match self {
+            Compression::Zstd(_) => ZSTD,
+            Compression::Deflate(_) => DEFLATE,
+            Compression::Gzip(_) => GZIP,
+        }
+    }
+
+    /// The [header flags](../container/struct.Header.html#structfield.flags) of the payload compressed this way
+    pub fn flags(self) -> u8 {
+        self.id() << FLAG_CODEC.trailing_zeros()
+    }
+
+    /// Compresses the data.
+    ///
+    /// # Errors
+    /// [`MissingFeature`](../enum.Error.html#variant.MissingFeature) when this build was compiled without the codec.
+    pub fn compress(self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(level) => Ok(zstd::encode_all(data, level)?),
+            #[cfg(not(feature = "zstd"))]
+            Compression::Zstd(_) => Err(Error::MissingFeature("zstd")),
+            Compression::Deflate(level) => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::new(level.min(9)));
+                encoder.write_all(data)?;
+                Ok(encoder.finish()?)
+            },
+            Compression::Gzip(level) => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::new(level.min(9)));
+                encoder.write_all(data)?;
+                Ok(encoder.finish()?)
+            },
+        }
+    }
+}
+
+/// Decompresses the data with the codec from the [header flags](../container/struct.Header.html#structfield.flags),
+/// returning the data as it is when the flags have no codec.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when the codec is an unknown one
+/// or the data is not compressed with it, and
+/// [`MissingFeature`](../enum.Error.html#variant.MissingFeature) when this build was compiled without the codec.
+pub fn decompress(flags: u8, data: &[u8]) -> Result<Vec<u8>, Error> {
+    match (flags & FLAG_CODEC) >> FLAG_CODEC.trailing_zeros() {
+        NONE => Ok(data.to_vec()),
+        #[cfg(feature = "zstd")]
+        ZSTD => zstd::decode_all(data).map_err(|e| Error::UnsupportedCarrier(format!("the data is not compressed with zstd: {}", e))),
+        #[cfg(not(feature = "zstd"))]
+        ZSTD => Err(Error::MissingFeature("zstd")),
+        DEFLATE => read_all(ZlibDecoder::new(data), "deflate"),
+        GZIP => read_all(GzDecoder::new(data), "gzip"),
+        id => Err(Error::UnsupportedCarrier(format!("the data is compressed with an unknown codec {}", id))),
+    }
+}
+
+fn read_all(mut decoder: impl Read, codec: &str) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::new();
+    decoder.read_to_end(&mut data)
+        .map_err(|e| Error::UnsupportedCarrier(format!("the data is not compressed with {}: {}", codec, e)))?;
+    Ok(data)
+}
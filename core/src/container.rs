@@ -0,0 +1,665 @@
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Read, Write};
+
+use byteorder::{BigEndian, ByteOrder, ReadBytesExt, WriteBytesExt};
+
+use image::ColorType;
+use sha2::{Digest, Sha256};
+
+use crate::binary::{check_magic, Bits, Layout, SteganographReader, SteganographWriter, FORMAT_VERSION, MAGIC, MAGIC_SIZE};
+use crate::matrix::{self, MAX_MATRIX};
+use crate::whitening::Keystream;
+use crate::wetpaper::SplitMix;
+use crate::Error;
+
+/// Size of the header in the hidden bytes
+pub const HEADER_SIZE: usize = MAGIC_SIZE + 4;
+
+/// [Flag](struct.Header.html#structfield.flags) of the payload encrypted with a password
+pub const FLAG_PASSWORD: u8 = 1;
+
+/// [Flag](struct.Header.html#structfield.flags) of the payload encrypted to the X25519 public keys of the recipients
+pub const FLAG_RECIPIENTS: u8 = 1 << 1;
+
+/// [Flag](struct.Header.html#structfield.flags) of the payload sealed with a one-time pad
+pub const FLAG_ONE_TIME_PAD: u8 = 1 << 2;
+
+/// [Flag](struct.Header.html#structfield.flags) of the payload signed with an Ed25519 key
+pub const FLAG_SIGNED_DATA: u8 = 1 << 3;
+
+/// [Flag](struct.Header.html#structfield.flags) of the payload that is an [archive](../toc/struct.Archive.html)
+/// of several named entries, each of them sealed in its own layers
+pub const FLAG_ENTRIES: u8 = 1 << 4;
+
+/// Bits of the [flags](struct.Header.html#structfield.flags) that hold the id of the codec the payload is
+/// [compressed](../compress/enum.Compression.html) with, which are all zero when it is not compressed
+pub const FLAG_CODEC: u8 = 0b11 << 5;
+
+/// [Flag](struct.Header.html#structfield.flags) of the payload sealed for the P-256 key of a smartcard
+pub const FLAG_CARD: u8 = 1 << 7;
+
+/// All of the [flags](struct.Header.html#structfield.flags) this version knows about, the data with any other one
+/// needs a newer version to be decoded
+pub const KNOWN_FLAGS: u8 = FLAG_PASSWORD | FLAG_RECIPIENTS | FLAG_ONE_TIME_PAD | FLAG_SIGNED_DATA | FLAG_ENTRIES | FLAG_CODEC | FLAG_CARD;
+
+/// Checks that all of the [flags](struct.Header.html#structfield.flags) are [known](constant.KNOWN_FLAGS.html)
+/// to this version, returning them.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when some of them are newer than this version.
+pub fn check_flags(flags: u8) -> Result<u8, Error> {
+    match flags & !KNOWN_FLAGS {
+        0 => Ok(flags),
+        _ => Err(Error::UnsupportedCarrier(format!("the data is sealed in some unknown layers (flags {:#04x})", flags))),
+    }
+}
+
+/// Size of the 64-bit length and the CRC32 of the payload that are hidden in front of it
+pub const PREFIX_SIZE: usize = 8 + 4;
+
+/// Order in which the pixels of the carrier are visited by the hidden data.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Traversal {
+    /// Row by row, left to right
+    #[default]
+    Raster,
+    /// Row by row, changing the direction on every row
+    Boustrophedon,
+    /// Along the Hilbert curve, so that consecutive pixels are always neighbours
+    /// but the path wanders all over the image
+    Hilbert,
+    /// In a pseudo-random order derived from the key, see [`scattered`](#method.scattered), so that the data
+    /// is spread all over the image and there is no telling which pixels hold it without the key.
+    ///
+    /// The key is not stored in the header, the decoder has to be given it.
+    Scattered(u64),
+}
+
+impl Traversal {
+    fn id(&self) -> u8 {
+        match self {
+            Traversal::Raster => 0,
+            Traversal::Boustrophedon => 1,
+            Traversal::Hilbert => 2,
+            Traversal::Scattered(_) => 3,
+        }
+    }
+
+    fn from_id(id: u8) -> io::Result<Self> {
+        match id {
+            0 => Ok(Traversal::Raster),
+            1 => Ok(Traversal::Boustrophedon),
+            2 => Ok(Traversal::Hilbert),
+            3 => Ok(Traversal::Scattered(0)),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "unknown pixel traversal")),
+        }
+    }
+
+    /// The [scattered](#variant.Scattered) traversal with the key derived from the seed, such as a password.
+    pub fn scattered(seed: &str) -> Self {
+        Traversal::Scattered(scatter_key(seed))
+    }
+
+    /// Returns the indices of the pixels of the `width` by `height` image in the order of this traversal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::container::Traversal;
+    /// assert_eq!(Traversal::Raster.order(3, 2), [0, 1, 2, 3, 4, 5]);
+    /// assert_eq!(Traversal::Boustrophedon.order(3, 2), [0, 1, 2, 5, 4, 3]);
+    /// assert_eq!(Traversal::Hilbert.order(2, 2), [0, 2, 3, 1]);
+    /// assert_eq!(Traversal::Hilbert.order(3, 2), [0, 1, 4, 3, 5, 2]);
+    ///
+    /// let mut scattered = Traversal::scattered("seed").order(16, 16);
+    /// assert_ne!(scattered, Traversal::scattered("other seed").order(16, 16));
+    /// scattered.sort();
+    /// assert_eq!(scattered, Traversal::Raster.order(16, 16));
+    /// ```
+    pub fn order(&self, width: usize, height: usize) -> Vec<usize> {
+        match self {
+            Traversal::Raster => (0..width * height).collect(),
+            Traversal::Boustrophedon => (0..height)
+                .flat_map(|y| (0..width).map(move |x| y * width + if y % 2 == 0 { x } else { width - 1 - x }))
+                .collect(),
+            Traversal::Hilbert => {
+                let side = width.max(height).max(1).next_power_of_two();
+                (0..side * side)
+                    .map(|d| hilbert_point(side, d))
+                    .filter(|&(x, y)| x < width && y < height)
+                    .map(|(x, y)| y * width + x)
+                    .collect()
+            },
+            Traversal::Scattered(key) => {
+                let mut order = (0..width * height).collect::<Vec<_>>();
+                SplitMix::new(*key).shuffle(&mut order);
+                order
+            },
+        }
+    }
+}
+
+/// The key of the [scattered](enum.Traversal.html#variant.Scattered) traversal derived from the seed
+pub(crate) fn scatter_key(seed: &str) -> u64 {
+    let hash = Sha256::new()
+        .chain_update(b"steganographer scatter")
+        .chain_update(seed)
+        .finalize();
+    BigEndian::read_u64(&hash[..8])
+}
+
+/// Coordinates of the `d`-th point of the Hilbert curve filling the `side` by `side` square
+fn hilbert_point(side: usize, d: usize) -> (usize, usize) {
+    let (mut x, mut y, mut t) = (0, 0, d);
+    let mut s = 1;
+    while s < side {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+    (x, y)
+}
+
+/// Settings of the hidden data that are stored in front of it, so that the decoder
+/// does not need to be told about them.
+///
+/// The header starts with the [magic](../binary/constant.MAGIC.html) and the version of the format,
+/// so that the decoder can tell right away whether there is anything hidden and whether it can read it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Header {
+    /// Version of the format the data is hidden in, which is the [current one](../binary/constant.FORMAT_VERSION.html)
+    /// for everything that is hidden by this version.
+    pub version: u8,
+    /// Layers the payload is sealed in, such as [`FLAG_PASSWORD`](constant.FLAG_PASSWORD.html),
+    /// so that the decoder can tell what it needs before it tries to open them.
+    pub flags: u8,
+    /// Interleaving depth, 1 means that payload bits are stored sequentially.
+    ///
+    /// Consecutive bits of the payload are spread `capacity / depth` bits apart,
+    /// so that a contiguous damaged region of the carrier turns into scattered
+    /// single-bit errors instead of a hole in the data.
+    pub interleave: u16,
+    /// Order in which the pixels after the ones holding the header are visited.
+    ///
+    /// The header itself is always stored in the first pixels, as the decoder has to find it first.
+    pub traversal: Traversal,
+    /// Bit mask of the channels the payload is stored in, where the lowest bit is the first channel
+    /// and 0 means all of the usable ones.
+    ///
+    /// Only the first four channels can be picked, and the header itself is always stored in all of them.
+    pub channels: u8,
+    /// Number of the payload bits in each block of the [matrix embedding](../matrix/index.html) of the body,
+    /// from 1, which is just replacing the bits, to [`MAX_MATRIX`](../matrix/constant.MAX_MATRIX.html).
+    ///
+    /// The header itself is always stored by replacing the bits.
+    pub matrix: u8,
+}
+
+impl Default for Header {
+    fn default() -> Self {
+        Header { version: FORMAT_VERSION, flags: 0, interleave: 1, traversal: Traversal::default(), channels: 0, matrix: 1 }
+    }
+}
+
+impl Header {
+    fn write(&self, mut to: impl Write) -> io::Result<()> {
+        to.write_all(&MAGIC)?;
+        to.write_u8(self.version)?;
+        to.write_u8(self.flags)?;
+        to.write_u16::<BigEndian>(self.interleave)?;
+        to.write_u8(self.traversal.id() | (self.matrix.clamp(1, MAX_MATRIX) - 1) << 2 | self.channels << 4)
+    }
+
+    /// Reads the header, checking the magic and the version first.
+    ///
+    /// # Errors
+    /// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when there is no magic, which means
+    /// that there is nothing hidden, or when the version or some of the flags are newer than this version.
+    fn read(bytes: &[u8]) -> Result<Self, Error> {
+        let version = check_magic(bytes)?;
+        let mut from = &bytes[MAGIC_SIZE..];
+        let flags = check_flags(from.read_u8()?)?;
+        let interleave = from.read_u16::<BigEndian>()?.max(1);
+        let byte = from.read_u8()?;
+        let (traversal, matrix) = (Traversal::from_id(byte & 0b11)?, (byte >> 2 & 0b11) + 1);
+        Ok(Header { version, flags, interleave, traversal, channels: byte >> 4, matrix })
+    }
+
+    /// The layout of the pixels after the header, with only the picked channels usable
+    fn body_layout(&self, layout: Layout) -> Layout {
+        match self.channels & 0xF {
+            0 => layout,
+            channels => layout.only_channels(channels),
+        }
+    }
+}
+
+/// Returns the order in which payload bits are placed into the `total` available bits.
+///
+/// This is a block interleaver: the bits are written into `depth` rows column by column
+/// and the rows are laid out one after another, with the cells past `total` skipped.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::container::interleaved_positions;
+/// let order = interleaved_positions(10, 3).collect::<Vec<_>>();
+///
+/// assert_eq!(order, [0, 4, 8, 1, 5, 9, 2, 6, 3, 7]);
+/// assert_eq!(interleaved_positions(4, 1).collect::<Vec<_>>(), [0, 1, 2, 3]);
+/// ```
+pub fn interleaved_positions(total: usize, depth: u16) -> impl Iterator<Item = usize> {
+    let depth = depth.max(1) as usize;
+    let columns = total.div_ceil(depth).max(1);
+    (0..depth * columns)
+        .map(move |cell| cell % depth * columns + cell / depth)
+        .filter(move |&position| position < total)
+}
+
+fn get_bit(plane: &[u8], position: usize) -> u8 {
+    plane[position / 8] >> (7 - position % 8) & 1
+}
+
+fn set_bit(plane: &mut [u8], position: usize, bit: u8) {
+    let shift = 7 - position % 8;
+    plane[position / 8] = plane[position / 8] & !(1 << shift) | bit << shift;
+}
+
+fn eof() -> Error {
+    io::Error::from(io::ErrorKind::UnexpectedEof).into()
+}
+
+/// CRC-32 as in zlib and PNG, with the reflected 0xEDB88320 polynomial, of the length and the payload,
+/// so that a carrier of zeroes does not pass for an empty payload
+fn crc32(length: u64, payload: &[u8]) -> u32 {
+    !length.to_be_bytes().iter().chain(payload).fold(!0u32, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| crc >> 1 ^ 0xEDB88320 & (crc & 1).wrapping_neg())
+    })
+}
+
+/// Returns the layout of the decoded pixels of the given color type, with all of the channels usable.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::container::image_layout;
+/// # use image::ColorType;
+/// let layout = image_layout(ColorType::Rgba16);
+///
+/// assert_eq!((layout.channels(), layout.sample_size(), layout.has_alpha()), (4, 2, true));
+/// ```
+pub fn image_layout(color_type: ColorType) -> Layout {
+    let channels = color_type.channel_count();
+    let sample_size = color_type.bytes_per_pixel() / channels;
+    Layout::new(channels, sample_size, color_type.has_alpha())
+}
+
+/// Change of the pixel format that happens to the PNG images that cannot hold the data as they are,
+/// such as the ones with less than 8 bits per sample or with a palette.
+///
+/// The pixels are expanded to the nearest format that has a byte or two per sample when reading such an image,
+/// so the encoded image is written in that format instead of the original one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversion {
+    /// Description of the original format, such as `1-bit grayscale`
+    pub from: String,
+    /// Format of the encoded image
+    pub to: ColorType,
+}
+
+impl Conversion {
+    /// Compares the format in the header of the PNG file, which begins with its first bytes,
+    /// to the format of its decoded pixels, returning `None` when they are the same
+    /// or when the bytes are not a PNG file at all.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image::ColorType;
+    /// # use steganographer_core::container::Conversion;
+    /// let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    /// png.extend_from_slice(&[0, 0, 0, 8, 0, 0, 0, 8, 1, 0]); // 8x8, 1-bit grayscale
+    ///
+    /// let conversion = Conversion::detect(&png, ColorType::L8).unwrap();
+    /// assert_eq!(conversion.to_string(), "1-bit grayscale to 8-bit grayscale");
+    ///
+    /// assert!(Conversion::detect(b"GIF89a", ColorType::L8).is_none());
+    /// ```
+    ///
+    pub fn detect(header: &[u8], decoded: ColorType) -> Option<Conversion> {
+        if header.len() < 26 || !header.starts_with(b"\x89PNG\r\n\x1a\n") || &header[12..16] != b"IHDR" {
+            return None;
+        }
+        let (depth, color) = (header[24], header[25]);
+        let alpha = color == 4 || color == 6;
+        if color != 3 && u16::from(depth) == sample_bits(decoded) && alpha == decoded.has_alpha() {
+            return None;
+        }
+        let mut from = format!("{}-bit {}", depth, match color {
+            0 => "grayscale",
+            2 => "RGB",
+            3 => "palette",
+            4 => "grayscale with alpha",
+            6 => "RGBA",
+            _ => "unknown",
+        });
+        if color != 3 && alpha != decoded.has_alpha() {
+            from += " with a transparent color";
+        }
+        Some(Conversion { from, to: decoded })
+    }
+}
+
+impl Display for Conversion {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self.to {
+            ColorType::L8 | ColorType::L16 => "grayscale",
+            ColorType::La8 | ColorType::La16 => "grayscale with alpha",
+            ColorType::Rgb8 | ColorType::Rgb16 | ColorType::Bgr8 => "RGB",
+            _ => "RGBA",
+        };
+        write!(f, "{} to {}-bit {}", self.from, sample_bits(self.to), name)
+    }
+}
+
+/// Amount of bits in each sample of the pixels of the given format
+fn sample_bits(color_type: ColorType) -> u16 {
+    color_type.bits_per_pixel() / u16::from(color_type.channel_count())
+}
+
+/// Indices of the carrier bytes after the first `head` ones in the order of the traversal, in whole pixels
+fn body_order(len: usize, head: usize, layout: Layout, traversal: Traversal) -> Vec<usize> {
+    let pixel_size = layout.pixel_size();
+    let pixels = len / pixel_size;
+    let first = head / pixel_size;
+    if traversal == Traversal::Raster {
+        return (head..pixels * pixel_size).collect();
+    }
+    let width = layout.pixels_per_row().unwrap_or(pixels).max(1);
+    traversal.order(width, pixels.div_ceil(width))
+        .into_iter()
+        .filter(|&pixel| pixel >= first && pixel < pixels)
+        .flat_map(|pixel| pixel * pixel_size..(pixel + 1) * pixel_size)
+        .collect()
+}
+
+/// Amount of the first carrier bytes, in whole pixels, that hold the header
+fn head_len(len: usize, bits: Bits, layout: Layout) -> Result<usize, Error> {
+    let head = layout.pixels_len(len, bits.carrier_len(HEADER_SIZE));
+    if layout.capacity(head, bits) < HEADER_SIZE {
+        return Err(eof());
+    }
+    Ok(head)
+}
+
+/// Returns how many bytes of payload [`hide_payload`](fn.hide_payload.html) can hide in `len` carrier bytes.
+pub fn payload_capacity(len: usize, bits: Bits, layout: Layout) -> usize {
+    matrix_capacity(len, bits, layout, 1)
+}
+
+/// Returns how many bytes of payload [`hide_payload`](fn.hide_payload.html) can hide in `len` carrier bytes
+/// with the [`matrix`](struct.Header.html#structfield.matrix) of the header.
+pub fn matrix_capacity(len: usize, bits: Bits, layout: Layout, matrix: u8) -> usize {
+    match head_len(len, bits, layout) {
+        Ok(head) => body_capacity(layout.capacity(len / layout.pixel_size() * layout.pixel_size() - head, bits), matrix),
+        Err(_) => 0,
+    }
+}
+
+/// How many bytes of payload the plane of `plane` bytes holds with the matrix, after the prefix
+fn body_capacity(plane: usize, matrix: u8) -> usize {
+    (matrix::capacity(plane * 8, matrix) / 8).saturating_sub(PREFIX_SIZE)
+}
+
+/// The bits of the plane in the order of the interleaving, along with their positions in it
+fn plane_bits(plane: &[u8], interleave: u16) -> (Vec<usize>, Vec<u8>) {
+    let positions = interleaved_positions(plane.len() * 8, interleave).collect::<Vec<_>>();
+    let bits = positions.iter().map(|&position| get_bit(plane, position)).collect();
+    (positions, bits)
+}
+
+/// Hides a slice of bytes along with its length, its CRC32 and a [header](struct.Header.html) in the carrier bytes
+/// in place, altering only the bytes that are usable according to the `layout`.
+///
+/// Unlike [`hide_bytes`](../binary/fn.hide_bytes.html), the whole carrier is considered,
+/// as with interleaving the payload bits are spread over all of it.
+/// The header is stored in the first pixels, and the rest of them are visited in the order
+/// of the [traversal](enum.Traversal.html), which needs the layout to know the
+/// [width](../binary/struct.Layout.html#method.width) of the image.
+///
+/// # Errors
+/// An `UnexpectedEof` IO error when there are not enough carrier bytes.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::container::{hide_payload, reveal_payload, Header, Traversal};
+/// # use steganographer_core::binary::{Bits, Layout};
+/// let mut carrier = vec![0b10101010; 256 * 3];
+/// let layout = Layout::new(3, 1, false).width(16);
+/// // only the red and blue channels after the header, 2 bits in each 3 of them
+/// let header = Header { interleave: 8, traversal: Traversal::Hilbert, channels: 0b101, matrix: 2, ..Header::default() };
+///
+/// hide_payload(&[1, 2, 3], &mut carrier, Bits::Two, layout, &header).unwrap();
+///
+/// assert_eq!(reveal_payload(&carrier, Bits::Two, layout).unwrap(), (header, vec![1, 2, 3]));
+/// assert!(carrier[30..].chunks(3).all(|pixel| pixel[1] == 0b10101010));
+/// ```
+///
+pub fn hide_payload(payload: &[u8], carrier: &mut [u8], bits: Bits, layout: Layout, header: &Header) -> Result<(), Error> {
+    hide_with(payload, |_| Ok(Vec::new()), None, carrier, bits, layout, header)
+}
+
+/// Same as [`hide_payload`](fn.hide_payload.html), but also hides the bytes that `volume` makes for the amount
+/// of the capacity that is left after the payload right after it, see [`crypto::seal_volume`](../crypto/fn.seal_volume.html),
+/// and [whitens](../whitening/index.html) all of the hidden bytes with the keystream of the seed if there is one
+pub(crate) fn hide_with(
+    payload: &[u8],
+    volume: impl FnOnce(usize) -> Result<Vec<u8>, Error>,
+    whitening: Option<&str>,
+    carrier: &mut [u8],
+    bits: Bits,
+    layout: Layout,
+    header: &Header,
+) -> Result<(), Error> {
+    let head = head_len(carrier.len(), bits, layout)?;
+    let order = body_order(carrier.len(), head, layout, header.traversal);
+    let mut body = order.iter().map(|&i| carrier[i]).collect::<Vec<_>>();
+
+    let body_layout = header.body_layout(layout);
+    let plane_len = body_layout.capacity(body.len(), bits);
+    let capacity = matrix::capacity(plane_len * 8, header.matrix) / 8;
+    let mut prefix = Vec::with_capacity(PREFIX_SIZE);
+    let length = payload.len() as u64;
+    prefix.write_u64::<BigEndian>(length)?;
+    prefix.write_u32::<BigEndian>(crc32(length, payload))?;
+    if PREFIX_SIZE + payload.len() > capacity {
+        return Err(eof());
+    }
+    let volume = volume(capacity - PREFIX_SIZE - payload.len())?;
+
+    let mut header_bytes = Vec::with_capacity(HEADER_SIZE);
+    header.write(&mut header_bytes)?;
+    let mut framed = [&prefix[..], payload, &volume].concat();
+    if let Some(seed) = whitening {
+        let mut keystream = Keystream::new(seed);
+        keystream.apply(&mut header_bytes);
+        keystream.apply(&mut framed);
+    }
+    let mut hidden = Vec::with_capacity(head);
+    SteganographWriter::new(&carrier[..head], &mut hidden).bits(bits).layout(layout).write_all(&header_bytes)?;
+    carrier[..hidden.len()].copy_from_slice(&hidden);
+
+    let mut plane = vec![0; plane_len];
+    SteganographReader::new(&body[..]).bits(bits).layout(body_layout).read_exact(&mut plane)?;
+    let framed = framed.iter()
+        .flat_map(|byte| (0..8).rev().map(move |shift| byte >> shift & 1));
+    let (positions, mut plane_bits) = plane_bits(&plane, header.interleave);
+    matrix::embed(&mut plane_bits, framed, header.matrix);
+    for (&position, &bit) in positions.iter().zip(&plane_bits) {
+        set_bit(&mut plane, position, bit);
+    }
+
+    let mut hidden = Vec::with_capacity(body.len());
+    SteganographWriter::new(&body[..], &mut hidden).bits(bits).layout(body_layout).write_all(&plane)?;
+    body[..hidden.len()].copy_from_slice(&hidden);
+    for (&i, &byte) in order.iter().zip(&body) {
+        carrier[i] = byte;
+    }
+    Ok(())
+}
+
+/// Reveals a slice of bytes and the header previously hidden by the [`hide_payload`](fn.hide_payload.html) function.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when there is no
+/// [magic](../binary/constant.MAGIC.html) in front of the header or the length stored in the carrier
+/// is greater than its capacity, which means that nothing was hidden in it, and when the data is of a newer format,
+/// [`IntegrityFailure`](../enum.Error.html#variant.IntegrityFailure) when there is a payload,
+/// but it does not match its CRC32, and an `InvalidData` IO error when the header is not valid.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::container::{hide_payload, reveal_payload, Header};
+/// # use steganographer_core::binary::{Bits, Layout};
+/// # use steganographer_core::Error;
+/// let layout = Layout::new(1, 1, false);
+/// let mut carrier = vec![0; 256];
+/// // there is no magic in front of it
+/// assert!(matches!(reveal_payload(&carrier, Bits::Two, layout), Err(Error::UnsupportedCarrier(_))));
+///
+/// hide_payload(b"hello", &mut carrier, Bits::Two, layout, &Header::default()).unwrap();
+/// let mut damaged = carrier.clone();
+/// damaged[60] ^= 1;
+/// assert!(matches!(reveal_payload(&damaged, Bits::Two, layout), Err(Error::IntegrityFailure)));
+///
+/// // a valid header followed by the bits that make for a length way too large
+/// carrier[28..44].fill(0b11);
+/// assert!(matches!(reveal_payload(&carrier, Bits::Two, layout), Err(Error::UnsupportedCarrier(_))));
+/// ```
+///
+pub fn reveal_payload(carrier: &[u8], bits: Bits, layout: Layout) -> Result<(Header, Vec<u8>), Error> {
+    reveal_with(carrier, bits, layout, None, None)
+}
+
+/// Same as [`reveal_payload`](fn.reveal_payload.html), but takes the whitening of the seed off, if there is one,
+/// and visits the pixels with the key if they are [scattered](enum.Traversal.html#variant.Scattered)
+pub(crate) fn reveal_with(carrier: &[u8], bits: Bits, layout: Layout, whitening: Option<&str>, scatter: Option<u64>)
+                          -> Result<(Header, Vec<u8>), Error> {
+    let (header, payload, crc, _) = reveal_framed(carrier, bits, layout, whitening, scatter, false)?;
+    if crc32(payload.len() as u64, &payload) != crc {
+        return Err(Error::IntegrityFailure);
+    }
+    Ok((header, payload))
+}
+
+/// Same as [`reveal_payload`](fn.reveal_payload.html), but returns the stored CRC32 instead of checking it,
+/// so that a damaged payload can still be looked at
+pub(crate) fn reveal_unchecked(carrier: &[u8], bits: Bits, layout: Layout) -> Result<(Header, Vec<u8>, u32), Error> {
+    let (header, payload, crc, _) = reveal_framed(carrier, bits, layout, None, None, false)?;
+    Ok((header, payload, crc))
+}
+
+/// Reveals the rest of the capacity after the payload, that holds the volume of
+/// [`hide_with`](fn.hide_with.html) if there is one, checking the payload first
+pub(crate) fn reveal_volume(carrier: &[u8], bits: Bits, layout: Layout, whitening: Option<&str>, scatter: Option<u64>)
+                            -> Result<Vec<u8>, Error> {
+    let (_, payload, crc, volume) = reveal_framed(carrier, bits, layout, whitening, scatter, true)?;
+    if crc32(payload.len() as u64, &payload) != crc {
+        return Err(Error::IntegrityFailure);
+    }
+    Ok(volume)
+}
+
+/// Reads the header, taking the whitening off it, and the plane of the bits of the body it describes,
+/// visiting the pixels with the key if they are scattered
+fn read_plane(carrier: &[u8], bits: Bits, layout: Layout, keystream: &mut Option<Keystream>, scatter: Option<u64>)
+              -> Result<(Header, Vec<u8>), Error> {
+    let head = head_len(carrier.len(), bits, layout)?;
+    let mut header = [0; HEADER_SIZE];
+    SteganographReader::new(&carrier[..head]).bits(bits).layout(layout).read_exact(&mut header)?;
+    if let Some(keystream) = keystream {
+        keystream.apply(&mut header);
+    }
+    let mut header = Header::read(&header[..])?;
+    if let Traversal::Scattered(_) = header.traversal {
+        let key = scatter.ok_or_else(|| Error::UnsupportedCarrier("the pixels are scattered with a key that was not given".into()))?;
+        header.traversal = Traversal::Scattered(key);
+    }
+
+    let body = body_order(carrier.len(), head, layout, header.traversal).into_iter()
+        .map(|i| carrier[i])
+        .collect::<Vec<_>>();
+    let body_layout = header.body_layout(layout);
+    let mut plane = vec![0; body_layout.capacity(body.len(), bits)];
+    SteganographReader::new(&body[..]).bits(bits).layout(body_layout).read_exact(&mut plane)?;
+    Ok((header, plane))
+}
+
+/// Reads just the header and the length stored in front of the payload, without checking the length,
+/// along with the capacity of the carrier for the payload
+pub(crate) fn reveal_prefix(carrier: &[u8], bits: Bits, layout: Layout, whitening: Option<&str>, scatter: Option<u64>)
+                            -> Result<(Header, u64, usize), Error> {
+    let mut keystream = whitening.map(Keystream::new);
+    let (header, plane) = read_plane(carrier, bits, layout, &mut keystream, scatter)?;
+    let (_, plane_bits) = plane_bits(&plane, header.interleave);
+    let mut message = matrix::extract(&plane_bits, header.matrix);
+    let mut length = [0; 8];
+    for byte in &mut length {
+        *byte = (0..8).try_fold(0, |acc, _| message.next().map(|bit| acc << 1 | bit)).ok_or_else(eof)?;
+    }
+    if let Some(keystream) = &mut keystream {
+        keystream.apply(&mut length);
+    }
+    Ok((header, u64::from_be_bytes(length), body_capacity(plane.len(), header.matrix)))
+}
+
+fn reveal_framed(
+    carrier: &[u8],
+    bits: Bits,
+    layout: Layout,
+    whitening: Option<&str>,
+    scatter: Option<u64>,
+    with_volume: bool,
+) -> Result<(Header, Vec<u8>, u32, Vec<u8>), Error> {
+    let mut keystream = whitening.map(Keystream::new);
+    let (header, plane) = read_plane(carrier, bits, layout, &mut keystream, scatter)?;
+    let mut unwhiten = |data: &mut [u8]| if let Some(keystream) = &mut keystream {
+        keystream.apply(data);
+    };
+
+    let (_, plane_bits) = plane_bits(&plane, header.interleave);
+    let mut message = matrix::extract(&plane_bits, header.matrix);
+    let mut next_byte = || -> Option<u8> {
+        (0..8).try_fold(0, |acc, _| message.next().map(|bit| acc << 1 | bit))
+    };
+
+    let mut prefix = [0; PREFIX_SIZE];
+    for byte in &mut prefix {
+        *byte = next_byte().ok_or_else(eof)?;
+    }
+    unwhiten(&mut prefix);
+    let length = BigEndian::read_u64(&prefix);
+    if length > body_capacity(plane.len(), header.matrix) as u64 {
+        return Err(Error::UnsupportedCarrier("there is no hidden data in it".into()));
+    }
+    let mut payload = (0..length).map(|_| next_byte().ok_or_else(eof)).collect::<Result<Vec<_>, _>>()?;
+    unwhiten(&mut payload);
+    let mut volume = match with_volume {
+        true => std::iter::from_fn(next_byte).collect(),
+        false => Vec::new(),
+    };
+    unwhiten(&mut volume);
+    Ok((header, payload, BigEndian::read_u32(&prefix[8..]), volume))
+}
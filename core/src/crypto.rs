@@ -0,0 +1,257 @@
+use std::convert::TryFrom;
+
+use argon2::Argon2;
+use chacha20poly1305::{KeyInit, XChaCha20Poly1305, XNonce};
+use chacha20poly1305::aead::Aead;
+use rand_core::{CryptoRng, OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+use crate::keys::KEY_SIZE;
+use crate::Error;
+
+/// Size of the random salt of the key derivation stored in front of the sealed payload
+pub const SALT_SIZE: usize = 16;
+
+/// Size of the random nonce stored after the salt
+pub const NONCE_SIZE: usize = 24;
+
+/// Size of the authentication tag that follows the encrypted payload
+pub const TAG_SIZE: usize = 16;
+
+/// How many bytes [`seal`](fn.seal.html) adds to the payload
+pub const OVERHEAD: usize = SALT_SIZE + NONCE_SIZE + TAG_SIZE;
+
+/// Derives the key from the password and the salt with Argon2id in its default, recommended settings
+fn derive_key(password: &str, salt: &[u8]) -> Result<XChaCha20Poly1305, Error> {
+    let mut key = Zeroizing::new([0; 32]);
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key[..])
+        .map_err(|e| Error::InvalidKey(format!("the key could not be derived from the password: {}", e)))?;
+    Ok(XChaCha20Poly1305::new_from_slice(&key[..]).expect("the key is of the right size"))
+}
+
+/// Encrypts the payload with XChaCha20-Poly1305 using the key derived from the password with Argon2id,
+/// storing the random salt and nonce in front of the result so that [`open`](fn.open.html) only needs the password.
+///
+/// The result is [`OVERHEAD`](constant.OVERHEAD.html) bytes larger than the payload.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::crypto::{open, seal, OVERHEAD};
+/// let sealed = seal(b"secret", "correct horse battery staple").unwrap();
+///
+/// assert_eq!(sealed.len(), 6 + OVERHEAD);
+/// assert_eq!(open(&sealed, "correct horse battery staple").unwrap(), b"secret");
+/// assert!(open(&sealed, "wrong horse").is_err());
+/// ```
+///
+pub fn seal(payload: &[u8], password: &str) -> Result<Vec<u8>, Error> {
+    seal_with_rng(payload, password, &mut OsRng)
+}
+
+/// Same as [`seal`](fn.seal.html), but the salt and the nonce come from the given generator
+pub(crate) fn seal_with_rng(payload: &[u8], password: &str, rng: &mut (impl RngCore + CryptoRng)) -> Result<Vec<u8>, Error> {
+    let mut sealed = vec![0; SALT_SIZE + NONCE_SIZE];
+    rng.fill_bytes(&mut sealed);
+    let (salt, nonce) = sealed.split_at(SALT_SIZE);
+    let encrypted = derive_key(password, salt)?
+        .encrypt(XNonce::from_slice(nonce), payload)
+        .map_err(|_| Error::InvalidKey("the payload is too large to be encrypted".into()))?;
+    sealed.extend(encrypted);
+    Ok(sealed)
+}
+
+/// Reverses the [`seal`](fn.seal.html) function with the same password.
+///
+/// # Errors
+/// [`WrongPassword`](../enum.Error.html#variant.WrongPassword) when the password is not the one the data
+/// was sealed with, when the data was altered since or when it was not sealed at all.
+pub fn open(sealed: &[u8], password: &str) -> Result<Vec<u8>, Error> {
+    if sealed.len() < OVERHEAD {
+        return Err(Error::WrongPassword);
+    }
+    let (salt, rest) = sealed.split_at(SALT_SIZE);
+    let (nonce, encrypted) = rest.split_at(NONCE_SIZE);
+    derive_key(password, salt)?
+        .decrypt(XNonce::from_slice(nonce), encrypted)
+        .map_err(|_| Error::WrongPassword)
+}
+
+/// Same as [`open`](fn.open.html), but only lends the decrypted payload to the closure
+/// and wipes it from the memory right after, instead of handing out a buffer that lives for who knows how long.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::crypto::{open_with, seal};
+/// let sealed = seal(b"secret", "hunter2").unwrap();
+///
+/// let length = open_with(&sealed, "hunter2", |payload| payload.len()).unwrap();
+/// assert_eq!(length, 6);
+/// ```
+///
+pub fn open_with<R>(sealed: &[u8], password: &str, f: impl FnOnce(&[u8]) -> R) -> Result<R, Error> {
+    let payload = Zeroizing::new(open(sealed, password)?);
+    Ok(f(&payload))
+}
+
+/// Seals the payload with the password into exactly `size` bytes, padding it with random bytes,
+/// so that it fills the rest of the capacity after a decoy payload as a hidden volume.
+///
+/// Without the password the result cannot be told from random bytes, so whoever is made to give up
+/// the password of the decoy cannot be shown that there is anything else hidden in the carrier.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when the payload does not fit
+/// into `size` bytes along with its length and the [`OVERHEAD`](constant.OVERHEAD.html).
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::crypto::{open_volume, seal_volume};
+/// let volume = seal_volume(b"the real plans", "second password", 100).unwrap();
+///
+/// assert_eq!(volume.len(), 100);
+/// assert_eq!(open_volume(&volume, "second password").unwrap(), b"the real plans");
+/// assert!(open_volume(&volume, "decoy password").is_err());
+/// ```
+///
+pub fn seal_volume(payload: &[u8], password: &str, size: usize) -> Result<Vec<u8>, Error> {
+    seal_volume_with_rng(payload, password, size, &mut OsRng)
+}
+
+/// Same as [`seal_volume`](fn.seal_volume.html), but the padding, the salt and the nonce come from the given generator
+pub(crate) fn seal_volume_with_rng(payload: &[u8], password: &str, size: usize, rng: &mut (impl RngCore + CryptoRng)) -> Result<Vec<u8>, Error> {
+    let length = u32::try_from(payload.len()).ok()
+        .filter(|_| OVERHEAD + 4 + payload.len() <= size)
+        .ok_or_else(|| Error::UnsupportedCarrier(format!(
+            "the hidden volume needs {} bytes, but only {} are left after the decoy", OVERHEAD + 4 + payload.len(), size,
+        )))?;
+    let mut plain = Zeroizing::new(vec![0; size - OVERHEAD]);
+    plain[..4].copy_from_slice(&length.to_be_bytes());
+    plain[4..4 + payload.len()].copy_from_slice(payload);
+    rng.fill_bytes(&mut plain[4 + payload.len()..]);
+    seal_with_rng(&plain, password, rng)
+}
+
+/// Reverses the [`seal_volume`](fn.seal_volume.html) function with the same password.
+///
+/// # Errors
+/// [`WrongPassword`](../enum.Error.html#variant.WrongPassword) when the password is not the one of the volume,
+/// which is exactly the same as when there is no volume at all.
+pub fn open_volume(sealed: &[u8], password: &str) -> Result<Vec<u8>, Error> {
+    let plain = Zeroizing::new(open(sealed, password)?);
+    let length = plain.get(..4)
+        .map(|length| u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize)
+        .filter(|&length| 4 + length <= plain.len())
+        .ok_or(Error::WrongPassword)?;
+    Ok(plain[4..4 + length].to_vec())
+}
+
+/// Size of the file key wrapped for each of the recipients, with its authentication tag
+pub const WRAPPED_KEY_SIZE: usize = KEY_SIZE + TAG_SIZE;
+
+/// Derives the key that wraps the file key for one recipient from the X25519 shared secret
+/// and both of the public keys involved
+fn wrapping_key(shared: &[u8], ephemeral: &[u8], recipient: &[u8]) -> XChaCha20Poly1305 {
+    let key = Sha256::new()
+        .chain_update(b"steganographer x25519")
+        .chain_update(shared)
+        .chain_update(ephemeral)
+        .chain_update(recipient)
+        .finalize();
+    XChaCha20Poly1305::new(&key)
+}
+
+/// Encrypts the payload with a random file key, which is in turn encrypted to each of the X25519 public keys
+/// of the recipients, so that any one of them can decrypt it with their secret key and nobody has to share a password.
+///
+/// The result starts with a random ephemeral public key and the number of recipients, followed by
+/// the file key wrapped for each of them, the nonce and the encrypted payload, which is
+/// `KEY_SIZE + 2 + recipients.len() * WRAPPED_KEY_SIZE + NONCE_SIZE + TAG_SIZE` bytes larger than the payload.
+/// The recipients cannot be told from the result without their secret keys.
+///
+/// # Errors
+/// [`InvalidKey`](../enum.Error.html#variant.InvalidKey) when there are no recipients or too many of them.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::crypto::{open_for, seal_for};
+/// # use steganographer_core::keys::{generate, KeyKind};
+/// let alice = generate(KeyKind::Encryption);
+/// let bob = generate(KeyKind::Encryption);
+/// let eve = generate(KeyKind::Encryption);
+///
+/// let sealed = seal_for(b"secret", &[alice.public.unwrap(), bob.public.unwrap()]).unwrap();
+///
+/// assert_eq!(open_for(&sealed, &alice.secret).unwrap(), b"secret");
+/// assert_eq!(open_for(&sealed, &bob.secret).unwrap(), b"secret");
+/// assert!(open_for(&sealed, &eve.secret).is_err());
+/// ```
+///
+pub fn seal_for(payload: &[u8], recipients: &[[u8; KEY_SIZE]]) -> Result<Vec<u8>, Error> {
+    seal_for_with_rng(payload, recipients, &mut OsRng)
+}
+
+/// Same as [`seal_for`](fn.seal_for.html), but the ephemeral key, the file key and the nonce come from the given generator
+pub(crate) fn seal_for_with_rng(payload: &[u8], recipients: &[[u8; KEY_SIZE]], rng: &mut (impl RngCore + CryptoRng)) -> Result<Vec<u8>, Error> {
+    let count = u16::try_from(recipients.len()).ok()
+        .filter(|&count| count > 0)
+        .ok_or_else(|| Error::InvalidKey(format!("cannot encrypt to {} recipients", recipients.len())))?;
+    let ephemeral = StaticSecret::random_from_rng(&mut *rng);
+    let ephemeral_public = PublicKey::from(&ephemeral).to_bytes();
+    let mut file_key = Zeroizing::new([0; KEY_SIZE]);
+    rng.fill_bytes(&mut file_key[..]);
+
+    let mut sealed = ephemeral_public.to_vec();
+    sealed.extend_from_slice(&count.to_be_bytes());
+    for recipient in recipients {
+        let shared = ephemeral.diffie_hellman(&PublicKey::from(*recipient));
+        // every wrapping key is only ever used once, so the nonce does not have to be random
+        let wrapped = wrapping_key(shared.as_bytes(), &ephemeral_public, recipient)
+            .encrypt(&XNonce::default(), &file_key[..])
+            .expect("a key is never too large to be encrypted");
+        sealed.extend(wrapped);
+    }
+    let mut nonce = XNonce::default();
+    rng.fill_bytes(&mut nonce);
+    let encrypted = XChaCha20Poly1305::new_from_slice(&file_key[..])
+        .expect("the key is of the right size")
+        .encrypt(&nonce, payload)
+        .map_err(|_| Error::InvalidKey("the payload is too large to be encrypted".into()))?;
+    sealed.extend_from_slice(&nonce);
+    sealed.extend(encrypted);
+    Ok(sealed)
+}
+
+/// Reverses the [`seal_for`](fn.seal_for.html) function with the X25519 secret key of any of the recipients.
+///
+/// # Errors
+/// [`InvalidKey`](../enum.Error.html#variant.InvalidKey) when the data was not encrypted to this key,
+/// was altered since or was not encrypted at all.
+pub fn open_for(sealed: &[u8], secret: &[u8; KEY_SIZE]) -> Result<Vec<u8>, Error> {
+    let not_recipient = || Error::InvalidKey("the data was not encrypted to this key".into());
+    let secret = StaticSecret::from(*secret);
+    let public = PublicKey::from(&secret).to_bytes();
+    let (ephemeral, rest) = sealed.split_at_checked(KEY_SIZE).ok_or_else(not_recipient)?;
+    let (count, rest) = rest.split_at_checked(2).ok_or_else(not_recipient)?;
+    let count = u16::from_be_bytes([count[0], count[1]]) as usize;
+    let (stanzas, rest) = rest.split_at_checked(count * WRAPPED_KEY_SIZE).ok_or_else(not_recipient)?;
+    let (nonce, encrypted) = rest.split_at_checked(NONCE_SIZE).ok_or_else(not_recipient)?;
+
+    let ephemeral = <[u8; KEY_SIZE]>::try_from(ephemeral).expect("the slice is of the key size");
+    let shared = secret.diffie_hellman(&PublicKey::from(ephemeral));
+    let wrapping_key = wrapping_key(shared.as_bytes(), &ephemeral, &public);
+    let file_key = stanzas.chunks_exact(WRAPPED_KEY_SIZE)
+        .find_map(|wrapped| wrapping_key.decrypt(&XNonce::default(), wrapped).ok())
+        .map(Zeroizing::new)
+        .ok_or_else(not_recipient)?;
+    XChaCha20Poly1305::new_from_slice(&file_key)
+        .map_err(|_| not_recipient())?
+        .decrypt(XNonce::from_slice(nonce), encrypted)
+        .map_err(|_| not_recipient())
+}
@@ -0,0 +1,105 @@
+use std::{
+    error::Error as StdError,
+    fmt,
+};
+
+/// Common Rust error implementation for this crate.
+///
+/// There is a wrapper variant for any other errors
+/// as well as specialized variants with crate-related logical errors.
+#[derive(Debug)]
+pub enum Error {
+    /// Specified number of bits is not between 1 and 8
+    WrongBits(u8),
+    /// Carrier file is not of a supported format or cannot hold the data
+    UnsupportedCarrier(String),
+    /// Key file is malformed or holds a key of some other kind
+    InvalidKey(String),
+    /// Signature of the carrier does not match its contents or the given key
+    InvalidSignature,
+    /// The data was encrypted with some other password, was altered since or was not encrypted at all
+    WrongPassword,
+    /// The integrity tag of the hidden data does not match it, so the data is damaged or there is none at all
+    IntegrityFailure,
+    /// The carrier cannot hold the data
+    InsufficientCapacity {
+        /// Size of the data in bytes
+        needed: usize,
+        /// How many bytes the carrier can hold
+        available: usize,
+    },
+    /// The extracted data of the given MIME type is binary and the stdout it is to be written to is a terminal
+    BinaryToTerminal(&'static str),
+    /// The operation needs a cargo feature this build was compiled without
+    MissingFeature(&'static str),
+    /// Wrapped lower level errors
+    Wrapped(Box<dyn StdError>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Error::*;
+        match self {
+            WrongBits(bits) => write!(f, "Specified number of bits ({}) is not between 1 and 8", bits),
+            UnsupportedCarrier(reason) => write!(f, "Unsupported carrier: {}", reason),
+            InvalidKey(reason) => write!(f, "Invalid key: {}", reason),
+            InvalidSignature => write!(f, "The signature is invalid, the file was altered or signed with some other key"),
+            WrongPassword => write!(f, "The password is wrong, or the data was altered or not encrypted at all"),
+            IntegrityFailure => write!(f, "The hidden data is damaged, or there is no data hidden at all"),
+            InsufficientCapacity { needed, available } => write!(f, "The data takes {}, but the carrier can only hold {}",
+                                                                 human_size(*needed), human_size(*available)),
+            BinaryToTerminal(mime) => write!(f, "The data is binary ({}), so it is not printed to the terminal", mime),
+            MissingFeature(feature) => write!(f, "This build was compiled without the `{}` feature", feature),
+            Wrapped(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Formats the number of bytes in the largest binary unit it has at least one of
+fn human_size(bytes: usize) -> String {
+    let mut size = bytes as f64;
+    let mut unit = None;
+    for next in &["KiB", "MiB", "GiB", "TiB"] {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = Some(next);
+    }
+    match unit {
+        Some(unit) => format!("{:.1} {} ({} bytes)", size, unit, bytes),
+        None => format!("{} bytes", bytes),
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::Wrapped(e) => Some(&**e),
+            _ => None
+        }
+    }
+}
+
+/// This macro exists due to inability to implement a generic From
+/// for all `T: std::error::Error`
+macro_rules! from_impls {
+    ($($wrapped:ty),*) => {
+        $(
+            impl From<$wrapped> for Error {
+                fn from(e: $wrapped) -> Self {
+                    Error::Wrapped(Box::new(e))
+                }
+            }
+        )*
+    };
+}
+
+from_impls!(std::io::Error, image::ImageError, gif::DecodingError, gif::EncodingError,
+            png::DecodingError, png::EncodingError);
+
+#[cfg(feature = "watch")]
+from_impls!(notify::Error);
+
+#[cfg(feature = "keyring")]
+from_impls!(keyring::Error);
@@ -0,0 +1,58 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::{decode_image, encode_image, DecodeOptions, EncodeOptions, Error};
+
+/// The first bytes of every PNG file
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Hides the contents read from the input in the cover PNG image and writes the resulting image to the output,
+/// which is what git stores in the repository.
+///
+/// The embedding does not use any randomness, so the same contents and cover always give the same image
+/// and git does not see the file as modified when it was not.
+pub fn clean(cover: &Path, mut input: impl Read, output: impl Write, options: &EncodeOptions) -> Result<(), Error> {
+    let mut contents = Vec::new();
+    input.read_to_end(&mut contents)?;
+    encode_image(File::open(cover)?, &contents, output, options)
+}
+
+/// Extracts the contents hidden with [`clean`](fn.clean.html) from the image read from the input
+/// and writes them to the output, which is what git puts into the working tree.
+///
+/// Anything that is not a PNG image is written to the output as is, so that the files
+/// committed before the filter was configured can still be checked out.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::{DecodeOptions, EncodeOptions};
+/// # use steganographer_core::filter::{clean, smudge};
+/// # use image::{ColorType, ImageEncoder};
+/// # use image::codecs::png::PngEncoder;
+/// let cover = std::env::temp_dir().join("steganographer-filter-cover.png");
+/// PngEncoder::new(std::fs::File::create(&cover).unwrap()).write_image(&[0; 32 * 32 * 3], 32, 32, ColorType::Rgb8).unwrap();
+///
+/// let mut stored = Vec::new();
+/// clean(&cover, &b"API_KEY=hunter2\n"[..], &mut stored, &EncodeOptions::new()).unwrap();
+/// let mut checked_out = Vec::new();
+/// smudge(&stored[..], &mut checked_out, &DecodeOptions::new()).unwrap();
+/// assert_eq!(checked_out, b"API_KEY=hunter2\n");
+///
+/// // committed before the filter was there
+/// let mut checked_out = Vec::new();
+/// smudge(&b"API_KEY=changeme\n"[..], &mut checked_out, &DecodeOptions::new()).unwrap();
+/// assert_eq!(checked_out, b"API_KEY=changeme\n");
+/// ```
+pub fn smudge(mut input: impl Read, mut output: impl Write, options: &DecodeOptions) -> Result<(), Error> {
+    let mut stored = Vec::new();
+    input.read_to_end(&mut stored)?;
+    if !stored.starts_with(PNG_SIGNATURE) {
+        output.write_all(&stored)?;
+        return Ok(());
+    }
+    let (contents, _) = decode_image(&stored[..], options)?;
+    output.write_all(&contents)?;
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use std::sync::OnceLock;
+
+use wgpu::util::DeviceExt;
+
+use crate::binary::Layout;
+
+/// Invocations in each workgroup of the shaders
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Largest amount of workgroups along one dimension of a dispatch
+const MAX_WORKGROUPS: u32 = 65535;
+
+/// The device shared by the [`texture_costs`](fn.texture_costs.html) calls, set up by the first one of them
+static GPU: OnceLock<Option<Gpu>> = OnceLock::new();
+
+/// Same as [`texture_costs`](../stc/fn.texture_costs.html), one invocation per carrier byte
+const TEXTURE_COSTS: &str = r#"
+struct Params {
+    len: u32,
+    step: u32,
+    stride: u32,
+    row: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> pixels: array<u32>;
+@group(0) @binding(2) var<storage, read_write> costs: array<f32>;
+
+fn sample(i: u32) -> f32 {
+    return f32(pixels[i / 4u] >> (i % 4u * 8u) & 0xFFu);
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.y * params.row + id.x;
+    if (i >= params.len) {
+        return;
+    }
+    let value = sample(i);
+    var sum = 0.0;
+    var count = 0.0;
+    if (i >= params.step) {
+        sum += abs(sample(i - params.step) - value);
+        count += 1.0;
+    }
+    if (i + params.step < params.len) {
+        sum += abs(sample(i + params.step) - value);
+        count += 1.0;
+    }
+    if (i >= params.stride) {
+        sum += abs(sample(i - params.stride) - value);
+        count += 1.0;
+    }
+    if (i + params.stride < params.len) {
+        sum += abs(sample(i + params.stride) - value);
+        count += 1.0;
+    }
+    costs[i] = 1.0 / (0.1 + sum / max(count, 1.0));
+}
+"#;
+
+/// A GPU device with the compute pipelines set up, worth keeping around for batch jobs.
+pub struct Gpu {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    texture_costs: wgpu::ComputePipeline,
+}
+
+impl Gpu {
+    /// Sets up the default GPU adapter, `None` when there is none that works.
+    pub fn new() -> Option<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))?;
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None)).ok()?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("texture costs"),
+            source: wgpu::ShaderSource::Wgsl(TEXTURE_COSTS.into()),
+        });
+        let texture_costs = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("texture costs"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        Some(Gpu { device, queue, texture_costs })
+    }
+
+    /// Computes the [`texture_costs`](../stc/fn.texture_costs.html) on the GPU,
+    /// `None` when the carrier is too large for the buffers this device supports.
+    pub fn texture_costs(&self, pixels: &[u8], width: usize, layout: Layout) -> Option<Vec<f32>> {
+        let len = pixels.len();
+        let output_size = (len * 4) as u64;
+        if len == 0 || output_size > self.device.limits().max_storage_buffer_binding_size as u64 {
+            return None;
+        }
+
+        let mut packed = vec![0u32; len.div_ceil(4)];
+        for (i, &byte) in pixels.iter().enumerate() {
+            packed[i / 4] |= (byte as u32) << (i % 4 * 8);
+        }
+        let groups = (len as u32).div_ceil(WORKGROUP_SIZE);
+        let (x, y) = (groups.min(MAX_WORKGROUPS), groups.div_ceil(MAX_WORKGROUPS));
+        let step = layout.pixel_size() as u32;
+        let params = [len as u32, step, width as u32 * step, x * WORKGROUP_SIZE];
+
+        let params = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&params),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let input = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&packed),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let output = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: output_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: output_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.texture_costs.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: params.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: input.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: output.as_entire_binding() },
+            ],
+        });
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(&self.texture_costs);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(x, y, 1);
+        }
+        encoder.copy_buffer_to_buffer(&output, 0, &staging, 0, output_size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = staging.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        self.device.poll(wgpu::Maintain::Wait);
+        let costs = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+        staging.unmap();
+        Some(costs)
+    }
+}
+
+/// Computes the [`texture_costs`](../stc/fn.texture_costs.html) on the GPU when there is one
+/// and the carrier fits into its buffers, and on the CPU otherwise.
+///
+/// The GPU is only set up once, on the first call, and every later one reuses it.
+pub fn texture_costs(pixels: &[u8], width: usize, layout: Layout) -> Vec<f32> {
+    GPU.get_or_init(Gpu::new).as_ref()
+        .and_then(|gpu| gpu.texture_costs(pixels, width, layout))
+        .unwrap_or_else(|| crate::stc::texture_costs(pixels, width, layout))
+}
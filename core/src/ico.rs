@@ -0,0 +1,190 @@
+use std::io::Cursor;
+use std::ops::Range;
+
+use byteorder::{ByteOrder, LittleEndian};
+use image::{ColorType, ImageDecoder};
+use image::codecs::png::{PngDecoder, PngEncoder};
+
+use crate::binary::{Bits, hide_bytes, reveal_bytes};
+use crate::Error;
+
+const DIR_SIZE: usize = 6;
+const ENTRY_SIZE: usize = 16;
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+fn malformed(what: &str) -> Error {
+    Error::UnsupportedCarrier(format!("malformed ICO file: {}", what))
+}
+
+/// One of the images of the icon, either a PNG file or a headerless BMP
+enum Image<'a> {
+    /// Decoded PNG image
+    Png { width: u32, height: u32, color_type: ColorType, pixels: Vec<u8> },
+    /// Ranges of the color bytes (without the row padding and the alpha bytes) of the color bitmap
+    Bmp(&'a [u8], Vec<Range<usize>>),
+    /// Paletted or compressed BMP images which are left alone
+    Other(&'a [u8]),
+}
+
+fn bmp_rows(data: &[u8]) -> Result<Option<Vec<Range<usize>>>, Error> {
+    if data.len() < 40 {
+        return Err(malformed("BMP image header is truncated"));
+    }
+    let header_size = LittleEndian::read_u32(data) as usize;
+    let width = LittleEndian::read_i32(&data[4..]).unsigned_abs() as usize;
+    // icon bitmaps store the height of the color and transparency masks combined
+    let height = LittleEndian::read_i32(&data[8..]).unsigned_abs() as usize / 2;
+    let bpp = LittleEndian::read_u16(&data[14..]) as usize;
+    let compression = LittleEndian::read_u32(&data[16..]);
+    let palette = LittleEndian::read_u32(&data[32..]) as usize;
+
+    if bpp < 24 || !(compression == 0 || compression == 3 && bpp == 32) {
+        return Ok(None);
+    }
+    let start = header_size + palette * 4 + if compression == 3 && header_size == 40 { 12 } else { 0 };
+    let stride = (width * bpp).div_ceil(32) * 4;
+    let end = stride.checked_mul(height)
+        .and_then(|size| size.checked_add(start))
+        .ok_or_else(|| malformed("BMP image dimensions are too large"))?;
+    if end > data.len() {
+        return Err(malformed("BMP image is truncated"));
+    }
+    if bpp == 32 {
+        // the alpha byte of every pixel is left alone, same as the one of the other images
+        return Ok(Some((0..height)
+            .flat_map(|row| (0..width).map(move |pixel| start + row * stride + pixel * 4))
+            .map(|pixel| pixel..pixel + 3)
+            .collect()));
+    }
+    Ok(Some((0..height).map(|row| start + row * stride..start + row * stride + width * bpp / 8).collect()))
+}
+
+fn parse(ico: &[u8]) -> Result<Vec<(&[u8], Image<'_>)>, Error> {
+    if ico.len() < DIR_SIZE || ico[..4] != [0, 0, 1, 0] {
+        return Err(Error::UnsupportedCarrier("not an ICO file".into()));
+    }
+    let count = LittleEndian::read_u16(&ico[4..]) as usize;
+    (0..count)
+        .map(|i| {
+            let entry = ico.get(DIR_SIZE + i * ENTRY_SIZE..DIR_SIZE + (i + 1) * ENTRY_SIZE)
+                .ok_or_else(|| malformed("directory is truncated"))?;
+            let size = LittleEndian::read_u32(&entry[8..]) as usize;
+            let offset = LittleEndian::read_u32(&entry[12..]) as usize;
+            let data = ico.get(offset..offset + size).ok_or_else(|| malformed("image is out of bounds"))?;
+            let image = if data.starts_with(PNG_MAGIC) {
+                let decoder = PngDecoder::new(Cursor::new(data))?;
+                let (width, height) = decoder.dimensions();
+                let color_type = decoder.color_type();
+                let mut pixels = vec![0; decoder.total_bytes() as usize];
+                decoder.read_image(&mut pixels)?;
+                Image::Png { width, height, color_type, pixels }
+            } else {
+                match bmp_rows(data)? {
+                    Some(rows) => Image::Bmp(data, rows),
+                    None => Image::Other(data),
+                }
+            };
+            Ok((entry, image))
+        })
+        .collect()
+}
+
+/// Collects the carrier bytes of every image in order into a single stream
+fn carrier_stream(images: &[(&[u8], Image)]) -> Vec<u8> {
+    let mut stream = Vec::new();
+    for (_, image) in images {
+        match image {
+            Image::Png { pixels, .. } => stream.extend_from_slice(pixels),
+            Image::Bmp(data, rows) => rows.iter().for_each(|row| stream.extend_from_slice(&data[row.clone()])),
+            Image::Other(_) => {},
+        }
+    }
+    stream
+}
+
+/// Hides a slice of bytes along with its length in the images of an ICO file.
+///
+/// The payload is distributed over all of the resolutions present in the icon, one after another,
+/// so that they all contribute their capacity.
+/// Both PNG and uncompressed 24 or 32 bit BMP images are used, without the alpha bytes of the latter,
+/// and images with palettes are left as is.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when `ico` is not a valid icon file,
+/// and `UnexpectedEof` IO error when all of the images combined do not have enough capacity.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::binary::Bits;
+/// # use steganographer_core::ico::{hide_in_ico, reveal_from_ico};
+/// // a single 16x16 BMP image of 32 bits per pixel, the height counts the rows of its transparency mask too
+/// let mut bmp = vec![0; 40];
+/// bmp[0] = 40;
+/// bmp[4] = 16;
+/// bmp[8] = 32;
+/// bmp[12] = 1;
+/// bmp[14] = 32;
+/// bmp.extend((0..16 * 16).flat_map(|i| vec![i as u8, 90, 180, 255]));
+/// bmp.extend(&[0; 16 * 4]);
+/// let mut ico = vec![0, 0, 1, 0, 1, 0, 16, 16, 0, 0, 1, 0, 32, 0];
+/// ico.extend(&(bmp.len() as u32).to_le_bytes());
+/// ico.extend(&22u32.to_le_bytes());
+/// ico.extend(&bmp);
+///
+/// let encoded = hide_in_ico(&ico, b"favicon", Bits::Two, 0).unwrap();
+/// assert_eq!(reveal_from_ico(&encoded, Bits::Two).unwrap(), (b"favicon".to_vec(), 0));
+///
+/// // the alpha bytes are left alone
+/// assert!(encoded[22 + 40..][..16 * 16 * 4].chunks(4).all(|pixel| pixel[3] == 255));
+/// ```
+///
+pub fn hide_in_ico(ico: &[u8], payload: &[u8], bits: Bits, flags: u8) -> Result<Vec<u8>, Error> {
+    let images = parse(ico)?;
+    let mut stream = carrier_stream(&images);
+    let hidden = hide_bytes(payload, &stream[..], bits, flags)?;
+    stream[..hidden.len()].copy_from_slice(&hidden);
+
+    let mut stream = &stream[..];
+    let mut encoded = Vec::with_capacity(images.len());
+    for (_, image) in &images {
+        encoded.push(match image {
+            Image::Png { width, height, color_type, pixels } => {
+                let mut png = Vec::with_capacity(pixels.len());
+                PngEncoder::new(&mut png).encode(&stream[..pixels.len()], *width, *height, *color_type)?;
+                stream = &stream[pixels.len()..];
+                png
+            },
+            Image::Bmp(data, rows) => {
+                let mut bmp = data.to_vec();
+                for row in rows {
+                    bmp[row.clone()].copy_from_slice(&stream[..row.len()]);
+                    stream = &stream[row.len()..];
+                }
+                bmp
+            },
+            Image::Other(data) => data.to_vec(),
+        });
+    }
+
+    let mut result = ico[..DIR_SIZE].to_vec();
+    let mut offset = DIR_SIZE + images.len() * ENTRY_SIZE;
+    for ((entry, _), data) in images.iter().zip(&encoded) {
+        let mut entry = entry.to_vec();
+        LittleEndian::write_u32(&mut entry[8..], data.len() as u32);
+        LittleEndian::write_u32(&mut entry[12..], offset as u32);
+        result.extend_from_slice(&entry);
+        offset += data.len();
+    }
+    encoded.iter().for_each(|data| result.extend_from_slice(data));
+    Ok(result)
+}
+
+/// Reveals a slice of bytes previously hidden by the [`hide_in_ico`](fn.hide_in_ico.html) function, along with its flags.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when `ico` is not a valid icon file,
+/// and lower-level errors same as [`reveal_bytes`](../binary/fn.reveal_bytes.html).
+pub fn reveal_from_ico(ico: &[u8], bits: Bits) -> Result<(Vec<u8>, u8), Error> {
+    reveal_bytes(&carrier_stream(&parse(ico)?)[..], bits)
+}
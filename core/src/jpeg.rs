@@ -0,0 +1,442 @@
+use std::convert::TryFrom;
+
+use crate::Error;
+
+/// Size of the payload length that comes before the payload in the coefficients
+pub const LENGTH_SIZE: usize = 4;
+
+const SOF0: u8 = 0xC0;
+const SOF1: u8 = 0xC1;
+const DHT: u8 = 0xC4;
+const DAC: u8 = 0xCC;
+const RST0: u8 = 0xD0;
+const EOI: u8 = 0xD9;
+const SOS: u8 = 0xDA;
+const DRI: u8 = 0xDD;
+
+fn malformed(what: &str) -> Error {
+    Error::UnsupportedCarrier(format!("malformed JPEG file: {}", what))
+}
+
+/// A Huffman table in the form both the decoder and the encoder need
+#[derive(Clone)]
+struct Huffman {
+    /// The code and its length for every symbol, the length being 0 for the ones that are not in the table
+    codes: Vec<(u16, u8)>,
+    max_code: [i32; 17],
+    min_code: [i32; 17],
+    offsets: [usize; 17],
+    values: Vec<u8>,
+}
+
+impl Huffman {
+    fn new(counts: &[u8], values: &[u8]) -> Self {
+        let mut table = Huffman {
+            codes: vec![(0, 0); 256],
+            max_code: [-1; 17],
+            min_code: [0; 17],
+            offsets: [0; 17],
+            values: values.to_vec(),
+        };
+        let (mut code, mut k) = (0i32, 0);
+        for len in 1..=16 {
+            table.offsets[len] = k;
+            table.min_code[len] = code;
+            for _ in 0..counts[len - 1] {
+                table.codes[values[k] as usize] = (code as u16, len as u8);
+                code += 1;
+                k += 1;
+            }
+            table.max_code[len] = code - 1;
+            code <<= 1;
+        }
+        table
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u8, Error> {
+        let mut code = 0;
+        for len in 1..=16 {
+            code = code << 1 | reader.bit()? as i32;
+            if code <= self.max_code[len] && code >= self.min_code[len] {
+                return Ok(self.values[self.offsets[len] + (code - self.min_code[len]) as usize]);
+            }
+        }
+        Err(malformed("invalid Huffman code"))
+    }
+
+    fn encode(&self, symbol: u8, writer: &mut BitWriter) -> Result<(), Error> {
+        match self.codes[symbol as usize] {
+            (_, 0) => Err(malformed("the Huffman tables do not cover the coefficients")),
+            (code, len) => {
+                writer.put(code as u32, len);
+                Ok(())
+            },
+        }
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    byte: u8,
+    left: u8,
+}
+
+impl BitReader<'_> {
+    fn bit(&mut self) -> Result<u8, Error> {
+        if self.left == 0 {
+            self.byte = match self.data.get(self.position..self.position + 2) {
+                Some([0xFF, 0x00]) => {
+                    self.position += 2;
+                    0xFF
+                },
+                Some([0xFF, _]) => return Err(malformed("the scan ends too early")),
+                _ => {
+                    let byte = *self.data.get(self.position).ok_or_else(|| malformed("the scan is truncated"))?;
+                    self.position += 1;
+                    byte
+                },
+            };
+            self.left = 8;
+        }
+        self.left -= 1;
+        Ok(self.byte >> self.left & 1)
+    }
+
+    fn receive(&mut self, size: u8) -> Result<i32, Error> {
+        let mut value = 0;
+        for _ in 0..size {
+            value = value << 1 | self.bit()? as i32;
+        }
+        Ok(match size {
+            0 => 0,
+            _ if value < 1 << (size - 1) => value - (1 << size) + 1,
+            _ => value,
+        })
+    }
+
+    fn restart(&mut self) -> Result<(), Error> {
+        self.left = 0;
+        match self.data.get(self.position..self.position + 2) {
+            Some([0xFF, marker]) if (RST0..RST0 + 8).contains(marker) => {
+                self.position += 2;
+                Ok(())
+            },
+            _ => Err(malformed("a restart marker is missing")),
+        }
+    }
+}
+
+#[derive(Default)]
+struct BitWriter {
+    data: Vec<u8>,
+    byte: u32,
+    used: u8,
+}
+
+impl BitWriter {
+    fn put(&mut self, bits: u32, len: u8) {
+        for i in (0..len).rev() {
+            self.byte = self.byte << 1 | bits >> i & 1;
+            self.used += 1;
+            if self.used == 8 {
+                self.data.push(self.byte as u8);
+                if self.byte == 0xFF {
+                    self.data.push(0);
+                }
+                self.byte = 0;
+                self.used = 0;
+            }
+        }
+    }
+
+    fn value(&mut self, table: &Huffman, run: u8, value: i32) -> Result<(), Error> {
+        let size = (32 - value.unsigned_abs().leading_zeros()) as u8;
+        table.encode(run << 4 | size, self)?;
+        self.put(if value < 0 { (value - 1) as u32 } else { value as u32 }, size);
+        Ok(())
+    }
+
+    /// Pads the last byte with ones, as the standard requires
+    fn align(&mut self) {
+        if self.used > 0 {
+            self.put(0xFF, 8 - self.used);
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Component {
+    id: u8,
+    horizontal: usize,
+    vertical: usize,
+}
+
+/// The first scan of a baseline JPEG decoded down to the quantized DCT coefficients
+struct Scan<'a> {
+    jpeg: &'a [u8],
+    /// Where the entropy-coded data of the scan starts and ends
+    start: usize,
+    end: usize,
+    restart_interval: usize,
+    mcus: usize,
+    /// DC and AC tables of every component of the scan, in the order of the scan
+    tables: Vec<(Huffman, Huffman)>,
+    /// Index of the component of the scan for each block of the MCU
+    mcu: Vec<usize>,
+    /// Coefficients of all the blocks in the zigzag order
+    blocks: Vec<[i32; 64]>,
+}
+
+fn segment(jpeg: &[u8], at: usize) -> Result<&[u8], Error> {
+    let len = jpeg.get(at..at + 2).map(|len| u16::from_be_bytes([len[0], len[1]]) as usize)
+        .ok_or_else(|| malformed("a segment is truncated"))?;
+    jpeg.get(at + 2..at + len).filter(|_| len >= 2).ok_or_else(|| malformed("a segment is truncated"))
+}
+
+fn read_scan(jpeg: &[u8]) -> Result<Scan<'_>, Error> {
+    if !jpeg.starts_with(&[0xFF, 0xD8]) {
+        return Err(Error::UnsupportedCarrier("not a JPEG file".into()));
+    }
+    let mut dc_tables: [Option<Huffman>; 4] = Default::default();
+    let mut ac_tables: [Option<Huffman>; 4] = Default::default();
+    let mut frame = None;
+    let mut restart_interval = 0;
+    let mut at = 2;
+    loop {
+        if jpeg.get(at) != Some(&0xFF) {
+            return Err(malformed("lost the segment boundary"));
+        }
+        while jpeg.get(at) == Some(&0xFF) {
+            at += 1;
+        }
+        let marker = *jpeg.get(at).ok_or_else(|| malformed("the file is truncated"))?;
+        let data = segment(jpeg, at + 1)?;
+        at += 1 + 2 + data.len();
+        match marker {
+            DHT => {
+                let mut table = data;
+                while !table.is_empty() {
+                    let counts = table.get(1..17).ok_or_else(|| malformed("a Huffman table is truncated"))?;
+                    let len = counts.iter().map(|&c| c as usize).sum::<usize>();
+                    let values = table.get(17..17 + len).ok_or_else(|| malformed("a Huffman table is truncated"))?;
+                    let huffman = Some(Huffman::new(counts, values));
+                    match table[0] >> 4 {
+                        0 => dc_tables[table[0] as usize & 3] = huffman,
+                        _ => ac_tables[table[0] as usize & 3] = huffman,
+                    }
+                    table = &table[17 + len..];
+                }
+            },
+            SOF0 | SOF1 => {
+                let header = data.get(..6).ok_or_else(|| malformed("the frame header is truncated"))?;
+                let height = u16::from_be_bytes([header[1], header[2]]) as usize;
+                let width = u16::from_be_bytes([header[3], header[4]]) as usize;
+                let components = data[6..].chunks_exact(3)
+                    .take(header[5] as usize)
+                    .map(|c| Component { id: c[0], horizontal: (c[1] >> 4).max(1) as usize, vertical: (c[1] & 15).max(1) as usize })
+                    .collect::<Vec<_>>();
+                if height == 0 || components.is_empty() {
+                    return Err(Error::UnsupportedCarrier("JPEG files with the height defined later are not supported".into()));
+                }
+                frame = Some((width, height, components));
+            },
+            0xC2..=0xCB | 0xCD..=0xCF => {
+                return Err(Error::UnsupportedCarrier("only baseline JPEG files are supported, not progressive, \
+                                                      lossless or arithmetic-coded ones".into()))
+            },
+            DRI => restart_interval = data.get(..2).map(|d| u16::from_be_bytes([d[0], d[1]]) as usize).unwrap_or(0),
+            SOS => {
+                let (width, height, components) = frame.ok_or_else(|| malformed("the scan comes before the frame header"))?;
+                let max_h = components.iter().map(|c| c.horizontal).max().unwrap_or(1);
+                let max_v = components.iter().map(|c| c.vertical).max().unwrap_or(1);
+                let count = *data.first().ok_or_else(|| malformed("the scan header is truncated"))? as usize;
+                let mut tables = Vec::with_capacity(count);
+                let mut scan_components = Vec::with_capacity(count);
+                for spec in data[1..].chunks_exact(2).take(count) {
+                    let component = *components.iter().find(|c| c.id == spec[0])
+                        .ok_or_else(|| malformed("the scan has an unknown component"))?;
+                    let dc = dc_tables[spec[1] as usize >> 4 & 3].clone();
+                    let ac = ac_tables[spec[1] as usize & 3].clone();
+                    tables.push(dc.zip(ac).ok_or_else(|| malformed("a Huffman table is missing"))?);
+                    scan_components.push(component);
+                }
+                let (mcus, mcu) = match &scan_components[..] {
+                    // a single component is not interleaved, so its blocks cover just the component itself
+                    [c] => {
+                        let blocks_x = (width * c.horizontal).div_ceil(max_h).div_ceil(8);
+                        let blocks_y = (height * c.vertical).div_ceil(max_v).div_ceil(8);
+                        (blocks_x * blocks_y, vec![0])
+                    },
+                    components => {
+                        let mcus = width.div_ceil(8 * max_h) * height.div_ceil(8 * max_v);
+                        let mcu = components.iter().enumerate()
+                            .flat_map(|(i, c)| std::iter::repeat_n(i, c.horizontal * c.vertical))
+                            .collect();
+                        (mcus, mcu)
+                    },
+                };
+                let mut scan = Scan { jpeg, start: at, end: at, restart_interval, mcus, tables, mcu, blocks: Vec::new() };
+                decode_scan(&mut scan)?;
+                return Ok(scan);
+            },
+            EOI => return Err(malformed("there is no scan")),
+            DAC => return Err(Error::UnsupportedCarrier("arithmetic-coded JPEG files are not supported".into())),
+            _ => {},
+        }
+    }
+}
+
+fn decode_scan(scan: &mut Scan) -> Result<(), Error> {
+    let mut reader = BitReader { data: scan.jpeg, position: scan.start, byte: 0, left: 0 };
+    let mut predictions = vec![0; scan.tables.len()];
+    scan.blocks.reserve(scan.mcus * scan.mcu.len());
+    for mcu in 0..scan.mcus {
+        if scan.restart_interval > 0 && mcu > 0 && mcu % scan.restart_interval == 0 {
+            reader.restart()?;
+            predictions.iter_mut().for_each(|p| *p = 0);
+        }
+        for &component in &scan.mcu {
+            let (dc, ac) = &scan.tables[component];
+            let mut block = [0; 64];
+            let size = dc.decode(&mut reader)?;
+            predictions[component] += reader.receive(size)?;
+            block[0] = predictions[component];
+            let mut k = 1;
+            while k < 64 {
+                let symbol = ac.decode(&mut reader)?;
+                let (run, size) = (symbol >> 4, symbol & 15);
+                if size == 0 {
+                    if run != 15 {
+                        break;
+                    }
+                    k += 16;
+                    continue;
+                }
+                k += run as usize;
+                *block.get_mut(k).ok_or_else(|| malformed("a block has too many coefficients"))? = reader.receive(size)?;
+                k += 1;
+            }
+            scan.blocks.push(block);
+        }
+    }
+    scan.end = reader.position;
+    Ok(())
+}
+
+fn encode_scan(scan: &Scan) -> Result<Vec<u8>, Error> {
+    let mut writer = BitWriter::default();
+    let mut predictions = vec![0; scan.tables.len()];
+    let mut blocks = scan.blocks.iter();
+    for mcu in 0..scan.mcus {
+        if scan.restart_interval > 0 && mcu > 0 && mcu % scan.restart_interval == 0 {
+            writer.align();
+            writer.data.extend_from_slice(&[0xFF, RST0 + ((mcu / scan.restart_interval - 1) % 8) as u8]);
+            predictions.iter_mut().for_each(|p| *p = 0);
+        }
+        for (&component, block) in scan.mcu.iter().zip(&mut blocks) {
+            let (dc, ac) = &scan.tables[component];
+            writer.value(dc, 0, block[0] - predictions[component])?;
+            predictions[component] = block[0];
+            let mut run = 0;
+            for &coefficient in &block[1..] {
+                if coefficient == 0 {
+                    run += 1;
+                    continue;
+                }
+                while run > 15 {
+                    ac.encode(0xF0, &mut writer)?;
+                    run -= 16;
+                }
+                writer.value(ac, run, coefficient)?;
+                run = 0;
+            }
+            if run > 0 {
+                ac.encode(0x00, &mut writer)?;
+            }
+        }
+    }
+    writer.align();
+    Ok(writer.data)
+}
+
+/// The AC coefficients that hold the bits, which are the ones with the magnitude of at least 2,
+/// so that changing the lowest bit of the magnitude never makes them zero or changes their Huffman symbol
+fn usable(blocks: &mut [[i32; 64]]) -> impl Iterator<Item = &mut i32> {
+    blocks.iter_mut().flat_map(|block| block[1..].iter_mut()).filter(|c| c.abs() >= 2)
+}
+
+/// Number of bytes that can be hidden in the JPEG image with [`hide_in_jpeg`](fn.hide_in_jpeg.html).
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when `jpeg` is not a valid baseline JPEG image.
+pub fn jpeg_capacity(jpeg: &[u8]) -> Result<usize, Error> {
+    Ok((usable(&mut read_scan(jpeg)?.blocks).count() / 8).saturating_sub(LENGTH_SIZE))
+}
+
+/// Hides a slice of bytes in the quantized DCT coefficients of a baseline JPEG image, the way jsteg does,
+/// so that the payload is stored in the same lossy format the image already was in, unlike the pixel bits
+/// that the JPEG compression destroys.
+///
+/// Only the lowest bit of the magnitude of the AC coefficients of at least 2 is replaced,
+/// which never changes the Huffman symbols, so the image is written back with its own tables and markers
+/// and is about the same size. Everything outside of the first scan is left as it is.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when `jpeg` is not a valid baseline JPEG image,
+/// [`InsufficientCapacity`](../enum.Error.html#variant.InsufficientCapacity) when the payload does not fit into it.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::jpeg::{hide_in_jpeg, jpeg_capacity, reveal_from_jpeg};
+/// # use image::GenericImageView;
+/// let pixels = (0..64 * 64 * 3).map(|i| (i * 7 % 251) as u8).collect::<Vec<_>>();
+/// let mut jpeg = Vec::new();
+/// image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, 90)
+///     .encode(&pixels, 64, 64, image::ColorType::Rgb8)
+///     .unwrap();
+///
+/// let cloaked = hide_in_jpeg(&jpeg, b"secret").unwrap();
+///
+/// assert_eq!(reveal_from_jpeg(&cloaked).unwrap(), b"secret");
+/// assert_eq!(image::load_from_memory(&cloaked).unwrap().width(), 64);
+/// assert!(hide_in_jpeg(&jpeg, &vec![0; jpeg_capacity(&jpeg).unwrap() + 1]).is_err());
+/// ```
+///
+pub fn hide_in_jpeg(jpeg: &[u8], payload: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut scan = read_scan(jpeg)?;
+    let capacity = (usable(&mut scan.blocks).count() / 8).saturating_sub(LENGTH_SIZE);
+    let len = u32::try_from(payload.len()).ok().filter(|&len| len as usize <= capacity).ok_or_else(|| {
+        Error::InsufficientCapacity { needed: payload.len(), available: capacity }
+    })?;
+
+    let bits = len.to_be_bytes().iter().chain(payload).flat_map(|byte| (0..8).rev().map(move |i| byte >> i & 1)).collect::<Vec<_>>();
+    for (coefficient, bit) in usable(&mut scan.blocks).zip(bits) {
+        let magnitude = coefficient.abs() & !1 | bit as i32;
+        *coefficient = magnitude * coefficient.signum();
+    }
+
+    let mut result = jpeg[..scan.start].to_vec();
+    result.extend(encode_scan(&scan)?);
+    result.extend_from_slice(&jpeg[scan.end..]);
+    Ok(result)
+}
+
+/// Reveals a slice of bytes previously hidden by the [`hide_in_jpeg`](fn.hide_in_jpeg.html) function.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when `jpeg` is not a valid baseline JPEG image
+/// or holds a length that does not fit into it, which means there is no hidden data.
+pub fn reveal_from_jpeg(jpeg: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut scan = read_scan(jpeg)?;
+    let bits = usable(&mut scan.blocks).map(|c| (c.abs() & 1) as u8).collect::<Vec<_>>();
+    let bytes = bits.chunks_exact(8).map(|byte| byte.iter().fold(0, |acc, bit| acc << 1 | bit)).collect::<Vec<u8>>();
+    if bytes.len() < LENGTH_SIZE {
+        return Err(Error::UnsupportedCarrier("there is no hidden data in the JPEG image".into()));
+    }
+    let (len, data) = bytes.split_at(LENGTH_SIZE);
+    data.get(..u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| Error::UnsupportedCarrier("there is no hidden data in the JPEG image".into()))
+}
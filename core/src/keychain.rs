@@ -0,0 +1,65 @@
+use keyring::Entry;
+
+use crate::Error;
+
+/// Service name under which all of the entries are stored in the OS keyring
+pub const SERVICE: &str = "steganographer";
+
+/// Fetches the password stored in the OS keyring under the given entry name.
+///
+/// # Errors
+/// Keyring errors, most notably when there is no such entry or the keyring is locked.
+pub fn fetch_password(entry: &str) -> Result<String, Error> {
+    Ok(Entry::new(SERVICE, entry)?.get_password()?)
+}
+
+/// Stores the password in the OS keyring under the given entry name, replacing the previous one.
+///
+/// # Examples
+///
+/// ```
+/// # use std::any::Any;
+/// # use std::collections::HashMap;
+/// # use std::sync::{Arc, Mutex};
+/// # use keyring::credential::{Credential, CredentialApi, CredentialBuilderApi};
+/// # use steganographer_core::keychain::{delete_password, fetch_password, store_password};
+/// # type Store = Arc<Mutex<HashMap<(String, String), Vec<u8>>>>;
+/// # struct Memory(Store, (String, String));
+/// # impl CredentialApi for Memory {
+/// #     fn set_secret(&self, secret: &[u8]) -> keyring::Result<()> {
+/// #         self.0.lock().unwrap().insert(self.1.clone(), secret.to_vec());
+/// #         Ok(())
+/// #     }
+/// #     fn get_secret(&self) -> keyring::Result<Vec<u8>> {
+/// #         self.0.lock().unwrap().get(&self.1).cloned().ok_or(keyring::Error::NoEntry)
+/// #     }
+/// #     fn delete_credential(&self) -> keyring::Result<()> {
+/// #         self.0.lock().unwrap().remove(&self.1).map(|_| ()).ok_or(keyring::Error::NoEntry)
+/// #     }
+/// #     fn as_any(&self) -> &dyn Any { self }
+/// # }
+/// # struct MemoryBuilder(Store);
+/// # impl CredentialBuilderApi for MemoryBuilder {
+/// #     fn build(&self, _: Option<&str>, service: &str, user: &str) -> keyring::Result<Box<Credential>> {
+/// #         Ok(Box::new(Memory(self.0.clone(), (service.into(), user.into()))))
+/// #     }
+/// #     fn as_any(&self) -> &dyn Any { self }
+/// # }
+/// // an in-memory keyring instead of the one of the operating system
+/// keyring::set_default_credential_builder(Box::new(MemoryBuilder(Store::default())));
+///
+/// store_password("backups", "correct horse").unwrap();
+/// store_password("backups", "battery staple").unwrap();
+/// assert_eq!(fetch_password("backups").unwrap(), "battery staple");
+///
+/// delete_password("backups").unwrap();
+/// assert!(fetch_password("backups").is_err());
+/// ```
+pub fn store_password(entry: &str, password: &str) -> Result<(), Error> {
+    Ok(Entry::new(SERVICE, entry)?.set_password(password)?)
+}
+
+/// Removes the entry with the given name from the OS keyring.
+pub fn delete_password(entry: &str) -> Result<(), Error> {
+    Ok(Entry::new(SERVICE, entry)?.delete_credential()?)
+}
@@ -0,0 +1,156 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use ed25519_dalek::SigningKey;
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroize;
+
+use crate::Error;
+
+/// Size of every key this crate works with
+pub const KEY_SIZE: usize = 32;
+
+/// Kinds of keys that can be generated.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyKind {
+    /// X25519 keypair for encrypting the data to a recipient
+    Encryption,
+    /// Ed25519 keypair for signing the data
+    Signing,
+    /// A single random key for symmetric encryption and authentication
+    Symmetric,
+}
+
+impl KeyKind {
+    fn name(&self) -> &'static str {
+        match self {
+            KeyKind::Encryption => "x25519",
+            KeyKind::Signing => "ed25519",
+            KeyKind::Symmetric => "symmetric",
+        }
+    }
+}
+
+impl Display for KeyKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for KeyKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "x25519" => Ok(KeyKind::Encryption),
+            "ed25519" => Ok(KeyKind::Signing),
+            "symmetric" => Ok(KeyKind::Symmetric),
+            _ => Err(Error::InvalidKey(format!("unknown key kind `{}`", s))),
+        }
+    }
+}
+
+/// A freshly generated key, with the public half for the keypairs.
+///
+/// The secret key is wiped from the memory when this is dropped.
+#[derive(Clone)]
+pub struct GeneratedKey {
+    /// Kind of the key
+    pub kind: KeyKind,
+    /// The secret key, or the only key for the symmetric kind
+    pub secret: [u8; KEY_SIZE],
+    /// The public key, `None` for the symmetric kind
+    pub public: Option<[u8; KEY_SIZE]>,
+}
+
+impl Drop for GeneratedKey {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+impl GeneratedKey {
+    /// Returns the fingerprint of the public key, or of the key itself for the symmetric kind.
+    pub fn fingerprint(&self) -> String {
+        fingerprint(self.public.as_ref().unwrap_or(&self.secret))
+    }
+}
+
+/// Generates a new key of the given kind from the randomness of the operating system.
+pub fn generate(kind: KeyKind) -> GeneratedKey {
+    match kind {
+        KeyKind::Encryption => {
+            let secret = StaticSecret::random_from_rng(OsRng);
+            let public = PublicKey::from(&secret);
+            GeneratedKey { kind, secret: secret.to_bytes(), public: Some(public.to_bytes()) }
+        },
+        KeyKind::Signing => {
+            let secret = SigningKey::generate(&mut OsRng);
+            GeneratedKey { kind, secret: secret.to_bytes(), public: Some(secret.verifying_key().to_bytes()) }
+        },
+        KeyKind::Symmetric => {
+            let mut secret = [0; KEY_SIZE];
+            OsRng.fill_bytes(&mut secret);
+            GeneratedKey { kind, secret, public: None }
+        },
+    }
+}
+
+/// Returns a short human-readable fingerprint of the key: the first 8 bytes
+/// of its SHA-256 hash, in groups of 4 hex digits.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::keys::fingerprint;
+/// assert_eq!(fingerprint(&[0; 32]), "6668:7aad:f862:bd77");
+/// ```
+///
+pub fn fingerprint(key: &[u8]) -> String {
+    Sha256::digest(key)[..8]
+        .chunks(2)
+        .map(|pair| format!("{:02x}{:02x}", pair[0], pair[1]))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Formats the key as it is stored in the key files: a line telling what it is followed by a line of hex.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::keys::{format_key, parse_key, KeyKind};
+/// let text = format_key(KeyKind::Signing, true, &[7; 32]);
+/// assert!(text.starts_with("steganographer ed25519 public key\n"));
+/// assert_eq!(parse_key(&text, KeyKind::Signing, true).unwrap(), [7; 32]);
+/// ```
+///
+pub fn format_key(kind: KeyKind, public: bool, key: &[u8; KEY_SIZE]) -> String {
+    let hex = key.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    format!("steganographer {} {} key\n{}\n", kind, if public { "public" } else { "secret" }, hex)
+}
+
+/// Parses the key formatted by [`format_key`](fn.format_key.html), checking that it is of the expected kind.
+///
+/// # Errors
+/// [`InvalidKey`](../enum.Error.html#variant.InvalidKey) when the text is not a key of the expected kind.
+pub fn parse_key(text: &str, kind: KeyKind, public: bool) -> Result<[u8; KEY_SIZE], Error> {
+    let mut lines = text.lines();
+    let expected = format!("steganographer {} {} key", kind, if public { "public" } else { "secret" });
+    if lines.next().map(str::trim) != Some(&expected) {
+        return Err(Error::InvalidKey(format!("not a {} {} key", kind, if public { "public" } else { "secret" })));
+    }
+    let hex = lines.next().unwrap_or_default().trim();
+    let mut key = [0; KEY_SIZE];
+    if hex.len() != KEY_SIZE * 2 || !hex.is_ascii() {
+        return Err(Error::InvalidKey("the key is not 32 bytes of hex".into()));
+    }
+    for (byte, digits) in key.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = std::str::from_utf8(digits).ok()
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| Error::InvalidKey("the key is not 32 bytes of hex".into()))?;
+    }
+    Ok(key)
+}
@@ -0,0 +1,2188 @@
+#![warn(missing_docs)]
+
+//! This crate provides an extremely simple set of tools for hiding data in some other data.
+//!
+//! This is mainly targeted to hide data in images, because pixels are altered slightly so that
+//! the human eye would not notice the difference.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, IsTerminal, Read, stdout, Write, stdin};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+use sha2::{Digest, Sha256};
+use image::{ColorType, GenericImageView, ImageDecoder, ImageEncoder, ImageFormat};
+use image::codecs::bmp::{BmpDecoder, BmpEncoder};
+use image::codecs::png::PngDecoder;
+use image::codecs::tiff::{TiffDecoder, TiffEncoder};
+use image::codecs::webp::WebPDecoder;
+use zeroize::Zeroizing;
+
+mod error;
+mod options;
+
+/// This module provides utilities for loosely hiding bytes in some carrying binary data by
+/// replacing its least significant bits.
+///
+/// This can be used to hide data in a sligt unnoticeable color variations of some image file,
+/// for example.
+pub mod binary;
+
+/// This module provides the layout of the hidden data in the pixels of the images:
+/// a small header with its settings followed by the payload with its length.
+pub mod container;
+
+/// This module provides the table of contents that lets a single carrier hold several named payloads.
+pub mod toc;
+
+/// This module provides bundling a whole directory into a single payload and unpacking it back.
+pub mod bundle;
+
+/// This module provides the record of the name, the permissions, the modification time and the MIME type
+/// of the file the data came from, so that it can be restored as it was, and telling the type of the data
+/// from its magic bytes.
+pub mod metadata;
+
+/// This module provides compressing the payload before it is hidden, so that more of it fits into the carrier.
+pub mod compress;
+
+/// This module provides an alternative layout of the hidden data that can still be found
+/// after the image was cropped.
+pub mod resync;
+
+/// This module provides measuring how much the hidden data has altered the image.
+pub mod quality;
+
+/// This module provides measuring the noise of the channels and of the regions of the images, to pick the ones
+/// the hidden data is the least noticeable in.
+pub mod noise;
+
+/// This module provides simulating what happens to the images on their way to the recipient,
+/// to find out which of those transformations the hidden data survives.
+pub mod stress;
+
+/// This module provides generating and storing the keys for encrypting and signing the hidden data.
+pub mod keys;
+
+/// This module provides extracting the data from many files at once.
+pub mod bulk;
+
+/// This module provides watching a directory and extracting the data from every new file in it.
+#[cfg(feature = "watch")]
+pub mod watch;
+
+/// This module provides running the encoder and the decoder as a small HTTP service.
+#[cfg(feature = "serve")]
+pub mod serve;
+
+/// This module provides the git clean and smudge filters that keep the files in the repository
+/// hidden inside of a cover image, while the working tree has them as they are.
+///
+/// A repository using them would have something like `secrets.env filter=steganographer`
+/// in its `.gitattributes` and this in its config:
+/// ```text
+/// git config filter.steganographer.clean "steganographer filter --clean --cover assets/logo.png"
+/// git config filter.steganographer.smudge "steganographer filter --smudge"
+/// git config filter.steganographer.required true
+/// ```
+pub mod filter;
+
+/// This module provides keeping the passwords in the keyring of the operating system,
+/// so that they don't have to be typed in for every run.
+#[cfg(feature = "keyring")]
+pub mod keychain;
+
+/// This module provides sealing the hidden data for the key of a PIV or OpenPGP smartcard,
+/// so that the data can only be read with the card plugged in.
+#[cfg(feature = "smartcard")]
+pub mod smartcard;
+
+/// This module provides encrypting the hidden data with a password, so that finding it
+/// is not enough to read it.
+///
+/// The derived keys are wiped from the memory once they are used, and so are the plaintext buffers
+/// of the decoding functions, see [`with_decoded_image`](fn.with_decoded_image.html).
+pub mod crypto;
+
+/// This module provides whitening the hidden bytes with a keystream derived from a seed,
+/// so that even the length in front of the data does not stand out in the least significant bits.
+pub mod whitening;
+
+/// This module provides sealing the hidden data with a one-time pad, for when nothing
+/// short of the information-theoretic secrecy will do and the pads are managed by hand.
+pub mod otp;
+
+/// This module provides signing the whole image with the data hidden in it,
+/// so that it can be proven that the image was not altered since, or just the data itself,
+/// so that it can be told who it came from.
+pub mod signature;
+
+/// This module provides hiding data in the carriers where some of the bytes must not be changed,
+/// without the recipient having to know which ones.
+pub mod wetpaper;
+
+/// This module provides filling the capacity that is left after the payload with bits
+/// that look like the noise of the carrier itself, or with random ones.
+pub mod chaff;
+
+/// This module provides hiding data with the least possible distortion according to some cost model.
+pub mod stc;
+
+/// This module provides running the heavier per-pixel computations on the GPU.
+///
+/// That is only the cost model of the [STC](stc/index.html) embedding, which takes
+/// the most time of the encoding of the very large images. There are no transforms to offload:
+/// the [JPEG](jpeg/index.html) carrier changes the quantized DCT coefficients as they are stored
+/// in the file without computing the DCT itself, and none of the carriers uses a DWT.
+#[cfg(feature = "gpu")]
+pub mod gpu;
+
+/// This module provides hiding data in all of the images of the ICO files at once.
+pub mod ico;
+
+/// This module provides hiding data in the coordinates of SVG documents by altering them
+/// on the scale that is far below what anyone could notice.
+pub mod svg;
+
+/// This module provides hiding data in the structure of ZIP archives and all of the formats
+/// built on top of them (office documents, jars, etc.) without touching their contents.
+pub mod zip;
+
+/// This module provides hiding data in the MP4 and Matroska video files next to their streams,
+/// so that the videos stay playable without being transcoded.
+pub mod video;
+
+/// This module provides hiding data in the comments of the Ogg Vorbis and Ogg Opus audio files,
+/// next to the usual tags such as the title or the artist.
+pub mod ogg;
+
+/// This module provides hiding data in all the frames of animated GIFs at once,
+/// in the palette indices of their pixels.
+pub mod animation;
+
+/// This module provides hiding data in the quantized DCT coefficients of baseline JPEG images,
+/// where it is not lost to the lossy compression like the bits of the pixels are.
+pub mod jpeg;
+
+/// This module provides hiding data in all the frames of animated PNG images at once,
+/// keeping the animation as it was.
+pub mod apng;
+
+/// This module provides reading and writing the QOI images, which hold the data
+/// the same way as the PNG ones.
+pub mod qoi;
+
+/// This module provides carrying the ancillary chunks of the PNG images, such as the text and the gamma,
+/// over to the images with the data hidden in them.
+pub mod chunks;
+
+/// This module provides reading the images of the Netpbm family and writing the raw PGM and PPM ones,
+/// so that the tool can sit in the pipelines of the other image tools.
+pub mod pnm;
+
+/// This module provides the matrix embedding, hiding several bits of the data in a block of the carrier bits
+/// by changing at most one of them.
+pub mod matrix;
+
+/// This module provides telling the images with some data hidden in their least significant bits
+/// from the clean ones, the other side of what the rest of this crate does.
+pub mod steganalysis;
+
+/// This module provides hiding data in the least significant bits of the most textured regions of the images first,
+/// away from the flat ones where the changes are easy to notice.
+pub mod adaptive;
+
+/// This module provides the common interface of the ways of hiding the payload in the carrier bytes,
+/// from replacing the least significant bits to the adaptive codes.
+pub mod strategy;
+
+use container::{image_layout, Conversion, Header};
+use resync::{hide_resync, reveal_resync, DEFAULT_SEGMENT_SIZE};
+use stress::{stress, survey, Attack, Outcome, SurveyRow};
+use steganalysis::Detection;
+pub use error::Error;
+pub use options::{Applet, DecodeOptions, DecodeReport, EncodeOptions, Embedding, Inspection, OutputFormat, PngFilter, Quality, Recovered, RoundTrip};
+use options::PngOutput;
+pub use container::Traversal;
+pub use chaff::Filler;
+use chaff::{modeled_chaff_with_rng, random_chaff_with_rng};
+use zip::{hide_in_zip, reveal_from_zip};
+use video::{hide_in_video, reveal_from_video};
+use ogg::{hide_in_ogg, reveal_from_ogg};
+use animation::{hide_in_gif, reveal_from_gif};
+use jpeg::{hide_in_jpeg, reveal_from_jpeg};
+use qoi::QoiImage;
+use pnm::PnmImage;
+use svg::{hide_in_svg, reveal_from_svg};
+use ico::{hide_in_ico, reveal_from_ico};
+use keys::{format_key, generate, parse_key, GeneratedKey, KeyKind, KEY_SIZE};
+use signature::{reserved_len, sign_carrier, verify_carrier};
+use wetpaper::saturated;
+use stc::map_costs;
+use strategy::EmbeddingStrategy;
+#[cfg(feature = "gpu")]
+use gpu::texture_costs;
+#[cfg(not(feature = "gpu"))]
+use stc::texture_costs;
+
+/// Kinds of carrier files, told apart by their first bytes
+enum Carrier {
+    Image,
+    Zip,
+    Svg,
+    Ico,
+    Video,
+    Ogg,
+    Gif,
+    Jpeg,
+    Qoi,
+    Pnm,
+}
+
+impl Carrier {
+    fn detect(path: &Path) -> Result<Self, Error> {
+        let mut header = Vec::with_capacity(1024);
+        File::open(path)?.take(1024).read_to_end(&mut header)?;
+
+        Ok(if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+            Carrier::Zip
+        } else if header.starts_with(&[0, 0, 1, 0]) {
+            Carrier::Ico
+        } else if header.get(4..8) == Some(b"ftyp") || header.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+            Carrier::Video
+        } else if header.starts_with(b"OggS") {
+            Carrier::Ogg
+        } else if header.starts_with(b"GIF87a") || header.starts_with(b"GIF89a") {
+            Carrier::Gif
+        } else if header.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Carrier::Jpeg
+        } else if header.starts_with(qoi::MAGIC) {
+            Carrier::Qoi
+        } else if pnm::is_pnm(&header) {
+            Carrier::Pnm
+        } else if header.windows(4).any(|w| w == b"<svg") {
+            Carrier::Svg
+        } else {
+            Carrier::Image
+        })
+    }
+
+    /// What the carrier is called in the error messages
+    fn describe(&self) -> &'static str {
+        match self {
+            Carrier::Image => "an image",
+            Carrier::Zip => "a ZIP archive",
+            Carrier::Svg => "an SVG document",
+            Carrier::Ico => "an ICO file",
+            Carrier::Video => "a video",
+            Carrier::Ogg => "an Ogg stream",
+            Carrier::Gif => "a GIF image",
+            Carrier::Jpeg => "a JPEG image",
+            Carrier::Qoi => "a QOI image",
+            Carrier::Pnm => "a PNM image",
+        }
+    }
+}
+
+/// The generator of what would be random, seeded from the operating system, or from the SHA-256 of the given parts
+/// when the output is [deterministic](struct.EncodeOptions.html#method.deterministic)
+fn entropy(options: &EncodeOptions, parts: &[&[u8]]) -> ChaCha20Rng {
+    match options.deterministic {
+        true => {
+            let hash = parts.iter().fold(Sha256::new().chain_update(b"steganographer deterministic"), |hash, part| {
+                hash.chain_update((part.len() as u64).to_be_bytes()).chain_update(part)
+            });
+            ChaCha20Rng::from_seed(hash.finalize().into())
+        },
+        false => ChaCha20Rng::from_entropy(),
+    }
+}
+
+/// Reads the data and seals it in all of the configured layers, putting it into an archive when it has a name
+fn read_payload(options: &EncodeOptions) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let payload = read_sealed(options)?;
+    match &options.name {
+        Some(name) => {
+            let mut archive = toc::Archive::new();
+            archive.push(name, options.layer_flags(), timestamp(options), &payload)?;
+            Ok(archive.to_bytes())
+        },
+        None => Ok(payload),
+    }
+}
+
+/// Reads the data and seals it in all of the configured layers, wiping the plaintext from the memory on the way
+fn read_sealed(options: &EncodeOptions) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let payload = Zeroizing::new(match &options.data {
+        Some(data) if data.is_dir() => bundle::pack(data)?,
+        Some(data) => {
+            let mut data = File::open(data)?;
+            let mut payload = Vec::with_capacity(data.metadata()?.len() as usize);
+            data.read_to_end(&mut payload)?;
+            payload
+        },
+        _ => {
+            let mut payload = Vec::with_capacity(256);
+            stdin().read_to_end(&mut payload)?;
+            payload
+        },
+    });
+    let payload = match (&options.data, options.metadata) {
+        (Some(data), true) => Zeroizing::new(metadata::Metadata::of_file(data)?.prepend(&payload)),
+        _ => payload,
+    };
+    let payload = match options.codec {
+        Some(compression) => Zeroizing::new(compression.compress(&payload)?),
+        None => payload,
+    };
+    let payload = match &options.data_signing_key {
+        Some(path) => Zeroizing::new(signature::sign_data(&payload, &SigningKey::from_bytes(&*read_key(path, KeyKind::Signing, false)?))),
+        None => payload,
+    };
+    let payload = match &options.password {
+        Some(password) => Zeroizing::new(crypto::seal_with_rng(&payload, password, &mut entropy(options, &[&payload, password.as_bytes()]))?),
+        None => payload,
+    };
+    let payload = match &options.card_key {
+        Some(path) => Zeroizing::new(seal_for_card(&payload, path, options)?),
+        None => payload,
+    };
+    let payload = match options.recipients.is_empty() {
+        true => payload,
+        false => {
+            let recipients = options.recipients.iter()
+                .map(|path| read_key(path, KeyKind::Encryption, true).map(|key| *key))
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut parts = vec![&payload[..]];
+            parts.extend(recipients.iter().map(|key| &key[..]));
+            let mut rng = entropy(options, &parts);
+            Zeroizing::new(crypto::seal_for_with_rng(&payload, &recipients, &mut rng)?)
+        },
+    };
+    match &options.otp_pad {
+        Some(pad) => Ok(Zeroizing::new(otp::seal_with_file(&payload, pad)?)),
+        None => Ok(payload),
+    }
+}
+
+/// The time the data is hidden at in seconds since the Unix epoch, or 0 when the output is
+/// [deterministic](struct.EncodeOptions.html#method.deterministic)
+fn timestamp(options: &EncodeOptions) -> u64 {
+    match options.deterministic {
+        true => 0,
+        false => SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs()),
+    }
+}
+
+#[cfg(feature = "smartcard")]
+fn seal_for_card(payload: &[u8], public_key: &Path, options: &EncodeOptions) -> Result<Vec<u8>, Error> {
+    let text = String::from_utf8(std::fs::read(public_key)?)
+        .map_err(|_| Error::InvalidKey("key file is not valid UTF-8".into()))?;
+    let key = smartcard::parse_public_key(&text)?;
+    let mut rng = entropy(options, &[payload, text.as_bytes()]);
+    smartcard::seal_for_card_with_rng(payload, &key, &mut rng)
+}
+
+#[cfg(not(feature = "smartcard"))]
+fn seal_for_card(_: &[u8], _: &Path, _: &EncodeOptions) -> Result<Vec<u8>, Error> {
+    Err(Error::MissingFeature("smartcard"))
+}
+
+#[cfg(feature = "smartcard")]
+fn open_with_card(sealed: &[u8], applet: Applet, pin: Option<&str>) -> Result<Vec<u8>, Error> {
+    smartcard::open_with_card(sealed, &mut smartcard::Pcsc::connect()?, applet, pin)
+}
+
+#[cfg(not(feature = "smartcard"))]
+fn open_with_card(_: &[u8], _: Applet, _: Option<&str>) -> Result<Vec<u8>, Error> {
+    Err(Error::MissingFeature("smartcard"))
+}
+
+fn open_output(output: impl AsRef<Path>, replace: bool) -> Result<File, Error> {
+    Ok(OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .create(true)
+        .create_new(!replace)
+        .open(output)?)
+}
+
+/// Same as `open_output`, but nobody except the owner can read the file
+fn open_secret_output(output: impl AsRef<Path>, replace: bool) -> Result<File, Error> {
+    let mut options = OpenOptions::new();
+    options.write(true).truncate(true).create(true).create_new(!replace);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    Ok(options.open(output)?)
+}
+
+/// Picks the named entry when the data is an archive of them, peels its remaining layers, checks its signature
+/// and decompresses it, adding the signer and the name of the entry to the report, along with the metadata
+/// of the file that is split off.
+///
+/// Every intermediate buffer is wiped from the memory once the next layer is peeled off.
+fn open_layers(options: &DecodeOptions, data: &[u8], mut report: DecodeReport) -> Result<(Zeroizing<Vec<u8>>, DecodeReport), Error> {
+    let archive;
+    let data = match (report.flags & container::FLAG_ENTRIES != 0, &options.name) {
+        (true, name) => {
+            archive = toc::Archive::from_bytes(data)?;
+            let (entry, data) = archive.get(name.as_deref()).ok_or_else(|| match name {
+                Some(name) => Error::UnsupportedCarrier(format!("there is no entry named {:?}", name)),
+                None => Error::UnsupportedCarrier("there are no entries left".into()),
+            })?;
+            report.flags = entry.flags;
+            report.entry = Some(entry.name.clone());
+            data
+        },
+        (false, Some(_)) => return Err(Error::UnsupportedCarrier("the carrier does not hold any named entries".into())),
+        (false, None) => data,
+    };
+    let missing = [
+        (container::FLAG_ONE_TIME_PAD, options.otp_pad.is_none(), "a one-time pad"),
+        (container::FLAG_RECIPIENTS, options.identity.is_none(), "the public keys of its recipients"),
+        (container::FLAG_CARD, options.card.is_none(), "the key of a smartcard"),
+        (container::FLAG_PASSWORD, options.password.is_none(), "a password"),
+    ];
+    if let Some(&(_, _, layer)) = missing.iter().find(|&&(flag, missing, _)| report.flags & flag != 0 && missing) {
+        return Err(Error::InvalidKey(format!("the data is sealed with {}, but nothing to open it with was given", layer)));
+    }
+    let mut data = Zeroizing::new(data.to_vec());
+    for _ in 1..options.depth {
+        data = Zeroizing::new(decode_image(&data[..], options)?.0);
+    }
+    if let Some(pad) = &options.otp_pad {
+        data = Zeroizing::new(otp::open(&data, &Zeroizing::new(std::fs::read(pad)?))?);
+    }
+    if let Some(identity) = &options.identity {
+        data = Zeroizing::new(crypto::open_for(&data, &*read_key(identity, KeyKind::Encryption, false)?)?);
+    }
+    if let Some(applet) = options.card {
+        data = Zeroizing::new(open_with_card(&data, applet, options.card_pin.as_deref().map(String::as_str))?);
+    }
+    // the password of the hidden volume was already used to open it
+    if let (Some(password), false) = (&options.password, options.hidden_volume) {
+        data = Zeroizing::new(crypto::open(&data, password)?);
+    }
+    if options.signed_data || options.signer.is_some() || report.flags & container::FLAG_SIGNED_DATA != 0 {
+        let (payload, public) = signature::verify_data(&data)?;
+        if let Some(signer) = &options.signer {
+            if *read_key(signer, KeyKind::Signing, true)? != public {
+                return Err(Error::InvalidSignature);
+            }
+        }
+        report.signer = Some(keys::fingerprint(&public));
+        data = Zeroizing::new(payload);
+    }
+    if report.flags & container::FLAG_CODEC != 0 {
+        data = Zeroizing::new(compress::decompress(report.flags, &data)?);
+    }
+    let (metadata, rest) = metadata::Metadata::split(&data)?;
+    if let Some(metadata) = metadata {
+        report.metadata = Some(metadata);
+        data = Zeroizing::new(rest.to_vec());
+    }
+    Ok((data, report))
+}
+
+/// Peels the remaining layers of the data and writes it out, see `open_layers`
+fn write_result(options: &DecodeOptions, data: &[u8], report: DecodeReport) -> Result<DecodeReport, Error> {
+    let (data, report) = open_layers(options, data, report)?;
+    let output = match (&options.output, options.auto_output) {
+        (Some(o), _) => Some(o.clone()),
+        (None, true) => Some(PathBuf::from(".")),
+        (None, false) => None,
+    };
+    match (&options.extract_to, output) {
+        (Some(dir), _) => {
+            bundle::unpack(&data, dir, options.replace)?;
+        },
+        (None, Some(o)) => {
+            // the directory gets the file under its original name, or one that fits the type of the data
+            let name = match report.metadata.as_ref().and_then(|metadata| metadata.safe_filename()) {
+                Some(name) => Some(name.to_string()),
+                None if options.auto_output => Some(format!("extracted.{}", metadata::extension(metadata::sniff(&data)))),
+                None => None,
+            };
+            let o = match name {
+                Some(name) if o.is_dir() => o.join(name),
+                _ => o,
+            };
+            open_output(&o, options.replace)?.write_all(&data)?;
+            if let Some(metadata) = &report.metadata {
+                metadata.apply(&o)?;
+            }
+        },
+        (None, None) => {
+            let mime = metadata::sniff(&data);
+            if !options.binary_to_terminal && !metadata::is_text(mime) && stdout().is_terminal() {
+                return Err(Error::BinaryToTerminal(mime));
+            }
+            stdout().write_all(&data)?
+        },
+    }
+    Ok(report)
+}
+
+/// Decodes the data from the image read from the reader, peeling all of the layers the options have
+/// and checking its signature, and lends it to the closure along with the report.
+/// The data and every intermediate buffer are wiped from the memory before this returns,
+/// so unlike [`decode_image`](fn.decode_image.html) no plaintext is handed out to outlive its use.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::{crypto, encode_image, with_decoded_image, DecodeOptions, EncodeOptions};
+/// # use image::{ColorType, ImageEncoder};
+/// # use image::codecs::png::PngEncoder;
+/// # let mut png = Vec::new();
+/// # PngEncoder::new(&mut png).write_image(&[0; 32 * 32 * 3], 32, 32, ColorType::Rgb8).unwrap();
+/// let sealed = crypto::seal(b"the plans", "hunter2").unwrap();
+/// let mut encoded = Vec::new();
+/// encode_image(&png[..], &sealed, &mut encoded, &EncodeOptions::new()).unwrap();
+///
+/// let options = DecodeOptions::new().password("hunter2");
+/// let words = with_decoded_image(&encoded[..], &options, |data, _| data.split(|&b| b == b' ').count()).unwrap();
+/// assert_eq!(words, 2);
+/// ```
+///
+pub fn with_decoded_image<R>(image: impl Read, options: &DecodeOptions, f: impl FnOnce(&[u8], &DecodeReport) -> R) -> Result<R, Error> {
+    let (payload, report) = decode_image(image, options)?;
+    let (data, report) = open_layers(options, &Zeroizing::new(payload), report)?;
+    Ok(f(&data, &report))
+}
+
+/// Decodes bytes from the carrier file, which is either an image, an icon, an SVG document or a ZIP archive,
+/// and writes them to either the configured output or to the stdout
+pub fn decode_from_file(encoded: PathBuf, options: &DecodeOptions) -> Result<DecodeReport, Error> {
+    match Carrier::detect(&encoded)? {
+        Carrier::Image => decode_from_image(encoded, options),
+        Carrier::Qoi => decode_from_qoi(encoded, options),
+        Carrier::Pnm => decode_from_pnm(encoded, options),
+        _ if options.hidden_volume => Err(Error::UnsupportedCarrier("only the images that hold the data in their pixels can hold a hidden volume".into())),
+        Carrier::Zip => decode_from_zip(encoded, options),
+        Carrier::Svg => decode_from_svg(encoded, options),
+        Carrier::Ico => decode_from_ico(encoded, options),
+        Carrier::Video => decode_from_video(encoded, options),
+        Carrier::Ogg => decode_from_ogg(encoded, options),
+        Carrier::Gif => decode_from_gif(encoded, options),
+        Carrier::Jpeg => decode_from_jpeg(encoded, options),
+    }
+}
+
+/// Encodes bytes either from the configured file or from the stdin into a copy of the carrier file,
+/// which is either an image, an icon, an SVG document or a ZIP archive.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](enum.Error.html#variant.UnsupportedCarrier) when the [`format`](struct.EncodeOptions.html#method.format)
+/// option is set for a carrier that does not hold the data in its pixels, and the same for the
+/// [`hidden_volume`](struct.EncodeOptions.html#method.hidden_volume) one.
+pub fn encode_into_file(carrier: PathBuf, output: PathBuf, options: &EncodeOptions) -> Result<(), Error> {
+    let carrier_kind = Carrier::detect(&carrier)?;
+    let in_pixels = matches!(carrier_kind, Carrier::Image | Carrier::Qoi | Carrier::Pnm);
+    if options.format.is_some() && !in_pixels {
+        return Err(Error::UnsupportedCarrier("only the images that hold the data in their pixels can be written in another format".into()));
+    }
+    if options.hidden_volume.is_some() && !in_pixels {
+        return Err(Error::UnsupportedCarrier("only the images that hold the data in their pixels can hold a hidden volume".into()));
+    }
+    if options.name.is_some() && !in_pixels {
+        return Err(Error::UnsupportedCarrier("only the images that hold the data in their pixels can hold named entries".into()));
+    }
+    if options.codec.is_some() && !in_pixels {
+        return Err(Error::UnsupportedCarrier("only the images that hold the data in their pixels can record the compression".into()));
+    }
+    match carrier_kind {
+        Carrier::Image => encode_into_image(carrier, output, options),
+        Carrier::Zip => encode_into_zip(carrier, output, options),
+        Carrier::Svg => encode_into_svg(carrier, output, options),
+        Carrier::Ico => encode_into_ico(carrier, output, options),
+        Carrier::Video => encode_into_video(carrier, output, options),
+        Carrier::Ogg => encode_into_ogg(carrier, output, options),
+        Carrier::Gif => encode_into_gif(carrier, output, options),
+        Carrier::Jpeg => encode_into_jpeg(carrier, output, options),
+        Carrier::Qoi => encode_into_qoi(carrier, output, options),
+        Carrier::Pnm => encode_into_pnm(carrier, output, options),
+    }
+}
+
+/// Decodes bytes from the ICO file and writes them to either the configured output or to the stdout
+pub fn decode_from_ico(encoded: PathBuf, options: &DecodeOptions) -> Result<DecodeReport, Error> {
+    let ico = std::fs::read(encoded)?;
+    let (data, flags) = with_any_bits(options.bits, options.detect_bits, |bits| reveal_from_ico(&ico, bits))?;
+    write_result(options, &data, DecodeReport { flags, ..DecodeReport::default() })
+}
+
+/// Encodes bytes either from the configured file or from the stdin into a copy of the given ICO file,
+/// spreading them over all of the icon images.
+pub fn encode_into_ico(ico: PathBuf, output: PathBuf, options: &EncodeOptions) -> Result<(), Error> {
+    let mut output = open_output(output, options.replace)?;
+    let ico = std::fs::read(ico)?;
+    let payload = read_payload(options)?;
+    output.write_all(&hide_in_ico(&ico, &payload, options.bits, options.header_flags())?)?;
+    Ok(())
+}
+
+/// Generates a new key of the given kind and writes it to the output file.
+///
+/// The secret key is written so that only the owner can read it, while the public key of the keypairs
+/// is written next to it, to the file with an additional `.pub` extension.
+pub fn generate_key_files(kind: KeyKind, output: PathBuf, replace: bool) -> Result<GeneratedKey, Error> {
+    let key = generate(kind);
+    if let Some(public) = &key.public {
+        let mut path = output.clone().into_os_string();
+        path.push(".pub");
+        open_output(path, replace)?.write_all(format_key(kind, true, public).as_bytes())?;
+    }
+    open_secret_output(output, replace)?.write_all(format_key(kind, false, &key.secret).as_bytes())?;
+    Ok(key)
+}
+
+/// Reads the key file, the key and the text of the file are wiped from the memory once they are dropped
+fn read_key(path: impl AsRef<Path>, kind: KeyKind, public: bool) -> Result<Zeroizing<[u8; KEY_SIZE]>, Error> {
+    let text = Zeroizing::new(String::from_utf8(std::fs::read(path)?)
+        .map_err(|_| Error::InvalidKey("key file is not valid UTF-8".into()))?);
+    Ok(Zeroizing::new(parse_key(&text, kind, public)?))
+}
+
+fn read_svg(path: PathBuf) -> Result<String, Error> {
+    String::from_utf8(std::fs::read(path)?)
+        .map_err(|_| Error::UnsupportedCarrier("SVG document is not valid UTF-8".into()))
+}
+
+/// Decodes bytes from the SVG document and writes them to either the configured output or to the stdout
+pub fn decode_from_svg(encoded: PathBuf, options: &DecodeOptions) -> Result<DecodeReport, Error> {
+    write_result(options, &reveal_from_svg(&read_svg(encoded)?)?, DecodeReport::default())
+}
+
+/// Encodes bytes either from the configured file or from the stdin into a copy of the given SVG document.
+pub fn encode_into_svg(svg: PathBuf, output: PathBuf, options: &EncodeOptions) -> Result<(), Error> {
+    let mut output = open_output(output, options.replace)?;
+    let svg = read_svg(svg)?;
+    let payload = read_payload(options)?;
+    output.write_all(hide_in_svg(&svg, &payload, options.svg_precision)?.as_bytes())?;
+    Ok(())
+}
+
+/// Decodes bytes from the ZIP archive and writes them to either the configured output or to the stdout
+pub fn decode_from_zip(encoded: PathBuf, options: &DecodeOptions) -> Result<DecodeReport, Error> {
+    write_result(options, &reveal_from_zip(&std::fs::read(encoded)?)?, DecodeReport::default())
+}
+
+/// Encodes bytes either from the configured file or from the stdin into a copy of the given ZIP archive.
+pub fn encode_into_zip(archive: PathBuf, output: PathBuf, options: &EncodeOptions) -> Result<(), Error> {
+    let mut output = open_output(output, options.replace)?;
+    let archive = std::fs::read(archive)?;
+    let payload = read_payload(options)?;
+    output.write_all(&hide_in_zip(&archive, &payload, options.zip_slot)?)?;
+    Ok(())
+}
+
+/// Decodes bytes from the MP4 or Matroska video file and writes them to either the configured output or to the stdout.
+pub fn decode_from_video(encoded: PathBuf, options: &DecodeOptions) -> Result<DecodeReport, Error> {
+    write_result(options, &reveal_from_video(&std::fs::read(encoded)?)?, DecodeReport::default())
+}
+
+/// Encodes bytes either from the configured file or from the stdin into an MP4 or Matroska video file,
+/// see [`video`](video/index.html).
+pub fn encode_into_video(video: PathBuf, output: PathBuf, options: &EncodeOptions) -> Result<(), Error> {
+    let mut output = open_output(output, options.replace)?;
+    let video = std::fs::read(video)?;
+    let payload = read_payload(options)?;
+    output.write_all(&hide_in_video(&video, &payload)?)?;
+    Ok(())
+}
+
+/// Decodes bytes from the Ogg file and writes them to either the configured output or to the stdout
+pub fn decode_from_ogg(encoded: PathBuf, options: &DecodeOptions) -> Result<DecodeReport, Error> {
+    write_result(options, &reveal_from_ogg(&std::fs::read(encoded)?)?, DecodeReport::default())
+}
+
+/// Encodes bytes either from the configured file or from the stdin into an Ogg Vorbis or Ogg Opus file,
+/// see [`ogg`](ogg/index.html).
+pub fn encode_into_ogg(ogg: PathBuf, output: PathBuf, options: &EncodeOptions) -> Result<(), Error> {
+    let mut output = open_output(output, options.replace)?;
+    let ogg = std::fs::read(ogg)?;
+    let payload = read_payload(options)?;
+    output.write_all(&hide_in_ogg(&ogg, &payload)?)?;
+    Ok(())
+}
+
+/// Decodes bytes from the GIF file and writes them to either the configured output or to the stdout
+pub fn decode_from_gif(encoded: PathBuf, options: &DecodeOptions) -> Result<DecodeReport, Error> {
+    write_result(options, &reveal_from_gif(&std::fs::read(encoded)?)?, DecodeReport::default())
+}
+
+/// Encodes bytes either from the configured file or from the stdin into a copy of the given GIF file,
+/// spreading them over all of its frames, see [`animation`](animation/index.html).
+pub fn encode_into_gif(gif: PathBuf, output: PathBuf, options: &EncodeOptions) -> Result<(), Error> {
+    let mut output = open_output(output, options.replace)?;
+    let gif = std::fs::read(gif)?;
+    let payload = read_payload(options)?;
+    output.write_all(&hide_in_gif(&gif, &payload)?)?;
+    Ok(())
+}
+
+/// Decodes bytes from the JPEG image and writes them to either the configured output or to the stdout
+pub fn decode_from_jpeg(encoded: PathBuf, options: &DecodeOptions) -> Result<DecodeReport, Error> {
+    write_result(options, &reveal_from_jpeg(&std::fs::read(encoded)?)?, DecodeReport::default())
+}
+
+/// Encodes bytes either from the configured file or from the stdin into a copy of the given JPEG image,
+/// see [`jpeg`](jpeg/index.html).
+pub fn encode_into_jpeg(jpeg: PathBuf, output: PathBuf, options: &EncodeOptions) -> Result<(), Error> {
+    let mut output = open_output(output, options.replace)?;
+    let jpeg = std::fs::read(jpeg)?;
+    let payload = read_payload(options)?;
+    output.write_all(&hide_in_jpeg(&jpeg, &payload)?)?;
+    Ok(())
+}
+
+fn qoi_layout(image: &QoiImage) -> binary::Layout {
+    let color_type = if image.channels == 4 { ColorType::Rgba8 } else { ColorType::Rgb8 };
+    image_layout(color_type).width(image.width as usize)
+}
+
+/// Decodes bytes from the QOI image and writes them to either the configured output or to the stdout,
+/// with all the same options as for the PNG images.
+pub fn decode_from_qoi(encoded: PathBuf, options: &DecodeOptions) -> Result<DecodeReport, Error> {
+    let image = QoiImage::decode(&std::fs::read(encoded)?)?;
+    let layout = qoi_layout(&image);
+    let (payload, report) = extract(image.pixels, layout, options)?;
+    write_result(options, &payload, report)
+}
+
+/// Encodes bytes either from the configured file or from the stdin into a copy of the given QOI image,
+/// with all the same options as for the PNG images.
+pub fn encode_into_qoi(qoi: PathBuf, output: PathBuf, options: &EncodeOptions) -> Result<(), Error> {
+    let mut output = open_output(output, options.replace)?;
+    let mut image = QoiImage::decode(&std::fs::read(qoi)?)?;
+    let layout = qoi_layout(&image);
+    embed(&mut image.pixels, image.width, image.height, layout, &read_payload(options)?, options)?;
+    match options.format {
+        None | Some(OutputFormat::Qoi) => output.write_all(&image.encode())?,
+        Some(format) => {
+            let color_type = if image.channels == 4 { ColorType::Rgba8 } else { ColorType::Rgb8 };
+            write_image(output, &image.pixels, image.width, image.height, color_type, format, &options.png_output(&[]))?;
+        },
+    }
+    Ok(())
+}
+
+fn pnm_layout(image: &PnmImage) -> binary::Layout {
+    image_layout(image.color_type).endianness(binary::Endianness::Big).width(image.width as usize)
+}
+
+/// Decodes bytes from the PBM, PGM or PPM image and writes them to either the configured output or to the stdout,
+/// with all the same options as for the PNG images.
+pub fn decode_from_pnm(encoded: PathBuf, options: &DecodeOptions) -> Result<DecodeReport, Error> {
+    let (payload, report) = decode_pnm(&std::fs::read(encoded)?, options)?;
+    write_result(options, &payload, report)
+}
+
+/// Decodes bytes from the PBM, PGM or PPM image and returns them, the output is not used.
+pub fn decode_pnm(pnm: &[u8], options: &DecodeOptions) -> Result<(Vec<u8>, DecodeReport), Error> {
+    let (image, _) = PnmImage::decode(pnm)?;
+    let layout = pnm_layout(&image);
+    extract(image.pixels, layout, options)
+}
+
+/// Encodes bytes either from the configured file or from the stdin into a copy of the given PBM, PGM or PPM image,
+/// with all the same options as for the PNG images. The result is a raw PGM or PPM image, unless the
+/// [`format`](struct.EncodeOptions.html#method.format) option says otherwise.
+pub fn encode_into_pnm(pnm: PathBuf, output: PathBuf, options: &EncodeOptions) -> Result<(), Error> {
+    let output = open_output(output, options.replace)?;
+    encode_pnm(&std::fs::read(pnm)?, &read_payload(options)?, output, options)
+}
+
+/// Encodes the payload into the PBM, PGM or PPM image and writes the resulting raw PGM or PPM image, or one in the
+/// configured [`format`](struct.EncodeOptions.html#method.format), to the writer. The data file of the options is not used.
+///
+/// The bitmaps and the images with the maximum sample value other than 255 or 65535
+/// are [converted](pnm/struct.PnmImage.html#method.decode) first.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](enum.Error.html#variant.UnsupportedCarrier) when the image would have to be converted
+/// and the [`convert`](struct.EncodeOptions.html#method.convert) option is off.
+pub fn encode_pnm(pnm: &[u8], payload: &[u8], mut output: impl Write, options: &EncodeOptions) -> Result<(), Error> {
+    let (mut image, conversion) = PnmImage::decode(pnm)?;
+    if let Some(conversion) = conversion.filter(|_| !options.convert) {
+        return Err(Error::UnsupportedCarrier(format!("the image would have to be converted from {}", conversion)));
+    }
+    let layout = pnm_layout(&image);
+    embed(&mut image.pixels, image.width, image.height, layout, payload, options)?;
+    match options.format {
+        None | Some(OutputFormat::Pnm) => output.write_all(&image.encode())?,
+        Some(format) => {
+            swap_wide_samples(&mut image.pixels, image.color_type);
+            write_image(output, &image.pixels, image.width, image.height, image.color_type, format, &options.png_output(&[]))?;
+        },
+    }
+    Ok(())
+}
+
+/// Decodes bytes from a PNG, BMP, TIFF, WebP or Netpbm image read from the reader, such as the stdin,
+/// and writes them to either the configured output or to the stdout.
+pub fn decode_stream(mut encoded: impl Read, options: &DecodeOptions) -> Result<DecodeReport, Error> {
+    let mut image = Vec::new();
+    encoded.read_to_end(&mut image)?;
+    let (payload, report) = match pnm::is_pnm(&image) {
+        true => decode_pnm(&image, options)?,
+        false => decode_image(&image[..], options)?,
+    };
+    write_result(options, &payload, report)
+}
+
+/// Encodes bytes from the configured file into a PNG, BMP, TIFF, WebP or Netpbm image read from the reader, such as the stdin,
+/// and writes the resulting image in the same format, or as a raw PGM or PPM one, to the writer.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](enum.Error.html#variant.UnsupportedCarrier) when no data file is configured,
+/// as the stdin cannot hold both the image and the data.
+pub fn encode_stream(mut carrier: impl Read, output: impl Write, options: &EncodeOptions) -> Result<(), Error> {
+    if options.data.is_none() {
+        return Err(Error::UnsupportedCarrier("the data has to come from a file when the image is read from the stdin".into()));
+    }
+    let mut image = Vec::new();
+    carrier.read_to_end(&mut image)?;
+    let payload = read_payload(options)?;
+    match pnm::is_pnm(&image) {
+        true => encode_pnm(&image, &payload, output, options),
+        false => encode_image(&image[..], &payload, output, options),
+    }
+}
+
+/// Width, height, pixel format and the pixels of a decoded image
+type Pixels = (u32, u32, ColorType, Vec<u8>);
+
+fn read_image(image: impl Read) -> Result<Pixels, Error> {
+    Ok(read_image_format(image)?.1)
+}
+
+/// Reads the whole image and decodes it with the decoder of the format told by its first bytes,
+/// returning that format too
+fn read_image_format(mut image: impl Read) -> Result<(ImageFormat, Pixels), Error> {
+    let mut data = Vec::new();
+    image.read_to_end(&mut data)?;
+    let format = image::guess_format(&data)
+        .map_err(|_| Error::UnsupportedCarrier("the image format is not recognized".into()))?;
+    let data = std::io::Cursor::new(data);
+    let decoded = match format {
+        ImageFormat::Png => decode_pixels(PngDecoder::new(data)?)?,
+        ImageFormat::Bmp => decode_pixels(BmpDecoder::new(data)?)?,
+        ImageFormat::Tiff => decode_pixels(TiffDecoder::new(data)?)?,
+        ImageFormat::WebP => decode_pixels(WebPDecoder::new(data)?)?,
+        _ => return Err(Error::UnsupportedCarrier(format!("{:?} images are not supported as carriers", format))),
+    };
+    Ok((format, decoded))
+}
+
+fn decode_pixels<'a>(decoder: impl ImageDecoder<'a>) -> Result<Pixels, Error> {
+    let (width, height) = decoder.dimensions();
+    let color_type = decoder.color_type();
+
+    let mut data = vec![0; decoder.total_bytes() as usize];
+    decoder.read_image(&mut data)?;
+    Ok((width, height, color_type, data))
+}
+
+/// Writes the pixels, with the 16-bit samples in the native byte order, as an image of the given format
+fn write_image(mut output: impl Write, pixels: &[u8], width: u32, height: u32, color_type: ColorType, format: OutputFormat, png: &PngOutput) -> Result<(), Error> {
+    let unsupported = || Error::UnsupportedCarrier(format!("{:?} images cannot hold {:?} pixels", format, color_type));
+    match format {
+        OutputFormat::Png => {
+            let (color, depth) = apng::png_color(color_type).ok_or_else(unsupported)?;
+            let mut encoder = png::Encoder::new(output, width, height);
+            encoder.set_color(color);
+            encoder.set_depth(depth);
+            encoder.set_deflate_compression(png.compression);
+            encoder.set_filter(png.filter);
+            let mut pixels = pixels.to_vec();
+            // the same byte order as in the Netpbm images
+            swap_wide_samples(&mut pixels, color_type);
+            let mut writer = encoder.write_header()?;
+            writer.write_image_data(&pixels)?;
+            writer.finish()?;
+        },
+        OutputFormat::Bmp => BmpEncoder::new(&mut output).encode(pixels, width, height, color_type)?,
+        OutputFormat::Tiff => {
+            // the TIFF encoder needs to seek back to write the offsets
+            let mut tiff = std::io::Cursor::new(Vec::new());
+            TiffEncoder::new(&mut tiff).write_image(pixels, width, height, color_type)?;
+            output.write_all(&tiff.into_inner())?;
+        },
+        OutputFormat::Pnm => {
+            if !matches!(color_type, ColorType::L8 | ColorType::L16 | ColorType::Rgb8 | ColorType::Rgb16) {
+                return Err(unsupported());
+            }
+            let mut pixels = pixels.to_vec();
+            swap_wide_samples(&mut pixels, color_type);
+            output.write_all(&PnmImage { width, height, color_type, pixels }.encode())?;
+        },
+        OutputFormat::Qoi => {
+            let channels = match color_type {
+                ColorType::Rgb8 => 3,
+                ColorType::Rgba8 => 4,
+                _ => return Err(unsupported()),
+            };
+            output.write_all(&QoiImage { width, height, channels, colorspace: 0, pixels: pixels.to_vec() }.encode())?;
+        },
+    }
+    Ok(())
+}
+
+/// Swaps the bytes of the 16-bit samples between the big-endian order of the PNG and the Netpbm images
+/// and the native order of the others, which is the same thing on the big-endian targets
+fn swap_wide_samples(pixels: &mut [u8], color_type: ColorType) {
+    if cfg!(target_endian = "little") && color_type.bytes_per_pixel() == 2 * color_type.channel_count() {
+        pixels.chunks_exact_mut(2).for_each(|sample| sample.swap(0, 1));
+    }
+}
+
+/// Reads the first bytes of the image, enough for the [format conversion](container/struct.Conversion.html) to be detected,
+/// returning them together with the reader of the whole image
+fn peek_header(mut image: impl Read) -> Result<(Vec<u8>, impl Read), Error> {
+    let mut header = Vec::with_capacity(26);
+    (&mut image).take(26).read_to_end(&mut header)?;
+    Ok((header.clone(), std::io::Cursor::new(header).chain(image)))
+}
+
+/// Tells how the pixel format of the PNG or Netpbm image read from the reader is going to be
+/// [converted](container/struct.Conversion.html) when encoding into it, if at all.
+///
+/// Anything else is never converted.
+pub fn image_conversion(image: impl Read) -> Result<Option<Conversion>, Error> {
+    let (header, mut image) = peek_header(image)?;
+    if pnm::is_pnm(&header) {
+        let mut pnm = Vec::new();
+        image.read_to_end(&mut pnm)?;
+        return Ok(PnmImage::decode(&pnm)?.1);
+    }
+    if !header.starts_with(b"\x89PNG") {
+        return Ok(None);
+    }
+    Ok(Conversion::detect(&header, PngDecoder::new(image)?.color_type()))
+}
+
+/// Decodes bytes from the image file and writes them to either the configured output or to the stdout.
+///
+/// With the [`resync`](struct.DecodeOptions.html#method.resync) option, the image may have been cropped,
+/// and the report lists the parts of the data that were lost because of that.
+///
+/// The data hidden in an animated PNG image is put back together from all of its frames,
+/// see [`apng`](apng/index.html).
+pub fn decode_from_image(encoded: PathBuf, options: &DecodeOptions) -> Result<DecodeReport, Error> {
+    let (payload, report) = reveal_from_pixels(&encoded, options)?;
+    write_result(options, &payload, report)
+}
+
+/// Reveals the payload hidden in the pixels of an image file of any format along with the report,
+/// without peeling any of its layers
+fn reveal_from_pixels(encoded: &Path, options: &DecodeOptions) -> Result<(Vec<u8>, DecodeReport), Error> {
+    let image = std::fs::read(encoded)?;
+    match Carrier::detect(encoded)? {
+        Carrier::Image if apng::is_animated(&image) => {
+            let (payload, flags) = apng::reveal_apng_with_flags(&image, options)?;
+            Ok((payload, DecodeReport { flags, ..DecodeReport::default() }))
+        },
+        Carrier::Image => decode_image(&image[..], options),
+        Carrier::Qoi => {
+            let image = QoiImage::decode(&image)?;
+            let layout = qoi_layout(&image);
+            extract(image.pixels, layout, options)
+        },
+        Carrier::Pnm => decode_pnm(&image, options),
+        carrier => Err(Error::UnsupportedCarrier(format!("{} does not hold the data in the pixels of an image", carrier.describe()))),
+    }
+}
+
+/// Lists the [entries](toc/struct.Entry.html) hidden in the pixels of the image file, or the single one without
+/// a name when the data was hidden without a [name](struct.EncodeOptions.html#method.name).
+///
+/// Only the table of contents is read, so none of the keys of the layers the entries are sealed in are needed.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::{encode_into_file, list_entries, EncodeOptions};
+/// # use image::{ColorType, ImageEncoder};
+/// # use image::codecs::png::PngEncoder;
+/// let cover = std::env::temp_dir().join("steganographer-list-cover.png");
+/// PngEncoder::new(std::fs::File::create(&cover).unwrap()).write_image(&[0; 32 * 32 * 3], 32, 32, ColorType::Rgb8).unwrap();
+/// let data = std::env::temp_dir().join("steganographer-list-data.txt");
+/// std::fs::write(&data, b"the plans").unwrap();
+/// let encoded = std::env::temp_dir().join("steganographer-list-encoded.png");
+///
+/// let options = EncodeOptions::new().data(&data).name("notes").password("hunter2").replace(true);
+/// encode_into_file(cover, encoded.clone(), &options).unwrap();
+///
+/// let entries = list_entries(encoded, &options.decoding()).unwrap();
+/// assert_eq!(entries.len(), 1);
+/// assert_eq!(entries[0].name, "notes");
+/// assert!(entries[0].len > b"the plans".len());
+/// ```
+pub fn list_entries(encoded: PathBuf, options: &DecodeOptions) -> Result<Vec<toc::Entry>, Error> {
+    let (payload, report) = reveal_from_pixels(&encoded, options)?;
+    if report.flags & container::FLAG_ENTRIES == 0 {
+        return Ok(vec![toc::Entry { name: String::new(), flags: report.flags, timestamp: 0, offset: 0, len: payload.len() }]);
+    }
+    Ok(toc::Archive::from_bytes(&payload)?.entries().to_vec())
+}
+
+/// Decodes bytes from the PNG, BMP, TIFF or WebP image read from the reader and returns them, the output is not used.
+pub fn decode_image(image: impl Read, options: &DecodeOptions) -> Result<(Vec<u8>, DecodeReport), Error> {
+    let (width, _, color_type, data) = read_image(image)?;
+    extract(data, image_layout(color_type).width(width as usize), options)
+}
+
+/// Reveals the data with the configured bits and, when they are to be [detected](struct.DecodeOptions.html#method.detect_bits)
+/// and that fails, with every other setting, returning the first data that is revealed or the error of the configured bits
+fn with_any_bits<T>(configured: binary::Bits, detect: bool, mut reveal: impl FnMut(binary::Bits) -> Result<T, Error>) -> Result<T, Error> {
+    let first = reveal(configured);
+    if first.is_ok() || !detect {
+        return first;
+    }
+    binary::Bits::ALL.iter()
+        .filter(|&&bits| bits != configured)
+        .find_map(|&bits| reveal(bits).ok())
+        .map_or(first, Ok)
+}
+
+/// Reveals the data from the decoded pixels of an image of any format in the given layout
+fn extract(mut data: Vec<u8>, layout: binary::Layout, options: &DecodeOptions) -> Result<(Vec<u8>, DecodeReport), Error> {
+    let layout = options.carrier_layout(layout);
+    let selected = match options.leaves_out_pixels() {
+        true => selected_pixels(&data, layout, None, options.skip_transparent, options.mask.as_deref())?,
+        false => None,
+    };
+    if let Some(selected) = selected {
+        let layout = compact_layout(layout, options.skip_transparent);
+        let options = DecodeOptions { skip_transparent: false, mask: None, ..options.clone() };
+        return extract(gather_pixels(&data, &selected, layout), layout, &options);
+    }
+    if let Some(lane) = options.lane(data.len(), layout) {
+        let data = lane.iter().map(|&i| data[i]).collect();
+        return extract(data, binary::Layout::default(), &options.in_lane(layout));
+    }
+    if options.detect_bits && options.embedding == Embedding::Lsb && !options.hidden_volume {
+        let options = DecodeOptions { detect_bits: false, ..options.clone() };
+        return with_any_bits(options.carrier_bits(layout), true, |bits| {
+            let options = match layout.sample_size() > 1 {
+                true => DecodeOptions { wide_bits: Some(bits), ..options.clone() },
+                false => DecodeOptions { bits, ..options.clone() },
+            };
+            extract(data.clone(), layout, &options)
+        });
+    }
+    let bits = options.carrier_bits(layout);
+    if options.signed {
+        data.truncate(data.len() - reserved_len(data.len(), bits, layout));
+    }
+    let whitening = options.whitening.as_deref().map(String::as_str);
+    if (options.hidden_volume || whitening.is_some()) && (options.embedding != Embedding::Lsb || options.resync) {
+        return Err(Error::UnsupportedCarrier("only the lsb embedding without resync can hold a hidden volume or be whitened".into()));
+    }
+    if options.hidden_volume {
+        let password = options.password.as_ref().ok_or(Error::WrongPassword)?;
+        let volume = container::reveal_volume(&data, bits, layout, whitening, options.scatter)?;
+        Ok((crypto::open_volume(&volume, password)?, DecodeReport::default()))
+    } else if options.embedding != Embedding::Lsb {
+        Ok((embedding_strategy(options.embedding, bits, 1).reveal(&data, layout)?, DecodeReport::default()))
+    } else if options.resync {
+        let resynced = reveal_resync(&data, bits, layout)?;
+        Ok((resynced.payload, DecodeReport { missing: resynced.missing, corrected: resynced.corrected, damaged: resynced.damaged, ..DecodeReport::default() }))
+    } else {
+        let (header, payload) = container::reveal_with(&data, bits, layout, whitening, options.scatter)?;
+        Ok((payload, DecodeReport { flags: header.flags, ..DecodeReport::default() }))
+    }
+}
+
+/// Reads the grayscale image that goes along with the carrier, such as the cost map or the mask
+fn read_gray_map(path: &Path, what: &str, width: u32, height: u32) -> Result<Vec<u8>, Error> {
+    let map = image::open(path)?;
+    if (map.width(), map.height()) != (width, height) {
+        return Err(Error::UnsupportedCarrier(format!(
+            "{} is {}x{} while the image is {}x{}", what, map.width(), map.height(), width, height,
+        )));
+    }
+    Ok(map.to_luma8().into_raw())
+}
+
+/// Encodes bytes either from the configured file or from the stdin into an image file with a given base image.
+///
+/// The data is spread over all the frames of an animated PNG image, see [`apng`](apng/index.html).
+/// With the [`verify`](struct.EncodeOptions.html#method.verify) option the written file is decoded back
+/// to check that it holds the data.
+pub fn encode_into_image(image: PathBuf, output: PathBuf, options: &EncodeOptions) -> Result<(), Error> {
+    // checking the output file early so it'll error out fast when it exists,
+    // but only creating it when the data is known to fit
+    if !options.replace && output.exists() {
+        return Err(io::Error::from(io::ErrorKind::AlreadyExists).into());
+    }
+    let image = std::fs::read(image)?;
+    let payload = read_payload(options)?;
+    let mut encoded = Vec::new();
+    if apng::is_animated(&image) {
+        if options.format.is_some_and(|format| format != OutputFormat::Png) {
+            return Err(Error::UnsupportedCarrier("animated PNG images can only be written as PNG".into()));
+        }
+        encoded = apng::hide_in_apng(&image, &payload, options)?;
+    } else {
+        encode_image(&image[..], &payload, &mut encoded, options)?;
+    }
+    open_output(&output, options.replace)?.write_all(&encoded)?;
+    if options.verify {
+        let written = std::fs::read(output)?;
+        let decoded = match apng::is_animated(&written) {
+            true => apng::reveal_from_apng(&written, &options.decoding()),
+            false => decode_image(&written[..], &options.decoding()).map(|(data, _)| data),
+        };
+        if decoded.ok().as_deref() != Some(&payload[..]) {
+            return Err(Error::IntegrityFailure);
+        }
+    }
+    Ok(())
+}
+
+/// Encodes the payload into the PNG, BMP, TIFF or WebP image read from the reader and writes the resulting image
+/// to the writer in the same format, except for WebP, which is written as PNG, unless the
+/// [`format`](struct.EncodeOptions.html#method.format) option says otherwise. The data file of the options is not used.
+///
+/// # Errors
+/// [`InsufficientCapacity`](enum.Error.html#variant.InsufficientCapacity) when the payload does not fit
+/// into the [capacity](fn.image_capacity.html) of the image, before anything is written.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::{encode_image, EncodeOptions, Error};
+/// # use image::{ColorType, ImageEncoder};
+/// # use image::codecs::png::PngEncoder;
+/// let mut png = Vec::new();
+/// PngEncoder::new(&mut png).write_image(&[0; 8 * 8 * 3], 8, 8, ColorType::Rgb8).unwrap();
+///
+/// let mut output = Vec::new();
+/// let result = encode_image(&png[..], &[0; 100], &mut output, &EncodeOptions::new());
+/// // two bits of each of the 162 color bytes after the header, less the length and the CRC32
+/// assert!(matches!(result, Err(Error::InsufficientCapacity { needed: 100, available: 28 })));
+/// assert!(output.is_empty());
+/// ```
+pub fn encode_image(mut image: impl Read, payload: &[u8], mut output: impl Write, options: &EncodeOptions) -> Result<(), Error> {
+    let mut original = Vec::new();
+    image.read_to_end(&mut original)?;
+    let (format, (width, height, color_type, mut pixels)) = read_image_format(&original[..])?;
+    let conversion = Conversion::detect(&original, color_type);
+    if !options.convert {
+        if let Some(conversion) = conversion {
+            return Err(Error::UnsupportedCarrier(format!("the image would have to be converted from {}", conversion)));
+        }
+    }
+
+    embed(&mut pixels, width, height, image_layout(color_type).width(width as usize), payload, options)?;
+    let output_format = output_format(format, options);
+    if format != ImageFormat::Png || output_format != OutputFormat::Png {
+        return write_image(output, &pixels, width, height, color_type, output_format, &options.png_output(&original));
+    }
+    let mut encoded = Vec::new();
+    write_image(&mut encoded, &pixels, width, height, color_type, output_format, &options.png_output(&original))?;
+    output.write_all(&chunks::copy_ancillary(&original, &encoded, conversion.is_some()))?;
+    Ok(())
+}
+
+/// Encodes bytes either from the configured file or from the stdin into a copy of the image with some data already
+/// hidden in it, as one more named entry, see [`append_image`](fn.append_image.html).
+///
+/// The output can be the image itself when the [`replace`](struct.EncodeOptions.html#method.replace) option is on.
+pub fn append_into_file(encoded: PathBuf, output: PathBuf, options: &EncodeOptions) -> Result<(), Error> {
+    if !options.replace && output.exists() {
+        return Err(io::Error::from(io::ErrorKind::AlreadyExists).into());
+    }
+    let image = std::fs::read(encoded)?;
+    let mut appended = Vec::new();
+    append_image(&image[..], &read_sealed(options)?, &mut appended, options)?;
+    open_output(output, options.replace)?.write_all(&appended)?;
+    Ok(())
+}
+
+/// Adds the payload as the entry with the configured [name](struct.EncodeOptions.html#method.name) to the data
+/// already hidden in the PNG, BMP, TIFF or WebP image read from the reader, and writes the resulting image to the writer
+/// the way [`encode_image`](fn.encode_image.html) does. The data file of the options is not used.
+///
+/// The payload is already sealed in the layers of the options, and the entry is put after all of the existing ones,
+/// with the table of contents moved after it. The interleave, the traversal and the matrix embedding are the ones
+/// from the header and the capacity after the data is [kept](enum.Filler.html#variant.Keep) as it is,
+/// so only the pixels holding the new entry, the table of contents and the length in front of them are changed.
+/// The data hidden without a name becomes the first entry, with an empty name, and whatever was hidden in the capacity
+/// after the data, such as a [hidden volume](struct.EncodeOptions.html#method.hidden_volume), is overwritten.
+///
+/// The rest of the options have to be the same as the ones the data was hidden with.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](enum.Error.html#variant.UnsupportedCarrier) when no name is configured, when there already
+/// is an entry with the name or when the channels of the data were [picked](struct.EncodeOptions.html#method.auto_channels)
+/// automatically, and [`InsufficientCapacity`](enum.Error.html#variant.InsufficientCapacity) when the entry does not fit.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::{append_image, decode_image, encode_image, sample_png, with_decoded_image, DecodeOptions, EncodeOptions};
+/// let png = sample_png(32, 32);
+///
+/// let mut encoded = Vec::new();
+/// encode_image(&png[..], b"the first one", &mut encoded, &EncodeOptions::new()).unwrap();
+/// let mut appended = Vec::new();
+/// append_image(&encoded[..], b"the second one", &mut appended, &EncodeOptions::new().name("second")).unwrap();
+///
+/// let decode = |name: Option<&str>| {
+///     let options = name.map_or(DecodeOptions::new(), |name| DecodeOptions::new().name(name));
+///     with_decoded_image(&appended[..], &options, |data, _| data.to_vec()).unwrap()
+/// };
+/// assert_eq!(decode(None), b"the first one");
+/// assert_eq!(decode(Some("second")), b"the second one");
+/// ```
+///
+/// The same goes for the data encrypted with a [password](struct.EncodeOptions.html#method.password),
+/// the capacity after it is left alone both when encoding and when appending:
+///
+/// ```
+/// # use steganographer_core::{append_image, crypto, encode_image, sample_png, with_decoded_image, DecodeOptions, EncodeOptions};
+/// let png = sample_png(32, 32);
+///
+/// let options = EncodeOptions::new().password("secret");
+/// let mut encoded = Vec::new();
+/// encode_image(&png[..], &crypto::seal(b"the first one", "secret").unwrap(), &mut encoded, &options).unwrap();
+/// let mut appended = Vec::new();
+/// let entry = crypto::seal(b"the second one", "secret").unwrap();
+/// append_image(&encoded[..], &entry, &mut appended, &options.name("second")).unwrap();
+///
+/// let last_rows = |png: &[u8]| image::load_from_memory(png).unwrap().to_rgb8().into_raw().split_off(32 * 24 * 3);
+/// assert_eq!(last_rows(&encoded), last_rows(&png));
+/// assert_eq!(last_rows(&appended), last_rows(&png));
+/// let options = DecodeOptions::new().password("secret").name("second");
+/// assert_eq!(with_decoded_image(&appended[..], &options, |data, _| data.to_vec()).unwrap(), b"the second one");
+/// ```
+pub fn append_image(image: impl Read, payload: &[u8], output: impl Write, options: &EncodeOptions) -> Result<(), Error> {
+    let name = options.name.as_deref().ok_or_else(|| Error::UnsupportedCarrier("the appended data needs a name".into()))?;
+    edit_entries(image, output, options, |archive| archive.push(name, options.layer_flags(), timestamp(options), payload))
+}
+
+/// Removes the entry with the configured [name](struct.EncodeOptions.html#method.name) from the data hidden
+/// in the image file, overwriting its bytes with random ones, see [`remove_from_image`](fn.remove_from_image.html).
+///
+/// The output can be the image itself when the [`replace`](struct.EncodeOptions.html#method.replace) option is on.
+pub fn remove_from_file(encoded: PathBuf, output: PathBuf, options: &EncodeOptions) -> Result<(), Error> {
+    if !options.replace && output.exists() {
+        return Err(io::Error::from(io::ErrorKind::AlreadyExists).into());
+    }
+    let image = std::fs::read(encoded)?;
+    let mut removed = Vec::new();
+    remove_from_image(&image[..], &mut removed, options)?;
+    open_output(output, options.replace)?.write_all(&removed)?;
+    Ok(())
+}
+
+/// Removes the entry with the configured [name](struct.EncodeOptions.html#method.name) from the data hidden
+/// in the PNG, BMP, TIFF or WebP image read from the reader, overwriting its bytes with random ones,
+/// and writes the resulting image to the writer, see [`toc::Archive::remove`](toc/struct.Archive.html#method.remove).
+///
+/// Only the pixels holding the entry, the table of contents and the length in front of them are changed,
+/// the same way as with [`append_image`](fn.append_image.html), and the rest of the options have to be the same
+/// as the ones the data was hidden with.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::{append_image, encode_image, list_entries, remove_from_image, with_decoded_image};
+/// # use steganographer_core::{sample_png, DecodeOptions, EncodeOptions};
+/// let png = sample_png(32, 32);
+///
+/// let mut encoded = Vec::new();
+/// encode_image(&png[..], b"the first one", &mut encoded, &EncodeOptions::new()).unwrap();
+/// let mut appended = Vec::new();
+/// append_image(&encoded[..], b"the second one", &mut appended, &EncodeOptions::new().name("second")).unwrap();
+/// let mut removed = Vec::new();
+/// remove_from_image(&appended[..], &mut removed, &EncodeOptions::new().name("")).unwrap();
+///
+/// let second = with_decoded_image(&removed[..], &DecodeOptions::new(), |data, report| (data.to_vec(), report.entry.clone()));
+/// assert_eq!(second.unwrap(), (b"the second one".to_vec(), Some("second".into())));
+/// assert!(with_decoded_image(&removed[..], &DecodeOptions::new().name(""), |_, _| ()).is_err());
+/// ```
+pub fn remove_from_image(image: impl Read, output: impl Write, options: &EncodeOptions) -> Result<(), Error> {
+    let name = options.name.as_deref().ok_or_else(|| Error::UnsupportedCarrier("the entry to remove needs a name".into()))?;
+    let mut rng = entropy(options, &[name.as_bytes()]);
+    edit_entries(image, output, options, |archive| archive.remove_with_rng(name, &mut rng).map(|_| ()))
+}
+
+/// Swaps the entry with the configured [name](struct.EncodeOptions.html#method.name) hidden in the image file
+/// for the bytes either from the configured file or from the stdin, see [`replace_in_image`](fn.replace_in_image.html).
+///
+/// The output can be the image itself when the [`replace`](struct.EncodeOptions.html#method.replace) option is on.
+pub fn replace_in_file(encoded: PathBuf, output: PathBuf, options: &EncodeOptions) -> Result<(), Error> {
+    if !options.replace && output.exists() {
+        return Err(io::Error::from(io::ErrorKind::AlreadyExists).into());
+    }
+    let image = std::fs::read(encoded)?;
+    let mut replaced = Vec::new();
+    replace_in_image(&image[..], &read_sealed(options)?, &mut replaced, options)?;
+    open_output(output, options.replace)?.write_all(&replaced)?;
+    Ok(())
+}
+
+/// Swaps the bytes of the entry with the configured [name](struct.EncodeOptions.html#method.name) hidden
+/// in the PNG, BMP, TIFF or WebP image read from the reader for the payload, which is already sealed in the layers
+/// of the options, and writes the resulting image to the writer, see
+/// [`toc::Archive::replace`](toc/struct.Archive.html#method.replace).
+///
+/// Only the pixels holding the entry, the table of contents and the length in front of them are changed,
+/// the same way as with [`append_image`](fn.append_image.html), and the rest of the options have to be the same
+/// as the ones the data was hidden with.
+///
+/// # Errors
+/// [`InsufficientCapacity`](enum.Error.html#variant.InsufficientCapacity) when the payload fits neither
+/// in the place of the old bytes nor after the other entries.
+pub fn replace_in_image(image: impl Read, payload: &[u8], output: impl Write, options: &EncodeOptions) -> Result<(), Error> {
+    let name = options.name.as_deref().ok_or_else(|| Error::UnsupportedCarrier("the entry to replace needs a name".into()))?;
+    let mut rng = entropy(options, &[name.as_bytes(), payload]);
+    edit_entries(image, output, options, |archive| {
+        archive.replace_with_rng(name, options.layer_flags(), timestamp(options), payload, &mut rng)
+    })
+}
+
+/// Changes the entries hidden in the image read from the reader and hides them back in place, keeping the settings
+/// from the header and the capacity after them, so that only the pixels holding the changes are touched
+fn edit_entries(mut image: impl Read, output: impl Write, options: &EncodeOptions,
+                edit: impl FnOnce(&mut toc::Archive) -> Result<(), Error>) -> Result<(), Error> {
+    if options.embedding != Embedding::Lsb || options.resync {
+        return Err(Error::UnsupportedCarrier("only the data hidden with the lsb embedding without resync can be changed in place".into()));
+    }
+    let mut original = Vec::new();
+    image.read_to_end(&mut original)?;
+    let decoding = options.decoding();
+    let header = inspect_image(&original[..], &decoding)?.header;
+    if header.channels != 0 {
+        return Err(Error::UnsupportedCarrier("the data in the automatically picked channels cannot be appended to".into()));
+    }
+    let (existing, report) = decode_image(&original[..], &decoding)?;
+    let existing = Zeroizing::new(existing);
+    let mut archive = match report.flags & container::FLAG_ENTRIES {
+        0 => {
+            let mut archive = toc::Archive::new();
+            archive.push("", report.flags, 0, &existing)?;
+            archive
+        },
+        _ => toc::Archive::from_bytes(&existing)?,
+    };
+    edit(&mut archive)?;
+    let options = EncodeOptions {
+        interleave: header.interleave,
+        traversal: match header.traversal {
+            // the key is not in the header, and the data could only be revealed with the one of the options
+            Traversal::Scattered(_) => options.traversal,
+            traversal => traversal,
+        },
+        matrix: header.matrix,
+        auto_channels: false,
+        filler: Filler::Keep,
+        hidden_volume: None,
+        ..options.clone()
+    };
+    encode_image(&original[..], &archive.to_bytes(), output, &options)
+}
+
+/// The configured output format, or the one of the image when it can be written
+fn output_format(format: ImageFormat, options: &EncodeOptions) -> OutputFormat {
+    options.format.unwrap_or(match format {
+        ImageFormat::Bmp => OutputFormat::Bmp,
+        ImageFormat::Tiff => OutputFormat::Tiff,
+        _ => OutputFormat::Png,
+    })
+}
+
+/// Overwrites the least significant bits of the image read from the reader, destroying whatever was hidden in them,
+/// and writes the image to the writer the way [`encode_image`](fn.encode_image.html) does, but without
+/// copying any of the ancillary PNG chunks, as those could carry data too.
+///
+/// The bits and the channels of the options are overwritten with the bits of their
+/// [filler](struct.EncodeOptions.html#method.filler), the random ones unless it is the modeled one,
+/// which keeps the statistics of the image instead. Either way the image looks the same,
+/// as only as many low bits as the [`bits`](struct.EncodeOptions.html#method.bits) setting says are changed.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::{decode_image, encode_image, sample_png, wipe_image, EncodeOptions};
+/// let png = sample_png(32, 32);
+///
+/// let options = EncodeOptions::new();
+/// let mut encoded = Vec::new();
+/// encode_image(&png[..], b"nobody will ever know", &mut encoded, &options).unwrap();
+///
+/// let mut wiped = Vec::new();
+/// wipe_image(&encoded[..], &mut wiped, &options).unwrap();
+/// assert!(decode_image(&wiped[..], &options.decoding()).is_err());
+/// ```
+pub fn wipe_image(mut image: impl Read, output: impl Write, options: &EncodeOptions) -> Result<(), Error> {
+    let mut original = Vec::new();
+    image.read_to_end(&mut original)?;
+    let (format, (width, height, color_type, mut pixels)) = read_image_format(&original[..])?;
+    let layout = options.carrier_layout(image_layout(color_type).width(width as usize));
+    let bits = options.carrier_bits(layout);
+    let mut rng = entropy(options, &[&pixels]);
+    match options.filler {
+        Filler::Modeled => modeled_chaff_with_rng(&mut pixels, bits, layout, &mut rng),
+        _ => random_chaff_with_rng(&mut pixels, bits, layout, &mut rng),
+    }
+    write_image(output, &pixels, width, height, color_type, output_format(format, options), &options.png_output(&original))
+}
+
+/// Wipes the hidden data from an image file, see [`wipe_image`](fn.wipe_image.html), writing the result to a new file.
+pub fn wipe_file(image: PathBuf, output: PathBuf, options: &EncodeOptions) -> Result<(), Error> {
+    let image = std::fs::read(image)?;
+    wipe_image(&image[..], open_output(output, options.replace)?, options)
+}
+
+/// Indices of the pixels that are not fully transparent, with every byte of their alpha sample being zero
+fn visible_pixels(pixels: &[u8], layout: binary::Layout) -> Vec<usize> {
+    let (size, alpha) = (layout.pixel_size(), layout.sample_size() as usize);
+    pixels.chunks_exact(size)
+        .enumerate()
+        .filter(|(_, pixel)| pixel[size - alpha..].iter().any(|&byte| byte != 0))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Indices of the pixels that may hold the data, the ones that are not fully transparent when they are skipped
+/// and the ones that are not black in the mask, or `None` when all of them may.
+///
+/// The `height` is only needed for the mask, and is taken from the width of the layout when not known.
+fn selected_pixels(pixels: &[u8], layout: binary::Layout, height: Option<u32>, skip_transparent: bool, mask: Option<&Path>)
+                   -> Result<Option<Vec<usize>>, Error> {
+    let skip_transparent = skip_transparent && layout.has_alpha();
+    if !skip_transparent && mask.is_none() {
+        return Ok(None);
+    }
+    let count = pixels.len() / layout.pixel_size();
+    let mask = match mask {
+        Some(path) => {
+            let width = layout.pixels_per_row().unwrap_or(count).max(1);
+            let height = height.unwrap_or((count / width) as u32);
+            Some(read_gray_map(path, "mask", width as u32, height)?)
+        },
+        None => None,
+    };
+    let candidates = match skip_transparent {
+        true => visible_pixels(pixels, layout),
+        false => (0..count).collect(),
+    };
+    Ok(Some(candidates.into_iter().filter(|&index| mask.as_ref().is_none_or(|mask| mask[index] != 0)).collect()))
+}
+
+/// The layout of the pixels [gathered](fn.gather_pixels.html) from the selected ones, which are no longer in rows
+fn compact_layout(layout: binary::Layout, skip_transparent: bool) -> binary::Layout {
+    let layout = if skip_transparent { layout.skip_alpha() } else { layout };
+    layout.width(0)
+}
+
+/// Collects the pixels with the given indices one after another
+fn gather_pixels(pixels: &[u8], indices: &[usize], layout: binary::Layout) -> Vec<u8> {
+    let size = layout.pixel_size();
+    indices.iter().flat_map(|&index| &pixels[index * size..(index + 1) * size]).copied().collect()
+}
+
+/// Marks the bytes the wet paper code must not change besides the ones of its cost model, the alpha samples
+/// when the alpha channel is left alone and all of the bytes of the pixels that are not selected
+fn wet_pixels(wet: &mut [bool], layout: binary::Layout, skip_alpha: bool, selected: Option<&[usize]>) {
+    let (size, alpha) = (layout.pixel_size(), layout.sample_size() as usize);
+    if skip_alpha && layout.has_alpha() {
+        wet.chunks_mut(size).for_each(|pixel| pixel[size - alpha..].fill(true));
+    }
+    if let Some(selected) = selected {
+        let mut dry = vec![false; wet.len().div_ceil(size)];
+        for &index in selected.iter().filter(|&&index| index < wet.len().div_ceil(size)) {
+            dry[index] = true;
+        }
+        wet.chunks_mut(size).zip(dry).filter(|(_, dry)| !dry).for_each(|(pixel, _)| pixel.fill(true));
+    }
+}
+
+/// Hides the payload in the decoded pixels of an image of any format in the given layout
+fn embed(pixels: &mut [u8], width: u32, height: u32, layout: binary::Layout, payload: &[u8], options: &EncodeOptions) -> Result<(), Error> {
+    let layout = options.carrier_layout(layout);
+    let selected = selected_pixels(pixels, layout.width(width as usize), Some(height), options.skip_transparent, options.mask.as_deref())?;
+    if let Some(selected) = selected.as_ref().filter(|_| options.leaves_out_pixels()) {
+        if options.cost_map.is_some() {
+            return Err(Error::UnsupportedCarrier("a cost map cannot be used when some of the pixels are left out".into()));
+        }
+        let mut compact = gather_pixels(pixels, selected, layout);
+        let compact_layout = compact_layout(layout, options.skip_transparent);
+        let options = EncodeOptions { skip_transparent: false, mask: None, ..options.clone() };
+        embed(&mut compact, selected.len() as u32, 1, compact_layout, payload, &options)?;
+        let size = layout.pixel_size();
+        for (index, pixel) in selected.iter().zip(compact.chunks_exact(size)) {
+            pixels[index * size..(index + 1) * size].copy_from_slice(pixel);
+        }
+        return Ok(());
+    }
+    if let Some(lane) = options.lane(pixels.len(), layout) {
+        if options.cost_map.is_some() || options.signing_key.is_some() {
+            return Err(Error::UnsupportedCarrier("a cost map or a signature cannot be used with an offset or a stride".into()));
+        }
+        if selected.is_some() {
+            return Err(Error::UnsupportedCarrier("the pixels of a lane cannot be left out by the wet paper embedding".into()));
+        }
+        let mut compact = lane.iter().map(|&i| pixels[i]).collect::<Vec<_>>();
+        embed(&mut compact, lane.len() as u32, 1, binary::Layout::default(), payload, &options.in_lane(layout))?;
+        lane.iter().zip(compact).for_each(|(&i, byte)| pixels[i] = byte);
+        return Ok(());
+    }
+    let available = carrier_capacity(pixels.len(), layout, options);
+    if payload.len() > available {
+        return Err(Error::InsufficientCapacity { needed: payload.len(), available });
+    }
+    let bits = options.carrier_bits(layout);
+    let signing_key = match &options.signing_key {
+        Some(path) => Some(SigningKey::from_bytes(&*read_key(path, KeyKind::Signing, false)?)),
+        None => None,
+    };
+    let end = pixels.len() - signing_key.as_ref().map_or(0, |_| reserved_len(pixels.len(), bits, layout));
+    let cost_map = match &options.cost_map {
+        Some(path) => Some(map_costs(&read_gray_map(path, "cost map", width, height)?, end, layout)),
+        None => None,
+    };
+    if options.hidden_volume.is_some() && (options.embedding != Embedding::Lsb || options.resync) {
+        return Err(Error::UnsupportedCarrier("only the lsb embedding without resync can hold a hidden volume".into()));
+    }
+    if options.whitening.is_some() && (options.embedding != Embedding::Lsb || options.resync) {
+        return Err(Error::UnsupportedCarrier("only the lsb embedding without resync can be whitened".into()));
+    }
+    if options.matrix > 1 && (options.embedding != Embedding::Lsb || options.resync) {
+        return Err(Error::UnsupportedCarrier("only the lsb embedding without resync can use the matrix embedding".into()));
+    }
+    if options.name.is_some() && (options.embedding != Embedding::Lsb || options.resync) {
+        return Err(Error::UnsupportedCarrier("only the lsb embedding without resync can hold named entries".into()));
+    }
+    if options.codec.is_some() && (options.embedding != Embedding::Lsb || options.resync) {
+        return Err(Error::UnsupportedCarrier("only the lsb embedding without resync can record the compression".into()));
+    }
+    if options.embedding == Embedding::WetPaper {
+        let mut wet = match cost_map {
+            Some(costs) => costs.iter().map(|cost| cost.is_infinite()).collect(),
+            None => saturated(&pixels[..end]),
+        };
+        wet_pixels(&mut wet, layout, options.skip_alpha, selected.as_deref());
+        strategy::WetPaper::default().wet(wet).hide(payload, &mut pixels[..end], layout)?;
+    } else if options.embedding == Embedding::Stc {
+        let costs = cost_map.unwrap_or_else(|| texture_costs(&pixels[..end], width as usize, layout));
+        strategy::Stc::default().costs(costs).hide(payload, &mut pixels[..end], layout)?;
+    } else if options.embedding == Embedding::Adaptive {
+        strategy::Adaptive::new(bits).hide(payload, &mut pixels[..end], layout)?;
+    } else {
+        let mut rng = entropy(options, &[pixels, payload]);
+        match options.filler {
+            Filler::Keep => {},
+            Filler::Modeled => modeled_chaff_with_rng(&mut pixels[..end], bits, layout, &mut rng),
+            Filler::Random => random_chaff_with_rng(&mut pixels[..end], bits, layout, &mut rng),
+        }
+        if options.resync {
+            hide_resync(payload, &mut pixels[..end], bits, layout, DEFAULT_SEGMENT_SIZE)?;
+        } else {
+            let channels = match options.auto_channels {
+                true => noise::pick_channels(&pixels[..end], layout, |mask| {
+                    container::matrix_capacity(end, bits, layout.only_channels(mask), options.matrix) >= payload.len()
+                }),
+                false => 0,
+            };
+            let header = Header {
+                interleave: options.interleave,
+                traversal: options.traversal,
+                channels,
+                flags: options.header_flags(),
+                matrix: options.matrix,
+                ..Header::default()
+            };
+            let volume = match &options.hidden_volume {
+                Some((data, password)) => Some((Zeroizing::new(std::fs::read(data)?), password)),
+                None => None,
+            };
+            let seal = |size| match (&volume, &options.password) {
+                (Some((volume, password)), _) => {
+                    let mut rng = entropy(options, &[volume, password.as_bytes(), &(size as u64).to_be_bytes()]);
+                    crypto::seal_volume_with_rng(volume, password, size, &mut rng)
+                },
+                // with the random filler the rest of the capacity after an encrypted decoy has to look the same
+                // whether there is a volume or not, with the other ones it is left to the filler
+                (None, Some(password)) if options.filler == Filler::Random => {
+                    let mut tail = vec![0; size];
+                    entropy(options, &[payload, password.as_bytes(), &(size as u64).to_be_bytes()]).fill_bytes(&mut tail);
+                    Ok(tail)
+                },
+                (None, _) => Ok(Vec::new()),
+            };
+            let whitening = options.whitening.as_deref().map(String::as_str);
+            container::hide_with(payload, seal, whitening, &mut pixels[..end], bits, layout, &header)?;
+        }
+    }
+    if let Some(key) = signing_key {
+        sign_carrier(pixels, bits, layout, &key)?;
+    }
+    Ok(())
+}
+
+/// Hides the `inner` file, usually an image with some data already hidden in it, in the first of the `covers`,
+/// then hides the result in the second one and so on, writing the outermost image to the output file.
+///
+/// Every layer is checked to fit into its cover before anything is written, and the data of the `inner` file
+/// can be decoded by peeling the layers with the [`depth`](struct.DecodeOptions.html#method.depth) option
+/// set to the number of covers plus one. The data file of the options is not used.
+///
+/// # Errors
+/// [`InsufficientCapacity`](enum.Error.html#variant.InsufficientCapacity) when some layer does not fit into its cover,
+/// before the output file is created.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::{nest_images, EncodeOptions, Error};
+/// # use image::{ColorType, ImageEncoder};
+/// # use image::codecs::png::PngEncoder;
+/// let inner = std::env::temp_dir().join("steganographer-nest-inner.png");
+/// PngEncoder::new(std::fs::File::create(&inner).unwrap()).write_image(&[0; 16 * 16 * 3], 16, 16, ColorType::Rgb8).unwrap();
+/// let cover = std::env::temp_dir().join("steganographer-nest-cover.png");
+/// PngEncoder::new(std::fs::File::create(&cover).unwrap()).write_image(&[0; 8 * 8 * 3], 8, 8, ColorType::Rgb8).unwrap();
+/// let output = std::env::temp_dir().join("steganographer-nest-output.png");
+/// let _ = std::fs::remove_file(&output);
+///
+/// let result = nest_images(inner, &[cover], output.clone(), &EncodeOptions::new());
+/// assert!(matches!(result, Err(Error::InsufficientCapacity { available: 28, .. })));
+/// assert!(!output.exists());
+/// ```
+pub fn nest_images(inner: PathBuf, covers: &[PathBuf], output: PathBuf, options: &EncodeOptions) -> Result<(), Error> {
+    let mut layer = std::fs::read(inner)?;
+    for cover in covers {
+        let cover_data = std::fs::read(cover)?;
+        let available = image_capacity(&cover_data[..], options)?;
+        if layer.len() > available {
+            return Err(Error::InsufficientCapacity { needed: layer.len(), available });
+        }
+        let mut encoded = Vec::new();
+        encode_image(&cover_data[..], &layer, &mut encoded, options)?;
+        layer = encoded;
+    }
+    open_output(output, options.replace)?.write_all(&layer)?;
+    Ok(())
+}
+
+/// Returns how many bytes of payload the image read from the reader can hold with the given options.
+///
+/// For the [wet paper](enum.Embedding.html#variant.WetPaper) embedding this is the upper bound,
+/// as the actual capacity depends on how many of the bytes are wet.
+pub fn image_capacity(image: impl Read, options: &EncodeOptions) -> Result<usize, Error> {
+    let (width, _, color_type, pixels) = read_image(image)?;
+    let layout = image_layout(color_type).width(width as usize);
+    if let Some(selected) = selected_pixels(&pixels, layout, None, options.skip_transparent, options.mask.as_deref())? {
+        return Ok(carrier_capacity(selected.len() * layout.pixel_size(), compact_layout(layout, options.skip_transparent), options));
+    }
+    Ok(carrier_capacity(pixels.len(), layout, options))
+}
+
+/// How many bytes of payload `len` bytes of pixels in the given layout can hold with the given options
+fn carrier_capacity(len: usize, layout: binary::Layout, options: &EncodeOptions) -> usize {
+    let layout = options.carrier_layout(layout);
+    if let Some(lane) = options.lane(len, layout) {
+        return carrier_capacity(lane.len(), binary::Layout::default(), &options.in_lane(layout));
+    }
+    let bits = options.carrier_bits(layout);
+    let mut len = len;
+    if options.signing_key.is_some() {
+        len -= reserved_len(len, bits, layout);
+    }
+    match options.embedding {
+        Embedding::Lsb if options.resync => stress::Method::Resync.capacity(len, bits, layout),
+        // the alpha samples are there, but they are all wet
+        Embedding::WetPaper if options.skip_alpha => strategy::WetPaper::default().capacity(len, layout.skip_alpha()),
+        embedding => embedding_strategy(embedding, bits, options.matrix).capacity(len, layout),
+    }
+}
+
+/// The strategy of the embedding with its defaults, the lsb one with the given bits and the matrix embedding
+fn embedding_strategy(embedding: Embedding, bits: binary::Bits, matrix: u8) -> Box<dyn EmbeddingStrategy> {
+    match embedding {
+        Embedding::Lsb => Box::new(strategy::Lsb::new(bits).header(Header { matrix, ..Header::default() })),
+        Embedding::WetPaper => Box::new(strategy::WetPaper::default()),
+        Embedding::Stc => Box::new(strategy::Stc::default()),
+        Embedding::Adaptive => Box::new(strategy::Adaptive::new(bits)),
+    }
+}
+
+/// Returns the fewest pixels in the given layout an image needs to hold `payload` bytes with the given options,
+/// the inverse of [`image_capacity`](fn.image_capacity.html), or `None` when no image of a sane size would do.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::{plan_pixels, EncodeOptions};
+/// # use steganographer_core::binary::{Bits, Layout};
+/// let rgb = Layout::new(3, 1, false);
+/// let options = EncodeOptions::new().bits(Bits::Two);
+///
+/// // 12 bytes of length and CRC32 and 1000 of payload, 4 color bytes for each of them, after the 10 pixels of the header
+/// assert_eq!(plan_pixels(1000, rgb, &options), Some(1360));
+/// ```
+pub fn plan_pixels(payload: usize, layout: binary::Layout, options: &EncodeOptions) -> Option<usize> {
+    let fits = |pixels: usize| carrier_capacity(pixels * layout.pixel_size(), layout, options) >= payload;
+    let mut high = 1;
+    while !fits(high) {
+        high = high.checked_mul(2).filter(|&high| high <= 1 << 40)?;
+    }
+    let mut low = high / 2;
+    while high - low > 1 {
+        let middle = low + (high - low) / 2;
+        match fits(middle) {
+            true => high = middle,
+            false => low = middle,
+        }
+    }
+    Some(high)
+}
+
+/// Returns the smallest dimensions with the given aspect ratio that have at least `pixels` pixels.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::plan_dimensions;
+/// assert_eq!(plan_dimensions(1343, (1, 1)), (37, 37));
+/// assert_eq!(plan_dimensions(1343, (16, 9)), (48, 28));
+/// ```
+pub fn plan_dimensions(pixels: usize, aspect: (u32, u32)) -> (u32, u32) {
+    let (a, b) = (aspect.0.max(1) as f64, aspect.1.max(1) as f64);
+    let height = ((pixels as f64 * b / a).sqrt().ceil() as usize).max(1);
+    (pixels.div_ceil(height) as u32, height as u32)
+}
+
+/// Reads the original image and the one with the data hidden in it, failing unless they have
+/// the same dimensions and pixel format
+fn read_pair(original: impl Read, altered: impl Read) -> Result<(Pixels, Vec<u8>), Error> {
+    let original = read_image(original)?;
+    let (width, height, color_type, altered) = read_image(altered)?;
+    if (width, height, color_type) != (original.0, original.1, original.2) {
+        return Err(Error::UnsupportedCarrier("the images have different dimensions or pixel formats".into()));
+    }
+    Ok((original, altered))
+}
+
+/// Computes the [PSNR](quality/fn.psnr.html) between the original image and the one
+/// with the data hidden in it, both read from the readers.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](enum.Error.html#variant.UnsupportedCarrier) when the images have different dimensions
+/// or pixel formats.
+pub fn image_psnr(original: impl Read, altered: impl Read) -> Result<f64, Error> {
+    let ((_, _, _, original), altered) = read_pair(original, altered)?;
+    Ok(quality::psnr(&original, &altered))
+}
+
+/// Measures how much hiding the data has cost the original image, comparing it with the image with the data hidden in it,
+/// both read from the readers, and tells how much of the capacity of the original image the data takes.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](enum.Error.html#variant.UnsupportedCarrier) when the images have different dimensions
+/// or pixel formats.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::{encode_image, image_quality, sample_png, EncodeOptions};
+/// # use steganographer_core::binary::Bits;
+/// let png = sample_png(32, 32);
+/// let options = EncodeOptions::new().bits(Bits::Four);
+/// let mut encoded = Vec::new();
+/// encode_image(&png[..], &[0xa5; 100], &mut encoded, &options).unwrap();
+///
+/// let quality = image_quality(&png[..], &encoded[..], &options).unwrap();
+/// assert_eq!(quality.used, Some(100));
+/// assert!(quality.psnr > 30.0 && quality.ssim > 0.9 && quality.mean_absolute_error < 8.0);
+/// ```
+pub fn image_quality(mut original: impl Read, mut altered: impl Read, options: &EncodeOptions) -> Result<Quality, Error> {
+    let (mut original_data, mut altered_data) = (Vec::new(), Vec::new());
+    original.read_to_end(&mut original_data)?;
+    altered.read_to_end(&mut altered_data)?;
+    let ((width, _, color_type, original), altered) = read_pair(&original_data[..], &altered_data[..])?;
+    let layout = image_layout(color_type).width(width as usize);
+    let used = if options.embedding == Embedding::Lsb && !options.resync {
+        inspect_image(&altered_data[..], &options.decoding()).ok().map(|inspection| inspection.length)
+    } else {
+        None
+    };
+    Ok(Quality {
+        psnr: quality::psnr(&original, &altered),
+        ssim: quality::ssim(&original, &altered, layout),
+        mean_absolute_error: quality::mean_absolute_error(&original, &altered, layout),
+        capacity: image_capacity(&original_data[..], options)?,
+        used,
+    })
+}
+
+/// [Compares](quality/fn.compare.html) the original image and the one with the data hidden in it,
+/// both read from the readers, bit by bit in each of their channels.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](enum.Error.html#variant.UnsupportedCarrier) when the images have different dimensions
+/// or pixel formats.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::{compare_images, encode_image, sample_png, EncodeOptions};
+/// # use steganographer_core::binary::Bits;
+/// let png = sample_png(16, 16);
+/// let mut encoded = Vec::new();
+/// encode_image(&png[..], b"nothing but the low bits", &mut encoded, &EncodeOptions::new().bits(Bits::Two)).unwrap();
+///
+/// let comparison = compare_images(&png[..], &encoded[..]).unwrap();
+/// assert!(comparison.changed_pixels > 0 && comparison.only_low_bits(2));
+/// ```
+pub fn compare_images(original: impl Read, altered: impl Read) -> Result<quality::Comparison, Error> {
+    let ((_, _, color_type, original), altered) = read_pair(original, altered)?;
+    Ok(quality::compare(&original, &altered, image_layout(color_type)))
+}
+
+/// Writes the [heat map](quality/fn.heat_map.html) of the pixels that differ between the original image
+/// and the one with the data hidden in it, both read from the readers, as a PNG image of the same size.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](enum.Error.html#variant.UnsupportedCarrier) when the images have different dimensions
+/// or pixel formats.
+pub fn heat_map_image(original: impl Read, altered: impl Read, output: impl Write) -> Result<(), Error> {
+    let ((width, height, color_type, original), altered) = read_pair(original, altered)?;
+    let map = quality::heat_map(&original, &altered, image_layout(color_type));
+    write_image(output, &map, width, height, ColorType::Rgb8, OutputFormat::Png, &EncodeOptions::new().png_output(&[]))
+}
+
+/// Hides the data from the supplied file or from the stdin in the image and decodes it back, all in memory,
+/// to check that the options work for this image before any files are written.
+///
+/// The data is decoded with the same settings it was encoded with, the output and the replace setting are not used.
+pub fn roundtrip_image(image: PathBuf, options: &EncodeOptions) -> Result<RoundTrip, Error> {
+    let cover = std::fs::read(image)?;
+    let payload = read_payload(options)?;
+    let capacity = image_capacity(&cover[..], options)?;
+
+    let mut encoded = Vec::new();
+    encode_image(&cover[..], &payload, &mut encoded, options)?;
+    let (decoded, _) = decode_image(&encoded[..], &options.decoding())?;
+
+    let wrong_bits = payload.iter().zip(&decoded).map(|(a, b)| (a ^ b).count_ones() as usize).sum::<usize>()
+        + 8 * payload.len().abs_diff(decoded.len());
+    Ok(RoundTrip { capacity, payload: payload.len(), psnr: image_psnr(&cover[..], &encoded[..])?, wrong_bits })
+}
+
+/// Checks the signature stored in the image by encoding it with a
+/// [signing key](struct.EncodeOptions.html#method.signing_key) against the given public key file,
+/// returning the fingerprint of that key.
+///
+/// Only the bits, the alpha, the transparency and the mask settings are used from the options.
+///
+/// # Errors
+/// [`InvalidSignature`](enum.Error.html#variant.InvalidSignature) when the image was altered since it was signed.
+pub fn verify_image(image: PathBuf, public_key: PathBuf, options: &DecodeOptions) -> Result<String, Error> {
+    let key = read_key(public_key, KeyKind::Signing, true)?;
+    let verifying_key = VerifyingKey::from_bytes(&key).map_err(|_| Error::InvalidKey("not an Ed25519 public key".into()))?;
+    let (width, _, color_type, mut data) = read_image(File::open(image)?)?;
+    let mut layout = options.carrier_layout(image_layout(color_type)).width(width as usize);
+    let selected = match options.leaves_out_pixels() {
+        true => selected_pixels(&data, layout, None, options.skip_transparent, options.mask.as_deref())?,
+        false => None,
+    };
+    if let Some(selected) = selected {
+        data = gather_pixels(&data, &selected, layout);
+        layout = compact_layout(layout, options.skip_transparent);
+    }
+    verify_carrier(&data, options.carrier_bits(layout), layout, &verifying_key)?;
+    Ok(keys::fingerprint(&key[..]))
+}
+
+/// Applies a [battery of transformations](stress/enum.Attack.html#method.battery) to the image with hidden data
+/// and reports whether the data can still be decoded after each of them.
+///
+/// The options tell how the data was hidden, the output is not used.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::{encode_image, stress_test_image, DecodeOptions, EncodeOptions};
+/// # use steganographer_core::stress::{Attack, Outcome};
+/// # use image::{ColorType, ImageEncoder};
+/// # use image::codecs::png::PngEncoder;
+/// let mut png = Vec::new();
+/// PngEncoder::new(&mut png).write_image(&[100; 32 * 32 * 3], 32, 32, ColorType::Rgb8).unwrap();
+/// let encoded = std::env::temp_dir().join("steganographer-stress-encoded.png");
+/// encode_image(&png[..], b"fragile", std::fs::File::create(&encoded).unwrap(), &EncodeOptions::new()).unwrap();
+///
+/// let report = stress_test_image(encoded, &DecodeOptions::new()).unwrap();
+/// assert_eq!(report.len(), Attack::battery().len());
+/// let outcome = |attack| &report.iter().find(|(a, _)| *a == attack).unwrap().1;
+/// // lossless saving keeps every bit, while cropping shifts them and the JPEG compression wipes out the low ones
+/// assert_eq!(outcome(Attack::Reencode), &Outcome::Intact);
+/// assert!(matches!(outcome(Attack::CropRows(1)), Outcome::Lost(_)));
+/// assert!(matches!(outcome(Attack::Jpeg(50)), Outcome::Lost(_)));
+/// ```
+pub fn stress_test_image(image: PathBuf, options: &DecodeOptions) -> Result<Vec<(Attack, Outcome)>, Error> {
+    let image = image::load_from_memory(&std::fs::read(image)?)?;
+    let layout = options.carrier_layout(image_layout(image.color())).width(image.width() as usize);
+    let bits = options.carrier_bits(layout);
+    stress(&image, &Attack::battery(), |pixels| if options.resync {
+        Ok(reveal_resync(pixels, bits, layout)?.payload)
+    } else {
+        Ok(container::reveal_with(pixels, bits, layout, None, options.scatter)?.1)
+    })
+}
+
+/// Measures the [noise of each region](noise/fn.region_noise.html) of `region_size` pixels of the image read
+/// from the reader, in the channels the options hide the data in, to tell the regions that are safe
+/// to hide the data in from the dangerously flat ones.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::{image_regions, EncodeOptions};
+/// # use steganographer_core::noise::Suitability;
+/// # use image::{ColorType, ImageEncoder};
+/// # use image::codecs::png::PngEncoder;
+/// // a flat top half and a noisy bottom half
+/// let pixels = (0..32 * 32 * 3u32)
+///     .map(|i| if i < 16 * 32 * 3 { 200 } else { (i.wrapping_mul(0x9e37_79b9) >> 24) as u8 })
+///     .collect::<Vec<_>>();
+/// let mut png = Vec::new();
+/// PngEncoder::new(&mut png).write_image(&pixels, 32, 32, ColorType::Rgb8).unwrap();
+///
+/// let regions = image_regions(&png[..], &EncodeOptions::new(), 16).unwrap();
+/// let suitability = regions.iter().map(|region| region.suitability()).collect::<Vec<_>>();
+/// assert_eq!(suitability, [Suitability::Flat, Suitability::Flat, Suitability::Safe, Suitability::Safe]);
+/// ```
+pub fn image_regions(image: impl Read, options: &EncodeOptions, region_size: usize) -> Result<Vec<noise::RegionNoise>, Error> {
+    let (width, _, color_type, pixels) = read_image(image)?;
+    let layout = options.carrier_layout(image_layout(color_type).width(width as usize));
+    Ok(noise::region_noise(&pixels, layout, region_size))
+}
+
+/// Writes the [suitability map](noise/fn.suitability_map.html) of the [regions](fn.image_regions.html)
+/// of the image read from the reader as a PNG image of the same size.
+pub fn suitability_map_image(image: impl Read, options: &EncodeOptions, region_size: usize, output: impl Write) -> Result<(), Error> {
+    let (width, height, color_type, pixels) = read_image(image)?;
+    let layout = options.carrier_layout(image_layout(color_type).width(width as usize));
+    let map = noise::suitability_map(&noise::region_noise(&pixels, layout, region_size), width as usize, height as usize);
+    write_image(output, &map, width, height, ColorType::Rgb8, OutputFormat::Png, &EncodeOptions::new().png_output(&[]))
+}
+
+/// Runs the [chi-square attack](steganalysis/fn.chi_square.html) on the color channels of the image read from the reader,
+/// as a whole and in the square regions of `region_size` pixels, and the [RS](steganalysis/fn.rs.html) and the
+/// [sample pair](steganalysis/fn.spa.html) analyses on each of them, telling whether it is likely to have some data
+/// hidden in its least significant bits, by this crate or by anything else.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](enum.Error.html#variant.UnsupportedCarrier) for the image formats that are not decoded
+/// as carriers, and any error of decoding the image.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::{detect_image, encode_image, EncodeOptions};
+/// # use steganographer_core::binary::Bits;
+/// # use image::{ColorType, ImageEncoder};
+/// # use image::codecs::png::PngEncoder;
+/// // a gradient has far more of the even values than the odd ones
+/// let pixels = (0..64 * 64 * 3).map(|i| (i / 96 * 2) as u8).collect::<Vec<_>>();
+/// let mut png = Vec::new();
+/// PngEncoder::new(&mut png).write_image(&pixels, 64, 64, ColorType::Rgb8).unwrap();
+/// assert!(!detect_image(&png[..], 32).unwrap().is_suspicious());
+///
+/// let data = (0..1500u32).map(|i| (i * 7919 % 251) as u8).collect::<Vec<_>>();
+/// let mut encoded = Vec::new();
+/// encode_image(&png[..], &data, &mut encoded, &EncodeOptions::new().bits(Bits::One)).unwrap();
+///
+/// let detection = detect_image(&encoded[..], 32).unwrap();
+/// assert!(detection.is_suspicious());
+/// assert_eq!(detection.regions.len(), 4);
+/// ```
+pub fn detect_image(image: impl Read, region_size: usize) -> Result<Detection, Error> {
+    let (width, _, color_type, pixels) = read_image(image)?;
+    let layout = image_layout(color_type).width(width as usize).skip_alpha();
+    Ok(steganalysis::analyze(&pixels, layout, region_size))
+}
+
+/// Writes one [bit plane](steganalysis/fn.bit_plane.html) of one channel of the image read from the reader
+/// as a grayscale PNG image of the same size, where the data hidden in that bit stands out as noise.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](enum.Error.html#variant.UnsupportedCarrier) when the image has no such channel,
+/// or its samples have no such bit.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::{bit_plane_image, encode_image, EncodeOptions};
+/// # use image::{ColorType, ImageEncoder, GenericImageView};
+/// # use image::codecs::png::PngEncoder;
+/// let pixels = vec![128; 16 * 16 * 3];
+/// let mut png = Vec::new();
+/// PngEncoder::new(&mut png).write_image(&pixels, 16, 16, ColorType::Rgb8).unwrap();
+/// let mut encoded = Vec::new();
+/// encode_image(&png[..], b"stands out", &mut encoded, &EncodeOptions::new()).unwrap();
+///
+/// let mut plane = Vec::new();
+/// bit_plane_image(&encoded[..], 2, 0, &mut plane).unwrap();
+/// let plane = image::load_from_memory(&plane).unwrap();
+/// assert_eq!((plane.dimensions(), plane.color()), ((16, 16), ColorType::L8));
+/// // the first pixels hold the data and the rest are left as they were
+/// let white = plane.to_luma8().pixels().map(|pixel| pixel[0] == 255).collect::<Vec<_>>();
+/// assert!(white[..32].contains(&true) && !white[128..].contains(&true));
+///
+/// assert!(bit_plane_image(&encoded[..], 3, 0, &mut Vec::new()).is_err());
+/// ```
+pub fn bit_plane_image(image: impl Read, channel: usize, bit: u8, output: impl Write) -> Result<(), Error> {
+    let (width, height, color_type, pixels) = read_image(image)?;
+    let layout = image_layout(color_type);
+    if channel >= layout.channels() as usize {
+        return Err(Error::UnsupportedCarrier(format!("{:?} images have no channel {}", color_type, channel)));
+    }
+    let plane = steganalysis::bit_plane(&pixels, layout, channel, bit);
+    if plane.is_empty() {
+        return Err(Error::UnsupportedCarrier(format!("{:?} images have no bit {} in their samples", color_type, bit)));
+    }
+    write_image(output, &plane, width, height, ColorType::L8, OutputFormat::Png, &EncodeOptions::new().png_output(&[]))
+}
+
+/// Writes the bit plane of an image file, see [`bit_plane_image`](fn.bit_plane_image.html), to a new file.
+pub fn bit_plane_file(image: PathBuf, channel: usize, bit: u8, output: PathBuf, replace: bool) -> Result<(), Error> {
+    let image = std::fs::read(image)?;
+    let mut plane = Vec::new();
+    bit_plane_image(&image[..], channel, bit, &mut plane)?;
+    Ok(open_output(output, replace)?.write_all(&plane)?)
+}
+
+/// Reads just the header and the length in front of the data hidden in the image read from the reader,
+/// without extracting the data, trying every other bits setting when the configured one finds no header.
+///
+/// Only the data [embedded](struct.DecodeOptions.html#method.embedding) into the least significant bits
+/// has a header, the rest of the options tell which pixels of the image to look at, and the output is not used.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](enum.Error.html#variant.UnsupportedCarrier) when there is no header with any bits setting,
+/// which means that nothing was hidden, or when the data is of a newer format.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::{encode_image, inspect_image, sample_png, DecodeOptions, EncodeOptions};
+/// # use steganographer_core::binary::Bits;
+/// let png = sample_png(32, 32);
+/// assert!(inspect_image(&png[..], &DecodeOptions::new()).is_err());
+///
+/// let mut encoded = Vec::new();
+/// encode_image(&png[..], b"look, but do not touch", &mut encoded, &EncodeOptions::new().bits(Bits::Three)).unwrap();
+///
+/// let inspection = inspect_image(&encoded[..], &DecodeOptions::new()).unwrap();
+/// assert_eq!((inspection.bits, inspection.length), (Bits::Three, 22));
+/// assert!(inspection.is_plausible());
+/// ```
+pub fn inspect_image(image: impl Read, options: &DecodeOptions) -> Result<Inspection, Error> {
+    let (width, _, color_type, pixels) = read_image(image)?;
+    let layout = options.carrier_layout(image_layout(color_type).width(width as usize));
+    let (pixels, layout) = match selected_pixels(&pixels, layout, None, options.skip_transparent, options.mask.as_deref())? {
+        Some(selected) => (gather_pixels(&pixels, &selected, layout), compact_layout(layout, options.skip_transparent)),
+        None => (pixels, layout),
+    };
+    let (pixels, layout, options) = match options.lane(pixels.len(), layout) {
+        Some(lane) => (lane.iter().map(|&i| pixels[i]).collect(), binary::Layout::default(), options.in_lane(layout)),
+        None => (pixels, layout, options.clone()),
+    };
+    let whitening = options.whitening.as_deref().map(String::as_str);
+    with_any_bits(options.carrier_bits(layout), true, |bits| {
+        let end = match options.signed {
+            true => pixels.len() - reserved_len(pixels.len(), bits, layout),
+            false => pixels.len(),
+        };
+        let (header, length, capacity) = container::reveal_prefix(&pixels[..end], bits, layout, whitening, options.scatter)?;
+        Ok(Inspection { bits, header, length, capacity })
+    })
+}
+
+/// Looks for the data in the image read from the reader with every bits setting, every combination of the channels,
+/// the alpha one included, and both with and without the fully transparent pixels,
+/// returning all the data that was found with an intact header and CRC32, the output is not used.
+///
+/// This is for when the settings the data was encoded with are lost. Only the data
+/// [embedded](struct.DecodeOptions.html#method.embedding) into the least significant bits can be found,
+/// the rest of the options, such as the [whitening](struct.DecodeOptions.html#method.whiten) seed, are used as they are.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::{encode_image, recover_image, sample_png, DecodeOptions, EncodeOptions};
+/// # use steganographer_core::binary::Bits;
+/// let png = sample_png(32, 32);
+///
+/// let mut encoded = Vec::new();
+/// encode_image(&png[..], b"forgotten", &mut encoded, &EncodeOptions::new().bits(Bits::Three).channels(0b101)).unwrap();
+///
+/// let found = recover_image(&encoded[..], &DecodeOptions::new()).unwrap();
+/// assert_eq!(found.len(), 1);
+/// assert_eq!((found[0].bits, found[0].channels), (Bits::Three, 0b101));
+/// assert_eq!(found[0].data, b"forgotten");
+/// ```
+pub fn recover_image(image: impl Read, options: &DecodeOptions) -> Result<Vec<Recovered>, Error> {
+    let (width, _, color_type, pixels) = read_image(image)?;
+    let layout = image_layout(color_type).width(width as usize);
+    let transparency: &[bool] = if layout.has_alpha() { &[false, true] } else { &[false] };
+    let mut found = Vec::new();
+    for &skip_transparent in transparency {
+        for channels in 1..1u8 << layout.channels() {
+            for &bits in &binary::Bits::ALL {
+                let attempt = DecodeOptions {
+                    bits,
+                    wide_bits: Some(bits),
+                    detect_bits: false,
+                    embedding: Embedding::Lsb,
+                    resync: false,
+                    hidden_volume: false,
+                    skip_alpha: false,
+                    skip_transparent,
+                    channels,
+                    ..options.clone()
+                };
+                if let Ok((data, report)) = extract(pixels.clone(), layout, &attempt) {
+                    found.push(Recovered { bits, channels, skip_transparent, flags: report.flags, data });
+                }
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Hides the data from the supplied file or from the stdin in the image with every available method and
+/// bits setting and reports how much capacity each of them has, how much they distort the image and which
+/// transformations from the [battery](stress/enum.Attack.html#method.battery) they survive.
+///
+/// Only the data file is used from the options, as all the other settings are what is being compared.
+pub fn survey_image(image: PathBuf, options: &EncodeOptions) -> Result<Vec<SurveyRow>, Error> {
+    let image = image::load_from_memory(&std::fs::read(image)?)?;
+    survey(&image, &read_payload(options)?, &Attack::battery())
+}
+
+/// The pixels of the gradient RGB image the examples hide their data in
+#[doc(hidden)]
+pub fn sample_pixels(width: u32, height: u32) -> Vec<u8> {
+    (0..width * height * 3).map(|i| (i * 7) as u8).collect()
+}
+
+/// The [sample pixels](fn.sample_pixels.html) written as a PNG image
+#[doc(hidden)]
+pub fn sample_png(width: u32, height: u32) -> Vec<u8> {
+    let mut png = Vec::new();
+    write_image(&mut png, &sample_pixels(width, height), width, height, ColorType::Rgb8, OutputFormat::Png, &EncodeOptions::new().png_output(&[]))
+        .expect("the sample pixels can always be written to memory");
+    png
+}
@@ -0,0 +1,85 @@
+/// The most payload bits each block can hold, as that is what fits into the [header](../container/struct.Header.html#structfield.matrix)
+pub const MAX_MATRIX: u8 = 4;
+
+/// Number of the carrier bits in each block of the code that holds `matrix` payload bits, `2^matrix - 1`.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::matrix::block_size;
+/// assert_eq!(block_size(1), 1);
+/// assert_eq!(block_size(3), 7);
+/// ```
+pub fn block_size(matrix: u8) -> usize {
+    (1 << matrix.clamp(1, MAX_MATRIX)) - 1
+}
+
+/// How many payload bits `bits` carrier bits can hold with `matrix` payload bits in each block.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::matrix::capacity;
+/// assert_eq!(capacity(100, 1), 100);
+/// // 14 blocks of 7 bits, the last 2 bits are not enough for another one
+/// assert_eq!(capacity(100, 3), 42);
+/// ```
+pub fn capacity(bits: usize, matrix: u8) -> usize {
+    bits / block_size(matrix) * matrix.clamp(1, MAX_MATRIX) as usize
+}
+
+/// The syndrome of the block, the XOR of the 1-based indices of the set bits in it,
+/// which is what the block holds
+fn syndrome(block: &[u8]) -> usize {
+    block.iter().enumerate().filter(|(_, &bit)| bit & 1 == 1).fold(0, |syndrome, (i, _)| syndrome ^ (i + 1))
+}
+
+/// Embeds the message bits into the carrier bits in place with the Hamming code, flipping at most one bit
+/// in each block of [`block_size`](fn.block_size.html) bits that holds `matrix` of them, and returns
+/// how many bits were flipped.
+///
+/// Plain replacing changes half of the carrier bits on average, while this changes
+/// `2^matrix / (2^matrix - 1) / matrix` per message bit, at the cost of needing that many more carrier bits,
+/// so it pays off when the payload is small compared to the capacity. The first `matrix` of 1 is just replacing.
+/// The message is padded with zeros to fill the last block, and it has to fit into the carrier.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::matrix::{embed, extract};
+/// let mut carrier = vec![1, 0, 1, 1, 0, 0, 1, 0, 1, 1, 1, 0, 0, 1];
+/// let original = carrier.clone();
+/// let message = [1, 0, 1, 1, 1, 0];
+///
+/// // the Hamming [7, 4] code, 3 bits in each 7 carrier bits
+/// let changed = embed(&mut carrier, message.iter().copied(), 3);
+/// assert!(changed <= 2);
+/// assert_eq!(carrier.iter().zip(&original).filter(|(a, b)| a != b).count(), changed);
+/// assert_eq!(extract(&carrier, 3).collect::<Vec<_>>(), message);
+/// ```
+pub fn embed(carrier: &mut [u8], message: impl IntoIterator<Item = u8>, matrix: u8) -> usize {
+    let matrix = matrix.clamp(1, MAX_MATRIX);
+    let mut message = message.into_iter().peekable();
+    let mut changed = 0;
+    for block in carrier.chunks_exact_mut(block_size(matrix)) {
+        if message.peek().is_none() {
+            break;
+        }
+        let wanted = (0..matrix).fold(0, |wanted, _| wanted << 1 | message.next().unwrap_or(0) as usize & 1);
+        let flip = syndrome(block) ^ wanted;
+        if flip != 0 {
+            block[flip - 1] ^= 1;
+            changed += 1;
+        }
+    }
+    changed
+}
+
+/// Extracts the message bits that [`embed`](fn.embed.html) has embedded into the carrier bits,
+/// `matrix` bits from each of the blocks.
+pub fn extract(carrier: &[u8], matrix: u8) -> impl Iterator<Item = u8> + '_ {
+    let matrix = matrix.clamp(1, MAX_MATRIX);
+    carrier.chunks_exact(block_size(matrix))
+        .map(syndrome)
+        .flat_map(move |syndrome| (0..matrix).rev().map(move |shift| (syndrome >> shift & 1) as u8))
+}
@@ -0,0 +1,222 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+use byteorder::{BigEndian, ReadBytesExt};
+
+use crate::Error;
+
+/// Magic at the start of the data with the metadata record in front of it
+pub const MAGIC: [u8; 4] = *b"SGM\x01";
+
+/// Bit of the stored mode that tells that there is one, as 0 is a valid mode
+const MODE_SET: u32 = 1 << 31;
+
+/// What is known about the file the data came from, stored in front of the data itself,
+/// so that the decoder can restore the file as it was.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::metadata::Metadata;
+/// let metadata = Metadata { filename: Some("plans.txt".into()), modified: Some(1_700_000_000), ..Metadata::default() };
+/// let data = metadata.prepend(b"the plans");
+///
+/// let (read, rest) = Metadata::split(&data).unwrap();
+/// assert_eq!(read, Some(metadata));
+/// assert_eq!(rest, b"the plans");
+/// assert_eq!(Metadata::split(b"the plans").unwrap(), (None, &b"the plans"[..]));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// Name of the file, without any of the directories it was in
+    pub filename: Option<String>,
+    /// Unix permissions of the file
+    pub mode: Option<u32>,
+    /// When the file was last modified, in seconds since the Unix epoch
+    pub modified: Option<u64>,
+    /// MIME type of the file, as guessed from its extension, see [`mime_type`](fn.mime_type.html)
+    pub mime: Option<String>,
+}
+
+impl Metadata {
+    /// Reads the metadata of the file
+    pub fn of_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let metadata = fs::metadata(path)?;
+        #[cfg(unix)]
+        let mode = Some(std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o7777);
+        #[cfg(not(unix))]
+        let mode = None;
+        Ok(Metadata {
+            filename: path.file_name().and_then(|name| name.to_str()).map(String::from),
+            mode,
+            modified: metadata.modified().ok().and_then(|time| time.duration_since(UNIX_EPOCH).ok()).map(|since| since.as_secs()),
+            mime: Some(mime_type(path).into()).filter(|_| metadata.is_file()),
+        })
+    }
+
+    /// Returns the data with the metadata record in front of it
+    pub fn prepend(&self, data: &[u8]) -> Vec<u8> {
+        let mut record = MAGIC.to_vec();
+        write_string(&mut record, &self.filename);
+        record.extend_from_slice(&self.mode.map_or(0, |mode| mode | MODE_SET).to_be_bytes());
+        record.extend_from_slice(&self.modified.unwrap_or(0).to_be_bytes());
+        write_string(&mut record, &self.mime);
+        record.extend_from_slice(data);
+        record
+    }
+
+    /// Splits the metadata record off the front of the data, when there is one.
+    ///
+    /// # Errors
+    /// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when the data starts with the
+    /// [magic](constant.MAGIC.html) of the record, but the record is malformed.
+    pub fn split(data: &[u8]) -> Result<(Option<Self>, &[u8]), Error> {
+        if !data.starts_with(&MAGIC) {
+            return Ok((None, data));
+        }
+        let mut from = &data[MAGIC.len()..];
+        let metadata = read_record(&mut from)
+            .map_err(|_| Error::UnsupportedCarrier("the metadata record in front of the data is malformed".into()))?;
+        Ok((Some(metadata), from))
+    }
+
+    /// The file name that is safe to create in a directory, the one from the record
+    /// unless it is empty or it could lead out of the directory
+    pub fn safe_filename(&self) -> Option<&str> {
+        self.filename.as_deref().filter(|name| !name.is_empty() && *name != "." && *name != ".." && !name.contains(['/', '\\']))
+    }
+
+    /// Sets the permissions and the modification time of the file to the ones from the record,
+    /// the permissions only on Unix
+    pub fn apply(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        if let Some(modified) = self.modified {
+            File::options().write(true).open(&path)?.set_modified(UNIX_EPOCH + Duration::from_secs(modified))?;
+        }
+        #[cfg(unix)]
+        if let Some(mode) = self.mode {
+            fs::set_permissions(&path, std::os::unix::fs::PermissionsExt::from_mode(mode))?;
+        }
+        Ok(())
+    }
+}
+
+fn write_string(record: &mut Vec<u8>, string: &Option<String>) {
+    let string = string.as_deref().unwrap_or_default();
+    let len = string.len().min(u8::MAX as usize);
+    record.push(len as u8);
+    record.extend_from_slice(&string.as_bytes()[..len]);
+}
+
+fn read_string(from: &mut &[u8]) -> io::Result<Option<String>> {
+    let mut string = vec![0; from.read_u8()? as usize];
+    from.read_exact(&mut string)?;
+    let string = String::from_utf8(string).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(Some(string).filter(|string| !string.is_empty()))
+}
+
+fn read_record(from: &mut &[u8]) -> io::Result<Metadata> {
+    let filename = read_string(from)?;
+    let mode = from.read_u32::<BigEndian>()?;
+    let modified = from.read_u64::<BigEndian>()?;
+    Ok(Metadata {
+        filename,
+        mode: Some(mode & !MODE_SET).filter(|_| mode & MODE_SET != 0),
+        modified: Some(modified).filter(|&modified| modified != 0),
+        mime: read_string(from)?,
+    })
+}
+
+/// Extensions of the files of the known MIME types, the first one of each type is the one it gets
+const TYPES: &[(&str, &str)] = &[
+    ("txt", "text/plain"),
+    ("md", "text/plain"),
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("csv", "text/csv"),
+    ("json", "application/json"),
+    ("xml", "application/xml"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("tar", "application/x-tar"),
+    ("zst", "application/zstd"),
+    ("7z", "application/x-7z-compressed"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("bmp", "image/bmp"),
+    ("svg", "image/svg+xml"),
+    ("mp3", "audio/mpeg"),
+    ("ogg", "audio/ogg"),
+    ("mp4", "video/mp4"),
+    ("bin", "application/octet-stream"),
+];
+
+/// Guesses the MIME type of the file from its extension, `application/octet-stream` when it is not a known one.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::metadata::mime_type;
+/// assert_eq!(mime_type("notes/plans.TXT"), "text/plain");
+/// assert_eq!(mime_type("backup.tar.gz"), "application/gzip");
+/// assert_eq!(mime_type("no-extension"), "application/octet-stream");
+/// ```
+pub fn mime_type(path: impl AsRef<Path>) -> &'static str {
+    let extension = path.as_ref().extension().and_then(|extension| extension.to_str()).unwrap_or_default().to_ascii_lowercase();
+    TYPES.iter().find(|&&(known, _)| known == extension).map_or("application/octet-stream", |&(_, mime)| mime)
+}
+
+/// The extension of the files of the MIME type, `bin` when it is not a known one
+pub fn extension(mime: &str) -> &'static str {
+    TYPES.iter().find(|&&(_, known)| known == mime).map_or("bin", |&(extension, _)| extension)
+}
+
+/// Tells the MIME type of the data from the magic bytes at its start, `text/plain` for the UTF-8 text
+/// without any control characters other than the whitespace and `application/octet-stream` for anything else.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::metadata::{extension, sniff};
+/// assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), "image/png");
+/// assert_eq!(sniff("just some text,\n\tnothing more\n".as_bytes()), "text/plain");
+/// assert_eq!(sniff(&[0, 1, 2, 3]), "application/octet-stream");
+/// assert_eq!(extension(sniff(b"%PDF-1.7")), "pdf");
+/// ```
+pub fn sniff(data: &[u8]) -> &'static str {
+    let magics: &[(usize, &[u8], &str)] = &[
+        (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+        (0, b"\xff\xd8\xff", "image/jpeg"),
+        (0, b"GIF8", "image/gif"),
+        (8, b"WEBP", "image/webp"),
+        (0, b"BM", "image/bmp"),
+        (0, b"%PDF-", "application/pdf"),
+        (0, b"PK\x03\x04", "application/zip"),
+        (0, b"\x1f\x8b", "application/gzip"),
+        (0, b"\x28\xb5\x2f\xfd", "application/zstd"),
+        (0, b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+        (257, b"ustar", "application/x-tar"),
+        (0, b"OggS", "audio/ogg"),
+        (0, b"ID3", "audio/mpeg"),
+        (4, b"ftyp", "video/mp4"),
+    ];
+    let found = magics.iter().find(|&&(offset, magic, _)| data.get(offset..offset + magic.len()) == Some(magic));
+    if let Some(&(_, _, mime)) = found {
+        return mime;
+    }
+    match std::str::from_utf8(data) {
+        Ok(text) if !text.chars().any(|c| c.is_control() && !c.is_whitespace()) => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Whether the data of the MIME type is text that can be shown in a terminal
+pub fn is_text(mime: &str) -> bool {
+    mime.starts_with("text/") || mime == "application/json" || mime == "application/xml" || mime == "image/svg+xml"
+}
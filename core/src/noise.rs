@@ -0,0 +1,227 @@
+use crate::binary::Layout;
+
+/// Measures how noisy each channel of the carrier is, as the mean absolute difference of its usable bytes
+/// from the average of their horizontal neighbours in the same channel, one value per channel.
+///
+/// The channels that are not usable according to the `layout` have no noise at all.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::binary::Layout;
+/// # use steganographer_core::noise::channel_noise;
+/// // a flat red channel, a noisy green one and a smooth gradient in the blue one
+/// let carrier = (0..32u8).flat_map(|x| vec![200, x % 2 * 9, x * 2]).collect::<Vec<_>>();
+/// let noise = channel_noise(&carrier, Layout::new(3, 1, false).width(8));
+///
+/// assert_eq!(noise, [0.0, 9.0, 0.0]);
+/// ```
+pub fn channel_noise(carrier: &[u8], layout: Layout) -> Vec<f64> {
+    let pixel_size = layout.pixel_size();
+    let sample_size = layout.sample_size() as usize;
+    let channels = layout.channels() as usize;
+    let width = layout.pixels_per_row().unwrap_or(carrier.len() / pixel_size).max(1);
+    let row_size = width * pixel_size;
+
+    let mut sums = vec![(0f64, 0usize); channels];
+    for row in carrier.chunks(row_size).filter(|row| row.len() == row_size) {
+        for i in (pixel_size..row_size - pixel_size).filter(|&i| layout.is_usable(i)) {
+            let prediction = (row[i - pixel_size] as f64 + row[i + pixel_size] as f64) / 2.0;
+            let sum = &mut sums[i / sample_size % channels];
+            sum.0 += (row[i] as f64 - prediction).abs();
+            sum.1 += 1;
+        }
+    }
+    sums.into_iter().map(|(sum, count)| if count == 0 { 0.0 } else { sum / count as f64 }).collect()
+}
+
+/// Picks the channels the payload is least noticeable in, as a bit mask where the lowest bit is the first channel.
+///
+/// Those are the channels at least half as noisy as the noisiest one, and then as many of the quieter ones,
+/// from the noisiest to the quietest, as it takes for the payload to `fit` into them.
+/// Only the first four usable channels are considered, as that is what fits into the
+/// [header](../container/struct.Header.html#structfield.channels).
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::binary::Layout;
+/// # use steganographer_core::noise::pick_channels;
+/// let carrier = (0..32u8).flat_map(|x| vec![200, x % 2 * 9, x % 3 * 4]).collect::<Vec<_>>();
+/// let layout = Layout::new(3, 1, false).width(8);
+///
+/// assert_eq!(pick_channels(&carrier, layout, |_| true), 0b010);
+/// // the quieter channels are added when the payload does not fit, the flat red one last
+/// assert_eq!(pick_channels(&carrier, layout, |mask| mask.count_ones() >= 2), 0b110);
+/// assert_eq!(pick_channels(&carrier, layout, |mask| mask == 0b111), 0b111);
+/// ```
+pub fn pick_channels(carrier: &[u8], layout: Layout, fits: impl Fn(u8) -> bool) -> u8 {
+    let noise = channel_noise(carrier, layout);
+    let mut ranked = (0..noise.len().min(4)).filter(|&c| layout.mask() >> c & 1 == 1).collect::<Vec<_>>();
+    ranked.sort_by(|&a, &b| noise[b].total_cmp(&noise[a]));
+
+    let loudest = ranked.first().map_or(0.0, |&c| noise[c]);
+    let mut mask = 0;
+    for channel in ranked {
+        if mask != 0 && noise[channel] < loudest / 2.0 && fits(mask) {
+            break;
+        }
+        mask |= 1 << channel;
+    }
+    mask
+}
+
+/// Local noise below which a region is too flat for the changes of its least significant bits to go unnoticed
+pub const FLAT_NOISE: f64 = 1.0;
+
+/// Local noise from which a region is noisy enough to hide the changes of its least significant bits
+pub const SAFE_NOISE: f64 = 3.0;
+
+/// Entropy of the values, in bits, below which a region is flat whatever its noise,
+/// as with the few colors of drawings and screenshots
+pub const FLAT_ENTROPY: f64 = 2.0;
+
+/// How safe a region of the carrier is to hide the data in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Suitability {
+    /// Flat, where the changed bits stand out as noise that was not there, and which the steganalysis easily tells
+    Flat,
+    /// Smooth, somewhere in between
+    Marginal,
+    /// Noisy enough for the changed bits to blend into the noise it already has
+    Safe,
+}
+
+/// How noisy one square region of the carrier is.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RegionNoise {
+    /// Column of the left pixels of the region
+    pub x: usize,
+    /// Row of the top pixels of the region
+    pub y: usize,
+    /// Width of the region in pixels, less than the region size at the right edge of the carrier
+    pub width: usize,
+    /// Height of the region in pixels, less than the region size at the bottom edge of the carrier
+    pub height: usize,
+    /// Shannon entropy of the values of its usable bytes, in bits, from 0 when they are all the same up to 8
+    pub entropy: f64,
+    /// Variance of the values of its usable bytes
+    pub variance: f64,
+    /// Mean absolute difference of its usable bytes from the average of their horizontal neighbours,
+    /// as [`channel_noise`](fn.channel_noise.html) measures it for the whole channels
+    pub noise: f64,
+}
+
+impl RegionNoise {
+    /// How safe the region is to hide the data in, by its noise and its entropy.
+    pub fn suitability(&self) -> Suitability {
+        if self.noise < FLAT_NOISE || self.entropy < FLAT_ENTROPY {
+            Suitability::Flat
+        } else if self.noise < SAFE_NOISE {
+            Suitability::Marginal
+        } else {
+            Suitability::Safe
+        }
+    }
+}
+
+/// Measures the entropy, the variance and the noise of the usable bytes of each square region
+/// of `region_size` pixels of the carrier, row by row, to tell the regions that are safe to hide the data in
+/// from the dangerously flat ones.
+///
+/// The layout needs the [width](../binary/struct.Layout.html#method.width) of the carrier, without it the pixels
+/// are taken as a single row.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::binary::Layout;
+/// # use steganographer_core::noise::{region_noise, Suitability};
+/// // a flat left half and a noisy right half of a gray image
+/// let carrier = (0..16 * 16u32)
+///     .map(|i| if i % 16 < 8 { 100 } else { (i.wrapping_mul(0x9e37_79b9) >> 24) as u8 })
+///     .collect::<Vec<_>>();
+/// let regions = region_noise(&carrier, Layout::new(1, 1, false).width(16), 8);
+///
+/// assert_eq!(regions.len(), 4);
+/// assert_eq!((regions[0].entropy, regions[0].variance), (0.0, 0.0));
+/// assert_eq!(regions[0].suitability(), Suitability::Flat);
+/// assert_eq!(regions[1].suitability(), Suitability::Safe);
+/// ```
+pub fn region_noise(carrier: &[u8], layout: Layout, region_size: usize) -> Vec<RegionNoise> {
+    let pixel_size = layout.pixel_size();
+    let width = layout.pixels_per_row().unwrap_or(carrier.len() / pixel_size).max(1);
+    let height = carrier.len() / pixel_size / width;
+    let region_size = region_size.max(1);
+
+    let mut regions = Vec::new();
+    for y in (0..height).step_by(region_size) {
+        for x in (0..width).step_by(region_size) {
+            let (region_width, region_height) = (region_size.min(width - x), region_size.min(height - y));
+            let mut histogram = [0usize; 256];
+            let (mut sum, mut squares, mut residuals, mut predicted) = (0.0, 0.0, 0.0, 0usize);
+            for row in y..y + region_height {
+                for column in x..x + region_width {
+                    let pixel = (row * width + column) * pixel_size;
+                    for i in (0..pixel_size).filter(|&i| layout.is_usable(i)) {
+                        let value = carrier[pixel + i];
+                        histogram[value as usize] += 1;
+                        sum += f64::from(value);
+                        squares += f64::from(value).powi(2);
+                        if column > 0 && column + 1 < width {
+                            let prediction = (f64::from(carrier[pixel + i - pixel_size]) + f64::from(carrier[pixel + i + pixel_size])) / 2.0;
+                            residuals += (f64::from(value) - prediction).abs();
+                            predicted += 1;
+                        }
+                    }
+                }
+            }
+            let count = histogram.iter().sum::<usize>().max(1) as f64;
+            let entropy = histogram.iter()
+                .filter(|&&n| n > 0)
+                .map(|&n| n as f64 / count)
+                .map(|p| -p * p.log2())
+                .sum::<f64>();
+            let mean = sum / count;
+            regions.push(RegionNoise {
+                x,
+                y,
+                width: region_width,
+                height: region_height,
+                entropy,
+                variance: squares / count - mean * mean,
+                noise: if predicted == 0 { 0.0 } else { residuals / predicted as f64 },
+            });
+        }
+    }
+    regions
+}
+
+/// Renders the suitability of each of the regions as RGB pixels of a carrier of the given size, red for the flat ones,
+/// yellow for the marginal ones and green for the safe ones.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::noise::{suitability_map, RegionNoise};
+/// let flat = RegionNoise { x: 0, y: 0, width: 1, height: 1, entropy: 0.0, variance: 0.0, noise: 0.0 };
+/// let safe = RegionNoise { x: 1, noise: 5.0, entropy: 6.0, ..flat };
+/// assert_eq!(suitability_map(&[flat, safe], 2, 1), [255, 0, 0, 0, 255, 0]);
+/// ```
+pub fn suitability_map(regions: &[RegionNoise], width: usize, height: usize) -> Vec<u8> {
+    let mut map = vec![0; width * height * 3];
+    for region in regions {
+        let color = match region.suitability() {
+            Suitability::Flat => [255, 0, 0],
+            Suitability::Marginal => [255, 255, 0],
+            Suitability::Safe => [0, 255, 0],
+        };
+        for row in region.y..(region.y + region.height).min(height) {
+            for column in region.x..(region.x + region.width).min(width) {
+                let pixel = (row * width + column) * 3;
+                map[pixel..pixel + 3].copy_from_slice(&color);
+            }
+        }
+    }
+    map
+}
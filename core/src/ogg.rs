@@ -0,0 +1,286 @@
+use std::convert::TryFrom;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::Error;
+
+/// Key of the comment that holds the hidden data
+const KEY: &[u8] = b"STEGANOGRAPHER=";
+/// Size of the page header before the segment table
+const PAGE_HEADER: usize = 27;
+const CONTINUED: u8 = 0x01;
+const FIRST: u8 = 0x02;
+
+fn malformed(what: &str) -> Error {
+    Error::UnsupportedCarrier(format!("malformed Ogg file: {}", what))
+}
+
+struct Page<'a> {
+    flags: u8,
+    granule: u64,
+    serial: u32,
+    sequence: u32,
+    segments: &'a [u8],
+    data: &'a [u8],
+}
+
+impl Page<'_> {
+    fn write(&self, sequence: u32, to: &mut Vec<u8>) {
+        write_page(self.flags, self.granule, self.serial, sequence, self.segments, self.data, to);
+    }
+}
+
+fn write_page(flags: u8, granule: u64, serial: u32, sequence: u32, segments: &[u8], data: &[u8], to: &mut Vec<u8>) {
+    let start = to.len();
+    to.extend_from_slice(b"OggS\0");
+    to.push(flags);
+    to.extend_from_slice(&granule.to_le_bytes());
+    to.extend_from_slice(&serial.to_le_bytes());
+    to.extend_from_slice(&sequence.to_le_bytes());
+    to.extend_from_slice(&[0; 4]);
+    to.push(segments.len() as u8);
+    to.extend_from_slice(segments);
+    to.extend_from_slice(data);
+    let crc = crc(&to[start..]);
+    LittleEndian::write_u32(&mut to[start + 22..], crc);
+}
+
+/// The CRC-32 of the Ogg pages, which is the unreflected one with the 0x04C11DB7 polynomial
+fn crc(data: &[u8]) -> u32 {
+    data.iter().fold(0u32, |crc, &byte| {
+        (0..8).fold(crc ^ (byte as u32) << 24, |crc, _| match crc & 0x8000_0000 {
+            0 => crc << 1,
+            _ => crc << 1 ^ 0x04C1_1DB7,
+        })
+    })
+}
+
+fn pages(ogg: &[u8]) -> Result<Vec<Page<'_>>, Error> {
+    let mut pages = Vec::new();
+    let mut start = 0;
+    while start < ogg.len() {
+        let header = ogg.get(start..start + PAGE_HEADER).ok_or_else(|| malformed("page header is truncated"))?;
+        if &header[..4] != b"OggS" {
+            return Err(match start {
+                0 => Error::UnsupportedCarrier("not an Ogg file".into()),
+                _ => malformed("lost the page boundary"),
+            });
+        }
+        let count = header[26] as usize;
+        let segments = ogg.get(start + PAGE_HEADER..start + PAGE_HEADER + count)
+            .ok_or_else(|| malformed("segment table is truncated"))?;
+        let data_start = start + PAGE_HEADER + count;
+        let data_len = segments.iter().map(|&s| s as usize).sum::<usize>();
+        let data = ogg.get(data_start..data_start + data_len).ok_or_else(|| malformed("page is truncated"))?;
+        pages.push(Page {
+            flags: header[5],
+            granule: LittleEndian::read_u64(&header[6..]),
+            serial: LittleEndian::read_u32(&header[14..]),
+            sequence: LittleEndian::read_u32(&header[18..]),
+            segments,
+            data,
+        });
+        start = data_start + data_len;
+    }
+    Ok(pages)
+}
+
+/// The header packets of the first logical stream and the number of pages they take.
+///
+/// The comment header is the second one of them both for Vorbis and Opus.
+fn header_packets(pages: &[Page]) -> Result<(Vec<Vec<u8>>, usize), Error> {
+    let serial = pages.first().ok_or_else(|| Error::UnsupportedCarrier("not an Ogg file".into()))?.serial;
+    let mut packets: Vec<Vec<u8>> = vec![Vec::new()];
+    let mut count = None;
+    for (index, page) in pages.iter().enumerate() {
+        if page.serial != serial {
+            return Err(Error::UnsupportedCarrier("multiplexed Ogg streams are not supported".into()));
+        }
+        let mut offset = 0;
+        for (i, &segment) in page.segments.iter().enumerate() {
+            packets.last_mut().unwrap().extend_from_slice(&page.data[offset..offset + segment as usize]);
+            offset += segment as usize;
+            if segment == 255 {
+                continue;
+            }
+            let count = *count.get_or_insert(match &packets[0] {
+                first if first.starts_with(b"\x01vorbis") => 3,
+                first if first.starts_with(b"OpusHead") => 2,
+                _ => return Err(Error::UnsupportedCarrier("only Vorbis and Opus Ogg streams are supported".into())),
+            });
+            if packets.len() == count {
+                if i + 1 != page.segments.len() {
+                    return Err(malformed("the audio starts on the same page as the headers"));
+                }
+                return Ok((packets, index + 1));
+            }
+            packets.push(Vec::new());
+        }
+    }
+    Err(malformed("the headers are truncated"))
+}
+
+/// The comment header split around its comments
+struct Comments<'a> {
+    /// The codec signature and the vendor string
+    prefix: &'a [u8],
+    comments: Vec<&'a [u8]>,
+    /// The framing bit of Vorbis or the binary data of Opus
+    rest: &'a [u8],
+}
+
+fn comments(packet: &[u8]) -> Result<Comments<'_>, Error> {
+    let prefix = if packet.starts_with(b"\x03vorbis") { 7 } else if packet.starts_with(b"OpusTags") { 8 } else {
+        return Err(malformed("the comment header is missing"));
+    };
+    let truncated = || malformed("the comment header is truncated");
+    let read_u32 = |at: usize| packet.get(at..at + 4).map(LittleEndian::read_u32).map(|n| n as usize).ok_or_else(truncated);
+
+    let mut at = prefix + 4 + read_u32(prefix)?;
+    let count = read_u32(at)?;
+    let vendor_end = at;
+    at += 4;
+    let mut comments = Vec::with_capacity(count.min(1024));
+    for _ in 0..count {
+        let len = read_u32(at)?;
+        comments.push(packet.get(at + 4..at + 4 + len).ok_or_else(truncated)?);
+        at += 4 + len;
+    }
+    Ok(Comments { prefix: &packet[..vendor_end], comments, rest: &packet[at..] })
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn to_base64(data: &[u8]) -> Vec<u8> {
+    data.chunks(3)
+        .flat_map(|chunk| {
+            let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+            (0..4).map(move |i| match i <= chunk.len() {
+                true => BASE64[(n >> (18 - 6 * i) & 63) as usize],
+                false => b'=',
+            })
+        })
+        .collect()
+}
+
+fn from_base64(text: &[u8]) -> Result<Vec<u8>, Error> {
+    let digits = text.iter()
+        .take_while(|&&c| c != b'=')
+        .map(|&c| BASE64.iter().position(|&d| d == c).map(|d| d as u32))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| malformed("the hidden data is not valid base64"))?;
+    Ok(digits.chunks(4)
+        .flat_map(|chunk| {
+            let n = chunk.iter().enumerate().fold(0u32, |n, (i, &d)| n | d << (18 - 6 * i));
+            (0..chunk.len().saturating_sub(1)).map(move |i| (n >> (16 - 8 * i)) as u8)
+        })
+        .collect())
+}
+
+fn is_hidden_data(comment: &[u8]) -> bool {
+    comment.len() >= KEY.len() && comment[..KEY.len()].eq_ignore_ascii_case(KEY)
+}
+
+/// Lays the header packets out on pages the way the encoders do, the first one on its own page,
+/// returning the number of pages written
+fn paginate(packets: &[Vec<u8>], serial: u32, to: &mut Vec<u8>) -> u32 {
+    let lacing = |packets: &[Vec<u8>]| packets.iter()
+        .flat_map(|p| std::iter::repeat_n(255, p.len() / 255).chain(Some((p.len() % 255) as u8)))
+        .collect::<Vec<u8>>();
+
+    let mut sequence = 0;
+    let mut flags = FIRST;
+    for group in [&packets[..1], &packets[1..]] {
+        let data = group.concat();
+        let mut offset = 0;
+        for segments in lacing(group).chunks(255) {
+            let size = segments.iter().map(|&s| s as usize).sum::<usize>();
+            // no packet ends on the page when its last segment is a full one
+            let continued = segments.last() == Some(&255);
+            let granule = if continued { u64::MAX } else { 0 };
+            write_page(flags, granule, serial, sequence, segments, &data[offset..offset + size], to);
+            offset += size;
+            sequence += 1;
+            flags = if continued { CONTINUED } else { 0 };
+        }
+    }
+    sequence
+}
+
+/// Hides a slice of bytes in a comment of an Ogg Vorbis or Ogg Opus file, the same kind of comment
+/// that holds the title or the artist of the track, encoded as base64.
+///
+/// The audio packets are not touched at all, but as the comment header grows,
+/// the pages after it are renumbered, just like when any tag of the file is edited.
+/// Any data hidden in the file before is replaced.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when `ogg` is not a valid Ogg file,
+/// has several multiplexed streams or has some other codec in it.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::ogg::{hide_in_ogg, reveal_from_ogg};
+/// # fn page(flags: u8, sequence: u8, packet: &[u8]) -> Vec<u8> {
+/// #     let mut page = b"OggS\0".to_vec();
+/// #     page.push(flags);
+/// #     page.extend_from_slice(&[0; 8]);
+/// #     page.extend_from_slice(&[1, 0, 0, 0, sequence, 0, 0, 0, 0, 0, 0, 0, 1, packet.len() as u8]);
+/// #     page.extend_from_slice(packet);
+/// #     page
+/// # }
+/// // the Opus identification and comment headers and a single audio packet
+/// let mut opus = page(2, 0, b"OpusHead\x01\x02\0\0\x80\xbb\0\0\0\0\0");
+/// opus.extend(page(0, 1, b"OpusTags\x04\0\0\0test\0\0\0\0"));
+/// opus.extend(page(4, 2, b"\xfc\xff\xfe"));
+///
+/// let cloaked = hide_in_ogg(&opus, b"secret").unwrap();
+///
+/// assert_eq!(reveal_from_ogg(&cloaked).unwrap(), b"secret");
+/// assert_eq!(reveal_from_ogg(&hide_in_ogg(&cloaked, b"other").unwrap()).unwrap(), b"other");
+/// assert!(cloaked.ends_with(b"\xfc\xff\xfe"));
+/// ```
+///
+pub fn hide_in_ogg(ogg: &[u8], payload: &[u8]) -> Result<Vec<u8>, Error> {
+    let pages = pages(ogg)?;
+    let (mut packets, header_pages) = header_packets(&pages)?;
+
+    let Comments { prefix, comments, rest } = comments(&packets[1])?;
+    let mut comments = comments.into_iter().filter(|c| !is_hidden_data(c)).map(<[u8]>::to_vec).collect::<Vec<_>>();
+    comments.push([KEY, &to_base64(payload)].concat());
+    let mut packet = prefix.to_vec();
+    packet.extend_from_slice(&u32::try_from(comments.len()).unwrap_or(u32::MAX).to_le_bytes());
+    for comment in &comments {
+        let len = u32::try_from(comment.len()).map_err(|_| Error::UnsupportedCarrier("the payload is too large".into()))?;
+        packet.extend_from_slice(&len.to_le_bytes());
+        packet.extend_from_slice(comment);
+    }
+    packet.extend_from_slice(rest);
+    packets[1] = packet;
+
+    let serial = pages[0].serial;
+    let mut result = Vec::with_capacity(ogg.len() + payload.len() * 4 / 3 + 1024);
+    let sequence = paginate(&packets, serial, &mut result);
+    let shift = sequence.wrapping_sub(header_pages as u32);
+    for page in &pages[header_pages..] {
+        match page.serial == serial {
+            true => page.write(page.sequence.wrapping_add(shift), &mut result),
+            false => page.write(page.sequence, &mut result),
+        }
+    }
+    Ok(result)
+}
+
+/// Reveals a slice of bytes previously hidden by the [`hide_in_ogg`](fn.hide_in_ogg.html) function.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when `ogg` is not a valid Ogg file
+/// or has nothing hidden in it.
+pub fn reveal_from_ogg(ogg: &[u8]) -> Result<Vec<u8>, Error> {
+    let pages = pages(ogg)?;
+    let (packets, _) = header_packets(&pages)?;
+    let comment = comments(&packets[1])?.comments.into_iter().rev().find(|c| is_hidden_data(c))
+        .ok_or_else(|| Error::UnsupportedCarrier("there is no hidden data in the Ogg file".into()))?;
+    from_base64(&comment[KEY.len()..])
+}
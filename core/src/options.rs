@@ -0,0 +1,1377 @@
+use std::ops::Range;
+use std::path::PathBuf;
+
+use zeroize::Zeroizing;
+
+use crate::binary::{Bits, Layout};
+use crate::chaff::Filler;
+use crate::chunks;
+use crate::compress::Compression;
+use crate::container::{self, Header, Traversal};
+use crate::metadata::Metadata;
+use crate::zip::ZipSlot;
+
+/// How the data is embedded into the pixels of the images, each of them is implemented
+/// as an [`EmbeddingStrategy`](strategy/trait.EmbeddingStrategy.html).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Embedding {
+    /// Replacing the least significant bits, see [`container`](container/index.html)
+    /// and [`resync`](resync/index.html)
+    #[default]
+    Lsb,
+    /// Wet paper code that never touches the saturated bytes, see [`wetpaper`](wetpaper/index.html).
+    ///
+    /// The alpha samples, the fully transparent pixels and the masked out ones are wet as well when they are
+    /// to be left alone, so the decoder does not need any of these settings.
+    WetPaper,
+    /// Syndrome-trellis code that changes the bytes in the textured regions of the image,
+    /// see [`stc`](stc/index.html)
+    Stc,
+    /// Replacing the least significant bits of the most textured regions of the image first,
+    /// see [`adaptive`](adaptive/index.html)
+    Adaptive,
+}
+
+/// Filter applied to the rows of the PNG images before they are compressed, see the
+/// [PNG specification](https://www.w3.org/TR/png/#9Filters).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum PngFilter {
+    /// Rows are compressed as they are
+    None,
+    /// Difference from the pixel to the left
+    Sub,
+    /// Difference from the pixel above
+    Up,
+    /// Difference from the average of the pixels to the left and above
+    Average,
+    /// Difference from the Paeth predictor of the pixels to the left, above and to the upper left
+    Paeth,
+    /// The filter that suits each row best
+    #[default]
+    Adaptive,
+}
+
+/// How the PNG images are compressed, the level being resolved against the carrier
+pub(crate) struct PngOutput {
+    pub compression: png::DeflateCompression,
+    pub filter: png::Filter,
+}
+
+/// Format of the image with the data hidden in it, when it differs from the format of the carrier.
+///
+/// Only the images that the data is hidden in the pixels of can be written in another format.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// PNG image
+    Png,
+    /// Uncompressed BMP image, which cannot hold 16-bit samples
+    Bmp,
+    /// Uncompressed TIFF image
+    Tiff,
+    /// Raw PGM or PPM image, which cannot hold an alpha channel, see [`pnm`](pnm/index.html)
+    Pnm,
+    /// QOI image, which can only hold 8-bit RGB or RGBA pixels, see [`qoi`](qoi/index.html)
+    Qoi,
+}
+
+impl OutputFormat {
+    /// The usual extension of the files of the format
+    pub(crate) fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Bmp => "bmp",
+            OutputFormat::Tiff => "tiff",
+            OutputFormat::Pnm => "pnm",
+            OutputFormat::Qoi => "qoi",
+        }
+    }
+}
+
+/// Application of the smartcard that holds the P-256 key the data is [sealed for](struct.EncodeOptions.html#method.card_key).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Applet {
+    /// The key management key in the 9D slot of the PIV application, as on the YubiKeys
+    Piv,
+    /// The decryption key of the OpenPGP card application
+    OpenPgp,
+}
+
+/// Settings of [`encode_into_file`](fn.encode_into_file.html) and the other encoding functions.
+///
+/// Every setting has a sensible default, so only the ones that matter need to be changed.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::EncodeOptions;
+/// # use steganographer_core::binary::Bits;
+/// let options = EncodeOptions::new()
+///     .data("secret.txt")
+///     .replace(true)
+///     .bits(Bits::One)
+///     .interleave(16);
+/// ```
+#[derive(Debug, Clone)]
+pub struct EncodeOptions {
+    pub(crate) data: Option<PathBuf>,
+    pub(crate) card_key: Option<PathBuf>,
+    pub(crate) metadata: bool,
+    pub(crate) codec: Option<Compression>,
+    pub(crate) name: Option<String>,
+    pub(crate) replace: bool,
+    pub(crate) bits: Bits,
+    pub(crate) wide_bits: Option<Bits>,
+    pub(crate) interleave: u16,
+    pub(crate) traversal: Traversal,
+    pub(crate) offset: usize,
+    pub(crate) stride: usize,
+    pub(crate) matrix: u8,
+    pub(crate) resync: bool,
+    pub(crate) zip_slot: ZipSlot,
+    pub(crate) svg_precision: usize,
+    pub(crate) signing_key: Option<PathBuf>,
+    pub(crate) embedding: Embedding,
+    pub(crate) cost_map: Option<PathBuf>,
+    pub(crate) convert: bool,
+    pub(crate) filler: Filler,
+    pub(crate) otp_pad: Option<PathBuf>,
+    pub(crate) password: Option<Zeroizing<String>>,
+    pub(crate) recipients: Vec<PathBuf>,
+    pub(crate) data_signing_key: Option<PathBuf>,
+    pub(crate) hidden_volume: Option<(PathBuf, Zeroizing<String>)>,
+    pub(crate) whitening: Option<Zeroizing<String>>,
+    pub(crate) auto_channels: bool,
+    pub(crate) format: Option<OutputFormat>,
+    pub(crate) compression: Option<u8>,
+    pub(crate) png_filter: PngFilter,
+    pub(crate) skip_alpha: bool,
+    pub(crate) skip_transparent: bool,
+    pub(crate) channels: u8,
+    pub(crate) mask: Option<PathBuf>,
+    pub(crate) deterministic: bool,
+    pub(crate) verify: bool,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        EncodeOptions {
+            data: None,
+            card_key: None,
+            metadata: false,
+            codec: None,
+            name: None,
+            replace: false,
+            bits: Bits::Two,
+            wide_bits: None,
+            interleave: 1,
+            traversal: Traversal::default(),
+            offset: 0,
+            stride: 1,
+            matrix: 1,
+            resync: false,
+            zip_slot: ZipSlot::default(),
+            svg_precision: 3,
+            signing_key: None,
+            embedding: Embedding::default(),
+            cost_map: None,
+            convert: true,
+            filler: Filler::default(),
+            otp_pad: None,
+            password: None,
+            recipients: Vec::new(),
+            data_signing_key: None,
+            hidden_volume: None,
+            whitening: None,
+            auto_channels: false,
+            format: None,
+            compression: None,
+            png_filter: PngFilter::default(),
+            skip_alpha: true,
+            skip_transparent: false,
+            channels: u8::MAX,
+            mask: None,
+            deterministic: false,
+            verify: false,
+        }
+    }
+}
+
+impl EncodeOptions {
+    /// Creates the default options, which read the data from the stdin, never replace an existing output
+    /// and use two bits of each image color byte except for the alpha ones.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the data to be hidden from the given file instead of the stdin.
+    ///
+    /// When it is a directory, all of the files in it are [bundled](bundle/index.html) into the data,
+    /// which can be unpacked with [`DecodeOptions::extract_to`](struct.DecodeOptions.html#method.extract_to).
+    pub fn data(self, data: impl Into<PathBuf>) -> Self {
+        EncodeOptions { data: Some(data.into()), ..self }
+    }
+
+    /// Seals the data for the P-256 key of a smartcard, whose public key is read from the PEM file,
+    /// see [`smartcard::seal_for_card`](smartcard/fn.seal_for_card.html), so that it can only be decoded
+    /// with the [card](struct.DecodeOptions.html#method.card) plugged in.
+    ///
+    /// This happens after the encryption with the [password](#method.password), so that whoever has the image
+    /// and the password still needs the card to decode it.
+    /// Needs the `smartcard` feature.
+    pub fn card_key(self, public_key: impl Into<PathBuf>) -> Self {
+        EncodeOptions { card_key: Some(public_key.into()), ..self }
+    }
+
+    /// Configures whether the [metadata](metadata/struct.Metadata.html) of the data file, its name, permissions,
+    /// modification time and MIME type, is hidden in front of the data, so that it is restored when the data
+    /// is decoded into a directory, see [`DecodeOptions::output`](struct.DecodeOptions.html#method.output).
+    ///
+    /// There is no metadata for the data read from the stdin.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::{decode_from_file, encode_into_file, DecodeOptions, EncodeOptions};
+    /// # use image::{ColorType, ImageEncoder};
+    /// # use image::codecs::png::PngEncoder;
+    /// let dir = std::env::temp_dir().join("steganographer-metadata");
+    /// std::fs::create_dir_all(dir.join("restored")).unwrap();
+    /// let cover = dir.join("cover.png");
+    /// PngEncoder::new(std::fs::File::create(&cover).unwrap()).write_image(&[0; 32 * 32 * 3], 32, 32, ColorType::Rgb8).unwrap();
+    /// std::fs::write(dir.join("plans.txt"), b"the plans").unwrap();
+    ///
+    /// let options = EncodeOptions::new().data(dir.join("plans.txt")).metadata(true).replace(true);
+    /// encode_into_file(cover, dir.join("encoded.png"), &options).unwrap();
+    ///
+    /// let report = decode_from_file(dir.join("encoded.png"), &DecodeOptions::new().output(dir.join("restored")).replace(true)).unwrap();
+    /// assert_eq!(report.metadata.unwrap().mime.as_deref(), Some("text/plain"));
+    /// assert_eq!(std::fs::read(dir.join("restored/plans.txt")).unwrap(), b"the plans");
+    /// ```
+    pub fn metadata(self, metadata: bool) -> Self {
+        EncodeOptions { metadata, ..self }
+    }
+
+    /// Compresses the data, along with its [metadata](#method.metadata), before it is sealed in any layers,
+    /// recording the codec in the header so that it is decompressed when decoding without any options.
+    ///
+    /// Only the [lsb embedding](enum.Embedding.html#variant.Lsb) without [resync](#method.resync) has the header.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::{encode_into_file, with_decoded_image, DecodeOptions, EncodeOptions};
+    /// # use steganographer_core::compress::Compression;
+    /// # use image::{ColorType, ImageEncoder};
+    /// # use image::codecs::png::PngEncoder;
+    /// let dir = std::env::temp_dir().join("steganographer-compress");
+    /// std::fs::create_dir_all(&dir).unwrap();
+    /// let cover = dir.join("cover.png");
+    /// PngEncoder::new(std::fs::File::create(&cover).unwrap()).write_image(&[0; 32 * 32 * 3], 32, 32, ColorType::Rgb8).unwrap();
+    /// let text = "all work and no play makes jack a dull boy\n".repeat(50);
+    /// std::fs::write(dir.join("text.txt"), &text).unwrap();
+    ///
+    /// let options = EncodeOptions::new().data(dir.join("text.txt")).replace(true);
+    /// assert!(encode_into_file(cover.clone(), dir.join("encoded.png"), &options).is_err());
+    ///
+    /// let options = options.compress(Compression::DEFAULT_ZSTD);
+    /// encode_into_file(cover, dir.join("encoded.png"), &options).unwrap();
+    /// let encoded = std::fs::File::open(dir.join("encoded.png")).unwrap();
+    /// let decoded = with_decoded_image(encoded, &DecodeOptions::new(), |data, _| data.to_vec()).unwrap();
+    /// assert_eq!(decoded, text.as_bytes());
+    /// ```
+    pub fn compress(self, compression: Compression) -> Self {
+        EncodeOptions { codec: Some(compression), ..self }
+    }
+
+    /// Hides the data as the entry with the given name of an [archive](toc/struct.Archive.html),
+    /// so that the carrier can hold several named payloads, each sealed in its own layers.
+    ///
+    /// The functions that read the data file put it into the archive themselves, while the ones that
+    /// take the payload, such as [`encode_image`](fn.encode_image.html), take the bytes of the whole archive.
+    /// Only the images with the default least significant bits embedding without the resync segments
+    /// can hold the named entries, and the data is decoded with [`DecodeOptions::name`](struct.DecodeOptions.html#method.name).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::{encode_image, with_decoded_image, DecodeOptions, EncodeOptions};
+    /// # use steganographer_core::toc::Archive;
+    /// # use image::{ColorType, ImageEncoder};
+    /// # use image::codecs::png::PngEncoder;
+    /// let mut png = Vec::new();
+    /// PngEncoder::new(&mut png).write_image(&[0; 32 * 32 * 3], 32, 32, ColorType::Rgb8).unwrap();
+    ///
+    /// let mut archive = Archive::new();
+    /// archive.push("notes", 0, 0, b"the plans").unwrap();
+    /// archive.push("keys", 0, 0, b"hunter2").unwrap();
+    /// let mut encoded = Vec::new();
+    /// encode_image(&png[..], &archive.to_bytes(), &mut encoded, &EncodeOptions::new().name("notes")).unwrap();
+    ///
+    /// let keys = with_decoded_image(&encoded[..], &DecodeOptions::new().name("keys"), |data, _| data.to_vec()).unwrap();
+    /// assert_eq!(keys, b"hunter2");
+    /// // the first entry is the default one
+    /// let notes = with_decoded_image(&encoded[..], &DecodeOptions::new(), |data, _| data.to_vec()).unwrap();
+    /// assert_eq!(notes, b"the plans");
+    /// ```
+    pub fn name(self, name: impl Into<String>) -> Self {
+        EncodeOptions { name: Some(name.into()), ..self }
+    }
+
+    /// Configures whether the output file is replaced if it already exists.
+    pub fn replace(self, replace: bool) -> Self {
+        EncodeOptions { replace, ..self }
+    }
+
+    /// Configures the number of bits of hidden data per image color byte.
+    pub fn bits(self, bits: Bits) -> Self {
+        EncodeOptions { bits, ..self }
+    }
+
+    /// Configures the number of bits of hidden data per 16-bit sample of the images, such as of the 16-bit PNG ones,
+    /// instead of the [`bits`](#method.bits) setting, which is used for them by default.
+    ///
+    /// The bits are the lowest ones of the whole sample, so even all 8 of them change it by less than 0.4%,
+    /// which no display and no eye can tell, while multiplying the capacity of the photos exported in 16 bits.
+    ///
+    /// The data has to be decoded with the same setting, see [`DecodeOptions::wide_bits`](struct.DecodeOptions.html#method.wide_bits).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::{decode_image, encode_image, image_capacity, EncodeOptions};
+    /// # use steganographer_core::binary::Bits;
+    /// let mut png = Vec::new();
+    /// let mut encoder = png::Encoder::new(&mut png, 32, 32);
+    /// encoder.set_color(png::ColorType::Rgb);
+    /// encoder.set_depth(png::BitDepth::Sixteen);
+    /// let samples = (0..32 * 32 * 3).flat_map(|i: u32| ((i * 997) as u16).to_be_bytes()).collect::<Vec<_>>();
+    /// encoder.write_header().unwrap().write_image_data(&samples).unwrap();
+    ///
+    /// let options = EncodeOptions::new().wide_bits(Bits::Eight);
+    /// assert_eq!(image_capacity(&png[..], &options).unwrap(), 32 * 32 * 3 - 9 - 12);
+    ///
+    /// let mut encoded = Vec::new();
+    /// encode_image(&png[..], &[42; 3000], &mut encoded, &options).unwrap();
+    /// assert_eq!(decode_image(&encoded[..], &options.decoding()).unwrap().0, [42; 3000]);
+    ///
+    /// // only the low bytes of the samples changed
+    /// let decoded = image::load_from_memory(&encoded).unwrap().to_rgb16().into_raw();
+    /// assert!(decoded.iter().zip(samples.chunks(2)).all(|(sample, original)| sample >> 8 == original[0] as u16));
+    /// ```
+    pub fn wide_bits(self, wide_bits: Bits) -> Self {
+        EncodeOptions { wide_bits: Some(wide_bits), ..self }
+    }
+
+    /// Configures the interleaving depth, see [`Header`](container/struct.Header.html).
+    pub fn interleave(self, interleave: u16) -> Self {
+        EncodeOptions { interleave, ..self }
+    }
+
+    /// Configures the order in which the pixels are visited, see [`Traversal`](enum.Traversal.html).
+    pub fn traversal(self, traversal: Traversal) -> Self {
+        EncodeOptions { traversal, ..self }
+    }
+
+    /// Visits the pixels after the header in a pseudo-random order derived from the seed, see
+    /// [`Traversal::Scattered`](enum.Traversal.html#variant.Scattered), instead of starting from the top left corner,
+    /// so that the data is spread over the whole image and there is no telling which of its pixels hold it.
+    ///
+    /// The data has to be decoded with the same seed, see [`DecodeOptions::scatter`](struct.DecodeOptions.html#method.scatter).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::{decode_image, encode_image, sample_pixels, sample_png, DecodeOptions, EncodeOptions};
+    /// let pixels = sample_pixels(64, 64);
+    /// let png = sample_png(64, 64);
+    ///
+    /// let options = EncodeOptions::new().scatter("seed");
+    /// let mut encoded = Vec::new();
+    /// encode_image(&png[..], &[42; 300], &mut encoded, &options).unwrap();
+    /// assert_eq!(decode_image(&encoded[..], &options.decoding()).unwrap().0, [42; 300]);
+    /// assert!(decode_image(&encoded[..], &DecodeOptions::new()).is_err());
+    /// assert!(decode_image(&encoded[..], &DecodeOptions::new().scatter("other seed")).is_err());
+    ///
+    /// // the changes are all over the image and not just in its first rows
+    /// let decoded = image::load_from_memory(&encoded).unwrap().to_rgb8().into_raw();
+    /// assert!(decoded[decoded.len() - 64 * 3..] != pixels[pixels.len() - 64 * 3..]);
+    /// ```
+    pub fn scatter(self, seed: &str) -> Self {
+        EncodeOptions { traversal: Traversal::scattered(seed), ..self }
+    }
+
+    /// Leaves the first `offset` bytes of the decoded pixels alone, such as to keep the data away from the top border.
+    ///
+    /// The data has to be decoded with the same offset, see [`DecodeOptions::offset`](struct.DecodeOptions.html#method.offset),
+    /// and it cannot be used along with a [cost map](#method.cost_map) or a [signature](#method.signing_key).
+    pub fn offset(self, offset: usize) -> Self {
+        EncodeOptions { offset, ..self }
+    }
+
+    /// Hides the data only in every `stride`-th byte of the decoded pixels after the [offset](#method.offset),
+    /// of the ones the other options let hold it, so that the payloads hidden with the same stride
+    /// and different offsets do not touch each other.
+    ///
+    /// The data has to be decoded with the same stride, see [`DecodeOptions::stride`](struct.DecodeOptions.html#method.stride),
+    /// and it cannot be used along with a [cost map](#method.cost_map) or a [signature](#method.signing_key).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::{decode_image, encode_image, image_capacity, sample_png, EncodeOptions};
+    /// let png = sample_png(64, 64);
+    ///
+    /// // two independent payloads in the odd and the even bytes
+    /// let (even, odd) = (EncodeOptions::new().stride(2), EncodeOptions::new().stride(2).offset(1));
+    /// assert_eq!(image_capacity(&png[..], &even).unwrap(), 64 * 64 * 3 / 2 / 4 - 19);
+    ///
+    /// let mut first = Vec::new();
+    /// encode_image(&png[..], b"first", &mut first, &even).unwrap();
+    /// let mut both = Vec::new();
+    /// encode_image(&first[..], b"second", &mut both, &odd).unwrap();
+    ///
+    /// assert_eq!(decode_image(&both[..], &even.decoding()).unwrap().0, b"first");
+    /// assert_eq!(decode_image(&both[..], &odd.decoding()).unwrap().0, b"second");
+    /// ```
+    pub fn stride(self, stride: usize) -> Self {
+        EncodeOptions { stride: stride.max(1), ..self }
+    }
+
+    /// Configures how many bits of the payload are hidden in each block of `2^matrix - 1` bits of the image
+    /// by changing at most one of them, see [`matrix`](matrix/index.html), from 1, which is plain replacing,
+    /// up to [`MAX_MATRIX`](matrix/constant.MAX_MATRIX.html), recording it in the [`Header`](container/struct.Header.html).
+    ///
+    /// The higher it is, the fewer bits are changed for the same payload, while the capacity shrinks
+    /// to `matrix / (2^matrix - 1)` of what it was. Only the [`Lsb`](enum.Embedding.html#variant.Lsb) embedding
+    /// without [`resync`](#method.resync) supports it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::{decode_image, encode_image, image_capacity, sample_pixels, sample_png, DecodeOptions, EncodeOptions};
+    /// # use steganographer_core::binary::Bits;
+    /// let pixels = sample_pixels(64, 64);
+    /// let png = sample_png(64, 64);
+    /// let payload = (0..500u32).map(|i| (i * 31 % 251) as u8).collect::<Vec<_>>();
+    ///
+    /// let changed = |options: &EncodeOptions| {
+    ///     let mut encoded = Vec::new();
+    ///     encode_image(&png[..], &payload, &mut encoded, options).unwrap();
+    ///     assert_eq!(decode_image(&encoded[..], &DecodeOptions::new().bits(Bits::One)).unwrap().0, payload);
+    ///     let decoded = image::load_from_memory(&encoded).unwrap().to_rgb8().into_raw();
+    ///     decoded.iter().zip(&pixels).filter(|(a, b)| a != b).count()
+    /// };
+    /// let plain = EncodeOptions::new().bits(Bits::One);
+    /// let hamming = plain.clone().matrix(3);
+    /// assert!(changed(&hamming) < changed(&plain) * 3 / 4);
+    ///
+    /// // 3 bits in each 7 bits of the image after the header, instead of 7
+    /// assert_eq!(image_capacity(&png[..], &plain).unwrap(), 1516);
+    /// assert_eq!(image_capacity(&png[..], &hamming).unwrap(), 642);
+    /// ```
+    pub fn matrix(self, matrix: u8) -> Self {
+        EncodeOptions { matrix: matrix.clamp(1, crate::matrix::MAX_MATRIX), ..self }
+    }
+
+    /// Configures whether the payload is stored only in the noisiest channels of the image that it fits in,
+    /// see [`pick_channels`](noise/fn.pick_channels.html), recording them in the [`Header`](container/struct.Header.html).
+    pub fn auto_channels(self, auto_channels: bool) -> Self {
+        EncodeOptions { auto_channels, ..self }
+    }
+
+    /// Configures whether the data is stored as [resync segments](resync/index.html)
+    /// that survive cropping of the image.
+    pub fn resync(self, resync: bool) -> Self {
+        EncodeOptions { resync, ..self }
+    }
+
+    /// Configures where the data is stored in ZIP archives.
+    pub fn zip_slot(self, zip_slot: ZipSlot) -> Self {
+        EncodeOptions { zip_slot, ..self }
+    }
+
+    /// Configures how many decimal places of the SVG coordinates are kept intact.
+    pub fn svg_precision(self, svg_precision: usize) -> Self {
+        EncodeOptions { svg_precision, ..self }
+    }
+
+    /// Signs the resulting image with the Ed25519 secret key from the given [key file](keys/index.html),
+    /// storing the signature in the last pixels, see [`signature`](signature/index.html).
+    pub fn signing_key(self, signing_key: impl Into<PathBuf>) -> Self {
+        EncodeOptions { signing_key: Some(signing_key.into()), ..self }
+    }
+
+    /// Configures how the data is embedded into the pixels of the images.
+    pub fn embedding(self, embedding: Embedding) -> Self {
+        EncodeOptions { embedding, ..self }
+    }
+
+    /// Uses the grayscale image of the same dimensions as the carrier as the costs of changing its pixels,
+    /// see [`map_costs`](stc/fn.map_costs.html), instead of the default cost model of the
+    /// [wet paper](enum.Embedding.html#variant.WetPaper) and [STC](enum.Embedding.html#variant.Stc) embeddings.
+    ///
+    /// For the wet paper embedding, the white pixels are the wet ones.
+    pub fn cost_map(self, cost_map: impl Into<PathBuf>) -> Self {
+        EncodeOptions { cost_map: Some(cost_map.into()), ..self }
+    }
+
+    /// Configures whether the PNG images that cannot hold the data in their original pixel format
+    /// are [converted](container/struct.Conversion.html) to the nearest one that can,
+    /// instead of failing with [`UnsupportedCarrier`](enum.Error.html#variant.UnsupportedCarrier).
+    pub fn convert(self, convert: bool) -> Self {
+        EncodeOptions { convert, ..self }
+    }
+
+    /// Configures what happens to the capacity of the image that is left after the payload
+    /// with the [LSB](enum.Embedding.html#variant.Lsb) embedding, see [`Filler`](enum.Filler.html).
+    pub fn filler(self, filler: Filler) -> Self {
+        EncodeOptions { filler, ..self }
+    }
+
+    /// Seals the data with the first unused bytes of the given [one-time pad](otp/index.html) file,
+    /// recording them as used in a file next to it.
+    ///
+    /// Every function that reads the data uses up a part of the pad, including
+    /// [`survey_image`](fn.survey_image.html) and [`roundtrip_image`](fn.roundtrip_image.html).
+    pub fn otp_pad(self, otp_pad: impl Into<PathBuf>) -> Self {
+        EncodeOptions { otp_pad: Some(otp_pad.into()), ..self }
+    }
+
+    /// Encrypts the data with the key derived from the password, see [`crypto`](crypto/index.html),
+    /// before it is sealed with the [one-time pad](#method.otp_pad), if there is one.
+    ///
+    /// The data has to be decoded with the same password, see
+    /// [`DecodeOptions::password`](struct.DecodeOptions.html#method.password).
+    pub fn password(self, password: impl Into<String>) -> Self {
+        EncodeOptions { password: Some(Zeroizing::new(password.into())), ..self }
+    }
+
+    /// Adds the X25519 public key file, as generated by [`keys::generate`](keys/fn.generate.html),
+    /// to the recipients the data is encrypted to, see [`crypto::seal_for`](crypto/fn.seal_for.html).
+    /// Any one of the recipients can decrypt it with their secret key and nobody else can.
+    ///
+    /// This happens after the encryption with the [password](#method.password), if there is one,
+    /// and the data has to be decoded with the [identity](struct.DecodeOptions.html#method.identity) of a recipient.
+    pub fn recipient(mut self, public_key: impl Into<PathBuf>) -> Self {
+        self.recipients.push(public_key.into());
+        self
+    }
+
+    /// Signs the data itself with the Ed25519 secret key file, see [`signature::sign_data`](signature/fn.sign_data.html),
+    /// before it is encrypted, so that the decoding side can tell who it came from.
+    ///
+    /// Unlike the [`signing_key`](#method.signing_key) this does not depend on the carrier,
+    /// and the data has to be decoded with [`DecodeOptions::signed_data`](struct.DecodeOptions.html#method.signed_data).
+    pub fn data_signing_key(self, signing_key: impl Into<PathBuf>) -> Self {
+        EncodeOptions { data_signing_key: Some(signing_key.into()), ..self }
+    }
+
+    /// Hides the second file in the capacity that is left after the data, which serves as a decoy,
+    /// sealed with its own password, see [`crypto::seal_volume`](crypto/fn.seal_volume.html).
+    ///
+    /// The whole rest of the capacity is filled with the sealed volume, and when the decoy is encrypted with a
+    /// [password](#method.password) and the [filler](#method.filler) is the random one, the rest of the capacity
+    /// after it is filled with random bytes even without a volume, so decoding the decoy with its password tells
+    /// nothing about whether there is a hidden volume after it.
+    /// The volume is decoded with [`DecodeOptions::hidden_volume`](struct.DecodeOptions.html#method.hidden_volume)
+    /// and its password, and none of the other layers apply to it.
+    ///
+    /// Only the images with the default least significant bits embedding without the resync segments
+    /// can hold a hidden volume.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::{decode_image, encode_image, DecodeOptions, EncodeOptions, Filler};
+    /// # use image::{ColorType, ImageEncoder};
+    /// # use image::codecs::png::PngEncoder;
+    /// let mut png = Vec::new();
+    /// PngEncoder::new(&mut png).write_image(&[0; 32 * 32 * 3], 32, 32, ColorType::Rgb8).unwrap();
+    /// let real = std::env::temp_dir().join("steganographer-hidden-volume.txt");
+    /// std::fs::write(&real, b"the real plans").unwrap();
+    ///
+    /// let options = EncodeOptions::new().hidden_volume(&real, "second password");
+    /// let mut encoded = Vec::new();
+    /// encode_image(&png[..], b"the decoy", &mut encoded, &options).unwrap();
+    ///
+    /// assert_eq!(decode_image(&encoded[..], &DecodeOptions::new()).unwrap().0, b"the decoy");
+    /// let hidden = DecodeOptions::new().hidden_volume(true).password("second password");
+    /// assert_eq!(decode_image(&encoded[..], &hidden).unwrap().0, b"the real plans");
+    ///
+    /// // past the encrypted decoy the low bits of the black image are just as random with a volume as with the random filler
+    /// let changed = |options: EncodeOptions| {
+    ///     let mut encoded = Vec::new();
+    ///     encode_image(&png[..], b"the decoy", &mut encoded, &options.password("first password")).unwrap();
+    ///     let tail = image::load_from_memory(&encoded).unwrap().to_rgb8().into_raw().split_off(32 * 16 * 3);
+    ///     tail.iter().filter(|&&byte| byte != 0).count() as f64 / tail.len() as f64
+    /// };
+    /// assert!((0.7..0.8).contains(&changed(options)));
+    /// assert!((0.7..0.8).contains(&changed(EncodeOptions::new().filler(Filler::Random))));
+    /// ```
+    ///
+    pub fn hidden_volume(self, data: impl Into<PathBuf>, password: impl Into<String>) -> Self {
+        EncodeOptions { hidden_volume: Some((data.into(), Zeroizing::new(password.into()))), ..self }
+    }
+
+    /// XORs all of the hidden bytes, the header and the length included, with the keystream derived from the seed,
+    /// see [`whitening`](whitening/index.html), so that the bits they leave in the image look uniformly random
+    /// instead of standing out with the zeroes of a small length.
+    ///
+    /// Whoever extracts the least significant bits without the seed learns nothing from the length in front
+    /// of the data. Together with the [random filler](enum.Filler.html#variant.Random) and an
+    /// [encrypted](#method.password) payload the whole capacity looks the same whatever the size of the data,
+    /// or whether there is any data at all.
+    ///
+    /// Only the default least significant bits embedding without the resync segments can be whitened,
+    /// and the data has to be decoded with the same seed, see [`DecodeOptions::whiten`](struct.DecodeOptions.html#method.whiten).
+    pub fn whiten(self, seed: impl Into<String>) -> Self {
+        EncodeOptions { whitening: Some(Zeroizing::new(seed.into())), ..self }
+    }
+
+    /// Writes the resulting image in the given format instead of the format of the carrier,
+    /// the data being hidden in the decoded pixels either way.
+    pub fn format(self, format: OutputFormat) -> Self {
+        EncodeOptions { format: Some(format), ..self }
+    }
+
+    /// Configures the zlib compression level of the resulting PNG images, from 0 for no compression to 9 for the best one.
+    ///
+    /// By default the level of the original PNG image is matched as closely as its zlib header tells,
+    /// see [`compression_level`](chunks/fn.compression_level.html), and the other images use the level 6.
+    pub fn compression(self, level: u8) -> Self {
+        EncodeOptions { compression: Some(level.min(9)), ..self }
+    }
+
+    /// Configures the filter applied to the rows of the resulting PNG images before they are compressed.
+    pub fn png_filter(self, png_filter: PngFilter) -> Self {
+        EncodeOptions { png_filter, ..self }
+    }
+
+    /// Configures whether the alpha channel of the images is left alone, so that their transparency never changes,
+    /// which is the default. The alpha samples are usually exactly 0 or 255, so changing them is easy to notice
+    /// and breaks the images with binary transparency.
+    ///
+    /// The data has to be decoded with the same setting, see [`DecodeOptions::skip_alpha`](struct.DecodeOptions.html#method.skip_alpha),
+    /// unless it is [embedded](#method.embedding) with the wet paper code, which marks the alpha samples wet instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::{decode_image, encode_image, image_capacity, EncodeOptions};
+    /// # use image::{ColorType, ImageEncoder};
+    /// # use image::codecs::png::PngEncoder;
+    /// let pixels = (0..16 * 16).flat_map(|i| vec![i as u8, if i % 2 == 0 { 0 } else { 255 }]).collect::<Vec<_>>();
+    /// let mut png = Vec::new();
+    /// PngEncoder::new(&mut png).write_image(&pixels, 16, 16, ColorType::La8).unwrap();
+    ///
+    /// let options = EncodeOptions::new();
+    /// // only the gray samples hold the data, two bits of each, less the header, the length and the CRC32
+    /// assert_eq!(image_capacity(&png[..], &options).unwrap(), 16 * 16 / 4 - 19);
+    ///
+    /// let mut encoded = Vec::new();
+    /// encode_image(&png[..], b"gray", &mut encoded, &options).unwrap();
+    /// assert_eq!(decode_image(&encoded[..], &options.decoding()).unwrap().0, b"gray");
+    ///
+    /// let decoded = image::load_from_memory(&encoded).unwrap().to_luma_alpha8();
+    /// assert!(decoded.pixels().zip(pixels.chunks(2)).all(|(pixel, original)| pixel[1] == original[1]));
+    /// ```
+    ///
+    pub fn skip_alpha(self, skip_alpha: bool) -> Self {
+        EncodeOptions { skip_alpha, ..self }
+    }
+
+    /// Configures whether the fully transparent pixels of the images are left out of the carrier, as the encoders
+    /// often make them black, so that any change to them stands out. The alpha channel is then always left alone,
+    /// whatever the [`skip_alpha`](#method.skip_alpha) setting, so that the same pixels are left out when decoding.
+    ///
+    /// The data has to be decoded with the same setting, see
+    /// [`DecodeOptions::skip_transparent`](struct.DecodeOptions.html#method.skip_transparent),
+    /// and no [cost map](#method.cost_map) can be used, as it does not match the pixels that are left.
+    /// The [wet paper](enum.Embedding.html#variant.WetPaper) embedding marks the transparent pixels wet instead,
+    /// so then neither is the case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::{decode_image, encode_image, EncodeOptions};
+    /// # use image::{ColorType, ImageEncoder};
+    /// # use image::codecs::png::PngEncoder;
+    /// // the left half is transparent black
+    /// let pixels = (0..32 * 32).flat_map(|i| if i % 32 < 16 { vec![0; 4] } else { vec![i as u8, 90, 180, 255] }).collect::<Vec<_>>();
+    /// let mut png = Vec::new();
+    /// PngEncoder::new(&mut png).write_image(&pixels, 32, 32, ColorType::Rgba8).unwrap();
+    ///
+    /// let options = EncodeOptions::new().skip_transparent(true);
+    /// let mut encoded = Vec::new();
+    /// encode_image(&png[..], &[7; 100], &mut encoded, &options).unwrap();
+    /// assert_eq!(decode_image(&encoded[..], &options.decoding()).unwrap().0, [7; 100]);
+    ///
+    /// let decoded = image::load_from_memory(&encoded).unwrap().to_rgba8().into_raw();
+    /// assert!(decoded.chunks(4).zip(pixels.chunks(4)).all(|(pixel, original)| original[3] != 0 || pixel == [0; 4]));
+    /// ```
+    ///
+    pub fn skip_transparent(self, skip_transparent: bool) -> Self {
+        EncodeOptions { skip_transparent, ..self }
+    }
+
+    /// Restricts the channels of the images that hold the data to the ones set in the bit mask,
+    /// where the lowest bit is the first channel, such as `0b100` for only the blue one of the RGB images.
+    /// The alpha channel is still left alone unless [`skip_alpha`](#method.skip_alpha) is off.
+    ///
+    /// The data has to be decoded with the same setting, see [`DecodeOptions::channels`](struct.DecodeOptions.html#method.channels).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::{decode_image, encode_image, sample_pixels, sample_png, EncodeOptions};
+    /// let pixels = sample_pixels(32, 32);
+    /// let png = sample_png(32, 32);
+    ///
+    /// let options = EncodeOptions::new().channels(0b100);
+    /// let mut encoded = Vec::new();
+    /// encode_image(&png[..], &[42; 200], &mut encoded, &options).unwrap();
+    /// assert_eq!(decode_image(&encoded[..], &options.decoding()).unwrap().0, [42; 200]);
+    ///
+    /// // the red and green planes are untouched
+    /// let decoded = image::load_from_memory(&encoded).unwrap().to_rgb8().into_raw();
+    /// assert!(decoded.chunks(3).zip(pixels.chunks(3)).all(|(pixel, original)| pixel[..2] == original[..2]));
+    /// ```
+    ///
+    pub fn channels(self, channels: u8) -> Self {
+        EncodeOptions { channels, ..self }
+    }
+
+    /// Uses the grayscale image of the same dimensions as the carrier as the mask of the pixels that may hold
+    /// the data, which are the ones that are not black in it, such as to keep the flat sky or background
+    /// regions, where the changes would be visible, out of the carrier.
+    ///
+    /// The data has to be decoded with the same mask, see [`DecodeOptions::mask`](struct.DecodeOptions.html#method.mask),
+    /// and no [cost map](#method.cost_map) can be used, as it does not match the pixels that are left.
+    /// The [wet paper](enum.Embedding.html#variant.WetPaper) embedding marks the masked out pixels wet instead,
+    /// so then the decoder needs no mask, and the cost map adds its own wet pixels to them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::{decode_image, encode_image, sample_pixels, sample_png, DecodeOptions, EncodeOptions, Embedding};
+    /// let pixels = sample_pixels(32, 32);
+    /// let png = sample_png(32, 32);
+    ///
+    /// // only the bottom half may be altered
+    /// let mask = std::env::temp_dir().join("steganographer-mask-example.png");
+    /// image::GrayImage::from_fn(32, 32, |_, y| image::Luma([if y < 16 { 0 } else { 255 }])).save(&mask).unwrap();
+    ///
+    /// let options = EncodeOptions::new().mask(&mask);
+    /// let mut encoded = Vec::new();
+    /// encode_image(&png[..], &[42; 100], &mut encoded, &options).unwrap();
+    /// assert_eq!(decode_image(&encoded[..], &options.decoding()).unwrap().0, [42; 100]);
+    ///
+    /// let decoded = image::load_from_memory(&encoded).unwrap().to_rgb8().into_raw();
+    /// assert_eq!(decoded[..16 * 32 * 3], pixels[..16 * 32 * 3]);
+    ///
+    /// // the wet paper code leaves the top half alone just as well, but it decodes without the mask
+    /// let options = options.embedding(Embedding::WetPaper);
+    /// let mut encoded = Vec::new();
+    /// encode_image(&png[..], &[42; 40], &mut encoded, &options).unwrap();
+    /// let decoding = DecodeOptions::new().embedding(Embedding::WetPaper);
+    /// assert_eq!(decode_image(&encoded[..], &decoding).unwrap().0, [42; 40]);
+    ///
+    /// let decoded = image::load_from_memory(&encoded).unwrap().to_rgb8().into_raw();
+    /// assert_eq!(decoded[..16 * 32 * 3], pixels[..16 * 32 * 3]);
+    /// ```
+    ///
+    pub fn mask(self, mask: impl Into<PathBuf>) -> Self {
+        EncodeOptions { mask: Some(mask.into()), ..self }
+    }
+
+    /// Configures whether the same carrier, data and options always give the very same bytes of the resulting image,
+    /// so that the outputs of automated pipelines can be hashed and compared.
+    ///
+    /// Everything that would otherwise come from the randomness of the operating system, which is the
+    /// [filler](#method.filler) of the capacity and the salts, the nonces and the keys of the encryption layers,
+    /// comes from a ChaCha20 generator seeded with the SHA-256 of what is being randomized instead,
+    /// along with the password or the recipients that protect it. The compression level of the PNG images is pinned to 6
+    /// instead of being guessed from the original, unless it is [configured](#method.compression).
+    ///
+    /// The same data encrypted with the same password then always gives the same bytes, which tells
+    /// whoever has two such images that they hold the same data. The [one-time pad](#method.otp_pad) still moves
+    /// on to its next unused bytes on every run, as reusing them would break it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::{encode_image, sample_png, EncodeOptions, Filler};
+    /// let png = sample_png(16, 16);
+    ///
+    /// let options = EncodeOptions::new().filler(Filler::Random).password("hunter2").deterministic(true);
+    /// let (mut first, mut second) = (Vec::new(), Vec::new());
+    /// encode_image(&png[..], b"plans", &mut first, &options).unwrap();
+    /// encode_image(&png[..], b"plans", &mut second, &options).unwrap();
+    /// assert_eq!(first, second);
+    ///
+    /// let mut other = Vec::new();
+    /// encode_image(&png[..], b"other", &mut other, &options).unwrap();
+    /// assert_ne!(first, other);
+    /// ```
+    pub fn deterministic(self, deterministic: bool) -> Self {
+        EncodeOptions { deterministic, ..self }
+    }
+
+    /// Configures whether the image written by [`encode_into_image`](fn.encode_into_image.html) is read back
+    /// and decoded right after it is written, failing with
+    /// [`IntegrityFailure`](enum.Error.html#variant.IntegrityFailure) unless the very same payload comes out of it.
+    ///
+    /// This catches the data lost on the way to the file, such as when the image is converted to a format
+    /// that cannot keep it. The file is left in place when the check fails, so that it can be looked at.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::{encode_into_image, EncodeOptions};
+    /// let dir = std::env::temp_dir();
+    /// let (cover, data, output) = (dir.join("steganographer-verify-cover.png"), dir.join("steganographer-verify.txt"),
+    ///                              dir.join("steganographer-verify-output.png"));
+    /// image::RgbImage::from_fn(32, 32, |x, y| image::Rgb([x as u8 * 8, y as u8 * 8, 128])).save(&cover).unwrap();
+    /// std::fs::write(&data, "checked on the way out").unwrap();
+    ///
+    /// let options = EncodeOptions::new().data(&data).replace(true).verify(true);
+    /// encode_into_image(cover, output, &options).unwrap();
+    /// ```
+    pub fn verify(self, verify: bool) -> Self {
+        EncodeOptions { verify, ..self }
+    }
+
+    /// The layout of the pixels with the channels these options use.
+    ///
+    /// The wet paper embedding keeps the alpha channel, as it marks the alpha samples wet instead of skipping them.
+    pub(crate) fn carrier_layout(&self, layout: Layout) -> Layout {
+        let layout = layout.only_channels(self.channels);
+        if self.skip_alpha && self.embedding != Embedding::WetPaper { layout.skip_alpha() } else { layout }
+    }
+
+    /// Indices of the bytes of the pixels in the given carrier layout that are in the lane of the
+    /// offset and the stride, or `None` when all of them are
+    pub(crate) fn lane(&self, len: usize, layout: Layout) -> Option<Vec<usize>> {
+        lane(len, layout, self.offset, self.stride)
+    }
+
+    /// These options for the bytes of the lane gathered one after another, with the bits of the given carrier layout
+    pub(crate) fn in_lane(&self, layout: Layout) -> Self {
+        EncodeOptions {
+            offset: 0,
+            stride: 1,
+            bits: self.carrier_bits(layout),
+            wide_bits: None,
+            channels: u8::MAX,
+            skip_transparent: false,
+            mask: None,
+            ..self.clone()
+        }
+    }
+
+    /// Whether the transparent and the masked out pixels are left out of the carrier, rather than marked wet
+    pub(crate) fn leaves_out_pixels(&self) -> bool {
+        self.embedding != Embedding::WetPaper
+    }
+
+    /// The number of bits of hidden data per sample of the given layout
+    pub(crate) fn carrier_bits(&self, layout: Layout) -> Bits {
+        carrier_bits(self.bits, self.wide_bits, layout)
+    }
+
+    /// The [header flags](container/struct.Header.html#structfield.flags) of the layers these options seal the payload in
+    pub(crate) fn layer_flags(&self) -> u8 {
+        let layers = [
+            (self.password.is_some(), container::FLAG_PASSWORD),
+            (!self.recipients.is_empty(), container::FLAG_RECIPIENTS),
+            (self.card_key.is_some(), container::FLAG_CARD),
+            (self.otp_pad.is_some(), container::FLAG_ONE_TIME_PAD),
+            (self.data_signing_key.is_some(), container::FLAG_SIGNED_DATA),
+        ];
+        let codec = self.codec.map_or(0, Compression::flags);
+        layers.iter().filter(|&&(sealed, _)| sealed).fold(codec, |flags, &(_, flag)| flags | flag)
+    }
+
+    /// The [header flags](container/struct.Header.html#structfield.flags) of the payload, which are the ones of its layers
+    /// unless it is an archive of the named entries that are sealed on their own
+    pub(crate) fn header_flags(&self) -> u8 {
+        match self.name {
+            Some(_) => container::FLAG_ENTRIES,
+            None => self.layer_flags(),
+        }
+    }
+
+    /// How the PNG images are written, with the compression level of the original image when it is a PNG one
+    /// and the output is not [deterministic](#method.deterministic)
+    pub(crate) fn png_output(&self, original: &[u8]) -> PngOutput {
+        let matched = || Some(original).filter(|_| !self.deterministic).and_then(chunks::compression_level);
+        let level = self.compression.or_else(matched).unwrap_or(6);
+        PngOutput {
+            compression: match level {
+                0 => png::DeflateCompression::NoCompression,
+                level => png::DeflateCompression::Level(level),
+            },
+            filter: match self.png_filter {
+                PngFilter::None => png::Filter::NoFilter,
+                PngFilter::Sub => png::Filter::Sub,
+                PngFilter::Up => png::Filter::Up,
+                PngFilter::Average => png::Filter::Avg,
+                PngFilter::Paeth => png::Filter::Paeth,
+                PngFilter::Adaptive => png::Filter::Adaptive,
+            },
+        }
+    }
+
+    /// Options that decode the data encoded with these ones
+    pub fn decoding(&self) -> DecodeOptions {
+        DecodeOptions {
+            bits: self.bits,
+            wide_bits: self.wide_bits,
+            resync: self.resync,
+            signed: self.signing_key.is_some(),
+            embedding: self.embedding,
+            skip_alpha: self.skip_alpha,
+            skip_transparent: self.skip_transparent,
+            channels: self.channels,
+            mask: self.mask.clone(),
+            password: self.password.clone(),
+            signed_data: self.data_signing_key.is_some(),
+            whitening: self.whitening.clone(),
+            offset: self.offset,
+            stride: self.stride,
+            name: self.name.clone(),
+            scatter: match self.traversal {
+                Traversal::Scattered(key) => Some(key),
+                _ => None,
+            },
+            ..DecodeOptions::default()
+        }
+    }
+}
+
+/// Settings of [`decode_from_file`](fn.decode_from_file.html) and the other decoding functions.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::DecodeOptions;
+/// let options = DecodeOptions::new()
+///     .output("extracted.txt")
+///     .resync(true);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DecodeOptions {
+    pub(crate) output: Option<PathBuf>,
+    pub(crate) card: Option<Applet>,
+    pub(crate) card_pin: Option<Zeroizing<String>>,
+    pub(crate) extract_to: Option<PathBuf>,
+    pub(crate) auto_output: bool,
+    pub(crate) binary_to_terminal: bool,
+    pub(crate) name: Option<String>,
+    pub(crate) replace: bool,
+    pub(crate) bits: Bits,
+    pub(crate) wide_bits: Option<Bits>,
+    pub(crate) detect_bits: bool,
+    pub(crate) resync: bool,
+    pub(crate) signed: bool,
+    pub(crate) embedding: Embedding,
+    pub(crate) otp_pad: Option<PathBuf>,
+    pub(crate) password: Option<Zeroizing<String>>,
+    pub(crate) identity: Option<PathBuf>,
+    pub(crate) signed_data: bool,
+    pub(crate) signer: Option<PathBuf>,
+    pub(crate) hidden_volume: bool,
+    pub(crate) whitening: Option<Zeroizing<String>>,
+    pub(crate) scatter: Option<u64>,
+    pub(crate) offset: usize,
+    pub(crate) stride: usize,
+    pub(crate) depth: usize,
+    pub(crate) skip_alpha: bool,
+    pub(crate) skip_transparent: bool,
+    pub(crate) channels: u8,
+    pub(crate) mask: Option<PathBuf>,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        DecodeOptions {
+            output: None,
+            card: None,
+            card_pin: None,
+            extract_to: None,
+            auto_output: false,
+            binary_to_terminal: false,
+            name: None,
+            replace: false,
+            bits: Bits::Two,
+            wide_bits: None,
+            detect_bits: false,
+            resync: false,
+            signed: false,
+            embedding: Embedding::default(),
+            otp_pad: None,
+            password: None,
+            identity: None,
+            signed_data: false,
+            signer: None,
+            hidden_volume: false,
+            whitening: None,
+            scatter: None,
+            offset: 0,
+            stride: 1,
+            depth: 1,
+            skip_alpha: true,
+            skip_transparent: false,
+            channels: u8::MAX,
+            mask: None,
+        }
+    }
+}
+
+impl DecodeOptions {
+    /// Creates the default options, which write the data to the stdout and use two bits of each image color byte.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes the extracted data to the given file instead of the stdout.
+    ///
+    /// When the data was hidden with its [metadata](struct.EncodeOptions.html#method.metadata), the file gets
+    /// the permissions and the modification time of the original one, and when the output is a directory,
+    /// the file is written into it under its original name.
+    pub fn output(self, output: impl Into<PathBuf>) -> Self {
+        DecodeOptions { output: Some(output.into()), ..self }
+    }
+
+    /// Decrypts the data [sealed for a smartcard](struct.EncodeOptions.html#method.card_key) with the key
+    /// of the given applet of the card in the first PC/SC reader that has one.
+    /// Needs the `smartcard` feature.
+    pub fn card(self, applet: Applet) -> Self {
+        DecodeOptions { card: Some(applet), ..self }
+    }
+
+    /// Unlocks the key of the [card](#method.card) with the PIN, for the cards that ask for one.
+    pub fn card_pin(self, pin: impl Into<String>) -> Self {
+        DecodeOptions { card_pin: Some(Zeroizing::new(pin.into())), ..self }
+    }
+
+    /// Unpacks the extracted data into the given directory instead of writing it out, when it is
+    /// a [bundle](bundle/index.html) of the directory that was given as the [data](struct.EncodeOptions.html#method.data)
+    /// when encoding. The paths of the files inside of it are preserved.
+    pub fn extract_to(self, dir: impl Into<PathBuf>) -> Self {
+        DecodeOptions { extract_to: Some(dir.into()), ..self }
+    }
+
+    /// Writes the extracted data to a file in the [output](#method.output) directory, or in the current one
+    /// when there is none, instead of the stdout. The file is named after the original one when the data was hidden
+    /// with its [metadata](struct.EncodeOptions.html#method.metadata), otherwise it is `extracted`
+    /// with the extension of the [sniffed](metadata/fn.sniff.html) type of the data.
+    pub fn auto_output(self, auto_output: bool) -> Self {
+        DecodeOptions { auto_output, ..self }
+    }
+
+    /// Configures whether the data is written to the stdout when it is a terminal even if the data
+    /// is [not text](metadata/fn.is_text.html), which otherwise fails with
+    /// [`BinaryToTerminal`](enum.Error.html#variant.BinaryToTerminal).
+    pub fn binary_to_terminal(self, binary_to_terminal: bool) -> Self {
+        DecodeOptions { binary_to_terminal, ..self }
+    }
+
+    /// Extracts the entry with the given name when the carrier holds [several named ones](struct.EncodeOptions.html#method.name),
+    /// instead of the first one.
+    pub fn name(self, name: impl Into<String>) -> Self {
+        DecodeOptions { name: Some(name.into()), ..self }
+    }
+
+    /// Configures whether the output file is replaced if it already exists.
+    pub fn replace(self, replace: bool) -> Self {
+        DecodeOptions { replace, ..self }
+    }
+
+    /// Configures the number of bits of hidden data per image color byte.
+    pub fn bits(self, bits: Bits) -> Self {
+        DecodeOptions { bits, ..self }
+    }
+
+    /// Configures the number of bits of hidden data per 16-bit sample of the images,
+    /// see [`EncodeOptions::wide_bits`](struct.EncodeOptions.html#method.wide_bits).
+    pub fn wide_bits(self, wide_bits: Bits) -> Self {
+        DecodeOptions { wide_bits: Some(wide_bits), ..self }
+    }
+
+    /// Configures whether the bits the data was encoded with are detected when the configured ones do not work,
+    /// trying every other setting until one of them reveals the data with an intact header and CRC32.
+    ///
+    /// Only the data [embedded](#method.embedding) into the least significant bits, other than a
+    /// [hidden volume](#method.hidden_volume), can be detected this way, the rest needs the right bits configured.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::{decode_image, encode_image, sample_png, DecodeOptions, EncodeOptions};
+    /// # use steganographer_core::binary::Bits;
+    /// let png = sample_png(64, 64);
+    ///
+    /// let mut encoded = Vec::new();
+    /// encode_image(&png[..], b"dense", &mut encoded, &EncodeOptions::new().bits(Bits::Five)).unwrap();
+    ///
+    /// assert!(decode_image(&encoded[..], &DecodeOptions::new()).is_err());
+    /// assert_eq!(decode_image(&encoded[..], &DecodeOptions::new().detect_bits(true)).unwrap().0, b"dense");
+    /// ```
+    pub fn detect_bits(self, detect_bits: bool) -> Self {
+        DecodeOptions { detect_bits, ..self }
+    }
+
+    /// Configures whether the data is looked for as [resync segments](resync/index.html).
+    pub fn resync(self, resync: bool) -> Self {
+        DecodeOptions { resync, ..self }
+    }
+
+    /// Configures whether the image was signed when encoding, so that the signature region is skipped.
+    pub fn signed(self, signed: bool) -> Self {
+        DecodeOptions { signed, ..self }
+    }
+
+    /// Configures how the data was embedded into the pixels of the images.
+    pub fn embedding(self, embedding: Embedding) -> Self {
+        DecodeOptions { embedding, ..self }
+    }
+
+    /// Opens the data sealed with the given [one-time pad](otp/index.html) file before writing it out.
+    pub fn otp_pad(self, otp_pad: impl Into<PathBuf>) -> Self {
+        DecodeOptions { otp_pad: Some(otp_pad.into()), ..self }
+    }
+
+    /// Decrypts the data encrypted with the given password before writing it out,
+    /// see [`EncodeOptions::password`](struct.EncodeOptions.html#method.password).
+    pub fn password(self, password: impl Into<String>) -> Self {
+        DecodeOptions { password: Some(Zeroizing::new(password.into())), ..self }
+    }
+
+    /// Decrypts the data encrypted to the [recipients](struct.EncodeOptions.html#method.recipient)
+    /// with the X25519 secret key file of one of them before writing it out.
+    pub fn identity(self, secret_key: impl Into<PathBuf>) -> Self {
+        DecodeOptions { identity: Some(secret_key.into()), ..self }
+    }
+
+    /// Configures whether the data was [signed](struct.EncodeOptions.html#method.data_signing_key) when encoding,
+    /// so that its signature is checked and the fingerprint of the signer is put into the
+    /// [report](struct.DecodeReport.html#structfield.signer).
+    pub fn signed_data(self, signed_data: bool) -> Self {
+        DecodeOptions { signed_data, ..self }
+    }
+
+    /// Requires the data to be signed with the secret key of the given Ed25519 public key file.
+    pub fn signer(self, public_key: impl Into<PathBuf>) -> Self {
+        DecodeOptions { signer: Some(public_key.into()), ..self }
+    }
+
+    /// Configures whether the [hidden volume](struct.EncodeOptions.html#method.hidden_volume) is decoded
+    /// instead of the decoy data, with the [password](#method.password) of the volume.
+    pub fn hidden_volume(self, hidden_volume: bool) -> Self {
+        DecodeOptions { hidden_volume, ..self }
+    }
+
+    /// Takes the [whitening](struct.EncodeOptions.html#method.whiten) with the keystream of the seed off the hidden bytes.
+    pub fn whiten(self, seed: impl Into<String>) -> Self {
+        DecodeOptions { whitening: Some(Zeroizing::new(seed.into())), ..self }
+    }
+
+    /// Visits the pixels in the order derived from the seed, when they were [scattered](struct.EncodeOptions.html#method.scatter)
+    /// with it when encoding.
+    pub fn scatter(self, seed: &str) -> Self {
+        DecodeOptions { scatter: Some(container::scatter_key(seed)), ..self }
+    }
+
+    /// Configures how many of the first bytes of the decoded pixels were left alone,
+    /// see [`EncodeOptions::offset`](struct.EncodeOptions.html#method.offset).
+    pub fn offset(self, offset: usize) -> Self {
+        DecodeOptions { offset, ..self }
+    }
+
+    /// Configures every which byte of the decoded pixels after the offset held the data,
+    /// see [`EncodeOptions::stride`](struct.EncodeOptions.html#method.stride).
+    pub fn stride(self, stride: usize) -> Self {
+        DecodeOptions { stride: stride.max(1), ..self }
+    }
+
+    /// Configures how many layers of images hidden in each other, see [`nest_images`](fn.nest_images.html),
+    /// are peeled to get to the data, 1 being just the data hidden in the image itself.
+    ///
+    /// The same settings are used for every layer.
+    pub fn depth(self, depth: usize) -> Self {
+        DecodeOptions { depth: depth.max(1), ..self }
+    }
+
+    /// Configures whether the alpha channel of the images was left alone when encoding, which is the default.
+    pub fn skip_alpha(self, skip_alpha: bool) -> Self {
+        DecodeOptions { skip_alpha, ..self }
+    }
+
+    /// Configures whether the fully transparent pixels of the images were left out of the carrier when encoding.
+    pub fn skip_transparent(self, skip_transparent: bool) -> Self {
+        DecodeOptions { skip_transparent, ..self }
+    }
+
+    /// Configures the bit mask of the channels of the images that hold the data,
+    /// see [`EncodeOptions::channels`](struct.EncodeOptions.html#method.channels).
+    pub fn channels(self, channels: u8) -> Self {
+        DecodeOptions { channels, ..self }
+    }
+
+    /// Uses the grayscale image as the mask of the pixels that hold the data,
+    /// see [`EncodeOptions::mask`](struct.EncodeOptions.html#method.mask).
+    pub fn mask(self, mask: impl Into<PathBuf>) -> Self {
+        DecodeOptions { mask: Some(mask.into()), ..self }
+    }
+
+    /// The layout of the pixels with the channels these options use.
+    ///
+    /// The wet paper embedding keeps the alpha channel, as it marks the alpha samples wet instead of skipping them.
+    pub(crate) fn carrier_layout(&self, layout: Layout) -> Layout {
+        let layout = layout.only_channels(self.channels);
+        if self.skip_alpha && self.embedding != Embedding::WetPaper { layout.skip_alpha() } else { layout }
+    }
+
+    /// Indices of the bytes of the pixels in the given carrier layout that are in the lane of the
+    /// offset and the stride, or `None` when all of them are
+    pub(crate) fn lane(&self, len: usize, layout: Layout) -> Option<Vec<usize>> {
+        lane(len, layout, self.offset, self.stride)
+    }
+
+    /// These options for the bytes of the lane gathered one after another, with the bits of the given carrier layout
+    pub(crate) fn in_lane(&self, layout: Layout) -> Self {
+        DecodeOptions {
+            offset: 0,
+            stride: 1,
+            bits: self.carrier_bits(layout),
+            wide_bits: None,
+            channels: u8::MAX,
+            skip_transparent: false,
+            mask: None,
+            ..self.clone()
+        }
+    }
+
+    /// Whether the transparent and the masked out pixels are left out of the carrier, rather than marked wet
+    pub(crate) fn leaves_out_pixels(&self) -> bool {
+        self.embedding != Embedding::WetPaper
+    }
+
+    /// The number of bits of hidden data per sample of the given layout
+    pub(crate) fn carrier_bits(&self, layout: Layout) -> Bits {
+        carrier_bits(self.bits, self.wide_bits, layout)
+    }
+}
+
+/// Indices of the usable bytes of the pixels in the given layout from the offset on with the stride,
+/// or `None` when those are all of them
+fn lane(len: usize, layout: Layout, offset: usize, stride: usize) -> Option<Vec<usize>> {
+    if offset == 0 && stride <= 1 {
+        return None;
+    }
+    Some((offset..len).step_by(stride.max(1)).filter(|&i| layout.is_usable(i)).collect())
+}
+
+/// The wide bits for the layouts with the multi-byte samples, when they are configured, and the bits otherwise
+fn carrier_bits(bits: Bits, wide_bits: Option<Bits>, layout: Layout) -> Bits {
+    match wide_bits {
+        Some(wide_bits) if layout.sample_size() > 1 => wide_bits,
+        _ => bits,
+    }
+}
+
+/// What the decoding functions have to say about the extracted data besides the data itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecodeReport {
+    /// Byte ranges of the data that could not be recovered and were replaced by zeroes
+    pub missing: Vec<Range<usize>>,
+    /// Byte ranges of the data that were damaged, but were repaired from the redundant copies
+    pub corrected: Vec<Range<usize>>,
+    /// Number of the damaged redundant copies of the parts of the data that were found
+    pub damaged: usize,
+    /// [Fingerprint](keys/fn.fingerprint.html) of the public key the data was signed with,
+    /// when it was decoded with [`DecodeOptions::signed_data`](struct.DecodeOptions.html#method.signed_data)
+    pub signer: Option<String>,
+    /// [Flags](container/struct.Header.html#structfield.flags) of the layers the data is sealed in,
+    /// as recorded in its header, or 0 when the embedding has no header
+    pub flags: u8,
+    /// Name of the [entry](toc/struct.Entry.html) the data was taken from, when the carrier holds several named ones
+    pub entry: Option<String>,
+    /// [Metadata](metadata/struct.Metadata.html) of the file the data came from, when it was hidden along with it
+    pub metadata: Option<Metadata>,
+}
+
+/// Outcome of [`roundtrip_image`](fn.roundtrip_image.html), encoding the data and decoding it back in memory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundTrip {
+    /// How many bytes of payload the image can hold with the options
+    pub capacity: usize,
+    /// Size of the payload in bytes
+    pub payload: usize,
+    /// [PSNR](quality/fn.psnr.html) of the image with the data hidden in it, in decibels
+    pub psnr: f64,
+    /// How many bits of the decoded data differ from the payload, counting the missing or the extra bytes
+    /// as eight wrong bits each
+    pub wrong_bits: usize,
+}
+
+/// Outcome of [`image_quality`](fn.image_quality.html), how much hiding the data has cost the image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quality {
+    /// [PSNR](quality/fn.psnr.html) of the image with the data hidden in it, in decibels
+    pub psnr: f64,
+    /// [SSIM](quality/fn.ssim.html) of the image with the data hidden in it, 1 for the same images
+    pub ssim: f64,
+    /// [Mean absolute error](quality/fn.mean_absolute_error.html) of the samples of the image with the data hidden in it
+    pub mean_absolute_error: f64,
+    /// How many bytes of payload the original image can hold with the options
+    pub capacity: usize,
+    /// How many bytes of the capacity the hidden data takes, as its header says,
+    /// `None` when the data is not embedded into the least significant bits sequentially and has no header
+    pub used: Option<u64>,
+}
+
+/// What [`inspect_image`](fn.inspect_image.html) finds in front of the hidden data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inspection {
+    /// Number of the bits of each color byte, or of each 16-bit sample, the header was found with
+    pub bits: Bits,
+    /// The header, with the version of the format and the [flags](container/struct.Header.html#structfield.flags)
+    /// of the layers the data is sealed in
+    pub header: Header,
+    /// Length of the payload the carrier claims to hold, in bytes
+    pub length: u64,
+    /// How many bytes of payload the carrier can hold with the settings of the header
+    pub capacity: usize,
+}
+
+impl Inspection {
+    /// Whether the carrier can hold as much data as its length claims, which it does unless it is damaged.
+    pub fn is_plausible(&self) -> bool {
+        self.length <= self.capacity as u64
+    }
+}
+
+/// Data found by [`recover_image`](fn.recover_image.html) along with the settings it was found with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recovered {
+    /// Number of the bits of each color byte, or of each 16-bit sample, that hold the data
+    pub bits: Bits,
+    /// Mask of the channels that hold the data, see [`DecodeOptions::channels`](struct.DecodeOptions.html#method.channels),
+    /// with the alpha channel counted as any other
+    pub channels: u8,
+    /// Whether the fully transparent pixels were left out of the carrier
+    pub skip_transparent: bool,
+    /// [Flags](container/struct.Header.html#structfield.flags) of the layers the data is sealed in
+    pub flags: u8,
+    /// The data, with none of its layers opened
+    pub data: Vec<u8>,
+}
+
+impl RoundTrip {
+    /// Whether the payload was decoded back bit-exact.
+    pub fn is_intact(&self) -> bool {
+        self.wrong_bits == 0
+    }
+}
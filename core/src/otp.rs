@@ -0,0 +1,85 @@
+use std::convert::TryFrom;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use zeroize::Zeroizing;
+
+use crate::Error;
+
+/// Size of the pad offset stored in front of the sealed payload
+pub const OFFSET_SIZE: usize = 8;
+
+/// XORs the payload with the bytes of the pad starting at `offset`, storing that offset in front of the result
+/// so that [`open`](fn.open.html) knows which bytes of the pad to use.
+///
+/// # Errors
+/// [`InvalidKey`](../enum.Error.html#variant.InvalidKey) when the pad does not have enough bytes after the offset.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::otp::{open, seal};
+/// let pad = [0x5a; 16];
+/// let sealed = seal(b"secret", &pad, 4).unwrap();
+///
+/// assert_eq!(&sealed[8..], &[0x29, 0x3f, 0x39, 0x28, 0x3f, 0x2e]);
+/// assert_eq!(open(&sealed, &pad).unwrap(), b"secret");
+/// assert!(seal(b"secret", &pad, 12).is_err());
+/// ```
+///
+pub fn seal(payload: &[u8], pad: &[u8], offset: u64) -> Result<Vec<u8>, Error> {
+    let key = pad_slice(pad, offset, payload.len())?;
+    let mut sealed = offset.to_be_bytes().to_vec();
+    sealed.extend(payload.iter().zip(key).map(|(byte, key)| byte ^ key));
+    Ok(sealed)
+}
+
+/// Reverses the [`seal`](fn.seal.html) function with the same pad.
+///
+/// # Errors
+/// [`InvalidKey`](../enum.Error.html#variant.InvalidKey) when the data was sealed with a longer pad
+/// or was not sealed at all.
+pub fn open(sealed: &[u8], pad: &[u8]) -> Result<Vec<u8>, Error> {
+    if sealed.len() < OFFSET_SIZE {
+        return Err(Error::InvalidKey("the data was not sealed with a pad".into()));
+    }
+    let (offset, payload) = sealed.split_at(OFFSET_SIZE);
+    let offset = u64::from_be_bytes(<[u8; OFFSET_SIZE]>::try_from(offset).unwrap());
+    let key = pad_slice(pad, offset, payload.len())?;
+    Ok(payload.iter().zip(key).map(|(byte, key)| byte ^ key).collect())
+}
+
+fn pad_slice(pad: &[u8], offset: u64, len: usize) -> Result<&[u8], Error> {
+    usize::try_from(offset).ok()
+        .and_then(|offset| pad.get(offset..offset.checked_add(len)?))
+        .ok_or_else(|| Error::InvalidKey(format!(
+            "the pad has {} bytes, not enough for {} bytes at the offset {}", pad.len(), len, offset,
+        )))
+}
+
+/// Path of the file next to the pad that records how many of its bytes were already used
+pub fn used_path(pad: &Path) -> PathBuf {
+    let mut path = OsString::from(pad.as_os_str());
+    path.push(".used");
+    PathBuf::from(path)
+}
+
+/// Seals the payload with the first unused bytes of the pad file and records them as used,
+/// so that no part of the pad is ever used twice.
+///
+/// The bytes are recorded as used before the sealed payload is returned, so they are lost
+/// rather than reused if the payload never makes it into the carrier.
+pub fn seal_with_file(payload: &[u8], pad: &Path) -> Result<Vec<u8>, Error> {
+    let used_path = used_path(pad);
+    let used = match fs::read_to_string(&used_path) {
+        Ok(used) => used.trim().parse::<u64>()
+            .map_err(|_| Error::InvalidKey(format!("{} does not hold a pad offset", used_path.display())))?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+        Err(e) => return Err(e.into()),
+    };
+    let sealed = seal(payload, &Zeroizing::new(fs::read(pad)?), used)?;
+    fs::write(&used_path, format!("{}\n", used + payload.len() as u64))?;
+    Ok(sealed)
+}
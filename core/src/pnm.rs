@@ -0,0 +1,176 @@
+use std::convert::TryFrom;
+
+use image::ColorType;
+
+use crate::container::Conversion;
+use crate::Error;
+
+/// A Netpbm image decoded to its pixels, with one or two bytes per sample,
+/// the two-byte ones being big-endian the same as in the files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PnmImage {
+    /// Width of the image in pixels
+    pub width: u32,
+    /// Height of the image in pixels
+    pub height: u32,
+    /// Either 8 or 16-bit grayscale or RGB
+    pub color_type: ColorType,
+    /// The pixels, row by row
+    pub pixels: Vec<u8>,
+}
+
+/// Whether the bytes start with the magic of a PBM, PGM or PPM image, in either the plain or the raw variant.
+pub fn is_pnm(header: &[u8]) -> bool {
+    matches!(header, [b'P', b'1'..=b'6', space, ..] if space.is_ascii_whitespace())
+}
+
+fn malformed(what: &str) -> Error {
+    Error::UnsupportedCarrier(format!("malformed Netpbm image: {}", what))
+}
+
+/// Reads the whitespace-separated header and plain raster tokens, skipping the comments
+struct Tokens<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Tokens<'_> {
+    fn skip_space(&mut self) {
+        while let Some(&byte) = self.data.get(self.position) {
+            match byte {
+                b'#' => while self.data.get(self.position).is_some_and(|&b| b != b'\n') {
+                    self.position += 1;
+                },
+                _ if byte.is_ascii_whitespace() => self.position += 1,
+                _ => break,
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<u32, Error> {
+        self.skip_space();
+        let start = self.position;
+        while self.data.get(self.position).is_some_and(u8::is_ascii_digit) {
+            self.position += 1;
+        }
+        std::str::from_utf8(&self.data[start..self.position]).ok()
+            .and_then(|number| number.parse().ok())
+            .ok_or_else(|| malformed("expected a number"))
+    }
+
+    /// A single digit of the plain bitmaps, which do not need to be separated
+    fn bit(&mut self) -> Result<u8, Error> {
+        self.skip_space();
+        let bit = match self.data.get(self.position) {
+            Some(b'0') => 0,
+            Some(b'1') => 1,
+            _ => return Err(malformed("expected a 0 or a 1")),
+        };
+        self.position += 1;
+        Ok(bit)
+    }
+}
+
+impl PnmImage {
+    /// Decodes a PBM, PGM or PPM image in either the plain or the raw variant.
+    ///
+    /// The bitmaps become 8-bit grayscale with 0 for black, and the samples with the maximum value
+    /// other than 255 or 65535 are scaled to the full range of one or two bytes,
+    /// which is returned as the [conversion](../container/struct.Conversion.html) of the image.
+    ///
+    /// # Errors
+    /// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when `pnm` is not a valid Netpbm image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::pnm::PnmImage;
+    /// # use image::ColorType;
+    /// let (image, conversion) = PnmImage::decode(b"P2\n# a comment\n2 1\n15\n0 15\n").unwrap();
+    ///
+    /// assert_eq!((image.color_type, image.pixels), (ColorType::L8, vec![0, 255]));
+    /// assert_eq!(conversion.unwrap().to_string(), "4-bit grayscale to 8-bit grayscale");
+    ///
+    /// let (bitmap, _) = PnmImage::decode(b"P1 3 1 101").unwrap();
+    /// assert_eq!(bitmap.pixels, [0, 255, 0]);
+    /// assert_eq!(PnmImage::decode(&bitmap.encode()).unwrap(), (bitmap, None));
+    /// ```
+    ///
+    pub fn decode(pnm: &[u8]) -> Result<(Self, Option<Conversion>), Error> {
+        if !is_pnm(pnm) {
+            return Err(Error::UnsupportedCarrier("not a Netpbm image".into()));
+        }
+        let kind = pnm[1];
+        let mut tokens = Tokens { data: pnm, position: 2 };
+        let (width, height) = (tokens.number()?, tokens.number()?);
+        let bitmap = kind == b'1' || kind == b'4';
+        let max = if bitmap { 1 } else { tokens.number()? };
+        if max == 0 || max > 65535 {
+            return Err(malformed("the maximum value is out of range"));
+        }
+        let channels = if kind == b'3' || kind == b'6' { 3 } else { 1 };
+        let wide = max > 255;
+        let samples = usize::try_from(width as u64 * height as u64 * channels as u64)
+            .ok()
+            .filter(|&samples| samples <= pnm.len() * 8)
+            .ok_or_else(|| malformed("the dimensions do not match the data"))?;
+
+        let mut values = Vec::with_capacity(samples);
+        match kind {
+            b'1' => for _ in 0..samples {
+                values.push(tokens.bit()? as u32 ^ 1);
+            },
+            b'2' | b'3' => for _ in 0..samples {
+                values.push(tokens.number()?.min(max));
+            },
+            _ => {
+                // a single whitespace byte separates the header from the raster
+                let raster = pnm.get(tokens.position + 1..).ok_or_else(|| malformed("the raster is missing"))?;
+                if kind == b'4' {
+                    let row = (width as usize).div_ceil(8);
+                    for y in 0..height as usize {
+                        for x in 0..width as usize {
+                            let byte = raster.get(y * row + x / 8).ok_or_else(|| malformed("the raster is truncated"))?;
+                            values.push((byte >> (7 - x % 8) & 1 ^ 1) as u32);
+                        }
+                    }
+                } else if wide {
+                    let raster = raster.get(..samples * 2).ok_or_else(|| malformed("the raster is truncated"))?;
+                    values.extend(raster.chunks_exact(2).map(|s| (u16::from_be_bytes([s[0], s[1]]) as u32).min(max)));
+                } else {
+                    let raster = raster.get(..samples).ok_or_else(|| malformed("the raster is truncated"))?;
+                    values.extend(raster.iter().map(|&s| (s as u32).min(max)));
+                }
+            },
+        }
+
+        let full = if wide { 65535 } else { 255 };
+        let pixels = match wide {
+            true => values.iter().flat_map(|&v| ((v * full / max) as u16).to_be_bytes()).collect(),
+            false => values.iter().map(|&v| (v * full / max) as u8).collect(),
+        };
+        let color_type = match (channels, wide) {
+            (1, false) => ColorType::L8,
+            (1, true) => ColorType::L16,
+            (_, false) => ColorType::Rgb8,
+            (_, true) => ColorType::Rgb16,
+        };
+        let conversion = Some(max).filter(|&max| max != full).map(|max| Conversion {
+            from: match bitmap {
+                true => "1-bit bitmap".into(),
+                false => format!("{}-bit {}", 32 - max.leading_zeros(), if channels == 1 { "grayscale" } else { "RGB" }),
+            },
+            to: color_type,
+        });
+        Ok((PnmImage { width, height, color_type, pixels }, conversion))
+    }
+
+    /// Encodes the image as a raw PGM or PPM image.
+    pub fn encode(&self) -> Vec<u8> {
+        let kind = if self.color_type.channel_count() == 1 { 5 } else { 6 };
+        let max = if self.color_type.bytes_per_pixel() / self.color_type.channel_count() == 2 { 65535 } else { 255 };
+        let mut pnm = format!("P{}\n{} {}\n{}\n", kind, self.width, self.height, max).into_bytes();
+        pnm.extend_from_slice(&self.pixels);
+        pnm
+    }
+}
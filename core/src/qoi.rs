@@ -0,0 +1,164 @@
+use std::convert::TryFrom;
+
+use crate::Error;
+
+/// First bytes of every QOI image
+pub const MAGIC: &[u8] = b"qoif";
+const HEADER_SIZE: usize = 14;
+const END: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+
+const OP_INDEX: u8 = 0x00;
+const OP_DIFF: u8 = 0x40;
+const OP_LUMA: u8 = 0x80;
+const OP_RUN: u8 = 0xC0;
+const OP_RGB: u8 = 0xFE;
+const OP_RGBA: u8 = 0xFF;
+
+/// A QOI image decoded to its pixels, which have 3 or 4 bytes each, depending on the channels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QoiImage {
+    /// Width of the image in pixels
+    pub width: u32,
+    /// Height of the image in pixels
+    pub height: u32,
+    /// Number of the channels, 3 for RGB and 4 for RGBA
+    pub channels: u8,
+    /// The color space byte of the header, which is only informative
+    pub colorspace: u8,
+    /// The pixels, row by row
+    pub pixels: Vec<u8>,
+}
+
+fn hash(pixel: [u8; 4]) -> usize {
+    let [r, g, b, a] = pixel.map(|c| c as usize);
+    (r * 3 + g * 5 + b * 7 + a * 11) % 64
+}
+
+fn malformed(what: &str) -> Error {
+    Error::UnsupportedCarrier(format!("malformed QOI image: {}", what))
+}
+
+impl QoiImage {
+    /// Decodes the QOI image.
+    ///
+    /// # Errors
+    /// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when `qoi` is not a valid QOI image.
+    pub fn decode(qoi: &[u8]) -> Result<Self, Error> {
+        if !qoi.starts_with(MAGIC) || qoi.len() < HEADER_SIZE {
+            return Err(Error::UnsupportedCarrier("not a QOI image".into()));
+        }
+        let width = u32::from_be_bytes([qoi[4], qoi[5], qoi[6], qoi[7]]);
+        let height = u32::from_be_bytes([qoi[8], qoi[9], qoi[10], qoi[11]]);
+        let (channels, colorspace) = (qoi[12], qoi[13]);
+        if channels != 3 && channels != 4 {
+            return Err(malformed("the number of channels is neither 3 nor 4"));
+        }
+        let len = usize::try_from(width as u64 * height as u64 * channels as u64)
+            .ok()
+            .filter(|&len| len <= (qoi.len() - HEADER_SIZE) * 62 * channels as usize)
+            .ok_or_else(|| malformed("the dimensions do not match the data"))?;
+
+        let mut pixels = Vec::with_capacity(len);
+        let mut index = [[0; 4]; 64];
+        let mut pixel = [0, 0, 0, 255];
+        let mut bytes = qoi[HEADER_SIZE..].iter().copied();
+        let mut next = || bytes.next().ok_or_else(|| malformed("the data is truncated"));
+        while pixels.len() < len {
+            let op = next()?;
+            let mut run = 1;
+            match op {
+                OP_RGB => pixel[..3].copy_from_slice(&[next()?, next()?, next()?]),
+                OP_RGBA => pixel = [next()?, next()?, next()?, next()?],
+                _ => match op & 0xC0 {
+                    OP_INDEX => pixel = index[op as usize],
+                    OP_DIFF => {
+                        for (i, shift) in [4, 2, 0].iter().enumerate() {
+                            pixel[i] = pixel[i].wrapping_add((op >> shift & 3).wrapping_sub(2));
+                        }
+                    },
+                    OP_LUMA => {
+                        let green = (op & 0x3F).wrapping_sub(32);
+                        let next = next()?;
+                        pixel[0] = pixel[0].wrapping_add(green.wrapping_add(next >> 4).wrapping_sub(8));
+                        pixel[1] = pixel[1].wrapping_add(green);
+                        pixel[2] = pixel[2].wrapping_add(green.wrapping_add(next & 15).wrapping_sub(8));
+                    },
+                    _ => run = (op & 0x3F) as usize + 1,
+                },
+            }
+            index[hash(pixel)] = pixel;
+            for _ in 0..run {
+                pixels.extend_from_slice(&pixel[..channels as usize]);
+            }
+        }
+        pixels.truncate(len);
+        Ok(QoiImage { width, height, channels, colorspace, pixels })
+    }
+
+    /// Encodes the image in the QOI format.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::qoi::QoiImage;
+    /// let pixels = (0..16 * 16 * 4).map(|i| (i / 7 % 256) as u8).collect();
+    /// let image = QoiImage { width: 16, height: 16, channels: 4, colorspace: 0, pixels };
+    /// let qoi = image.encode();
+    ///
+    /// assert!(qoi.len() < image.pixels.len());
+    /// assert_eq!(QoiImage::decode(&qoi).unwrap(), image);
+    /// ```
+    pub fn encode(&self) -> Vec<u8> {
+        let channels = self.channels as usize;
+        let mut qoi = Vec::with_capacity(HEADER_SIZE + self.pixels.len() / 2 + END.len());
+        qoi.extend_from_slice(MAGIC);
+        qoi.extend_from_slice(&self.width.to_be_bytes());
+        qoi.extend_from_slice(&self.height.to_be_bytes());
+        qoi.extend_from_slice(&[self.channels, self.colorspace]);
+
+        let mut index = [[0; 4]; 64];
+        let mut previous = [0, 0, 0, 255];
+        let mut run = 0;
+        let pixels = self.pixels.chunks_exact(channels);
+        let count = pixels.len();
+        for (i, chunk) in pixels.enumerate() {
+            let mut pixel = [0, 0, 0, 255];
+            pixel[..channels].copy_from_slice(chunk);
+            if pixel == previous {
+                run += 1;
+                if run == 62 || i == count - 1 {
+                    qoi.push(OP_RUN | (run - 1));
+                    run = 0;
+                }
+                continue;
+            }
+            if run > 0 {
+                qoi.push(OP_RUN | (run - 1));
+                run = 0;
+            }
+            let slot = hash(pixel);
+            if index[slot] == pixel {
+                qoi.push(OP_INDEX | slot as u8);
+            } else if pixel[3] != previous[3] {
+                qoi.push(OP_RGBA);
+                qoi.extend_from_slice(&pixel);
+            } else {
+                let [dr, dg, db] = [0, 1, 2].map(|c| pixel[c].wrapping_sub(previous[c]) as i8);
+                let (dr_dg, db_dg) = (dr.wrapping_sub(dg), db.wrapping_sub(dg));
+                if [dr, dg, db].iter().all(|d| (-2..=1).contains(d)) {
+                    qoi.push(OP_DIFF | ((dr + 2) as u8) << 4 | ((dg + 2) as u8) << 2 | (db + 2) as u8);
+                } else if (-32..=31).contains(&dg) && (-8..=7).contains(&dr_dg) && (-8..=7).contains(&db_dg) {
+                    qoi.push(OP_LUMA | (dg + 32) as u8);
+                    qoi.push(((dr_dg + 8) as u8) << 4 | (db_dg + 8) as u8);
+                } else {
+                    qoi.push(OP_RGB);
+                    qoi.extend_from_slice(&pixel[..3]);
+                }
+            }
+            index[slot] = pixel;
+            previous = pixel;
+        }
+        qoi.extend_from_slice(&END);
+        qoi
+    }
+}
@@ -0,0 +1,230 @@
+use crate::binary::{Endianness, Layout};
+
+/// Computes the peak signal-to-noise ratio between two sample buffers of the same size, in decibels.
+///
+/// Identical buffers have an infinite PSNR, anything above 40 dB is usually
+/// indistinguishable to the human eye.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::quality::psnr;
+/// assert_eq!(psnr(&[1, 2, 3], &[1, 2, 3]), f64::INFINITY);
+/// assert!((psnr(&[0, 0, 0, 0], &[1, 1, 1, 1]) - 48.13).abs() < 0.01);
+/// ```
+pub fn psnr(original: &[u8], altered: &[u8]) -> f64 {
+    let squared: f64 = original.iter()
+        .zip(altered)
+        .map(|(&a, &b)| (a as f64 - b as f64).powi(2))
+        .sum();
+    let mse = squared / original.len().max(1) as f64;
+    10.0 * (255.0 * 255.0 / mse).log10()
+}
+
+/// Side of the square windows the SSIM is computed in
+const SSIM_WINDOW: usize = 8;
+
+/// The values of the samples of each pixel, in the native byte order as the decoded pixels are stored
+fn samples<'a>(pixels: &'a [u8], layout: Layout) -> impl Iterator<Item = f64> + 'a {
+    pixels.chunks_exact(layout.sample_size() as usize).map(|bytes| f64::from(sample(bytes)))
+}
+
+/// Computes the mean absolute difference between the samples of two images of the same size,
+/// in the units of the samples, so that changing every least significant bit gives 1.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::binary::Layout;
+/// # use steganographer_core::quality::mean_absolute_error;
+/// let rgb = Layout::new(3, 1, false);
+/// assert_eq!(mean_absolute_error(&[1, 2, 3, 4, 5, 6], &[1, 2, 3, 5, 4, 9], rgb), 5.0 / 6.0);
+/// ```
+pub fn mean_absolute_error(original: &[u8], altered: &[u8], layout: Layout) -> f64 {
+    let (sum, count) = samples(original, layout)
+        .zip(samples(altered, layout))
+        .fold((0.0, 0), |(sum, count), (a, b)| (sum + (a - b).abs(), count + 1));
+    sum / f64::from(count.max(1))
+}
+
+/// Computes the structural similarity index between two images of the same size, from 1 for the same images
+/// down to 0 and below for the unrelated ones.
+///
+/// Unlike the [PSNR](fn.psnr.html), it compares the local means, contrasts and structures of the images,
+/// which is closer to what the eye notices. It is the mean over the channels and over the windows of 8 by 8
+/// pixels, overlapping by half, and needs the [width](../binary/struct.Layout.html#method.width) of the images
+/// in the layout, without which the pixels are taken as a single row.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::binary::Layout;
+/// # use steganographer_core::quality::ssim;
+/// let gray = Layout::new(1, 1, false).width(16);
+/// let original = (0..256).map(|i| (i * 37 % 256) as u8).collect::<Vec<_>>();
+/// assert!((ssim(&original, &original, gray) - 1.0).abs() < 1e-9);
+///
+/// let flipped = original.iter().map(|v| v ^ 1).collect::<Vec<_>>();
+/// let inverted = original.iter().map(|v| !v).collect::<Vec<_>>();
+/// assert!(ssim(&original, &flipped, gray) > 0.99);
+/// assert!(ssim(&original, &inverted, gray) < 0.0);
+/// ```
+pub fn ssim(original: &[u8], altered: &[u8], layout: Layout) -> f64 {
+    let channels = layout.channels() as usize;
+    let count = original.len().min(altered.len()) / layout.pixel_size();
+    let width = layout.pixels_per_row().unwrap_or(count).clamp(1, count.max(1));
+    let height = count / width;
+    if height == 0 {
+        return 1.0;
+    }
+    let peak = 2f64.powi(i32::from(layout.sample_size()) * 8) - 1.0;
+    let (c1, c2) = ((0.01 * peak).powi(2), (0.03 * peak).powi(2));
+    let (a, b) = (samples(original, layout).collect::<Vec<_>>(), samples(altered, layout).collect::<Vec<_>>());
+
+    // windows at every half of their size, and the last ones flush with the edges
+    let starts = |size: usize| {
+        let window = SSIM_WINDOW.min(size);
+        let mut starts = (0..=size - window).step_by((window / 2).max(1)).collect::<Vec<_>>();
+        if starts.last() != Some(&(size - window)) {
+            starts.push(size - window);
+        }
+        (window, starts)
+    };
+    let ((window_width, columns), (window_height, rows)) = (starts(width), starts(height));
+    let n = (window_width * window_height) as f64;
+    let (mut total, mut windows) = (0.0, 0);
+    for channel in 0..channels {
+        for &y in &rows {
+            for &x in &columns {
+                let indices = (y..y + window_height)
+                    .flat_map(|row| (x..x + window_width).map(move |column| (row * width + column) * channels + channel));
+                let (mut sa, mut sb, mut saa, mut sbb, mut sab) = (0.0, 0.0, 0.0, 0.0, 0.0);
+                for i in indices {
+                    sa += a[i];
+                    sb += b[i];
+                    saa += a[i] * a[i];
+                    sbb += b[i] * b[i];
+                    sab += a[i] * b[i];
+                }
+                let (ma, mb) = (sa / n, sb / n);
+                let (va, vb, cov) = (saa / n - ma * ma, sbb / n - mb * mb, sab / n - ma * mb);
+                total += (2.0 * ma * mb + c1) * (2.0 * cov + c2) / ((ma * ma + mb * mb + c1) * (va + vb + c2));
+                windows += 1;
+            }
+        }
+    }
+    total / f64::from(windows)
+}
+
+/// How the samples of one channel differ between two images.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelDifference {
+    /// Index of the channel in the pixels
+    pub channel: usize,
+    /// Number of the samples that differ in any of their bits
+    pub changed: usize,
+    /// Number of the samples that differ in each of their bits, the least significant one first
+    pub bits: Vec<usize>,
+}
+
+impl ChannelDifference {
+    /// Indices of the bit planes that differ anywhere, the least significant one first.
+    pub fn changed_planes(&self) -> Vec<usize> {
+        self.bits.iter().enumerate().filter(|&(_, &count)| count > 0).map(|(bit, _)| bit).collect()
+    }
+}
+
+/// How two images of the same size and pixel format differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comparison {
+    /// Number of the pixels in each of the images
+    pub pixels: usize,
+    /// Number of the pixels that differ in any of their samples
+    pub changed_pixels: usize,
+    /// Differences of each of the channels, the alpha one included
+    pub channels: Vec<ChannelDifference>,
+}
+
+impl Comparison {
+    /// Whether no more than the given number of the least significant bits of any sample differ,
+    /// which is what hiding the data with that many [bits](../binary/enum.Bits.html) may change.
+    pub fn only_low_bits(&self, bits: usize) -> bool {
+        self.channels.iter().all(|channel| channel.bits.iter().skip(bits).all(|&count| count == 0))
+    }
+}
+
+/// Value of the sample in the native byte order, as the decoded pixels are stored
+fn sample(bytes: &[u8]) -> u32 {
+    let fold = |value: u32, &byte: &u8| value << 8 | u32::from(byte);
+    match Endianness::native() {
+        Endianness::Little => bytes.iter().rev().fold(0, fold),
+        Endianness::Big => bytes.iter().fold(0, fold),
+    }
+}
+
+/// The bits that differ between the samples of each channel of each pair of pixels
+fn differences<'a>(original: &'a [u8], altered: &'a [u8], layout: Layout) -> impl Iterator<Item = Vec<u32>> + 'a {
+    let sample_size = layout.sample_size() as usize;
+    original.chunks_exact(layout.pixel_size())
+        .zip(altered.chunks_exact(layout.pixel_size()))
+        .map(move |(a, b)| a.chunks_exact(sample_size).zip(b.chunks_exact(sample_size)).map(|(a, b)| sample(a) ^ sample(b)).collect())
+}
+
+/// Compares the decoded pixels of two images, counting the samples of each channel that differ in each of their bits.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::binary::Layout;
+/// # use steganographer_core::quality::compare;
+/// let rgb = Layout::new(3, 1, false);
+/// let comparison = compare(&[10, 20, 30, 40, 50, 60], &[11, 20, 30, 40, 50, 62], rgb);
+/// assert_eq!(comparison.changed_pixels, 2);
+/// assert_eq!(comparison.channels[0].bits[..2], [1, 0]);
+/// assert_eq!(comparison.channels[2].changed_planes(), [1]);
+/// assert!(comparison.only_low_bits(2) && !comparison.only_low_bits(1));
+/// ```
+pub fn compare(original: &[u8], altered: &[u8], layout: Layout) -> Comparison {
+    let bits = layout.sample_size() as usize * 8;
+    let mut channels = (0..layout.channels() as usize)
+        .map(|channel| ChannelDifference { channel, changed: 0, bits: vec![0; bits] })
+        .collect::<Vec<_>>();
+    let (mut pixels, mut changed_pixels) = (0, 0);
+    for pixel in differences(original, altered, layout) {
+        pixels += 1;
+        if pixel.iter().any(|&difference| difference != 0) {
+            changed_pixels += 1;
+        }
+        for (channel, difference) in channels.iter_mut().zip(pixel) {
+            if difference != 0 {
+                channel.changed += 1;
+            }
+            for (bit, count) in channel.bits.iter_mut().enumerate() {
+                *count += (difference >> bit & 1) as usize;
+            }
+        }
+    }
+    Comparison { pixels, changed_pixels, channels }
+}
+
+/// Renders the pixels that differ between two images as RGB pixels, the black ones being the same,
+/// and the ones that differ going from yellow, when only the least significant bits differ, to red,
+/// when the most significant ones do.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::binary::Layout;
+/// # use steganographer_core::quality::heat_map;
+/// let gray = Layout::new(1, 1, false);
+/// assert_eq!(heat_map(&[0, 0, 0], &[0, 1, 128], gray), [0, 0, 0, 255, 255, 0, 255, 0, 0]);
+/// ```
+pub fn heat_map(original: &[u8], altered: &[u8], layout: Layout) -> Vec<u8> {
+    let top = (layout.sample_size() as u32 * 8 - 1) as f64;
+    differences(original, altered, layout)
+        .flat_map(|pixel| match pixel.into_iter().fold(0, |all, difference| all | difference) {
+            0 => [0, 0, 0],
+            all => [255, (255.0 * (1.0 - f64::from(31 - all.leading_zeros()) / top)) as u8, 0],
+        })
+        .collect()
+}
@@ -0,0 +1,259 @@
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use std::ops::Range;
+
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+
+use crate::binary::{Bits, Layout, SteganographReader, SteganographWriter};
+use crate::Error;
+
+/// Pattern that starts every segment
+pub const SYNC_MARKER: [u8; 4] = [0xA5, 0x5A, 0xC3, 0x3C];
+
+/// Size of the segment header: marker, total length, offset, data length and checksum
+pub const SEGMENT_HEADER: usize = 4 + 4 + 4 + 2 + 2;
+
+/// Default amount of payload bytes in one segment
+pub const DEFAULT_SEGMENT_SIZE: u16 = 32;
+
+/// Fletcher-16 over the segment header fields and data
+fn checksum(data: &[u8]) -> u16 {
+    let (a, b) = data.iter().fold((0u16, 0u16), |(a, b), &byte| {
+        let a = (a + byte as u16) % 255;
+        (a, (b + a) % 255)
+    });
+    b << 8 | a
+}
+
+/// Payload recovered by [`reveal_resync`](fn.reveal_resync.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resynced {
+    /// Recovered payload, with the bytes that were not found set to zero
+    pub payload: Vec<u8>,
+    /// Byte ranges of the payload that were not found in any intact segment and could not be repaired
+    pub missing: Vec<Range<usize>>,
+    /// Byte ranges of the payload that were not found in any intact segment,
+    /// but were repaired by a vote over the damaged copies of their segments
+    pub corrected: Vec<Range<usize>>,
+    /// Number of the segment copies that were found with a wrong checksum
+    pub damaged: usize,
+}
+
+/// Collects the indices where `flags` are set into contiguous ranges
+fn ranges(flags: impl Iterator<Item = bool>) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for (i, _) in flags.enumerate().filter(|&(_, flag)| flag) {
+        match ranges.last_mut() {
+            Some(range) if range.end == i => range.end += 1,
+            _ => ranges.push(i..i + 1),
+        }
+    }
+    ranges
+}
+
+/// Takes the most common value of each byte among the copies of a segment, the first one on a tie
+fn vote(copies: &[&[u8]]) -> Vec<u8> {
+    (0..copies[0].len())
+        .map(|i| {
+            let mut counts = [0u16; 256];
+            copies.iter().for_each(|copy| counts[copy[i] as usize] += 1);
+            copies.iter().rev().map(|copy| copy[i]).max_by_key(|&byte| counts[byte as usize]).unwrap()
+        })
+        .collect()
+}
+
+fn no_segments() -> Error {
+    Error::UnsupportedCarrier("no intact segments were found".into())
+}
+
+fn is_intact(segment: &[u8]) -> bool {
+    let mut check = segment[4..].to_vec();
+    BigEndian::write_u16(&mut check[10..], 0);
+    checksum(&check) == BigEndian::read_u16(&segment[14..])
+}
+
+/// Hides a slice of bytes in the carrier bytes in place, as a sequence of self-describing segments.
+///
+/// Every segment starts with a [sync marker](constant.SYNC_MARKER.html) and knows its offset in the payload,
+/// so the decoder can find segments anywhere in the carrier, even if it was cropped or had some rows removed.
+/// The segments are repeated until the whole carrier is filled, so that each part of the payload is likely
+/// to survive in at least one of the copies.
+///
+/// `segment_size` is the amount of payload bytes in each segment, smaller segments fit in more places
+/// between the damaged regions but waste more capacity on their headers.
+///
+/// # Errors
+/// An `UnexpectedEof` IO error when the carrier cannot hold even a single copy of the payload.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::resync::{hide_resync, reveal_resync};
+/// # use steganographer_core::binary::{Bits, Layout};
+/// let mut carrier = vec![0; 1024];
+/// hide_resync(b"hello there", &mut carrier, Bits::Two, Layout::default(), 4).unwrap();
+///
+/// // cut off a part of the beginning
+/// let cropped = &carrier[123..];
+///
+/// let recovered = reveal_resync(cropped, Bits::Two, Layout::default()).unwrap();
+/// assert_eq!(recovered.payload, b"hello there");
+/// assert!(recovered.missing.is_empty());
+/// ```
+///
+pub fn hide_resync(payload: &[u8], carrier: &mut [u8], bits: Bits, layout: Layout, segment_size: u16) -> Result<(), Error> {
+    let total = u32::try_from(payload.len()).map_err(|_| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+    let segment_size = segment_size.max(1) as usize;
+
+    let mut segments = Vec::new();
+    for (i, chunk) in payload.chunks(segment_size).enumerate() {
+        let mut segment = Vec::with_capacity(SEGMENT_HEADER + chunk.len());
+        segment.extend_from_slice(&SYNC_MARKER);
+        segment.write_u32::<BigEndian>(total)?;
+        segment.write_u32::<BigEndian>((i * segment_size) as u32)?;
+        segment.write_u16::<BigEndian>(chunk.len() as u16)?;
+        segment.write_u16::<BigEndian>(0)?;
+        segment.extend_from_slice(chunk);
+        let sum = checksum(&segment[4..]);
+        BigEndian::write_u16(&mut segment[14..], sum);
+        // the segments can only be found where they start at the beginning of a carrier byte
+        segment.resize(segment.len().next_multiple_of(bits.payload_len(bits.period())), 0);
+        segments.push(segment);
+    }
+    let copy = segments.concat();
+
+    let capacity = layout.capacity(carrier.len(), bits);
+    if copy.len() > capacity {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    let filled = copy.iter().cycle().take(capacity / copy.len() * copy.len()).copied().collect::<Vec<_>>();
+
+    let mut hidden = Vec::with_capacity(carrier.len());
+    SteganographWriter::new(&carrier[..], &mut hidden).bits(bits).layout(layout).write_all(&filled)?;
+    carrier[..hidden.len()].copy_from_slice(&hidden);
+    Ok(())
+}
+
+/// Scans the carrier for segments previously hidden by the [`hide_resync`](fn.hide_resync.html) function
+/// at every possible alignment and reassembles the payload from all the intact ones.
+///
+/// The parts of the payload that have no intact segments, but have several damaged copies of them,
+/// are repaired by taking the most common value of each byte among the copies, as long as the result
+/// passes the checksum.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::resync::{hide_resync, reveal_resync, SEGMENT_HEADER};
+/// # use steganographer_core::binary::{Bits, Layout};
+/// let mut carrier = vec![0; 1024];
+/// hide_resync(b"hello there", &mut carrier, Bits::Two, Layout::default(), 16).unwrap();
+///
+/// // flip a different bit of the data in each of the 9 copies of the only segment
+/// let copy = SEGMENT_HEADER + 11;
+/// for k in 0..9 {
+///     carrier[(k * copy + SEGMENT_HEADER + k) * 4] ^= 1;
+/// }
+///
+/// let recovered = reveal_resync(&carrier, Bits::Two, Layout::default()).unwrap();
+/// assert_eq!(recovered.payload, b"hello there");
+/// assert_eq!((recovered.corrected, recovered.damaged), (vec![0..11], 9));
+/// assert!(recovered.missing.is_empty());
+/// ```
+///
+///
+/// The `layout` must be the same as when hiding, but as long as the carrier was cropped by whole pixels
+/// the usable bytes stay in the same places within them.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when no intact segment was found at all.
+pub fn reveal_resync(carrier: &[u8], bits: Bits, layout: Layout) -> Result<Resynced, Error> {
+    let carrier = &layout.usable(carrier)[..];
+    let mut total = None;
+    let mut found: Vec<(usize, Vec<u8>)> = Vec::new();
+    let mut damaged: Vec<Vec<u8>> = Vec::new();
+
+    for phase in 0..bits.period().min(carrier.len()) {
+        let body = &carrier[phase..];
+        let mut plane = vec![0; bits.payload_len(body.len())];
+        SteganographReader::new(body).bits(bits).read_exact(&mut plane)?;
+
+        let mut pos = 0;
+        while pos + SEGMENT_HEADER <= plane.len() {
+            if plane[pos..pos + 4] != SYNC_MARKER {
+                pos += 1;
+                continue;
+            }
+            let header = &plane[pos..pos + SEGMENT_HEADER];
+            let len = BigEndian::read_u16(&header[12..]) as usize;
+            let segment = match plane.get(pos..pos + SEGMENT_HEADER + len) {
+                Some(segment) => segment,
+                None => break,
+            };
+            if !is_intact(segment) {
+                damaged.push(segment.to_vec());
+                pos += 1;
+                continue;
+            }
+            let segment_total = BigEndian::read_u32(&header[4..]) as usize;
+            // a few intact segments of some other payload would be a very weird coincidence
+            if *total.get_or_insert(segment_total) == segment_total {
+                let offset = BigEndian::read_u32(&header[8..]) as usize;
+                found.push((offset, segment[SEGMENT_HEADER..].to_vec()));
+            }
+            pos += SEGMENT_HEADER + len;
+        }
+    }
+
+    // without any intact segments, the damaged ones still tell the length of the payload if they agree on it
+    let totals = damaged.iter().map(|segment| &segment[4..8]).collect::<Vec<_>>();
+    let total = match total {
+        Some(total) => total,
+        None if !totals.is_empty() => BigEndian::read_u32(&vote(&totals)) as usize,
+        None => return Err(no_segments()),
+    };
+    let mut payload = vec![0; total];
+    let mut present = vec![false; total];
+    for (offset, data) in found {
+        if offset + data.len() <= total {
+            payload[offset..offset + data.len()].copy_from_slice(&data);
+            present[offset..offset + data.len()].iter_mut().for_each(|p| *p = true);
+        }
+    }
+
+    // the damaged copies of the same segment, as far as their possibly damaged headers tell
+    damaged.retain(|segment| BigEndian::read_u32(&segment[4..]) as usize == total);
+    let mut groups: Vec<(usize, Vec<&[u8]>)> = Vec::new();
+    for segment in &damaged {
+        let offset = BigEndian::read_u32(&segment[8..]) as usize;
+        match groups.iter_mut().find(|(o, copies)| *o == offset && copies[0].len() == segment.len()) {
+            Some((_, copies)) => copies.push(segment),
+            None => groups.push((offset, vec![segment])),
+        }
+    }
+    let mut corrected = vec![false; total];
+    for (offset, copies) in groups {
+        let end = offset + copies[0].len() - SEGMENT_HEADER;
+        if copies.len() < 2 || end > total || present[offset..end].iter().all(|&p| p) {
+            continue;
+        }
+        let segment = vote(&copies);
+        if is_intact(&segment) {
+            payload[offset..end].copy_from_slice(&segment[SEGMENT_HEADER..]);
+            for i in offset..end {
+                corrected[i] = !present[i];
+                present[i] = true;
+            }
+        }
+    }
+
+    if !present.iter().any(|&p| p) && total > 0 {
+        return Err(no_segments());
+    }
+    Ok(Resynced {
+        payload,
+        missing: ranges(present.iter().map(|&p| !p)),
+        corrected: ranges(corrected.into_iter()),
+        damaged: damaged.len(),
+    })
+}
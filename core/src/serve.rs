@@ -0,0 +1,177 @@
+use std::io::{Cursor, Read};
+
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{decode_image, encode_image, image_capacity, DecodeOptions, EncodeOptions, Embedding, Error};
+
+/// Largest request body that is accepted
+pub const MAX_BODY_SIZE: usize = 64 * 1024 * 1024;
+
+/// Fields of a `multipart/form-data` body, as pairs of names and contents
+fn parse_multipart(content_type: &str, body: &[u8]) -> Option<Vec<(String, Vec<u8>)>> {
+    let boundary = content_type.split(';')
+        .map(str::trim)
+        .find_map(|param| param.strip_prefix("boundary="))?
+        .trim_matches('"');
+    let delimiter = format!("--{}", boundary).into_bytes();
+
+    let mut fields = Vec::new();
+    let mut parts = split(body, &delimiter).into_iter().skip(1);
+    while let Some(part) = parts.next().filter(|part| !part.starts_with(b"--")) {
+        let part = part.strip_prefix(b"\r\n").unwrap_or(part);
+        let part = part.strip_suffix(b"\r\n").unwrap_or(part);
+        let split_at = part.windows(4).position(|w| w == b"\r\n\r\n")?;
+        let headers = String::from_utf8_lossy(&part[..split_at]);
+        let name = headers.lines()
+            .filter(|line| line.to_ascii_lowercase().starts_with("content-disposition:"))
+            .flat_map(|line| line.split(';'))
+            .find_map(|param| param.trim().strip_prefix("name="))?
+            .trim_matches('"')
+            .to_owned();
+        fields.push((name, part[split_at + 4..].to_vec()));
+    }
+    Some(fields)
+}
+
+fn split<'a>(data: &'a [u8], delimiter: &[u8]) -> Vec<&'a [u8]> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i + delimiter.len() <= data.len() {
+        if &data[i..i + delimiter.len()] == delimiter {
+            parts.push(&data[start..i]);
+            i += delimiter.len();
+            start = i;
+        } else {
+            i += 1;
+        }
+    }
+    parts.push(&data[start..]);
+    parts
+}
+
+fn query_embedding(url: &str) -> Result<Embedding, String> {
+    let query = url.split_once('?').map_or("", |(_, query)| query);
+    match query.split('&').find_map(|pair| pair.strip_prefix("method=")) {
+        None | Some("lsb") => Ok(Embedding::Lsb),
+        Some("wet") => Ok(Embedding::WetPaper),
+        Some("stc") => Ok(Embedding::Stc),
+        Some("adaptive") => Ok(Embedding::Adaptive),
+        Some(other) => Err(format!("unknown method `{}`", other)),
+    }
+}
+
+type Reply = Response<Cursor<Vec<u8>>>;
+
+fn reply(status: u16, content_type: &str, body: Vec<u8>) -> Reply {
+    let header = Header::from_bytes("Content-Type", content_type).expect("content type header is valid");
+    Response::from_data(body).with_status_code(status).with_header(header)
+}
+
+fn bad_request(message: impl ToString) -> Reply {
+    reply(400, "text/plain; charset=utf-8", message.to_string().into_bytes())
+}
+
+/// Responds to a request with the method, the URL, the content type and the body taken out of it
+/// the same way the [`serve`](fn.serve.html) endpoints do, without a server.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::serve::respond;
+/// # use image::{ColorType, ImageEncoder};
+/// # use image::codecs::png::PngEncoder;
+/// # use tiny_http::Method;
+/// fn form(fields: &[(&str, &[u8])]) -> Vec<u8> {
+///     let mut body = Vec::new();
+///     for (name, data) in fields {
+///         body.extend_from_slice(format!("--xyz\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n", name).as_bytes());
+///         body.extend_from_slice(data);
+///         body.extend_from_slice(b"\r\n");
+///     }
+///     body.extend_from_slice(b"--xyz--\r\n");
+///     body
+/// }
+/// let content_type = "multipart/form-data; boundary=xyz";
+/// let mut png = Vec::new();
+/// PngEncoder::new(&mut png).write_image(&[0; 16 * 16 * 3], 16, 16, ColorType::Rgb8).unwrap();
+///
+/// let body = form(&[("image", &png), ("payload", b"over the wire")]);
+/// let encoded = respond(&Method::Post, "/encode", content_type, &body);
+/// assert_eq!(encoded.status_code().0, 200);
+/// let encoded = encoded.into_reader().into_inner();
+///
+/// let decoded = respond(&Method::Post, "/decode", content_type, &form(&[("image", &encoded)]));
+/// assert_eq!(decoded.status_code().0, 200);
+/// assert_eq!(decoded.into_reader().into_inner(), b"over the wire");
+///
+/// assert_eq!(respond(&Method::Get, "/decode", content_type, &body).status_code().0, 404);
+/// assert_eq!(respond(&Method::Post, "/encode?method=nope", content_type, &body).status_code().0, 400);
+/// ```
+pub fn respond(method: &Method, url: &str, content_type: &str, body: &[u8]) -> Response<Cursor<Vec<u8>>> {
+    let path = url.split('?').next().unwrap_or_default();
+    if *method != Method::Post || !["/encode", "/decode", "/capacity"].contains(&path) {
+        return reply(404, "text/plain; charset=utf-8", b"POST /encode, /decode or /capacity\n".to_vec());
+    }
+    let embedding = match query_embedding(url) {
+        Ok(embedding) => embedding,
+        Err(e) => return bad_request(e),
+    };
+    if body.len() > MAX_BODY_SIZE {
+        return reply(413, "text/plain; charset=utf-8", b"request body is too large\n".to_vec());
+    }
+    let fields = match parse_multipart(content_type, body) {
+        Some(fields) => fields,
+        None => return bad_request("expected a multipart/form-data body"),
+    };
+    let field = |name: &str| fields.iter().find(|(field, _)| field == name).map(|(_, data)| &data[..]);
+    let image = match field("image") {
+        Some(image) => image,
+        None => return bad_request("the `image` field is missing"),
+    };
+
+    let result: Result<Reply, Error> = match path {
+        "/encode" => match field("payload") {
+            Some(payload) => {
+                let mut png = Vec::new();
+                encode_image(image, payload, &mut png, &EncodeOptions::new().embedding(embedding))
+                    .map(|_| reply(200, "image/png", png))
+            },
+            None => return bad_request("the `payload` field is missing"),
+        },
+        "/decode" => decode_image(image, &DecodeOptions::new().embedding(embedding))
+            .map(|(payload, _)| reply(200, "application/octet-stream", payload)),
+        _ => image_capacity(image, &EncodeOptions::new().embedding(embedding))
+            .map(|capacity| reply(200, "application/json", format!("{{\"capacity\":{}}}\n", capacity).into_bytes())),
+    };
+    result.unwrap_or_else(bad_request)
+}
+
+fn handle(request: &mut Request) -> Reply {
+    let content_type = request.headers().iter()
+        .find(|header| header.field.equiv("Content-Type"))
+        .map(|header| header.value.to_string())
+        .unwrap_or_default();
+    let mut body = Vec::new();
+    if let Err(e) = request.as_reader().take(MAX_BODY_SIZE as u64 + 1).read_to_end(&mut body) {
+        return bad_request(e);
+    }
+    respond(request.method(), request.url(), &content_type, &body)
+}
+
+/// Serves the `POST /encode`, `/decode` and `/capacity` endpoints on the given address until the server fails.
+///
+/// Each of them takes a `multipart/form-data` body with the PNG image in the `image` field,
+/// and the `/encode` one also takes the data to hide in the `payload` field.
+/// The embedding can be chosen with the `method` query parameter (`lsb`, `wet`, `stc` or `adaptive`).
+/// They respond with the resulting PNG image, the extracted data or `{"capacity":N}` respectively,
+/// or with a 400 status and the error message.
+pub fn serve(address: &str, mut log: impl FnMut(&str, u16)) -> Result<(), Error> {
+    let server = Server::http(address).map_err(|e| Error::Wrapped(e))?;
+    for mut request in server.incoming_requests() {
+        let response = handle(&mut request);
+        log(request.url(), response.status_code().0);
+        request.respond(response)?;
+    }
+    Ok(())
+}
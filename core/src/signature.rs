@@ -0,0 +1,140 @@
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+use crate::binary::{Bits, Layout, SteganographReader, SteganographWriter};
+use crate::keys::KEY_SIZE;
+use crate::Error;
+
+/// Size of the Ed25519 signature stored in the carrier
+pub const SIGNATURE_SIZE: usize = 64;
+
+/// Returns how many of the last carrier bytes are reserved for the signature.
+///
+/// The rest of the carrier, before them, is what the hidden data can use.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::signature::reserved_len;
+/// # use steganographer_core::binary::{Bits, Layout};
+/// // 64 bytes of signature in 4 bits of each of the 3 color channels of RGBA pixels
+/// let rgba = Layout::new(4, 1, true).skip_alpha();
+/// assert_eq!(reserved_len(1000, Bits::Four, rgba), 43 * 4);
+/// ```
+///
+pub fn reserved_len(carrier_len: usize, bits: Bits, layout: Layout) -> usize {
+    layout.pixels_len(carrier_len, bits.carrier_len(SIGNATURE_SIZE))
+}
+
+/// SHA-256 of the carrier with the hidden bits of the signature region cleared
+fn digest(carrier: &[u8], bits: Bits, layout: Layout) -> [u8; 32] {
+    let start = carrier.len() - reserved_len(carrier.len(), bits, layout);
+    let mut hasher = Sha256::new();
+    hasher.update(&carrier[..start]);
+    let tail = carrier[start..].iter().enumerate()
+        .map(|(i, &byte)| if layout.is_usable(i) { byte & !bits.mask() } else { byte })
+        .collect::<Vec<_>>();
+    hasher.update(&tail);
+    hasher.finalize().into()
+}
+
+/// Signs the whole carrier, with the data already hidden in it, and stores the signature
+/// in the [reserved](fn.reserved_len.html) bytes at its end.
+///
+/// Anything that changes a single bit of the carrier outside of the signature bits themselves
+/// makes the signature invalid.
+///
+/// # Errors
+/// An `UnexpectedEof` IO error when the carrier is too small to hold the signature.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::signature::{sign_carrier, verify_carrier};
+/// # use steganographer_core::binary::{Bits, Layout};
+/// # use ed25519_dalek::SigningKey;
+/// let key = SigningKey::from_bytes(&[42; 32]);
+/// let mut carrier = vec![0b10101010; 512];
+///
+/// sign_carrier(&mut carrier, Bits::Two, Layout::default(), &key).unwrap();
+/// assert!(verify_carrier(&carrier, Bits::Two, Layout::default(), &key.verifying_key()).is_ok());
+///
+/// carrier[7] ^= 1;
+/// assert!(verify_carrier(&carrier, Bits::Two, Layout::default(), &key.verifying_key()).is_err());
+/// ```
+///
+pub fn sign_carrier(carrier: &mut [u8], bits: Bits, layout: Layout, key: &SigningKey) -> Result<(), Error> {
+    let start = carrier.len() - reserved_len(carrier.len(), bits, layout);
+    let signature = key.sign(&digest(carrier, bits, layout)).to_bytes();
+
+    let mut hidden = Vec::with_capacity(carrier.len() - start);
+    SteganographWriter::new(&carrier[start..], &mut hidden).bits(bits).layout(layout).write_all(&signature)?;
+    carrier[start..start + hidden.len()].copy_from_slice(&hidden);
+    Ok(())
+}
+
+/// Checks the signature stored by [`sign_carrier`](fn.sign_carrier.html) against the public key.
+///
+/// # Errors
+/// [`InvalidSignature`](../enum.Error.html#variant.InvalidSignature) when the carrier was altered
+/// since it was signed, was signed with some other key or was not signed at all.
+pub fn verify_carrier(carrier: &[u8], bits: Bits, layout: Layout, key: &VerifyingKey) -> Result<(), Error> {
+    let start = carrier.len() - reserved_len(carrier.len(), bits, layout);
+    let mut signature = [0; SIGNATURE_SIZE];
+    SteganographReader::new(&carrier[start..]).bits(bits).layout(layout).read_exact(&mut signature)?;
+
+    key.verify(&digest(carrier, bits, layout), &Signature::from_bytes(&signature)).map_err(|_| Error::InvalidSignature)
+}
+
+/// How many bytes [`sign_data`](fn.sign_data.html) adds to the data: the public key of the signer and the signature
+pub const SIGNED_DATA_OVERHEAD: usize = KEY_SIZE + SIGNATURE_SIZE;
+
+/// Signs the data itself, rather than the carrier, appending the public key of the signer and the signature to it,
+/// so that whoever extracts the data can tell that it was not altered and who it came from.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::signature::{sign_data, verify_data};
+/// # use ed25519_dalek::SigningKey;
+/// let key = SigningKey::from_bytes(&[42; 32]);
+/// let mut signed = sign_data(b"hello", &key);
+///
+/// assert_eq!(verify_data(&signed).unwrap(), (b"hello".to_vec(), key.verifying_key().to_bytes()));
+///
+/// signed[0] ^= 1;
+/// assert!(verify_data(&signed).is_err());
+/// ```
+///
+pub fn sign_data(data: &[u8], key: &SigningKey) -> Vec<u8> {
+    let mut signed = Vec::with_capacity(data.len() + SIGNED_DATA_OVERHEAD);
+    signed.extend_from_slice(data);
+    signed.extend_from_slice(key.verifying_key().as_bytes());
+    signed.extend_from_slice(&key.sign(&signed).to_bytes());
+    signed
+}
+
+/// Checks the signature appended by [`sign_data`](fn.sign_data.html), returning the data without it
+/// and the public key of the signer.
+///
+/// This only tells that the data was signed by the owner of the returned key, which has to be compared
+/// to the expected one to tell that it came from someone in particular.
+///
+/// # Errors
+/// [`InvalidSignature`](../enum.Error.html#variant.InvalidSignature) when the data was altered since it was signed
+/// or was not signed at all.
+pub fn verify_data(signed: &[u8]) -> Result<(Vec<u8>, [u8; KEY_SIZE]), Error> {
+    let split = signed.len().checked_sub(SIGNATURE_SIZE).ok_or(Error::InvalidSignature)?;
+    let (message, signature) = signed.split_at(split);
+    let data_len = message.len().checked_sub(KEY_SIZE).ok_or(Error::InvalidSignature)?;
+    let signer = <[u8; KEY_SIZE]>::try_from(&message[data_len..]).expect("the slice is of the key size");
+    let signature = <[u8; SIGNATURE_SIZE]>::try_from(signature).expect("the slice is of the signature size");
+    VerifyingKey::from_bytes(&signer)
+        .map_err(|_| Error::InvalidSignature)?
+        .verify(message, &Signature::from_bytes(&signature))
+        .map_err(|_| Error::InvalidSignature)?;
+    Ok((message[..data_len].to_vec(), signer))
+}
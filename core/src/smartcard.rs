@@ -0,0 +1,424 @@
+use std::ffi::CString;
+use std::io;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+
+use chacha20poly1305::{KeyInit, XChaCha20Poly1305, XNonce};
+use chacha20poly1305::aead::Aead;
+use libloading::Library;
+use p256::{EncodedPoint, PublicKey};
+use p256::ecdh::EphemeralSecret;
+use p256::pkcs8::{DecodePublicKey, EncodePublicKey, LineEnding};
+use rand_core::{CryptoRng, OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::crypto::{NONCE_SIZE, TAG_SIZE, WRAPPED_KEY_SIZE};
+use crate::keys::KEY_SIZE;
+use crate::options::Applet;
+use crate::Error;
+
+/// Size of an uncompressed P-256 point, the ephemeral public key in front of the sealed payload
+pub const POINT_SIZE: usize = 65;
+
+/// How many bytes [`seal_for_card`](fn.seal_for_card.html) adds to the payload
+pub const OVERHEAD: usize = POINT_SIZE + WRAPPED_KEY_SIZE + NONCE_SIZE + TAG_SIZE;
+
+/// A smartcard that takes the command APDUs, such as the one in a [PC/SC reader](struct.Pcsc.html).
+pub trait Card {
+    /// Sends the command APDU to the card and returns its response, with the two status bytes at the end
+    fn transmit(&mut self, apdu: &[u8]) -> Result<Vec<u8>, Error>;
+}
+
+/// Value of the BER-TLV with the given tag among the ones that make up the data
+fn find_tlv<'a>(mut data: &'a [u8], tag: &[u8]) -> Option<&'a [u8]> {
+    while !data.is_empty() {
+        let tag_len = if data[0] & 0x1f == 0x1f { 2 } else { 1 };
+        let (len, len_len) = match *data.get(tag_len)? {
+            0x81 => (*data.get(tag_len + 1)? as usize, 2),
+            0x82 => (u16::from_be_bytes([*data.get(tag_len + 1)?, *data.get(tag_len + 2)?]) as usize, 3),
+            len if len < 0x80 => (len as usize, 1),
+            _ => return None,
+        };
+        let value = data.get(tag_len + len_len..tag_len + len_len + len)?;
+        if &data[..tag_len] == tag {
+            return Some(value);
+        }
+        data = &data[tag_len + len_len + len..];
+    }
+    None
+}
+
+fn tlv(tag: &[u8], value: &[u8]) -> Vec<u8> {
+    let mut tlv = tag.to_vec();
+    if value.len() >= 0x80 {
+        tlv.push(0x81);
+    }
+    tlv.push(value.len() as u8);
+    tlv.extend_from_slice(value);
+    tlv
+}
+
+/// Sends the short APDU with the given header and data and returns the data of the response,
+/// collecting the rest of it with GET RESPONSE for as long as the card says there is more
+fn command(card: &mut dyn Card, header: [u8; 4], data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut apdu = header.to_vec();
+    if !data.is_empty() {
+        apdu.push(data.len() as u8);
+        apdu.extend_from_slice(data);
+    }
+    apdu.push(0);
+
+    let mut response = Vec::new();
+    loop {
+        let mut reply = card.transmit(&apdu)?;
+        let status = reply.len().checked_sub(2)
+            .map(|len| (reply[len], reply[len + 1]))
+            .ok_or_else(|| Error::InvalidKey("the card sent a truncated response".into()))?;
+        reply.truncate(reply.len() - 2);
+        response.extend(reply);
+        match status {
+            (0x90, 0x00) => return Ok(response),
+            (0x61, more) => apdu = vec![0x00, 0xc0, 0x00, 0x00, more],
+            (0x63, tries) if tries & 0xf0 == 0xc0 => return Err(Error::InvalidKey(format!("wrong PIN, {} tries left", tries & 0x0f))),
+            (0x69, 0x82) => return Err(Error::InvalidKey("the card wants the PIN or a touch first".into())),
+            (sw1, sw2) => return Err(Error::InvalidKey(format!("the card refused the command with status {:02X}{:02X}", sw1, sw2))),
+        }
+    }
+}
+
+/// Selects the applet and verifies the PIN that unlocks its decryption key
+fn unlock(card: &mut dyn Card, applet: Applet, pin: Option<&str>) -> Result<(), Error> {
+    match applet {
+        Applet::Piv => command(card, [0x00, 0xa4, 0x04, 0x00], &[0xa0, 0x00, 0x00, 0x03, 0x08])?,
+        Applet::OpenPgp => command(card, [0x00, 0xa4, 0x04, 0x00], &[0xd2, 0x76, 0x00, 0x01, 0x24, 0x01])?,
+    };
+    if let Some(pin) = pin {
+        let pin = Zeroizing::new(match applet {
+            // the PIV PIN is always padded to 8 bytes
+            Applet::Piv if pin.len() <= 8 => pin.bytes().chain(std::iter::repeat(0xff)).take(8).collect(),
+            Applet::Piv => return Err(Error::InvalidKey("the PIV PIN is at most 8 characters long".into())),
+            Applet::OpenPgp => pin.as_bytes().to_vec(),
+        });
+        let reference = match applet {
+            Applet::Piv => 0x80,
+            Applet::OpenPgp => 0x82,
+        };
+        command(card, [0x00, 0x20, 0x00, reference], &pin)?;
+    }
+    Ok(())
+}
+
+/// Reads the P-256 public key of the decryption key from the OpenPGP applet of the card, to
+/// [seal](fn.seal_for_card.html) the data for it.
+///
+/// # Errors
+/// [`InvalidKey`](../enum.Error.html#variant.InvalidKey) for the PIV applet, which does not hand out
+/// the public keys of its slots, those have to be exported from the certificate of the 9D slot
+/// with the tools of the card, such as `ykman piv keys export 9d`.
+pub fn public_key(card: &mut dyn Card, applet: Applet) -> Result<PublicKey, Error> {
+    if applet == Applet::Piv {
+        return Err(Error::InvalidKey("the PIV applet does not hand out its public keys, export the one of the 9D slot".into()));
+    }
+    unlock(card, applet, None)?;
+    // the control reference template of the decryption key
+    let response = command(card, [0x00, 0x47, 0x81, 0x00], &[0xb8, 0x00])?;
+    let point = find_tlv(&response, &[0x7f, 0x49])
+        .and_then(|template| find_tlv(template, &[0x86]))
+        .ok_or_else(|| Error::InvalidKey("the card sent no public key".into()))?;
+    PublicKey::from_sec1_bytes(point).map_err(|_| Error::InvalidKey("the decryption key of the card is not a P-256 one".into()))
+}
+
+/// Has the card compute the P-256 Diffie-Hellman shared secret of its decryption key and the point,
+/// which is the 9D key management slot of the PIV applet and the decryption key of the OpenPGP one
+fn card_ecdh(card: &mut dyn Card, applet: Applet, pin: Option<&str>, point: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+    unlock(card, applet, pin)?;
+    let response = Zeroizing::new(match applet {
+        // GENERAL AUTHENTICATE with the dynamic authentication template holding the exponentiation of the point
+        Applet::Piv => command(card, [0x00, 0x87, 0x11, 0x9d], &tlv(&[0x7c], &[&[0x82, 0x00][..], &tlv(&[0x85], point)].concat()))?,
+        // PSO:DECIPHER with the cipher DO holding the public key DO of the point
+        Applet::OpenPgp => command(card, [0x00, 0x2a, 0x80, 0x86], &tlv(&[0xa6], &tlv(&[0x7f, 0x49], &tlv(&[0x86], point))))?,
+    });
+    let shared = match applet {
+        Applet::Piv => find_tlv(&response, &[0x7c]).and_then(|template| find_tlv(template, &[0x82])),
+        // some of the cards send the whole point and not just its x coordinate
+        Applet::OpenPgp if response.len() == POINT_SIZE && response[0] == 0x04 => Some(&response[1..33]),
+        Applet::OpenPgp => Some(&response[..]),
+    };
+    match shared {
+        Some(shared) if shared.len() == 32 => Ok(Zeroizing::new(shared.to_vec())),
+        _ => Err(Error::InvalidKey("the card sent a malformed shared secret".into())),
+    }
+}
+
+/// Derives the key that wraps the file key from the P-256 shared secret and the ephemeral public key.
+///
+/// The public key of the card is left out, as the PIV applet has no way of telling it to the decryption.
+fn wrapping_key(shared: &[u8], ephemeral: &[u8]) -> XChaCha20Poly1305 {
+    let key = Sha256::new()
+        .chain_update(b"steganographer p256")
+        .chain_update(shared)
+        .chain_update(ephemeral)
+        .finalize();
+    XChaCha20Poly1305::new(&key)
+}
+
+/// Parses the P-256 public key of the card from a PEM `PUBLIC KEY` block, as exported by the tools of the card
+/// or written by [`format_public_key`](fn.format_public_key.html).
+pub fn parse_public_key(text: &str) -> Result<PublicKey, Error> {
+    PublicKey::from_public_key_pem(text.trim()).map_err(|_| Error::InvalidKey("not a P-256 public key in PEM format".into()))
+}
+
+/// Formats the P-256 public key of the card as a PEM `PUBLIC KEY` block.
+pub fn format_public_key(key: &PublicKey) -> String {
+    key.to_public_key_pem(LineEnding::LF).expect("a P-256 public key can always be encoded")
+}
+
+/// Encrypts the payload with a random file key, which is in turn wrapped with the P-256 Diffie-Hellman
+/// shared secret of a random ephemeral key and the decryption key of a smartcard, so that the data
+/// can only be read while the card is plugged in, and the PIN or the touch it asks for is given.
+///
+/// The result starts with the ephemeral public key, followed by the wrapped file key, the nonce
+/// and the encrypted payload, which is [`OVERHEAD`](constant.OVERHEAD.html) bytes larger than the payload.
+///
+/// # Examples
+///
+/// ```
+/// # use p256::{PublicKey, SecretKey};
+/// # use rand_core::OsRng;
+/// # use steganographer_core::{Applet, Error};
+/// # use steganographer_core::smartcard::{open_with_card, seal_for_card, Card, OVERHEAD};
+/// /// A PIV card that only knows the commands the decryption sends to it
+/// struct SoftCard(SecretKey);
+///
+/// impl Card for SoftCard {
+///     fn transmit(&mut self, apdu: &[u8]) -> Result<Vec<u8>, Error> {
+///         Ok(match apdu[1..4] {
+///             // SELECT and VERIFY
+///             [0xa4, ..] => vec![0x90, 0x00],
+///             [0x20, ..] if &apdu[5..9] == b"1234" => vec![0x90, 0x00],
+///             [0x20, ..] => vec![0x63, 0xc2],
+///             // GENERAL AUTHENTICATE of the point that comes last in the data, before the Le byte
+///             [0x87, 0x11, 0x9d] => {
+///                 let point = PublicKey::from_sec1_bytes(&apdu[apdu.len() - 66..apdu.len() - 1]).unwrap();
+///                 let shared = p256::ecdh::diffie_hellman(self.0.to_nonzero_scalar(), point.as_affine());
+///                 [&[0x7c, 0x22, 0x82, 0x20][..], shared.raw_secret_bytes(), &[0x90, 0x00]].concat()
+///             },
+///             _ => vec![0x6d, 0x00],
+///         })
+///     }
+/// }
+/// let mut card = SoftCard(SecretKey::random(&mut OsRng));
+///
+/// let sealed = seal_for_card(b"secret", &card.0.public_key()).unwrap();
+///
+/// assert_eq!(sealed.len(), 6 + OVERHEAD);
+/// assert_eq!(open_with_card(&sealed, &mut card, Applet::Piv, Some("1234")).unwrap(), b"secret");
+/// assert!(open_with_card(&sealed, &mut card, Applet::Piv, Some("0000")).is_err());
+/// let mut other = SoftCard(SecretKey::random(&mut OsRng));
+/// assert!(open_with_card(&sealed, &mut other, Applet::Piv, Some("1234")).is_err());
+/// ```
+///
+pub fn seal_for_card(payload: &[u8], card: &PublicKey) -> Result<Vec<u8>, Error> {
+    seal_for_card_with_rng(payload, card, &mut OsRng)
+}
+
+/// Same as [`seal_for_card`](fn.seal_for_card.html), but the ephemeral key, the file key and the nonce come from the given generator
+pub(crate) fn seal_for_card_with_rng(payload: &[u8], card: &PublicKey, rng: &mut (impl RngCore + CryptoRng)) -> Result<Vec<u8>, Error> {
+    let ephemeral = EphemeralSecret::random(&mut *rng);
+    let ephemeral_public = EncodedPoint::from(ephemeral.public_key());
+    let mut file_key = Zeroizing::new([0; KEY_SIZE]);
+    rng.fill_bytes(&mut file_key[..]);
+
+    let shared = ephemeral.diffie_hellman(card);
+    // the wrapping key is only ever used once, so the nonce does not have to be random
+    let wrapped = wrapping_key(shared.raw_secret_bytes(), ephemeral_public.as_bytes())
+        .encrypt(&XNonce::default(), &file_key[..])
+        .expect("a key is never too large to be encrypted");
+    let mut nonce = XNonce::default();
+    rng.fill_bytes(&mut nonce);
+    let encrypted = XChaCha20Poly1305::new_from_slice(&file_key[..])
+        .expect("the key is of the right size")
+        .encrypt(&nonce, payload)
+        .map_err(|_| Error::InvalidKey("the payload is too large to be encrypted".into()))?;
+
+    let mut sealed = ephemeral_public.as_bytes().to_vec();
+    sealed.extend(wrapped);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend(encrypted);
+    Ok(sealed)
+}
+
+/// Reverses the [`seal_for_card`](fn.seal_for_card.html) function with the card the data was sealed for,
+/// unlocking its decryption key with the PIN, if it asks for one.
+///
+/// # Errors
+/// [`InvalidKey`](../enum.Error.html#variant.InvalidKey) when the card refuses to decrypt, most notably
+/// because of a wrong PIN, when the data was not sealed for this card, was altered since or was not sealed at all.
+pub fn open_with_card(sealed: &[u8], card: &mut dyn Card, applet: Applet, pin: Option<&str>) -> Result<Vec<u8>, Error> {
+    let not_sealed = || Error::InvalidKey("the data was not sealed for this card".into());
+    let (ephemeral, rest) = sealed.split_at_checked(POINT_SIZE).ok_or_else(not_sealed)?;
+    let (wrapped, rest) = rest.split_at_checked(WRAPPED_KEY_SIZE).ok_or_else(not_sealed)?;
+    let (nonce, encrypted) = rest.split_at_checked(NONCE_SIZE).ok_or_else(not_sealed)?;
+    // checked before the card is bothered with it
+    PublicKey::from_sec1_bytes(ephemeral).map_err(|_| not_sealed())?;
+
+    let shared = card_ecdh(card, applet, pin, ephemeral)?;
+    let file_key = wrapping_key(&shared, ephemeral)
+        .decrypt(&XNonce::default(), wrapped)
+        .map(Zeroizing::new)
+        .map_err(|_| not_sealed())?;
+    XChaCha20Poly1305::new_from_slice(&file_key)
+        .map_err(|_| not_sealed())?
+        .decrypt(XNonce::from_slice(nonce), encrypted)
+        .map_err(|_| not_sealed())
+}
+
+#[cfg(windows)]
+mod ffi {
+    pub type Long = i32;
+    pub type Dword = u32;
+    pub type Handle = usize;
+    pub const LIBRARY: &str = "winscard.dll";
+    pub const LIST_READERS: &[u8] = b"SCardListReadersA\0";
+    pub const CONNECT: &[u8] = b"SCardConnectA\0";
+}
+
+#[cfg(target_os = "macos")]
+mod ffi {
+    pub type Long = i32;
+    pub type Dword = u32;
+    pub type Handle = i32;
+    pub const LIBRARY: &str = "/System/Library/Frameworks/PCSC.framework/PCSC";
+    pub const LIST_READERS: &[u8] = b"SCardListReaders\0";
+    pub const CONNECT: &[u8] = b"SCardConnect\0";
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+mod ffi {
+    pub type Long = std::os::raw::c_long;
+    pub type Dword = std::os::raw::c_ulong;
+    pub type Handle = std::os::raw::c_long;
+    pub const LIBRARY: &str = "libpcsclite.so.1";
+    pub const LIST_READERS: &[u8] = b"SCardListReaders\0";
+    pub const CONNECT: &[u8] = b"SCardConnect\0";
+}
+
+use ffi::{Dword, Handle, Long};
+
+/// The `SCARD_IO_REQUEST` structure that tells the protocol of a transmitted APDU
+#[repr(C)]
+struct IoRequest {
+    protocol: Dword,
+    length: Dword,
+}
+
+const SCOPE_SYSTEM: Dword = 2;
+const SHARE_SHARED: Dword = 2;
+const PROTOCOL_T0: Dword = 1;
+const PROTOCOL_T1: Dword = 2;
+const LEAVE_CARD: Dword = 0;
+
+type EstablishContext = unsafe extern "system" fn(Dword, *const c_void, *const c_void, *mut Handle) -> Long;
+type ReleaseContext = unsafe extern "system" fn(Handle) -> Long;
+type ListReaders = unsafe extern "system" fn(Handle, *const c_char, *mut c_char, *mut Dword) -> Long;
+type Connect = unsafe extern "system" fn(Handle, *const c_char, Dword, Dword, *mut Handle, *mut Dword) -> Long;
+type Disconnect = unsafe extern "system" fn(Handle, Dword) -> Long;
+type Transmit = unsafe extern "system" fn(Handle, *const IoRequest, *const u8, Dword, *mut IoRequest, *mut u8, *mut Dword) -> Long;
+
+fn check(function: &str, result: Long) -> Result<(), Error> {
+    match result {
+        0 => Ok(()),
+        code => Err(io::Error::other(format!("{} failed with the PC/SC error {:#010x}", function, code as u32)).into()),
+    }
+}
+
+/// A card in a PC/SC reader, talked to through the PC/SC library of the system, which is loaded at runtime:
+/// `libpcsclite.so.1` on Linux, the `PCSC` framework on macOS and `winscard.dll` on Windows.
+pub struct Pcsc {
+    library: Library,
+    context: Handle,
+    card: Handle,
+    protocol: Dword,
+}
+
+impl Pcsc {
+    /// Connects to the card in the first of the readers that has one.
+    ///
+    /// # Errors
+    /// IO errors when the PC/SC library or service is not there, and when there is no reader with a card in it.
+    pub fn connect() -> Result<Pcsc, Error> {
+        // loading the system library runs nothing but its initializers
+        let library = unsafe { Library::new(ffi::LIBRARY) }.map_err(|e| io::Error::other(e.to_string()))?;
+        let mut pcsc = Pcsc { library, context: 0, card: 0, protocol: 0 };
+        let mut context = 0;
+        // all of the functions are called with the signatures from the PC/SC headers
+        unsafe {
+            let establish = pcsc.library.get::<EstablishContext>(b"SCardEstablishContext\0").map_err(|e| io::Error::other(e.to_string()))?;
+            check("SCardEstablishContext", establish(SCOPE_SYSTEM, ptr::null(), ptr::null(), &mut context))?;
+        }
+        pcsc.context = context;
+
+        let mut readers = Vec::new();
+        unsafe {
+            let list = pcsc.library.get::<ListReaders>(ffi::LIST_READERS).map_err(|e| io::Error::other(e.to_string()))?;
+            let mut len = 0;
+            check("SCardListReaders", list(context, ptr::null(), ptr::null_mut(), &mut len))?;
+            readers.resize(len as usize, 0);
+            check("SCardListReaders", list(context, ptr::null(), readers.as_mut_ptr() as *mut c_char, &mut len))?;
+        }
+        // the names of the readers are separated with zero bytes and end with an empty one
+        let readers = readers.split(|&byte| byte == 0)
+            .filter(|name| !name.is_empty())
+            .map(|name| CString::new(name).expect("the zero bytes are split off"))
+            .collect::<Vec<_>>();
+
+        let mut last_error = Err(io::Error::other("there are no smartcard readers").into());
+        for reader in &readers {
+            let (mut card, mut protocol) = (0, 0);
+            let result = unsafe {
+                let connect = pcsc.library.get::<Connect>(ffi::CONNECT).map_err(|e| io::Error::other(e.to_string()))?;
+                connect(context, reader.as_ptr(), SHARE_SHARED, PROTOCOL_T0 | PROTOCOL_T1, &mut card, &mut protocol)
+            };
+            last_error = check(&format!("SCardConnect to {}", reader.to_string_lossy()), result);
+            if last_error.is_ok() {
+                pcsc.card = card;
+                pcsc.protocol = protocol;
+                return Ok(pcsc);
+            }
+        }
+        last_error.map(|_| pcsc)
+    }
+}
+
+impl Card for Pcsc {
+    fn transmit(&mut self, apdu: &[u8]) -> Result<Vec<u8>, Error> {
+        let request = IoRequest { protocol: self.protocol, length: std::mem::size_of::<IoRequest>() as Dword };
+        // the longest short response and its status bytes
+        let mut response = vec![0; 258];
+        let mut len = response.len() as Dword;
+        unsafe {
+            let transmit = self.library.get::<Transmit>(b"SCardTransmit\0").map_err(|e| io::Error::other(e.to_string()))?;
+            check("SCardTransmit", transmit(self.card, &request, apdu.as_ptr(), apdu.len() as Dword, ptr::null_mut(), response.as_mut_ptr(), &mut len))?;
+        }
+        response.truncate(len as usize);
+        Ok(response)
+    }
+}
+
+impl Drop for Pcsc {
+    fn drop(&mut self) {
+        unsafe {
+            if let Ok(disconnect) = self.library.get::<Disconnect>(b"SCardDisconnect\0") {
+                if self.card != 0 {
+                    disconnect(self.card, LEAVE_CARD);
+                }
+            }
+            if let Ok(release) = self.library.get::<ReleaseContext>(b"SCardReleaseContext\0") {
+                if self.context != 0 {
+                    release(self.context);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::io;
+
+use crate::binary::{pack_bits, unpack_bits, Bits, Layout};
+use crate::wetpaper::SplitMix;
+use crate::Error;
+
+/// Constraint height of the code, the trellis has `2^h` states
+pub const CONSTRAINT_HEIGHT: usize = 7;
+
+/// Seed of the shared submatrix when no other one is given
+pub const DEFAULT_SEED: u64 = 0x5354_4353_5443_5354;
+
+const STATES: usize = 1 << CONSTRAINT_HEIGHT;
+
+/// Carrier bytes in front of the code that hold the payload length in their least significant bits
+pub(crate) const LENGTH_BYTES: usize = 32;
+
+/// Columns of the submatrix, random with the first and the last of the `h` bits always set
+fn submatrix(width: usize, seed: u64) -> Vec<usize> {
+    let mut rng = SplitMix::new(seed ^ width as u64);
+    (0..width)
+        .map(|_| (rng.next_u64() as usize & (STATES - 1)) | 1 | STATES >> 1)
+        .collect()
+}
+
+fn eof() -> Error {
+    io::Error::from(io::ErrorKind::UnexpectedEof).into()
+}
+
+/// The default cost model: changing a byte costs less the more it differs from its neighbours
+/// of the same channel, as noise in the textured regions of an image is much harder to notice
+/// (and to detect statistically) than in the smooth ones.
+///
+/// `width` is the number of pixels in each row of the image.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::stc::texture_costs;
+/// # use steganographer_core::binary::Layout;
+/// // a flat row next to a noisy one
+/// let pixels = [10, 10, 10, 10, 0, 200, 30, 250];
+/// let costs = texture_costs(&pixels, 4, Layout::default());
+///
+/// assert!(costs[1] > costs[5]);
+/// ```
+///
+pub fn texture_costs(pixels: &[u8], width: usize, layout: Layout) -> Vec<f32> {
+    let step = layout.pixel_size();
+    let stride = width * step;
+    (0..pixels.len())
+        .map(|i| {
+            let neighbours = [i.checked_sub(step), i.checked_add(step), i.checked_sub(stride), i.checked_add(stride)];
+            let (sum, count) = neighbours.iter()
+                .filter_map(|&n| n.and_then(|n| pixels.get(n)))
+                .fold((0.0, 0), |(sum, count), &n| (sum + (n as f32 - pixels[i] as f32).abs(), count + 1));
+            1.0 / (0.1 + sum / count.max(1) as f32)
+        })
+        .collect()
+}
+
+/// Turns a cost map, one grayscale value per pixel, into the costs of the carrier bytes:
+/// the darker the pixel, the cheaper it is to change any of its bytes, and the white pixels
+/// are never changed at all.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::stc::map_costs;
+/// # use steganographer_core::binary::Layout;
+/// let costs = map_costs(&[0, 127, 255], 6, Layout::new(2, 1, false));
+///
+/// assert!(costs[0] < costs[2] && costs[0] == costs[1]);
+/// assert!(costs[4].is_infinite());
+/// ```
+///
+pub fn map_costs(map: &[u8], carrier_len: usize, layout: Layout) -> Vec<f32> {
+    let step = layout.pixel_size();
+    (0..carrier_len)
+        .map(|i| match map.get(i / step) {
+            Some(255) | None => f32::INFINITY,
+            Some(&value) => (value as f32 + 1.0) / 256.0,
+        })
+        .collect()
+}
+
+/// Hides a slice of bytes in the least significant bits of the carrier bytes in place with a syndrome-trellis code,
+/// changing the ones that cost less according to the `costs` of each carrier byte.
+///
+/// The payload bits are the syndrome of the carrier bits under a sparse parity check matrix made of a shifted
+/// random submatrix, and the Viterbi algorithm finds the carrier bits with that syndrome that are the cheapest
+/// to get to. Any cost model can be plugged in, and an infinite cost means that the byte is never changed.
+/// The recipient only needs the `seed`, and the payload length is stored in the first few usable bytes as is.
+///
+/// Only one least significant bit of each carrier byte is used, regardless of the bits setting elsewhere.
+///
+/// # Errors
+/// An `UnexpectedEof` IO error when there are not enough carrier bytes, and
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when there are not enough
+/// bytes with a finite cost.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::stc::{hide_stc, reveal_stc, DEFAULT_SEED};
+/// # use steganographer_core::binary::Layout;
+/// let mut carrier = (0..2048).map(|i| (i * 13 % 256) as u8).collect::<Vec<_>>();
+/// let original = carrier.clone();
+/// let costs = vec![1.0; carrier.len()];
+///
+/// hide_stc(b"hello there", &mut carrier, Layout::default(), &costs, DEFAULT_SEED).unwrap();
+///
+/// // 88 bits hidden with a lot less changes than the 44 that the plain LSB replacement makes on average
+/// let changed = carrier.iter().zip(&original).filter(|(a, b)| a != b).count();
+/// assert!(changed < 44);
+/// assert_eq!(reveal_stc(&carrier, Layout::default(), DEFAULT_SEED).unwrap(), b"hello there");
+/// ```
+///
+pub fn hide_stc(payload: &[u8], carrier: &mut [u8], layout: Layout, costs: &[f32], seed: u64) -> Result<(), Error> {
+    assert_eq!(carrier.len(), costs.len(), "every carrier byte must have a cost");
+    let positions = (0..carrier.len()).filter(|&i| layout.is_usable(i)).collect::<Vec<_>>();
+    if positions.len() < LENGTH_BYTES {
+        return Err(eof());
+    }
+    let (head, body) = positions.split_at(LENGTH_BYTES);
+
+    let mut length = head.iter().map(|&i| carrier[i]).collect::<Vec<_>>();
+    pack_bits(&(payload.len() as u32).to_be_bytes(), &mut length, Bits::One);
+    head.iter().zip(&length).for_each(|(&i, &byte)| carrier[i] = byte);
+
+    let message = payload.iter()
+        .flat_map(|byte| (0..8).rev().map(move |shift| (byte >> shift & 1) as usize))
+        .collect::<Vec<_>>();
+    if message.is_empty() {
+        return Ok(());
+    }
+    let width = body.len() / message.len();
+    if width == 0 {
+        return Err(eof());
+    }
+    let columns = submatrix(width, seed);
+
+    // the forward pass of the Viterbi algorithm, remembering which choice led to each state
+    let mut weights = [f32::INFINITY; STATES];
+    weights[0] = 0.0;
+    let mut path = Vec::with_capacity(message.len() * width);
+    for (i, &bit) in message.iter().enumerate() {
+        for (j, &column) in columns.iter().enumerate() {
+            let position = body[i * width + j];
+            let cost = costs[position];
+            let (keep, flip) = if carrier[position] & 1 == 0 { (0.0, cost) } else { (cost, 0.0) };
+            let mut next = [f32::INFINITY; STATES];
+            let mut choices = 0u128;
+            for (state, weight) in next.iter_mut().enumerate() {
+                let zero = weights[state] + keep;
+                let one = weights[state ^ column] + flip;
+                if one < zero {
+                    *weight = one;
+                    choices |= 1 << state;
+                } else {
+                    *weight = zero;
+                }
+            }
+            weights = next;
+            path.push(choices);
+        }
+        let mut shifted = [f32::INFINITY; STATES];
+        for (state, weight) in shifted.iter_mut().take(STATES / 2).enumerate() {
+            *weight = weights[state << 1 | bit];
+        }
+        weights = shifted;
+    }
+
+    let (mut state, best) = weights.iter().enumerate()
+        .min_by(|a, b| a.1.total_cmp(b.1))
+        .expect("there is always at least one state");
+    if best.is_infinite() {
+        return Err(Error::UnsupportedCarrier("too many of the carrier bytes cannot be changed".into()));
+    }
+
+    // and the backward pass, setting the carrier bits along the cheapest path
+    for (i, &bit) in message.iter().enumerate().rev() {
+        state = state << 1 | bit;
+        for (j, &column) in columns.iter().enumerate().rev() {
+            let position = body[i * width + j];
+            let one = (path[i * width + j] >> state & 1) as u8;
+            carrier[position] = carrier[position] & !1 | one;
+            if one == 1 {
+                state ^= column;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reveals a slice of bytes previously hidden by the [`hide_stc`](fn.hide_stc.html) function.
+///
+/// # Errors
+/// An `UnexpectedEof` IO error when the length stored in the carrier is greater than its capacity.
+pub fn reveal_stc(carrier: &[u8], layout: Layout, seed: u64) -> Result<Vec<u8>, Error> {
+    let positions = (0..carrier.len()).filter(|&i| layout.is_usable(i)).collect::<Vec<_>>();
+    if positions.len() < LENGTH_BYTES {
+        return Err(eof());
+    }
+    let (head, body) = positions.split_at(LENGTH_BYTES);
+
+    let mut length = [0; 4];
+    unpack_bits(&head.iter().map(|&i| carrier[i]).collect::<Vec<_>>(), &mut length, Bits::One);
+    let length = u32::from_be_bytes(length) as usize;
+    if length == 0 {
+        return Ok(Vec::new());
+    }
+    if length * 8 > body.len() {
+        return Err(eof());
+    }
+    let width = body.len() / (length * 8);
+    let columns = submatrix(width, seed);
+
+    let mut state = 0;
+    let mut payload = vec![0u8; length];
+    for (i, block) in body.chunks_exact(width).take(length * 8).enumerate() {
+        for (&position, &column) in block.iter().zip(&columns) {
+            if carrier[position] & 1 == 1 {
+                state ^= column;
+            }
+        }
+        payload[i / 8] |= ((state & 1) as u8) << (7 - i % 8);
+        state >>= 1;
+    }
+    Ok(payload)
+}
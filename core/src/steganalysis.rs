@@ -0,0 +1,445 @@
+use crate::binary::{Endianness, Layout};
+
+/// Probability of the chi-square attack above which a region, or the whole image, is considered to have data hidden in it
+pub const CHI_SQUARE_THRESHOLD: f64 = 0.95;
+
+/// Embedding rate estimated by the RS analysis above which a channel is considered to have data hidden in it
+pub const RS_THRESHOLD: f64 = 0.2;
+
+/// Embedding rate estimated by the sample pair analysis above which a channel is considered to have data hidden in it
+pub const SPA_THRESHOLD: f64 = 0.1;
+
+/// Side of the square regions the images are tested in by default
+pub const REGION_SIZE: usize = 64;
+
+/// Which pixels of each group of the RS analysis are flipped, the ones in between the edges of it
+const GROUP_MASK: [i16; 4] = [0, 1, 1, 0];
+
+/// Fewest samples a pair of values needs for its category to count, so that it is not all noise
+const MIN_PAIR_SAMPLES: u32 = 10;
+
+/// Probability that one region of the image has data hidden in it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Region {
+    /// Column of the left pixels of the region
+    pub x: usize,
+    /// Row of the top pixels of the region
+    pub y: usize,
+    /// Width of the region in pixels, less than the region size at the right edge of the image
+    pub width: usize,
+    /// Height of the region in pixels, less than the region size at the bottom edge of the image
+    pub height: usize,
+    /// Probability from 0 to 1
+    pub probability: f64,
+}
+
+/// Share of the least significant bits of one channel of the image that are estimated to carry some data.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ChannelRate {
+    /// Index of the channel in the pixels
+    pub channel: usize,
+    /// Estimated embedding rate from 0 to 1, where 1 is every bit of the channel
+    pub rate: f64,
+}
+
+/// Outcome of testing an image for the data hidden in its least significant bits with each of the detectors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    /// Probability that the image has data hidden in it, from all of its samples at once
+    pub probability: f64,
+    /// The regions of the image row by row, each with its own probability
+    pub regions: Vec<Region>,
+    /// Embedding rate of each usable channel estimated by the [RS analysis](fn.rs.html)
+    pub rs_rates: Vec<ChannelRate>,
+    /// Embedding rate of each usable channel estimated by the [sample pair analysis](fn.spa.html)
+    pub spa_rates: Vec<ChannelRate>,
+}
+
+/// The highest of the rates, 0 when there are none
+fn highest(rates: &[ChannelRate]) -> f64 {
+    rates.iter().map(|channel| channel.rate).fold(0.0, f64::max)
+}
+
+impl Detection {
+    /// The highest probability of the chi-square attack, of the whole image or of any of its regions.
+    ///
+    /// The data hidden sequentially takes over the first regions completely, while it only
+    /// moves the probability of the whole image when it fills a good part of the capacity.
+    pub fn chi_square(&self) -> f64 {
+        self.regions.iter().map(|region| region.probability).fold(self.probability, f64::max)
+    }
+
+    /// The highest embedding rate of any of the channels by the RS analysis.
+    pub fn rs(&self) -> f64 {
+        highest(&self.rs_rates)
+    }
+
+    /// The highest embedding rate of any of the channels by the sample pair analysis.
+    pub fn spa(&self) -> f64 {
+        highest(&self.spa_rates)
+    }
+
+    /// Each of the detectors, the chi-square attack, the RS analysis and the sample pair analysis,
+    /// with its outcome and its threshold
+    fn detectors(&self) -> [(f64, f64); 3] {
+        [(self.chi_square(), CHI_SQUARE_THRESHOLD), (self.rs(), RS_THRESHOLD), (self.spa(), SPA_THRESHOLD)]
+    }
+
+    /// Combined confidence of all the detectors that the image has data hidden in it, from 0 to 1.
+    ///
+    /// It is the mean of the probability of the chi-square attack and of the highest rates of the other two
+    /// scaled so that their thresholds are at one half.
+    pub fn confidence(&self) -> f64 {
+        let [(chi_square, _), (rs, _), (spa, _)] = self.detectors();
+        let scaled = |rate: f64, threshold: f64| (rate / threshold / 2.0).min(1.0);
+        (chi_square + scaled(rs, RS_THRESHOLD) + scaled(spa, SPA_THRESHOLD)) / 3.0
+    }
+
+    /// Number of the detectors that are above their own thresholds.
+    pub fn positives(&self) -> usize {
+        self.detectors().iter().filter(|(outcome, threshold)| outcome > threshold).count()
+    }
+
+    /// Whether at least two of the three detectors are above their own thresholds.
+    ///
+    /// Every one of them is wrong about some images on its own: the chi-square attack about the very noisy
+    /// regions of photos, where the neighbouring values are about as common anyway, and the other two about
+    /// the images that are not natural enough for their models.
+    pub fn is_suspicious(&self) -> bool {
+        self.positives() >= 2
+    }
+}
+
+/// The natural logarithm of the gamma function, by the Lanczos approximation
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.18009172947146, -86.50532032941677, 24.01409824083091,
+        -1.231739572450155, 0.1208650973866179e-2, -0.5395239384953e-5,
+    ];
+    let tmp = x + 5.5 - (x + 0.5) * (x + 5.5).ln();
+    let series = COEFFICIENTS.iter().enumerate().fold(1.000000000190015, |acc, (i, c)| acc + c / (x + 1.0 + i as f64));
+    -tmp + (2.5066282746310005 * series / x).ln()
+}
+
+/// The regularized upper incomplete gamma function Q(a, x), by its series below `a + 1`
+/// and by its continued fraction above
+fn gamma_q(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let front = -x + a * x.ln() - ln_gamma(a);
+    if x < a + 1.0 {
+        let (mut term, mut sum) = (1.0 / a, 1.0 / a);
+        for n in 1..1000 {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * 1e-15 {
+                break;
+            }
+        }
+        return 1.0 - sum * front.exp();
+    }
+    let tiny = 1e-300;
+    let mut b = x + 1.0 - a;
+    let (mut c, mut d) = (1.0 / tiny, 1.0 / b);
+    let mut h = d;
+    for n in 1..1000 {
+        let an = -(n as f64) * (n as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        d = if d.abs() < tiny { tiny } else { d };
+        c = b + an / c;
+        c = if c.abs() < tiny { tiny } else { c };
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    front.exp() * h
+}
+
+/// The chi-square attack of Westfeld and Pfitzmann on the samples, returning the probability
+/// that their least significant bits were replaced with the bits of some data.
+///
+/// Replacing the lowest bits with random-looking ones evens out the counts of the values that only differ
+/// in that bit, such as 50 and 51, which rarely happens by itself. The probability is how well the counts
+/// of each such pair fit their mean by the chi-square test, 0 when there are too few samples to tell.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::steganalysis::chi_square;
+/// // a smooth gradient, where the even values are much more common than the odd ones
+/// let clean = (0..4096u32).map(|i| (i / 32 * 2) as u8).collect::<Vec<_>>();
+/// assert!(chi_square(clean.iter().copied()) < 0.05);
+///
+/// // the same gradient with its lowest bits replaced with some pseudo-random ones
+/// let stego = clean.iter().enumerate().map(|(i, &v)| v | (i * 7919 % 13 % 2) as u8).collect::<Vec<_>>();
+/// assert!(chi_square(stego.iter().copied()) > 0.95);
+/// ```
+pub fn chi_square(samples: impl IntoIterator<Item = u8>) -> f64 {
+    let mut histogram = [0u32; 256];
+    for sample in samples {
+        histogram[sample as usize] += 1;
+    }
+    let (statistic, categories) = histogram.chunks_exact(2)
+        .filter(|pair| pair[0] + pair[1] >= MIN_PAIR_SAMPLES)
+        .fold((0.0, 0), |(statistic, categories), pair| {
+            let expected = f64::from(pair[0] + pair[1]) / 2.0;
+            (statistic + (f64::from(pair[0]) - expected).powi(2) / expected, categories + 1)
+        });
+    if categories < 2 {
+        return 0.0;
+    }
+    gamma_q(f64::from(categories - 1) / 2.0, statistic / 2.0)
+}
+
+/// Width and height of the decoded pixels, a single row without the [width](../binary/struct.Layout.html#method.width)
+fn dimensions(pixels: &[u8], layout: Layout) -> (usize, usize) {
+    let width = layout.pixels_per_row().unwrap_or(pixels.len() / layout.pixel_size()).max(1);
+    (width, pixels.len() / layout.pixel_size() / width)
+}
+
+/// Runs the [chi-square attack](fn.chi_square.html) on the usable samples of the decoded pixels,
+/// returning the probability of the whole image and of each of the square regions of `region_size` pixels.
+pub fn chi_square_image(pixels: &[u8], layout: Layout, region_size: usize) -> (f64, Vec<Region>) {
+    let pixel_size = layout.pixel_size();
+    let (width, height) = dimensions(pixels, layout);
+    let region_size = region_size.max(1);
+    let usable = |pixel: usize| (0..pixel_size)
+        .filter(move |&i| layout.is_usable(i))
+        .map(move |i| pixels[pixel * pixel_size + i]);
+
+    let mut regions = Vec::new();
+    for y in (0..height).step_by(region_size) {
+        for x in (0..width).step_by(region_size) {
+            let (region_width, region_height) = (region_size.min(width - x), region_size.min(height - y));
+            let samples = (y..y + region_height)
+                .flat_map(|row| (x..x + region_width).map(move |column| row * width + column))
+                .flat_map(usable);
+            regions.push(Region { x, y, width: region_width, height: region_height, probability: chi_square(samples) });
+        }
+    }
+    (chi_square((0..width * height).flat_map(usable)), regions)
+}
+
+/// Flips the least significant bit of the value, shifted by one for the negative direction so that
+/// 0 and -1 are swapped instead of 0 and 1
+fn flip(value: i16, direction: i16) -> i16 {
+    match direction {
+        1 => value ^ 1,
+        -1 => ((value + 1) ^ 1) - 1,
+        _ => value,
+    }
+}
+
+/// How noisy the group is, the sum of the differences between the neighbouring values
+fn noise(group: &[i16]) -> i32 {
+    group.windows(2).map(|pair| i32::from((pair[1] - pair[0]).abs())).sum()
+}
+
+/// Differences between the shares of the regular and the singular groups with the mask and with the negated mask,
+/// the groups being regular when flipping makes them noisier and singular when it makes them smoother
+fn regular_minus_singular(groups: &[[i16; 4]]) -> (f64, f64) {
+    let difference = |sign: i16| groups.iter()
+        .map(|group| {
+            let mut flipped = *group;
+            for (value, &mask) in flipped.iter_mut().zip(&GROUP_MASK) {
+                *value = flip(*value, mask * sign);
+            }
+            (noise(&flipped) - noise(group)).signum() as i64
+        })
+        .sum::<i64>() as f64 / groups.len().max(1) as f64;
+    (difference(1), difference(-1))
+}
+
+/// The RS analysis of Fridrich, Goljan and Du on the samples of a single channel given row by row, returning
+/// the estimated share of their least significant bits that were replaced with the bits of some data.
+///
+/// Flipping the lowest bits of groups of neighbouring samples makes natural images noisier about as often
+/// with the shifted flipping as with the plain one, while the replaced bits skew the two apart in a way that
+/// follows the share of them. The estimate is only as good as the image is natural, and it is 0 for the rows
+/// too short to have groups in them.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::steganalysis::rs;
+/// let noise = |i: u32| {
+///     let x = i.wrapping_mul(0x9e37_79b9);
+///     (x ^ x >> 15).wrapping_mul(0x85eb_ca6b) >> 24
+/// };
+/// // smooth waves across a 64 by 64 image, with some noise of their own
+/// let clean = (0..64 * 64u32)
+///     .map(|i| (120.0 + 50.0 * (f64::from(i % 64) / 9.0).sin() + 50.0 * (f64::from(i / 64) / 7.0).cos()) as u8 + (noise(i) % 4) as u8)
+///     .collect::<Vec<_>>();
+/// assert!(rs(&clean, 64) < 0.1);
+///
+/// // the lowest bits of the first half of the samples replaced with some pseudo-random ones
+/// let stego = clean.iter().zip(0..)
+///     .map(|(&v, i)| if i < 32 * 64 { v & !1 | (noise(i ^ 0x5555) & 1) as u8 } else { v })
+///     .collect::<Vec<_>>();
+/// assert!(rs(&stego, 64) > 0.3);
+/// ```
+pub fn rs(samples: &[u8], row_length: usize) -> f64 {
+    let groups = samples.chunks_exact(row_length.max(1))
+        .flat_map(|row| row.chunks_exact(GROUP_MASK.len()))
+        .map(|group| {
+            let mut values = [0; 4];
+            for (value, &sample) in values.iter_mut().zip(group) {
+                *value = i16::from(sample);
+            }
+            values
+        })
+        .collect::<Vec<_>>();
+    if groups.is_empty() {
+        return 0.0;
+    }
+    let inverted = groups.iter()
+        .map(|group| {
+            let mut values = *group;
+            for value in &mut values {
+                *value ^= 1;
+            }
+            values
+        })
+        .collect::<Vec<_>>();
+    let (d0, negative_d0) = regular_minus_singular(&groups);
+    let (d1, negative_d1) = regular_minus_singular(&inverted);
+
+    // the share x of the flipped bits is where the lines and the parabolas of the regular and the singular
+    // groups meet, from 2(d1 + d0)x² + (d-0 - d-1 - d1 - 3d0)x + d0 - d-0 = 0
+    let (a, b, c) = (2.0 * (d1 + d0), negative_d0 - negative_d1 - d1 - 3.0 * d0, d0 - negative_d0);
+    let x = if a.abs() < f64::EPSILON {
+        if b.abs() < f64::EPSILON { 0.0 } else { -c / b }
+    } else {
+        let root = (b * b - 4.0 * a * c).max(0.0).sqrt();
+        let (first, second) = ((-b + root) / (2.0 * a), (-b - root) / (2.0 * a));
+        // only the non-positive roots are rates from 0 to 1, of which the one closer to 0 is the commonly right one
+        match (first <= 0.0, second <= 0.0) {
+            (true, false) => first,
+            (false, true) => second,
+            _ if first.abs() < second.abs() => first,
+            _ => second,
+        }
+    };
+    (x / (x - 0.5)).clamp(0.0, 1.0)
+}
+
+/// The sample pair analysis of Dumitrescu, Wu and Wang on the samples of a single channel given row by row, returning
+/// the estimated share of their least significant bits that were replaced with the bits of some data.
+///
+/// The pairs of horizontally neighbouring samples fall into sets by how their values and lowest bits relate,
+/// sets that are about as large as each other in natural images and that the replaced bits move between at
+/// known rates. The estimate is 0 when there are no pairs with the two values that only differ in the lowest bit,
+/// as with the rows too short to have pairs in them.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::steganalysis::spa;
+/// let noise = |i: u32| {
+///     let x = i.wrapping_mul(0x9e37_79b9);
+///     (x ^ x >> 15).wrapping_mul(0x85eb_ca6b) >> 24
+/// };
+/// // smooth waves across a 64 by 64 image, with some noise of their own
+/// let clean = (0..64 * 64u32)
+///     .map(|i| (120.0 + 50.0 * (f64::from(i % 64) / 9.0).sin() + 50.0 * (f64::from(i / 64) / 7.0).cos()) as u8 + (noise(i) % 4) as u8)
+///     .collect::<Vec<_>>();
+/// assert!(spa(&clean, 64) < 0.1);
+///
+/// // the lowest bits of the first half of the samples replaced with some pseudo-random ones
+/// let stego = clean.iter().zip(0..)
+///     .map(|(&v, i)| if i < 32 * 64 { v & !1 | (noise(i ^ 0x5555) & 1) as u8 } else { v })
+///     .collect::<Vec<_>>();
+/// assert!(spa(&stego, 64) > 0.3);
+/// ```
+pub fn spa(samples: &[u8], row_length: usize) -> f64 {
+    let (mut pairs, mut x, mut y, mut k) = (0.0f64, 0.0, 0.0, 0.0);
+    for pair in samples.chunks_exact(row_length.max(1)).flat_map(|row| row.windows(2)) {
+        let (r, s) = (pair[0], pair[1]);
+        pairs += 1.0;
+        match (s % 2 == 0, r.cmp(&s)) {
+            (true, std::cmp::Ordering::Less) | (false, std::cmp::Ordering::Greater) => x += 1.0,
+            (true, std::cmp::Ordering::Greater) | (false, std::cmp::Ordering::Less) => y += 1.0,
+            _ => {},
+        }
+        if r / 2 == s / 2 {
+            k += 1.0;
+        }
+    }
+    if k == 0.0 {
+        return 0.0;
+    }
+    let (a, b, c) = (2.0 * k, 2.0 * (2.0 * x - pairs), y - x);
+    let root = (b * b - 4.0 * a * c).max(0.0).sqrt();
+    // the smaller root is the share of the flipped bits, which is half of the replaced ones
+    (((-b + root) / (2.0 * a)).min((-b - root) / (2.0 * a)) * 2.0).clamp(0.0, 1.0)
+}
+
+/// Runs the detector on the samples of each usable channel of the decoded pixels on its own,
+/// only looking at the low byte of the wider samples
+fn channel_rates(pixels: &[u8], layout: Layout, detector: fn(&[u8], usize) -> f64) -> Vec<ChannelRate> {
+    let pixel_size = layout.pixel_size();
+    let sample_size = layout.sample_size() as usize;
+    let (width, _) = dimensions(pixels, layout);
+    (0..layout.channels() as usize)
+        .filter_map(|channel| (channel * sample_size..(channel + 1) * sample_size).find(|&i| layout.is_usable(i)))
+        .map(|offset| {
+            let samples = pixels.chunks_exact(pixel_size).map(|pixel| pixel[offset]).collect::<Vec<_>>();
+            ChannelRate { channel: offset / sample_size, rate: detector(&samples, width) }
+        })
+        .collect()
+}
+
+/// Runs all of the detectors on the usable samples of the decoded pixels, the chi-square attack
+/// in the square regions of `region_size` pixels as well.
+///
+/// The layout needs the [width](../binary/struct.Layout.html#method.width) of the image to tell the rows
+/// and the regions apart, without it the pixels are taken as a single row.
+pub fn analyze(pixels: &[u8], layout: Layout, region_size: usize) -> Detection {
+    let (probability, regions) = chi_square_image(pixels, layout, region_size);
+    Detection {
+        probability,
+        regions,
+        rs_rates: channel_rates(pixels, layout, rs),
+        spa_rates: channel_rates(pixels, layout, spa),
+    }
+}
+
+/// One bit plane of one channel of the decoded pixels, amplified to a gray pixel for each of them,
+/// black where the bit is 0 and white where it is 1, like in the visual attack of Westfeld and Pfitzmann.
+///
+/// Bit 0 is the least significant one, the wider samples have as many more planes as they have bits,
+/// and they are expected in the native byte order, as the decoded pixels are. The channels and bits
+/// that are not in the pixels give an empty plane.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::binary::Layout;
+/// # use steganographer_core::steganalysis::bit_plane;
+/// let rgb = Layout::new(3, 1, false);
+/// let pixels = [0b01, 0, 0, 0b10, 0, 0, 0b11, 0, 0];
+/// assert_eq!(bit_plane(&pixels, rgb, 0, 0), [255, 0, 255]);
+/// assert_eq!(bit_plane(&pixels, rgb, 0, 1), [0, 255, 255]);
+/// assert!(bit_plane(&pixels, rgb, 0, 8).is_empty());
+/// ```
+pub fn bit_plane(pixels: &[u8], layout: Layout, channel: usize, bit: u8) -> Vec<u8> {
+    let sample_size = layout.sample_size() as usize;
+    if channel >= layout.channels() as usize || bit as usize >= sample_size * 8 {
+        return Vec::new();
+    }
+    let byte = match Endianness::native() {
+        Endianness::Little => bit as usize / 8,
+        Endianness::Big => sample_size - 1 - bit as usize / 8,
+    };
+    pixels.chunks_exact(layout.pixel_size())
+        .map(|pixel| match pixel[channel * sample_size + byte] >> (bit % 8) & 1 {
+            1 => 255,
+            _ => 0,
+        })
+        .collect()
+}
@@ -0,0 +1,199 @@
+use crate::binary::{Bits, Layout};
+use crate::container::{self, Header, Traversal};
+use crate::{adaptive, stc, wetpaper, Error};
+
+/// A way of hiding the payload in the carrier bytes, so that the rest of the crate
+/// does not have to care which of the embeddings it is using.
+///
+/// The strategy is given the carrier bytes of the whole image along with their layout,
+/// and it is up to it which of the usable bytes it touches and how.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::binary::{Bits, Layout};
+/// # use steganographer_core::strategy::{EmbeddingStrategy, Lsb, Stc};
+/// let original = (0..4096).map(|i| (i * 13 % 256) as u8).collect::<Vec<_>>();
+/// let strategies: [Box<dyn EmbeddingStrategy>; 2] = [Box::new(Lsb::new(Bits::One)), Box::new(Stc::default())];
+///
+/// for strategy in &strategies {
+///     assert!(strategy.capacity(original.len(), Layout::default()) > 11);
+///
+///     let mut carrier = original.clone();
+///     strategy.hide(b"hello there", &mut carrier, Layout::default()).unwrap();
+///     assert_eq!(strategy.reveal(&carrier, Layout::default()).unwrap(), b"hello there");
+/// }
+/// ```
+pub trait EmbeddingStrategy {
+    /// How many bytes of payload `len` carrier bytes in the given layout can hold.
+    ///
+    /// For the strategies that cannot touch some of the bytes this is the upper bound.
+    fn capacity(&self, len: usize, layout: Layout) -> usize;
+
+    /// Hides the payload in the carrier bytes in place.
+    ///
+    /// # Errors
+    /// An `UnexpectedEof` IO error when the payload does not fit.
+    fn hide(&self, payload: &[u8], carrier: &mut [u8], layout: Layout) -> Result<(), Error>;
+
+    /// Reveals the payload previously hidden in the carrier bytes by the same strategy.
+    fn reveal(&self, carrier: &[u8], layout: Layout) -> Result<Vec<u8>, Error>;
+}
+
+/// Replacing the least significant bits, with the payload framed by the [`container`](../container/index.html).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Lsb {
+    bits: Bits,
+    header: Header,
+}
+
+impl Lsb {
+    /// Creates the strategy that replaces the given number of the least significant bits
+    pub fn new(bits: Bits) -> Self {
+        Lsb { bits, header: Header::default() }
+    }
+
+    /// Sets the header the payload is hidden with, the version and the magic are always the current ones.
+    ///
+    /// Revealing reads the header from the carrier, so this only matters for hiding and the capacity,
+    /// other than the key of the [scattered](../container/enum.Traversal.html#variant.Scattered) traversal.
+    pub fn header(self, header: Header) -> Self {
+        Lsb { header, ..self }
+    }
+}
+
+impl EmbeddingStrategy for Lsb {
+    fn capacity(&self, len: usize, layout: Layout) -> usize {
+        container::matrix_capacity(len, self.bits, layout, self.header.matrix)
+    }
+
+    fn hide(&self, payload: &[u8], carrier: &mut [u8], layout: Layout) -> Result<(), Error> {
+        container::hide_with(payload, |_| Ok(Vec::new()), None, carrier, self.bits, layout, &self.header)
+    }
+
+    fn reveal(&self, carrier: &[u8], layout: Layout) -> Result<Vec<u8>, Error> {
+        let scatter = match self.header.traversal {
+            Traversal::Scattered(key) => Some(key),
+            _ => None,
+        };
+        Ok(container::reveal_with(carrier, self.bits, layout, None, scatter)?.1)
+    }
+}
+
+/// Replacing the least significant bits in the [order](../adaptive/fn.order.html) of the texture of the carrier.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Adaptive {
+    bits: Bits,
+}
+
+impl Adaptive {
+    /// Creates the strategy that replaces the given number of the least significant bits
+    pub fn new(bits: Bits) -> Self {
+        Adaptive { bits }
+    }
+}
+
+impl EmbeddingStrategy for Adaptive {
+    fn capacity(&self, len: usize, layout: Layout) -> usize {
+        adaptive::capacity(len, layout, self.bits)
+    }
+
+    fn hide(&self, payload: &[u8], carrier: &mut [u8], layout: Layout) -> Result<(), Error> {
+        adaptive::hide_adaptive(payload, carrier, layout, self.bits)
+    }
+
+    fn reveal(&self, carrier: &[u8], layout: Layout) -> Result<Vec<u8>, Error> {
+        adaptive::reveal_adaptive(carrier, layout, self.bits)
+    }
+}
+
+/// The [syndrome-trellis code](../stc/index.html), which makes the changes where they cost the least.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stc {
+    seed: u64,
+    costs: Option<Vec<f32>>,
+}
+
+impl Default for Stc {
+    fn default() -> Self {
+        Stc::new(stc::DEFAULT_SEED)
+    }
+}
+
+impl Stc {
+    /// Creates the strategy with the seed of the shared pseudo-random code, where changing any byte costs the same
+    pub fn new(seed: u64) -> Self {
+        Stc { seed, costs: None }
+    }
+
+    /// Sets the cost of changing each of the carrier bytes, such as the ones from
+    /// [`texture_costs`](../stc/fn.texture_costs.html), with infinity for the ones that must not be changed.
+    ///
+    /// Revealing does not need the costs, so this only matters for hiding.
+    pub fn costs(self, costs: Vec<f32>) -> Self {
+        Stc { costs: Some(costs), ..self }
+    }
+}
+
+impl EmbeddingStrategy for Stc {
+    fn capacity(&self, len: usize, layout: Layout) -> usize {
+        layout.usable_bytes(len).saturating_sub(stc::LENGTH_BYTES) / 8
+    }
+
+    fn hide(&self, payload: &[u8], carrier: &mut [u8], layout: Layout) -> Result<(), Error> {
+        match &self.costs {
+            Some(costs) => stc::hide_stc(payload, carrier, layout, costs, self.seed),
+            None => stc::hide_stc(payload, carrier, layout, &vec![1.0; carrier.len()], self.seed),
+        }
+    }
+
+    fn reveal(&self, carrier: &[u8], layout: Layout) -> Result<Vec<u8>, Error> {
+        stc::reveal_stc(carrier, layout, self.seed)
+    }
+}
+
+/// The [wet paper code](../wetpaper/index.html), which never touches the wet bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WetPaper {
+    seed: u64,
+    wet: Option<Vec<bool>>,
+}
+
+impl Default for WetPaper {
+    fn default() -> Self {
+        WetPaper::new(wetpaper::DEFAULT_SEED)
+    }
+}
+
+impl WetPaper {
+    /// Creates the strategy with the seed of the shared pseudo-random code, where the
+    /// [saturated](../wetpaper/fn.saturated.html) bytes are wet
+    pub fn new(seed: u64) -> Self {
+        WetPaper { seed, wet: None }
+    }
+
+    /// Sets which of the carrier bytes are wet.
+    ///
+    /// Revealing does not need to know that, so this only matters for hiding.
+    pub fn wet(self, wet: Vec<bool>) -> Self {
+        WetPaper { wet: Some(wet), ..self }
+    }
+}
+
+impl EmbeddingStrategy for WetPaper {
+    fn capacity(&self, len: usize, layout: Layout) -> usize {
+        let blocks = layout.usable_bytes(len) / wetpaper::BLOCK_SIZE;
+        (blocks.saturating_sub(1) * wetpaper::BLOCK_SIZE / 8).saturating_sub(4)
+    }
+
+    fn hide(&self, payload: &[u8], carrier: &mut [u8], layout: Layout) -> Result<(), Error> {
+        match &self.wet {
+            Some(wet) => wetpaper::hide_wet(payload, carrier, layout, wet, self.seed),
+            None => wetpaper::hide_wet(payload, carrier, layout, &wetpaper::saturated(carrier), self.seed),
+        }
+    }
+
+    fn reveal(&self, carrier: &[u8], layout: Layout) -> Result<Vec<u8>, Error> {
+        wetpaper::reveal_wet(carrier, layout, self.seed)
+    }
+}
@@ -0,0 +1,263 @@
+use std::fmt::{self, Display, Formatter};
+use std::io::Cursor;
+
+use image::{ColorType, DynamicImage, GenericImageView, ImageBuffer, ImageOutputFormat};
+use image::codecs::jpeg::JpegDecoder;
+use image::codecs::png::PngDecoder;
+use image::imageops::FilterType;
+
+use crate::binary::{Bits, Layout};
+use crate::container::{hide_payload, image_layout, payload_capacity, reveal_unchecked, Header};
+use crate::quality::psnr;
+use crate::resync::{hide_resync, reveal_resync, DEFAULT_SEGMENT_SIZE, SEGMENT_HEADER};
+use crate::Error;
+
+/// A transformation that a stego image may go through on its way to the recipient.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Attack {
+    /// Saving the image as a PNG again
+    Reencode,
+    /// Removing this many rows from the top
+    CropRows(u32),
+    /// Removing this percentage of the width and height from the top left corner
+    Crop(u32),
+    /// Scaling the image to this percentage of its size
+    Resize(u32),
+    /// Adding this value to every color channel
+    Brightness(i32),
+    /// Saving the image as a JPEG with this quality
+    Jpeg(u8),
+}
+
+impl Display for Attack {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Attack::Reencode => write!(f, "PNG re-encode"),
+            Attack::CropRows(rows) => write!(f, "crop {} rows", rows),
+            Attack::Crop(percent) => write!(f, "crop {}%", percent),
+            Attack::Resize(percent) => write!(f, "resize to {}%", percent),
+            Attack::Brightness(value) => write!(f, "brightness {:+}", value),
+            Attack::Jpeg(quality) => write!(f, "JPEG quality {}", quality),
+        }
+    }
+}
+
+/// Converts the image back to the color type it had before some transformation
+fn restore_color(image: DynamicImage, color_type: ColorType) -> DynamicImage {
+    match color_type {
+        ColorType::L8 => DynamicImage::ImageLuma8(image.to_luma8()),
+        ColorType::La8 => DynamicImage::ImageLumaA8(image.to_luma_alpha8()),
+        ColorType::Rgba8 => DynamicImage::ImageRgba8(image.to_rgba8()),
+        _ => DynamicImage::ImageRgb8(image.to_rgb8()),
+    }
+}
+
+impl Attack {
+    /// The default set of transformations, from the mildest to the harshest
+    pub fn battery() -> Vec<Attack> {
+        vec![
+            Attack::Reencode,
+            Attack::CropRows(1),
+            Attack::Crop(10),
+            Attack::Brightness(1),
+            Attack::Brightness(-8),
+            Attack::Resize(200),
+            Attack::Resize(50),
+            Attack::Jpeg(100),
+            Attack::Jpeg(90),
+            Attack::Jpeg(75),
+            Attack::Jpeg(50),
+        ]
+    }
+
+    /// Applies the transformation to the image
+    pub fn apply(&self, image: &DynamicImage) -> Result<DynamicImage, Error> {
+        let (width, height) = (image.width(), image.height());
+        Ok(match *self {
+            Attack::Reencode => {
+                let mut png = Vec::new();
+                image.write_to(&mut png, ImageOutputFormat::Png)?;
+                DynamicImage::from_decoder(PngDecoder::new(Cursor::new(png))?)?
+            },
+            Attack::CropRows(rows) => image.crop_imm(0, rows.min(height), width, height.saturating_sub(rows)),
+            Attack::Crop(percent) => {
+                let (x, y) = (width * percent / 100, height * percent / 100);
+                image.crop_imm(x, y, width - x, height - y)
+            },
+            Attack::Resize(percent) => image.resize_exact(
+                (width * percent / 100).max(1),
+                (height * percent / 100).max(1),
+                FilterType::Triangle,
+            ),
+            Attack::Brightness(value) => image.brighten(value),
+            Attack::Jpeg(quality) => {
+                let mut jpeg = Vec::new();
+                DynamicImage::ImageRgb8(image.to_rgb8()).write_to(&mut jpeg, ImageOutputFormat::Jpeg(quality))?;
+                let decoded = DynamicImage::from_decoder(JpegDecoder::new(Cursor::new(jpeg))?)?;
+                restore_color(decoded, image.color())
+            },
+        })
+    }
+}
+
+/// What happened to the payload after an [attack](enum.Attack.html).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// The payload was extracted without a single changed byte
+    Intact,
+    /// The payload of the same length was extracted, but only this fraction of bytes is correct
+    Damaged(f64),
+    /// The payload could not be extracted at all
+    Lost(String),
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Outcome::Intact => write!(f, "intact"),
+            Outcome::Damaged(correct) => write!(f, "damaged, {:.1}% of bytes correct", correct * 100.0),
+            Outcome::Lost(reason) => write!(f, "lost ({})", reason),
+        }
+    }
+}
+
+/// Applies each of the attacks to the stego image and checks what the `extract` function
+/// is able to recover from the result, compared to what it recovers from the untouched image.
+///
+/// # Errors
+/// Errors of the `extract` function on the untouched image and errors of the attacks themselves.
+pub fn stress(image: &DynamicImage, attacks: &[Attack], extract: impl Fn(&[u8]) -> Result<Vec<u8>, Error>) -> Result<Vec<(Attack, Outcome)>, Error> {
+    let expected = extract(image.as_bytes())?;
+
+    attacks.iter()
+        .map(|attack| {
+            let attacked = attack.apply(image)?;
+            let outcome = match extract(attacked.as_bytes()) {
+                Ok(payload) if payload == expected => Outcome::Intact,
+                Ok(payload) if payload.len() == expected.len() => {
+                    let correct = payload.iter().zip(&expected).filter(|(a, b)| a == b).count();
+                    Outcome::Damaged(correct as f64 / expected.len() as f64)
+                },
+                Ok(payload) => Outcome::Lost(format!("got {} bytes instead of {}", payload.len(), expected.len())),
+                Err(e) => Outcome::Lost(e.to_string()),
+            };
+            Ok((*attack, outcome))
+        })
+        .collect()
+}
+
+/// A way of laying out the hidden data that can be compared in the [`survey`](fn.survey.html).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Method {
+    /// [Container](../container/index.html) with payload bits stored sequentially
+    Sequential,
+    /// [Container](../container/index.html) with payload bits interleaved at this depth
+    Interleaved(u16),
+    /// [Resync](../resync/index.html) segments with the default size
+    Resync,
+}
+
+impl Display for Method {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Method::Sequential => write!(f, "sequential"),
+            Method::Interleaved(depth) => write!(f, "interleave {}", depth),
+            Method::Resync => write!(f, "resync"),
+        }
+    }
+}
+
+impl Method {
+    /// All of the methods worth comparing
+    pub fn all() -> Vec<Method> {
+        vec![Method::Sequential, Method::Interleaved(64), Method::Resync]
+    }
+
+    /// Returns how many bytes of payload can be hidden in a carrier of `carrier_len` bytes
+    pub fn capacity(&self, carrier_len: usize, bits: Bits, layout: Layout) -> usize {
+        let hidden = layout.capacity(carrier_len, bits);
+        match self {
+            Method::Sequential | Method::Interleaved(_) => payload_capacity(carrier_len, bits, layout),
+            Method::Resync => {
+                let segment = DEFAULT_SEGMENT_SIZE as usize;
+                let full = hidden / (segment + SEGMENT_HEADER);
+                let rest = (hidden % (segment + SEGMENT_HEADER)).saturating_sub(SEGMENT_HEADER);
+                full * segment + rest
+            },
+        }
+    }
+
+    /// Hides the payload in the carrier bytes in place
+    pub fn hide(&self, payload: &[u8], carrier: &mut [u8], bits: Bits, layout: Layout) -> Result<(), Error> {
+        match *self {
+            Method::Sequential => hide_payload(payload, carrier, bits, layout, &Header::default()),
+            Method::Interleaved(interleave) => hide_payload(payload, carrier, bits, layout, &Header { interleave, ..Header::default() }),
+            Method::Resync => hide_resync(payload, carrier, bits, layout, DEFAULT_SEGMENT_SIZE),
+        }
+    }
+
+    /// Reveals the payload hidden by [`hide`](#method.hide)
+    pub fn reveal(&self, carrier: &[u8], bits: Bits, layout: Layout) -> Result<Vec<u8>, Error> {
+        match self {
+            // the damaged payloads are still compared with the original one byte by byte
+            Method::Sequential | Method::Interleaved(_) => Ok(reveal_unchecked(carrier, bits, layout)?.1),
+            Method::Resync => Ok(reveal_resync(carrier, bits, layout)?.payload),
+        }
+    }
+}
+
+/// One row of the [`survey`](fn.survey.html) matrix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurveyRow {
+    /// Method used to hide the payload
+    pub method: Method,
+    /// Bits setting used to hide the payload
+    pub bits: Bits,
+    /// How many bytes of payload the carrier could hold with these settings
+    pub capacity: usize,
+    /// PSNR of the stego image against the original one, `None` when the payload did not fit
+    pub psnr: Option<f64>,
+    /// Attacks after which the payload was still intact
+    pub survived: Vec<Attack>,
+}
+
+/// Replaces the pixels of the image keeping its dimensions and color type
+fn with_pixels(image: &DynamicImage, pixels: Vec<u8>) -> DynamicImage {
+    let (width, height) = (image.width(), image.height());
+    match image.color() {
+        ColorType::L8 => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8),
+        ColorType::La8 => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLumaA8),
+        ColorType::Rgba8 => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgba8),
+        _ => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8),
+    }.expect("pixel buffer has the same size as the image it came from")
+}
+
+/// Hides the same payload in the cover image with every method and bits setting,
+/// measuring capacity, distortion and which of the attacks each combination survives.
+///
+/// # Errors
+/// Only errors of the attacks themselves, combinations that cannot hold the payload are reported
+/// with no PSNR and no survived attacks.
+pub fn survey(cover: &DynamicImage, payload: &[u8], attacks: &[Attack]) -> Result<Vec<SurveyRow>, Error> {
+    let layout = image_layout(cover.color()).width(cover.width() as usize);
+    let mut rows = Vec::new();
+    for method in Method::all() {
+        for &bits in &[Bits::One, Bits::Two, Bits::Four] {
+            let capacity = method.capacity(cover.as_bytes().len(), bits, layout);
+            let mut pixels = cover.as_bytes().to_vec();
+            if method.hide(payload, &mut pixels, bits, layout).is_err() {
+                rows.push(SurveyRow { method, bits, capacity, psnr: None, survived: vec![] });
+                continue;
+            }
+            let psnr = psnr(cover.as_bytes(), &pixels);
+            let stego = with_pixels(cover, pixels);
+            let survived = stress(&stego, attacks, |pixels| method.reveal(pixels, bits, layout))?
+                .into_iter()
+                .filter(|(_, outcome)| *outcome == Outcome::Intact)
+                .map(|(attack, _)| attack)
+                .collect();
+            rows.push(SurveyRow { method, bits, capacity, psnr: Some(psnr), survived });
+        }
+    }
+    Ok(rows)
+}
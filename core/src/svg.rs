@@ -0,0 +1,254 @@
+use std::convert::TryFrom;
+use std::ops::Range;
+
+use crate::Error;
+
+/// How many bits of hidden data are stored in the last decimal digit of each coordinate
+const BITS_PER_NUMBER: usize = 3;
+
+/// Attributes whose numeric values are used to store hidden data
+const GEOMETRY_ATTRIBUTES: &[&str] = &["d", "points", "x", "y", "x1", "y1", "x2", "y2", "cx", "cy"];
+
+fn skip_until(svg: &str, from: usize, pattern: &str) -> usize {
+    svg[from..].find(pattern).map_or(svg.len(), |i| from + i + pattern.len())
+}
+
+/// Finds the value spans of all the geometry attributes of all the tags in the document
+fn geometry_values(svg: &str) -> Vec<(bool, Range<usize>)> {
+    let bytes = svg.as_bytes();
+    let mut values = Vec::new();
+    let mut pos = 0;
+
+    while let Some(i) = svg[pos..].find('<') {
+        pos += i;
+        let rest = &svg[pos..];
+        if rest.starts_with("<!--") {
+            pos = skip_until(svg, pos, "-->");
+            continue;
+        }
+        if rest.starts_with("<![CDATA[") {
+            pos = skip_until(svg, pos, "]]>");
+            continue;
+        }
+        if rest.starts_with("<?") || rest.starts_with("<!") {
+            pos = skip_until(svg, pos, ">");
+            continue;
+        }
+        pos += 1;
+        // skip the tag name
+        while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() && bytes[pos] != b'>' {
+            pos += 1;
+        }
+        // attributes until the end of the tag
+        loop {
+            while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if pos >= bytes.len() || bytes[pos] == b'>' || bytes[pos] == b'/' {
+                break;
+            }
+            let name_start = pos;
+            while pos < bytes.len() && !matches!(bytes[pos], b'=' | b'>' | b'/') && !bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            let name = &svg[name_start..pos];
+            while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if pos >= bytes.len() || bytes[pos] != b'=' {
+                continue;
+            }
+            pos += 1;
+            while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if pos >= bytes.len() || !matches!(bytes[pos], b'"' | b'\'') {
+                continue;
+            }
+            let quote = bytes[pos] as char;
+            let value_start = pos + 1;
+            let value_end = svg[value_start..].find(quote).map_or(svg.len(), |i| value_start + i);
+            if GEOMETRY_ATTRIBUTES.contains(&name) {
+                values.push((name == "d", value_start..value_end));
+            }
+            pos = (value_end + 1).min(svg.len());
+        }
+        pos = skip_until(svg, pos, ">");
+    }
+    values
+}
+
+/// Finds spans of all the numbers in the value that are safe to alter.
+///
+/// Numbers with exponents are left alone, as well as the flags of the elliptical arc path
+/// commands, which can be written without any separators between them.
+fn carrier_numbers(value: &str, is_path: bool) -> Vec<Range<usize>> {
+    let bytes = value.as_bytes();
+    let mut numbers = Vec::new();
+    let mut pos = 0;
+    let mut arc_param = None;
+
+    while pos < bytes.len() {
+        let c = bytes[pos];
+        if is_path && c.is_ascii_alphabetic() && c != b'e' && c != b'E' {
+            arc_param = if c == b'a' || c == b'A' { Some(0) } else { None };
+            pos += 1;
+            continue;
+        }
+        if !(c.is_ascii_digit() || matches!(c, b'-' | b'+' | b'.')) {
+            pos += 1;
+            continue;
+        }
+        if let Some(param) = arc_param.as_mut() {
+            let is_flag = *param % 7 == 3 || *param % 7 == 4;
+            *param += 1;
+            if is_flag {
+                pos += 1;
+                continue;
+            }
+        }
+        let start = pos;
+        if matches!(bytes[pos], b'-' | b'+') {
+            pos += 1;
+        }
+        let mut seen_dot = false;
+        while pos < bytes.len() && (bytes[pos].is_ascii_digit() || bytes[pos] == b'.' && !seen_dot) {
+            seen_dot |= bytes[pos] == b'.';
+            pos += 1;
+        }
+        let mut has_exponent = false;
+        if pos < bytes.len() && matches!(bytes[pos], b'e' | b'E') {
+            has_exponent = true;
+            pos += 1;
+            if pos < bytes.len() && matches!(bytes[pos], b'-' | b'+') {
+                pos += 1;
+            }
+            while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+                pos += 1;
+            }
+        }
+        if !has_exponent && value[start..pos].bytes().any(|b| b.is_ascii_digit()) {
+            numbers.push(start..pos);
+        }
+    }
+    numbers
+}
+
+/// All the carrier numbers of the document in order, as absolute spans
+fn carriers(svg: &str) -> Vec<Range<usize>> {
+    geometry_values(svg).into_iter()
+        .flat_map(|(is_path, value)| carrier_numbers(&svg[value.clone()], is_path).into_iter()
+            .map(move |n| value.start + n.start..value.start + n.end))
+        .collect()
+}
+
+/// Returns how many bytes of payload can be hidden in the given SVG document.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::svg::svg_capacity;
+/// let svg = r#"<svg><polyline points="0,0 10,10 20,5 30,15 40,0 50,10 60,5 70,15 80,0 90,10 100,5"/></svg>"#;
+/// // 22 coordinates, 3 bits each, minus the length
+/// assert_eq!(svg_capacity(svg), 4);
+/// ```
+pub fn svg_capacity(svg: &str) -> usize {
+    (carriers(svg).len() * BITS_PER_NUMBER / 8).saturating_sub(4)
+}
+
+/// Hides a slice of bytes along with its length in the coordinates of an SVG document.
+///
+/// Every number in the geometry attributes (`d`, `points`, `x`, `y` and so on) is rounded
+/// to `precision` decimal places and gets one more decimal digit appended, which stores
+/// 3 bits of the data. With a reasonable precision this is visually imperceptible.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when there are not enough
+/// coordinates in the document.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::svg::{hide_in_svg, reveal_from_svg};
+/// let svg = r#"<svg><path d="M0 0L10 10 20 5 30 15 40 0 50 10 60 5 70 15 80 0 90 10 100 5 110 3.5z"/></svg>"#;
+/// let cloaked = hide_in_svg(svg, b"hi", 2).unwrap();
+///
+/// assert!(cloaked.starts_with(r#"<svg><path d="M0.000 0.000L10.000 10.000 20.000"#));
+/// assert_eq!(reveal_from_svg(&cloaked).unwrap(), b"hi");
+/// ```
+///
+pub fn hide_in_svg(svg: &str, payload: &[u8], precision: usize) -> Result<String, Error> {
+    let numbers = carriers(svg);
+
+    let length = u32::try_from(payload.len()).map_err(|_| no_room())?.to_be_bytes();
+    let mut bits = length.iter().chain(payload)
+        .flat_map(|byte| (0..8).rev().map(move |shift| byte >> shift & 1));
+
+    let needed = (4 + payload.len()) * 8;
+    if numbers.len() * BITS_PER_NUMBER < needed {
+        return Err(no_room());
+    }
+
+    let mut result = String::with_capacity(svg.len() + numbers.len() * (precision + 2));
+    let mut last = 0;
+    for number in numbers {
+        let digit = (0..BITS_PER_NUMBER).fold(0, |acc, _| acc << 1 | bits.next().unwrap_or(0));
+        let value: f64 = svg[number.clone()].parse()
+            .map_err(|_| Error::UnsupportedCarrier("malformed SVG number".into()))?;
+
+        result.push_str(&svg[last..number.start]);
+        // numbers may be separated by just a dot, and we always write the leading zero
+        if result.ends_with(|c: char| c.is_ascii_digit() || c == '.') {
+            result.push(' ');
+        }
+        result.push_str(&format!("{:.*}", precision, value));
+        if precision == 0 {
+            result.push('.');
+        }
+        result.push((b'0' + digit) as char);
+        last = number.end;
+    }
+    result.push_str(&svg[last..]);
+    Ok(result)
+}
+
+fn no_room() -> Error {
+    Error::UnsupportedCarrier("not enough coordinates in the SVG document for the payload".into())
+}
+
+/// Reveals a slice of bytes previously hidden by the [`hide_in_svg`](fn.hide_in_svg.html) function.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when the coordinates
+/// do not look like they contain hidden data, or an `UnexpectedEof` IO error when the document
+/// was truncated.
+pub fn reveal_from_svg(svg: &str) -> Result<Vec<u8>, Error> {
+    let mut bits = Vec::new();
+    for number in carriers(svg) {
+        let text = &svg[number];
+        let digit = match text.rsplit_once('.') {
+            Some((_, fraction)) if !fraction.is_empty() => fraction.as_bytes()[fraction.len() - 1] - b'0',
+            _ => return Err(Error::UnsupportedCarrier("SVG coordinates contain no hidden data".into())),
+        };
+        if digit >= 1 << BITS_PER_NUMBER {
+            return Err(Error::UnsupportedCarrier("SVG coordinates contain no hidden data".into()));
+        }
+        bits.extend((0..BITS_PER_NUMBER).rev().map(|shift| digit >> shift & 1));
+    }
+
+    let mut bytes = bits.chunks_exact(8).map(|byte| byte.iter().fold(0, |acc, bit| acc << 1 | bit));
+    let mut length = [0; 4];
+    for b in &mut length {
+        *b = bytes.next().ok_or_else(eof)?;
+    }
+    let length = u32::from_be_bytes(length) as usize;
+    let result = bytes.take(length).collect::<Vec<_>>();
+    if result.len() < length {
+        return Err(eof());
+    }
+    Ok(result)
+}
+
+fn eof() -> Error {
+    std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into()
+}
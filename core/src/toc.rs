@@ -0,0 +1,246 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use rand_core::{CryptoRng, OsRng, RngCore};
+use zeroize::Zeroizing;
+
+use crate::Error;
+
+/// Size of the length of the table of contents that ends the archive
+const LENGTH_SIZE: usize = 4;
+
+/// One of the payloads listed in the table of contents of an [`Archive`](struct.Archive.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Name of the entry, unique within the archive and at most 255 bytes long
+    pub name: String,
+    /// [Flags](../container/struct.Header.html#structfield.flags) of the layers this entry is sealed in,
+    /// as every entry is sealed on its own
+    pub flags: u8,
+    /// When the entry was hidden, in seconds since the Unix epoch, or 0 when that is not known
+    pub timestamp: u64,
+    /// Where the bytes of the entry start in the archive
+    pub offset: usize,
+    /// Size of the entry in bytes
+    pub len: usize,
+}
+
+impl Entry {
+    /// Size of the entry in the table of contents
+    fn record_len(&self) -> usize {
+        1 + self.name.len() + 1 + 8 + 8 + 8
+    }
+
+    fn write(&self, mut to: impl Write) -> io::Result<()> {
+        to.write_u8(self.name.len() as u8)?;
+        to.write_all(self.name.as_bytes())?;
+        to.write_u8(self.flags)?;
+        to.write_u64::<BigEndian>(self.timestamp)?;
+        to.write_u64::<BigEndian>(self.offset as u64)?;
+        to.write_u64::<BigEndian>(self.len as u64)
+    }
+
+    fn read(mut from: impl Read) -> Result<Self, Error> {
+        let mut name = vec![0; from.read_u8()? as usize];
+        from.read_exact(&mut name)?;
+        let name = String::from_utf8(name).map_err(|_| Error::UnsupportedCarrier("the name of an entry is not UTF-8".into()))?;
+        Ok(Entry {
+            name,
+            flags: from.read_u8()?,
+            timestamp: from.read_u64::<BigEndian>()?,
+            offset: from.read_u64::<BigEndian>()? as usize,
+            len: from.read_u64::<BigEndian>()? as usize,
+        })
+    }
+}
+
+/// Several named payloads hidden as one, the bytes of the entries followed by the table of contents
+/// and its length.
+///
+/// The table is at the end, so adding an entry never moves the bytes of the ones that are already there,
+/// and the first entry is the default one, which is decoded when no name is given.
+///
+/// The bytes are wiped from the memory once the archive is dropped, as the entries that are not sealed
+/// in any layers are the plaintext.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::toc::Archive;
+/// let mut archive = Archive::new();
+/// archive.push("notes", 0, 0, b"the plans").unwrap();
+/// archive.push("keys", 0, 0, b"hunter2").unwrap();
+/// assert!(archive.push("notes", 0, 0, b"again").is_err());
+///
+/// let archive = Archive::from_bytes(&archive.to_bytes()).unwrap();
+/// assert_eq!(archive.entries().len(), 2);
+/// assert_eq!(archive.get(Some("keys")).unwrap().1, b"hunter2");
+/// assert_eq!(archive.get(None).unwrap().1, b"the plans");
+/// assert!(archive.get(Some("photos")).is_none());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Archive {
+    entries: Vec<Entry>,
+    data: Zeroizing<Vec<u8>>,
+}
+
+impl Archive {
+    /// Creates an archive without any entries
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the archive from its bytes, as written by [`to_bytes`](#method.to_bytes).
+    ///
+    /// # Errors
+    /// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when the bytes are not an archive.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let malformed = || Error::UnsupportedCarrier("the table of contents of the entries is malformed".into());
+        let end = bytes.len().checked_sub(LENGTH_SIZE).ok_or_else(malformed)?;
+        let start = end.checked_sub((&bytes[end..]).read_u32::<BigEndian>()? as usize).ok_or_else(malformed)?;
+        let mut toc = &bytes[start..end];
+        let mut entries = Vec::new();
+        while !toc.is_empty() {
+            let entry = Entry::read(&mut toc).map_err(|_| malformed())?;
+            if entry.offset.checked_add(entry.len).is_none_or(|end| end > start) {
+                return Err(malformed());
+            }
+            entries.push(entry);
+        }
+        Ok(Archive { entries, data: Zeroizing::new(bytes[..start].to_vec()) })
+    }
+
+    /// Writes the bytes of the entries followed by the table of contents
+    pub fn to_bytes(&self) -> Zeroizing<Vec<u8>> {
+        let mut toc = Vec::new();
+        for entry in &self.entries {
+            entry.write(&mut toc).expect("writing to a vector never fails");
+        }
+        let mut bytes = Zeroizing::new(Vec::with_capacity(self.data.len() + toc.len() + LENGTH_SIZE));
+        bytes.extend_from_slice(&self.data);
+        bytes.extend_from_slice(&toc);
+        bytes.extend_from_slice(&(toc.len() as u32).to_be_bytes());
+        bytes
+    }
+
+    /// The entries in the order they were added in
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Returns the entry with the given name along with its bytes, or the first one when there is no name.
+    pub fn get(&self, name: Option<&str>) -> Option<(&Entry, &[u8])> {
+        let entry = match name {
+            Some(name) => self.entries.iter().find(|entry| entry.name == name),
+            None => self.entries.first(),
+        }?;
+        Some((entry, &self.data[entry.offset..entry.offset + entry.len]))
+    }
+
+    /// Adds the entry after all of the other ones.
+    ///
+    /// # Errors
+    /// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when there already is an entry
+    /// with the same name or when the name is longer than 255 bytes.
+    pub fn push(&mut self, name: &str, flags: u8, timestamp: u64, payload: &[u8]) -> Result<(), Error> {
+        if name.len() > u8::MAX as usize {
+            return Err(Error::UnsupportedCarrier(format!("the name of the entry is longer than {} bytes", u8::MAX)));
+        }
+        if self.entries.iter().any(|entry| entry.name == name) {
+            return Err(Error::UnsupportedCarrier(format!("there already is an entry named {:?}", name)));
+        }
+        self.entries.push(Entry { name: name.into(), flags, timestamp, offset: self.data.len(), len: payload.len() });
+        self.data.extend_from_slice(payload);
+        Ok(())
+    }
+
+    /// Removes the entry with the given name, overwriting its bytes with random ones, and returns it.
+    ///
+    /// The archive does not get any shorter, as the place the entry took in the table of contents
+    /// is filled with random bytes too, so none of the bytes of the table are left after its new end.
+    ///
+    /// # Errors
+    /// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when there is no entry with the name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::toc::Archive;
+    /// let mut archive = Archive::new();
+    /// archive.push("notes", 0, 0, b"the plans").unwrap();
+    /// archive.push("keys", 0, 0, b"hunter2").unwrap();
+    /// let len = archive.to_bytes().len();
+    ///
+    /// assert_eq!(archive.remove("notes").unwrap().len, 9);
+    /// assert_eq!(archive.to_bytes().len(), len);
+    /// assert!(!archive.to_bytes().windows(9).any(|window| window == b"the plans"));
+    /// assert_eq!(archive.get(None).unwrap().1, b"hunter2");
+    /// ```
+    pub fn remove(&mut self, name: &str) -> Result<Entry, Error> {
+        self.remove_with_rng(name, &mut OsRng)
+    }
+
+    pub(crate) fn remove_with_rng(&mut self, name: &str, rng: &mut (impl RngCore + CryptoRng)) -> Result<Entry, Error> {
+        let entry = self.entries.remove(self.position(name)?);
+        rng.fill_bytes(&mut self.data[entry.offset..entry.offset + entry.len]);
+        let start = self.data.len();
+        self.data.resize(start + entry.record_len(), 0);
+        rng.fill_bytes(&mut self.data[start..]);
+        Ok(entry)
+    }
+
+    /// Swaps the bytes of the entry with the given name for the payload, keeping its place in the table of contents.
+    ///
+    /// The payload takes the place of the old bytes when it fits there, with the rest of them overwritten
+    /// with random ones, otherwise the old bytes are all overwritten and the payload is put after the other entries.
+    ///
+    /// # Errors
+    /// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when there is no entry with the name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use steganographer_core::toc::Archive;
+    /// let mut archive = Archive::new();
+    /// archive.push("notes", 0, 0, b"the plans").unwrap();
+    /// archive.push("keys", 0, 0, b"hunter2").unwrap();
+    /// let len = archive.to_bytes().len();
+    ///
+    /// archive.replace("notes", 0, 0, b"new plan").unwrap();
+    /// assert_eq!(archive.to_bytes().len(), len);
+    /// archive.replace("keys", 0, 0, b"correct horse battery staple").unwrap();
+    /// assert!(archive.to_bytes().len() > len);
+    ///
+    /// assert_eq!(archive.get(None).unwrap().1, b"new plan");
+    /// assert_eq!(archive.get(Some("keys")).unwrap().1, b"correct horse battery staple");
+    /// ```
+    pub fn replace(&mut self, name: &str, flags: u8, timestamp: u64, payload: &[u8]) -> Result<(), Error> {
+        self.replace_with_rng(name, flags, timestamp, payload, &mut OsRng)
+    }
+
+    pub(crate) fn replace_with_rng(&mut self, name: &str, flags: u8, timestamp: u64, payload: &[u8],
+                                   rng: &mut (impl RngCore + CryptoRng)) -> Result<(), Error> {
+        let index = self.position(name)?;
+        let Entry { offset, len, .. } = self.entries[index];
+        let offset = match payload.len() <= len {
+            true => {
+                self.data[offset..offset + payload.len()].copy_from_slice(payload);
+                rng.fill_bytes(&mut self.data[offset + payload.len()..offset + len]);
+                offset
+            },
+            false => {
+                rng.fill_bytes(&mut self.data[offset..offset + len]);
+                self.data.extend_from_slice(payload);
+                self.data.len() - payload.len()
+            },
+        };
+        self.entries[index] = Entry { name: name.into(), flags, timestamp, offset, len: payload.len() };
+        Ok(())
+    }
+
+    fn position(&self, name: &str) -> Result<usize, Error> {
+        self.entries.iter()
+            .position(|entry| entry.name == name)
+            .ok_or_else(|| Error::UnsupportedCarrier(format!("there is no entry named {:?}", name)))
+    }
+}
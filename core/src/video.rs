@@ -0,0 +1,202 @@
+use std::convert::TryFrom;
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::Error;
+
+/// Bytes that start the hidden data inside of the box or element that holds it
+const MAGIC: &[u8] = b"steganographer\0";
+/// First bytes of every Matroska and WebM file
+const EBML_MAGIC: &[u8] = &[0x1A, 0x45, 0xDF, 0xA3];
+/// ID of the Matroska element that is ignored by the players wherever it is
+const EBML_VOID: u8 = 0xEC;
+
+fn malformed(what: &str) -> Error {
+    Error::UnsupportedCarrier(format!("malformed MP4 file: {}", what))
+}
+
+/// A top-level box of an MP4 file
+struct Mp4Box {
+    start: usize,
+    header: usize,
+    end: usize,
+    kind: [u8; 4],
+    /// The box has the size of 0, extending to the end of the file
+    open_ended: bool,
+}
+
+impl Mp4Box {
+    fn is_hidden_data(&self, mp4: &[u8]) -> bool {
+        &self.kind == b"free" && mp4[self.start + self.header..self.end].starts_with(MAGIC)
+    }
+}
+
+fn mp4_boxes(mp4: &[u8]) -> Result<Vec<Mp4Box>, Error> {
+    let mut boxes = Vec::new();
+    let mut start = 0;
+    while start < mp4.len() {
+        let header = mp4.get(start..start + 8).ok_or_else(|| malformed("box header is truncated"))?;
+        let kind = <[u8; 4]>::try_from(&header[4..]).unwrap();
+        let (size, header, open_ended) = match BigEndian::read_u32(header) {
+            0 => (mp4.len() - start, 8, true),
+            1 => {
+                let size = mp4.get(start + 8..start + 16).ok_or_else(|| malformed("box header is truncated"))?;
+                (usize::try_from(BigEndian::read_u64(size)).map_err(|_| malformed("box is too large"))?, 16, false)
+            },
+            size => (size as usize, 8, false),
+        };
+        if size < header || start + size > mp4.len() {
+            return Err(malformed("box size is out of bounds"));
+        }
+        boxes.push(Mp4Box { start, header, end: start + size, kind, open_ended });
+        start += size;
+    }
+    if boxes.first().map(|b| &b.kind) != Some(b"ftyp") {
+        return Err(Error::UnsupportedCarrier("not an MP4 file".into()));
+    }
+    Ok(boxes)
+}
+
+/// Hides a slice of bytes in a `free` box appended to the end of an MP4 file (or any other ISO base media file,
+/// such as MOV, M4A or 3GP ones).
+///
+/// Nothing before it is moved, so all the offsets of the media data stay valid and the file
+/// plays as before. The box of the data hidden in the file before is replaced if it is still the last one.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when `mp4` is not a valid MP4 file.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::video::{hide_in_mp4, reveal_from_mp4};
+/// let mp4 = b"\0\0\0\x10ftypisom\0\0\x02\0";
+/// let cloaked = hide_in_mp4(mp4, b"secret").unwrap();
+///
+/// assert!(cloaked.starts_with(mp4));
+/// assert_eq!(reveal_from_mp4(&cloaked).unwrap(), b"secret");
+/// assert_eq!(hide_in_mp4(&cloaked, b"other").unwrap().len(), cloaked.len() - 1);
+/// ```
+///
+pub fn hide_in_mp4(mp4: &[u8], payload: &[u8]) -> Result<Vec<u8>, Error> {
+    let boxes = mp4_boxes(mp4)?;
+    let mut result = mp4.to_vec();
+    match boxes.last() {
+        Some(last) if last.is_hidden_data(mp4) => result.truncate(last.start),
+        // the box would extend over the one with the data otherwise
+        Some(last) if last.open_ended => {
+            let size = u32::try_from(last.end - last.start).map_err(|_| malformed("open-ended box is too large"))?;
+            BigEndian::write_u32(&mut result[last.start..], size);
+        },
+        _ => {},
+    }
+
+    let size = 8 + MAGIC.len() + payload.len();
+    match u32::try_from(size) {
+        Ok(size) => result.extend_from_slice(&size.to_be_bytes()),
+        Err(_) => result.extend_from_slice(&1u32.to_be_bytes()),
+    }
+    result.extend_from_slice(b"free");
+    if size > u32::MAX as usize {
+        result.extend_from_slice(&(size as u64 + 8).to_be_bytes());
+    }
+    result.extend_from_slice(MAGIC);
+    result.extend_from_slice(payload);
+    Ok(result)
+}
+
+/// Reveals a slice of bytes previously hidden by the [`hide_in_mp4`](fn.hide_in_mp4.html) function.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when `mp4` is not a valid MP4 file
+/// or has nothing hidden in it.
+pub fn reveal_from_mp4(mp4: &[u8]) -> Result<Vec<u8>, Error> {
+    mp4_boxes(mp4)?.iter()
+        .rev()
+        .find(|b| b.is_hidden_data(mp4))
+        .map(|b| mp4[b.start + b.header + MAGIC.len()..b.end].to_vec())
+        .ok_or_else(|| Error::UnsupportedCarrier("there is no hidden data in the MP4 file".into()))
+}
+
+/// Position of the contents of the void element with the hidden data that ends the Matroska file, if there is one
+fn matroska_data(mkv: &[u8]) -> Option<usize> {
+    (9..mkv.len().saturating_sub(MAGIC.len() - 1))
+        .filter(|&start| mkv[start..].starts_with(MAGIC))
+        .find(|&start| {
+            let header = &mkv[start - 9..start];
+            let mut size = [0; 8];
+            size[1..].copy_from_slice(&header[2..]);
+            header[0] == EBML_VOID && header[1] == 0x01 && u64::from_be_bytes(size) == (mkv.len() - start) as u64
+        })
+}
+
+/// Hides a slice of bytes in a void element appended to the end of a Matroska or WebM file.
+///
+/// The void elements are ignored by the players wherever they are, and nothing before it is moved,
+/// so the file plays as before. The element of the data hidden in the file before is replaced.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when `mkv` is not a Matroska file.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::video::{hide_in_matroska, reveal_from_matroska};
+/// // just the EBML header with the `webm` doctype
+/// let webm = [0x1A, 0x45, 0xDF, 0xA3, 0x87, 0x42, 0x82, 0x84, b'w', b'e', b'b', b'm'];
+/// let cloaked = hide_in_matroska(&webm, b"secret").unwrap();
+///
+/// assert!(cloaked.starts_with(&webm));
+/// assert_eq!(reveal_from_matroska(&cloaked).unwrap(), b"secret");
+/// assert_eq!(hide_in_matroska(&cloaked, b"other").unwrap().len(), cloaked.len() - 1);
+/// ```
+///
+pub fn hide_in_matroska(mkv: &[u8], payload: &[u8]) -> Result<Vec<u8>, Error> {
+    if !mkv.starts_with(EBML_MAGIC) {
+        return Err(Error::UnsupportedCarrier("not a Matroska file".into()));
+    }
+    let mut result = mkv.to_vec();
+    if let Some(start) = matroska_data(mkv) {
+        result.truncate(start - 9);
+    }
+    let size = (MAGIC.len() + payload.len()) as u64;
+    if size >= 1 << 56 {
+        return Err(Error::UnsupportedCarrier("the payload is too large for a Matroska element".into()));
+    }
+    result.push(EBML_VOID);
+    result.push(0x01);
+    result.extend_from_slice(&size.to_be_bytes()[1..]);
+    result.extend_from_slice(MAGIC);
+    result.extend_from_slice(payload);
+    Ok(result)
+}
+
+/// Reveals a slice of bytes previously hidden by the [`hide_in_matroska`](fn.hide_in_matroska.html) function.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when `mkv` is not a Matroska file
+/// or has nothing hidden in it.
+pub fn reveal_from_matroska(mkv: &[u8]) -> Result<Vec<u8>, Error> {
+    if !mkv.starts_with(EBML_MAGIC) {
+        return Err(Error::UnsupportedCarrier("not a Matroska file".into()));
+    }
+    matroska_data(mkv)
+        .map(|start| mkv[start + MAGIC.len()..].to_vec())
+        .ok_or_else(|| Error::UnsupportedCarrier("there is no hidden data in the Matroska file".into()))
+}
+
+/// Hides a slice of bytes in either an MP4 or a Matroska file, depending on what `video` is.
+pub fn hide_in_video(video: &[u8], payload: &[u8]) -> Result<Vec<u8>, Error> {
+    match video.starts_with(EBML_MAGIC) {
+        true => hide_in_matroska(video, payload),
+        false => hide_in_mp4(video, payload),
+    }
+}
+
+/// Reveals a slice of bytes previously hidden by the [`hide_in_video`](fn.hide_in_video.html) function.
+pub fn reveal_from_video(video: &[u8]) -> Result<Vec<u8>, Error> {
+    match video.starts_with(EBML_MAGIC) {
+        true => reveal_from_matroska(video),
+        false => reveal_from_mp4(video),
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use notify::{recommended_watcher, Event, EventKind, RecursiveMode, Watcher};
+
+use crate::bulk::extract;
+pub use crate::bulk::Extraction;
+use crate::{DecodeOptions, Error};
+
+/// How long a file has to stay unchanged before it is considered completely written
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// Files that were created or modified in the watched directory, waiting to stay unchanged
+/// for a short while before the data is extracted from them.
+///
+/// # Examples
+///
+/// ```
+/// # use std::path::PathBuf;
+/// # use std::time::{Duration, Instant};
+/// # use notify::{Event, EventKind};
+/// # use notify::event::{CreateKind, ModifyKind, RemoveKind};
+/// # use steganographer_core::watch::Pending;
+/// let mut pending = Pending::new(PathBuf::from("/inbox/out"));
+/// let start = Instant::now();
+/// pending.record(Event::new(EventKind::Create(CreateKind::File)).add_path("/inbox/cat.png".into()), start);
+/// pending.record(Event::new(EventKind::Create(CreateKind::File)).add_path("/inbox/out/cat".into()), start);
+/// pending.record(Event::new(EventKind::Remove(RemoveKind::File)).add_path("/inbox/old.png".into()), start);
+///
+/// // still being copied in
+/// let later = start + Duration::from_millis(300);
+/// pending.record(Event::new(EventKind::Modify(ModifyKind::Any)).add_path("/inbox/cat.png".into()), later);
+/// assert!(pending.settled(start + Duration::from_millis(600)).is_empty());
+///
+/// let jobs = pending.settled(later + Duration::from_secs(1));
+/// assert_eq!(jobs, [(PathBuf::from("/inbox/cat.png"), PathBuf::from("/inbox/out/cat"))]);
+/// assert!(pending.settled(later + Duration::from_secs(2)).is_empty());
+/// ```
+#[derive(Debug)]
+pub struct Pending {
+    out: PathBuf,
+    changed: HashMap<PathBuf, Instant>,
+}
+
+impl Pending {
+    /// Nothing pending yet, the files in the `out` directory are never picked up
+    pub fn new(out: PathBuf) -> Pending {
+        Pending { out, changed: HashMap::new() }
+    }
+
+    /// Remembers the files the event created or modified as changed at the given moment
+    pub fn record(&mut self, event: Event, now: Instant) {
+        if let EventKind::Create(_) | EventKind::Modify(_) = event.kind {
+            let (out, changed) = (&self.out, &mut self.changed);
+            for path in event.paths.into_iter().filter(|path| !path.starts_with(out)) {
+                changed.insert(path, now);
+            }
+        }
+    }
+
+    /// Takes out the files that have not changed for a while at the given moment, each paired with the file
+    /// its data is to be extracted to, which has the same name, but without the extension, in the `out` directory
+    pub fn settled(&mut self, now: Instant) -> Vec<(PathBuf, PathBuf)> {
+        let settled = self.changed.iter()
+            .filter(|(_, &changed)| now.saturating_duration_since(changed) >= SETTLE_TIME)
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        settled.into_iter()
+            .filter_map(|source| {
+                self.changed.remove(&source);
+                let output = self.out.join(source.file_stem()?);
+                Some((source, output))
+            })
+            .collect()
+    }
+}
+
+/// Watches the `incoming` directory and tries to extract the data from every file that appears in it,
+/// writing the data to a file with the same name, but without the extension, in the `out` directory.
+///
+/// Files are only processed once they have not changed for a short while, so that the ones being
+/// copied in are not read halfway, see [`Pending`](struct.Pending.html). Every attempt is reported to the `log` callback.
+///
+/// This function only returns when watching fails.
+pub fn watch(incoming: &Path, out: &Path, options: &DecodeOptions, mut log: impl FnMut(Extraction)) -> Result<(), Error> {
+    std::fs::create_dir_all(out)?;
+
+    let (sender, receiver) = channel();
+    let mut watcher = recommended_watcher(sender)?;
+    watcher.watch(incoming, RecursiveMode::NonRecursive)?;
+
+    let mut pending = Pending::new(out.canonicalize()?);
+    loop {
+        match receiver.recv_timeout(SETTLE_TIME / 2) {
+            Ok(event) => pending.record(event?, Instant::now()),
+            Err(RecvTimeoutError::Timeout) => {},
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+
+        for (source, output) in pending.settled(Instant::now()) {
+            if source.is_file() {
+                log(extract(source, output, options));
+            }
+        }
+    }
+}
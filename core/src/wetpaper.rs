@@ -0,0 +1,237 @@
+use std::io;
+
+use crate::binary::Layout;
+use crate::Error;
+
+/// Amount of carrier bytes in one block of the code
+pub const BLOCK_SIZE: usize = 256;
+
+/// Seed of the shared pseudo-random permutation and matrices when no other one is given
+pub const DEFAULT_SEED: u64 = 0x5745_5450_4150_4552;
+
+const WORDS: usize = BLOCK_SIZE / 64;
+
+/// Bits of the first block that tell how many bits each of the other blocks carries
+const RATE_BITS: usize = 16;
+
+type Row = [u64; WORDS];
+
+/// SplitMix64, small and good enough to derive the shared permutation and matrices from the seed
+pub(crate) struct SplitMix(u64);
+
+impl SplitMix {
+    pub(crate) fn new(seed: u64) -> Self {
+        SplitMix(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ z >> 30).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ z >> 27).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ z >> 31
+    }
+
+    /// Shuffles the slice with the Fisher-Yates algorithm
+    pub(crate) fn shuffle<T>(&mut self, slice: &mut [T]) {
+        for i in (1..slice.len()).rev() {
+            slice.swap(i, (self.next_u64() % (i as u64 + 1)) as usize);
+        }
+    }
+}
+
+fn get(row: &Row, column: usize) -> bool {
+    row[column / 64] >> (column % 64) & 1 == 1
+}
+
+fn parity(a: &Row, b: &Row) -> bool {
+    a.iter().zip(b).map(|(a, b)| (a & b).count_ones()).sum::<u32>() % 2 == 1
+}
+
+/// Rows of the shared matrix of the block
+fn matrix(seed: u64, block: usize, rows: usize) -> Vec<Row> {
+    let mut rng = SplitMix::new(seed ^ (block as u64).wrapping_mul(0xD6E8_FEB8_6659_FD93));
+    (0..rows).map(|_| {
+        let mut row = [0; WORDS];
+        row.iter_mut().for_each(|word| *word = rng.next_u64());
+        row
+    }).collect()
+}
+
+/// Carrier positions in the order of the shared permutation
+fn positions(len: usize, layout: Layout, seed: u64) -> Vec<usize> {
+    let mut positions = (0..len).filter(|&i| layout.is_usable(i)).collect::<Vec<_>>();
+    SplitMix::new(seed).shuffle(&mut positions);
+    positions
+}
+
+fn block_bits(carrier: &[u8], block: &[usize]) -> Row {
+    let mut bits = [0; WORDS];
+    for (column, &position) in block.iter().enumerate() {
+        bits[column / 64] |= ((carrier[position] & 1) as u64) << (column % 64);
+    }
+    bits
+}
+
+/// Changes the least significant bits of the dry positions of the block so that
+/// multiplying the block bits by the matrix gives the message bits
+fn embed_block(carrier: &mut [u8], block: &[usize], wet: &[bool], matrix: &[Row], message: &[bool]) -> bool {
+    let x = block_bits(carrier, block);
+    let mut dry = [0; WORDS];
+    for (column, &position) in block.iter().enumerate() {
+        if !wet[position] {
+            dry[column / 64] |= 1 << (column % 64);
+        }
+    }
+
+    // solving D_dry * v = message - D * x over GF(2) with the Gauss-Jordan elimination
+    let mut rows = matrix.iter().zip(message)
+        .map(|(row, &bit)| {
+            let mut masked = *row;
+            masked.iter_mut().zip(&dry).for_each(|(word, dry)| *word &= dry);
+            (masked, bit ^ parity(row, &x))
+        })
+        .collect::<Vec<_>>();
+    let mut pivots = Vec::with_capacity(rows.len());
+    for column in (0..block.len()).filter(|&column| get(&dry, column)) {
+        let rank = pivots.len();
+        if rank == rows.len() {
+            break;
+        }
+        let pivot = match (rank..rows.len()).find(|&i| get(&rows[i].0, column)) {
+            Some(pivot) => pivot,
+            None => continue,
+        };
+        rows.swap(rank, pivot);
+        let (row, rhs) = rows[rank];
+        for (i, other) in rows.iter_mut().enumerate() {
+            if i != rank && get(&other.0, column) {
+                other.0.iter_mut().zip(&row).for_each(|(a, b)| *a ^= b);
+                other.1 ^= rhs;
+            }
+        }
+        pivots.push(column);
+    }
+    if rows[pivots.len()..].iter().any(|&(_, rhs)| rhs) {
+        return false;
+    }
+    for (&column, &(_, flip)) in pivots.iter().zip(&rows) {
+        if flip {
+            carrier[block[column]] ^= 1;
+        }
+    }
+    true
+}
+
+fn extract_block(carrier: &[u8], block: &[usize], matrix: &[Row]) -> impl Iterator<Item = bool> {
+    let x = block_bits(carrier, block);
+    matrix.iter().map(move |row| parity(row, &x)).collect::<Vec<_>>().into_iter()
+}
+
+fn to_bits(bytes: &[u8]) -> impl Iterator<Item = bool> + '_ {
+    bytes.iter().flat_map(|byte| (0..8).rev().map(move |shift| byte >> shift & 1 == 1))
+}
+
+fn eof() -> Error {
+    io::Error::from(io::ErrorKind::UnexpectedEof).into()
+}
+
+/// Returns the positions of the bytes that are 0 or 255, a common choice of the wet ones,
+/// as a telltale of the altered saturated regions is easier to notice.
+pub fn saturated(carrier: &[u8]) -> Vec<bool> {
+    carrier.iter().map(|&byte| byte == 0 || byte == 255).collect()
+}
+
+/// Hides a slice of bytes along with its length in the least significant bits of the carrier bytes in place,
+/// never touching the ones marked as `wet`, using the wet paper code.
+///
+/// The receiver does not need to know which of the bytes were wet, only the `seed` and the `layout`.
+/// The usable carrier bytes are shuffled with the seed and split into blocks of
+/// [`BLOCK_SIZE`](constant.BLOCK_SIZE.html) bytes, where the message bits of each block are the product of its
+/// least significant bits and a pseudo-random binary matrix. The sender solves for the changes to the dry bytes
+/// that give the wanted product, which is possible as long as there are somewhat more dry bytes in a block
+/// than the bits it carries. The first block tells how many bits the other ones carry.
+///
+/// Only one least significant bit of each carrier byte is used, regardless of the bits setting elsewhere.
+///
+/// # Errors
+/// An `UnexpectedEof` IO error when there are not enough carrier bytes, and
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when there are too many wet ones.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::wetpaper::{hide_wet, reveal_wet, DEFAULT_SEED};
+/// # use steganographer_core::binary::Layout;
+/// let mut carrier = (0..4096).map(|i| (i * 7 % 256) as u8).collect::<Vec<_>>();
+/// let original = carrier.clone();
+/// // a third of the carrier may not be touched
+/// let wet = (0..carrier.len()).map(|i| i % 3 == 0).collect::<Vec<_>>();
+///
+/// hide_wet(b"hello there", &mut carrier, Layout::default(), &wet, DEFAULT_SEED).unwrap();
+///
+/// assert!(wet.iter().zip(carrier.iter().zip(&original)).all(|(&wet, (a, b))| !wet || a == b));
+/// assert_eq!(reveal_wet(&carrier, Layout::default(), DEFAULT_SEED).unwrap(), b"hello there");
+/// ```
+///
+pub fn hide_wet(payload: &[u8], carrier: &mut [u8], layout: Layout, wet: &[bool], seed: u64) -> Result<(), Error> {
+    assert_eq!(carrier.len(), wet.len(), "every carrier byte must be either wet or dry");
+    let positions = positions(carrier.len(), layout, seed);
+    let mut blocks = positions.chunks_exact(BLOCK_SIZE);
+    let header = blocks.next().ok_or_else(eof)?;
+    let blocks = blocks.collect::<Vec<_>>();
+
+    let length = (payload.len() as u32).to_be_bytes();
+    let message = to_bits(&length).chain(to_bits(payload)).collect::<Vec<_>>();
+    if blocks.is_empty() {
+        return Err(eof());
+    }
+    let rate = message.len().div_ceil(blocks.len()).max(1);
+    if rate > BLOCK_SIZE {
+        return Err(eof());
+    }
+
+    let too_wet = || Error::UnsupportedCarrier("too many of the carrier bytes are wet".into());
+    let rate_bits = to_bits(&(rate as u16).to_be_bytes()).collect::<Vec<_>>();
+    if !embed_block(carrier, header, wet, &matrix(seed, 0, RATE_BITS), &rate_bits) {
+        return Err(too_wet());
+    }
+    for (i, chunk) in message.chunks(rate).enumerate() {
+        let mut bits = chunk.to_vec();
+        bits.resize(rate, false);
+        if !embed_block(carrier, blocks[i], wet, &matrix(seed, i + 1, rate), &bits) {
+            return Err(too_wet());
+        }
+    }
+    Ok(())
+}
+
+/// Reveals a slice of bytes previously hidden by the [`hide_wet`](fn.hide_wet.html) function.
+///
+/// # Errors
+/// An `UnexpectedEof` IO error when the carrier does not seem to hold anything hidden with this seed.
+pub fn reveal_wet(carrier: &[u8], layout: Layout, seed: u64) -> Result<Vec<u8>, Error> {
+    let positions = positions(carrier.len(), layout, seed);
+    let mut blocks = positions.chunks_exact(BLOCK_SIZE);
+    let header = blocks.next().ok_or_else(eof)?;
+
+    let rate = extract_block(carrier, header, &matrix(seed, 0, RATE_BITS))
+        .fold(0usize, |acc, bit| acc << 1 | bit as usize);
+    if rate == 0 || rate > BLOCK_SIZE {
+        return Err(eof());
+    }
+
+    let mut bits = blocks.enumerate()
+        .flat_map(|(i, block)| extract_block(carrier, block, &matrix(seed, i + 1, rate)));
+    let mut next_byte = || (0..8).try_fold(0u8, |acc, _| bits.next().map(|bit| acc << 1 | bit as u8));
+
+    let mut length = [0; 4];
+    for byte in &mut length {
+        *byte = next_byte().ok_or_else(eof)?;
+    }
+    let length = u32::from_be_bytes(length) as usize;
+    if length > positions.len() / 8 {
+        return Err(eof());
+    }
+    (0..length).map(|_| next_byte().ok_or_else(eof)).collect()
+}
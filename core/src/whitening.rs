@@ -0,0 +1,43 @@
+use chacha20::ChaCha20;
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use sha2::{Digest, Sha256};
+
+/// Keystream that the hidden bytes are XORed with, so that the bits they leave in the carrier
+/// look uniformly random, the length and the header included.
+///
+/// It is ChaCha20 keyed with the SHA-256 of the seed. The same seed always gives the same keystream,
+/// which is what lets the decoder take the whitening off, and there is no salt to store anywhere,
+/// so this is about the statistics of the carrier rather than about keeping the data secret,
+/// for that there is [`crypto`](../crypto/index.html).
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::whitening::Keystream;
+/// let mut data = *b"aaaaaaaaaaaaaaaa";
+/// Keystream::new("seed").apply(&mut data);
+/// assert_ne!(&data, b"aaaaaaaaaaaaaaaa");
+///
+/// // applied in any pieces, the same keystream takes the whitening off
+/// let mut keystream = Keystream::new("seed");
+/// keystream.apply(&mut data[..3]);
+/// keystream.apply(&mut data[3..]);
+/// assert_eq!(&data, b"aaaaaaaaaaaaaaaa");
+/// ```
+pub struct Keystream(ChaCha20);
+
+impl Keystream {
+    /// Derives the keystream from the seed
+    pub fn new(seed: &str) -> Self {
+        let key = Sha256::new()
+            .chain_update(b"steganographer whitening")
+            .chain_update(seed)
+            .finalize();
+        Keystream(ChaCha20::new(&key, &Default::default()))
+    }
+
+    /// XORs the next bytes of the keystream into the data
+    pub fn apply(&mut self, data: &mut [u8]) {
+        self.0.apply_keystream(data);
+    }
+}
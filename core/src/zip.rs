@@ -0,0 +1,271 @@
+use std::convert::TryFrom;
+use std::io::{self, Cursor, Read};
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::Error;
+
+const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+const CENTRAL_SIGNATURE: u32 = 0x0201_4b50;
+const EOCD_SIZE: usize = 22;
+const CENTRAL_HEADER_SIZE: usize = 46;
+
+/// The header id of the extra fields that carry the hidden data, `st` in little endian
+pub const EXTRA_FIELD_ID: u16 = 0x7473;
+
+/// Prefix of the archive comment when it carries the hidden data
+const COMMENT_MAGIC: &[u8; 4] = b"STEG";
+
+/// A place in the ZIP structure where the payload is stored.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ZipSlot {
+    /// Custom extra fields of the central directory entries.
+    ///
+    /// Those are kept by most tools that update an archive without recreating it and they
+    /// do not show up anywhere, but the archive must have at least one entry.
+    #[default]
+    ExtraFields,
+    /// The archive comment, limited to 64 KiB and visible to anyone running `unzip -z`.
+    ///
+    /// An existing comment of the archive is kept and the payload is appended after it,
+    /// so the room left for the payload is what the comment does not take already.
+    Comment,
+}
+
+struct CentralEntry<'a> {
+    header: &'a [u8],
+    name: &'a [u8],
+    extra: Vec<(u16, &'a [u8])>,
+    comment: &'a [u8],
+}
+
+struct Archive<'a> {
+    data: &'a [u8],
+    cd_offset: usize,
+    eocd: &'a [u8],
+    entries: Vec<CentralEntry<'a>>,
+    comment: &'a [u8],
+}
+
+fn malformed(what: &str) -> Error {
+    Error::UnsupportedCarrier(format!("malformed ZIP archive: {}", what))
+}
+
+fn parse_extra(mut extra: &[u8]) -> Result<Vec<(u16, &[u8])>, Error> {
+    let mut fields = Vec::new();
+    while extra.len() >= 4 {
+        let id = LittleEndian::read_u16(extra);
+        let len = LittleEndian::read_u16(&extra[2..]) as usize;
+        let data = extra.get(4..4 + len).ok_or_else(|| malformed("extra field is out of bounds"))?;
+        fields.push((id, data));
+        extra = &extra[4 + len..];
+    }
+    Ok(fields)
+}
+
+impl<'a> Archive<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        if data.len() < EOCD_SIZE {
+            return Err(Error::UnsupportedCarrier("not a ZIP archive".into()));
+        }
+        let eocd_offset = (0..=data.len() - EOCD_SIZE)
+            .rev()
+            .take(EOCD_SIZE + 0xFFFF)
+            .find(|&i| LittleEndian::read_u32(&data[i..]) == EOCD_SIGNATURE)
+            .ok_or_else(|| Error::UnsupportedCarrier("not a ZIP archive".into()))?;
+        let eocd = &data[eocd_offset..eocd_offset + EOCD_SIZE];
+
+        let entry_count = LittleEndian::read_u16(&eocd[10..]);
+        let cd_size = LittleEndian::read_u32(&eocd[12..]);
+        let cd_offset = LittleEndian::read_u32(&eocd[16..]);
+        let comment_len = LittleEndian::read_u16(&eocd[20..]) as usize;
+
+        if entry_count == 0xFFFF || cd_size == 0xFFFF_FFFF || cd_offset == 0xFFFF_FFFF {
+            return Err(Error::UnsupportedCarrier("ZIP64 archives are not supported".into()));
+        }
+        let (cd_offset, cd_size) = (cd_offset as usize, cd_size as usize);
+        if cd_offset + cd_size != eocd_offset {
+            return Err(malformed("central directory is not followed by its end record"));
+        }
+        let comment = data.get(eocd_offset + EOCD_SIZE..eocd_offset + EOCD_SIZE + comment_len)
+            .ok_or_else(|| malformed("archive comment is out of bounds"))?;
+
+        let mut entries = Vec::with_capacity(entry_count as usize);
+        let mut pos = cd_offset;
+        for _ in 0..entry_count {
+            let header = data.get(pos..pos + CENTRAL_HEADER_SIZE)
+                .filter(|h| LittleEndian::read_u32(h) == CENTRAL_SIGNATURE)
+                .ok_or_else(|| malformed("bad central directory entry"))?;
+            let name_len = LittleEndian::read_u16(&header[28..]) as usize;
+            let extra_len = LittleEndian::read_u16(&header[30..]) as usize;
+            let comment_len = LittleEndian::read_u16(&header[32..]) as usize;
+
+            let mut var = data.get(pos + CENTRAL_HEADER_SIZE..pos + CENTRAL_HEADER_SIZE + name_len + extra_len + comment_len)
+                .ok_or_else(|| malformed("central directory entry is out of bounds"))?;
+            let name = &var[..name_len];
+            var = &var[name_len..];
+            entries.push(CentralEntry {
+                header,
+                name,
+                extra: parse_extra(&var[..extra_len])?,
+                comment: &var[extra_len..],
+            });
+            pos += CENTRAL_HEADER_SIZE + name_len + extra_len + comment_len;
+        }
+
+        Ok(Archive { data, cd_offset, eocd, entries, comment })
+    }
+
+    fn write(&self, comment: &[u8]) -> Vec<u8> {
+        let mut result = Vec::with_capacity(self.data.len() + comment.len());
+        result.extend_from_slice(&self.data[..self.cd_offset]);
+
+        for entry in &self.entries {
+            let extra_len: usize = entry.extra.iter().map(|(_, data)| 4 + data.len()).sum();
+            let mut header = entry.header.to_vec();
+            LittleEndian::write_u16(&mut header[30..], extra_len as u16);
+            result.extend_from_slice(&header);
+            result.extend_from_slice(entry.name);
+            for (id, data) in &entry.extra {
+                result.write_u16::<LittleEndian>(*id).unwrap();
+                result.write_u16::<LittleEndian>(data.len() as u16).unwrap();
+                result.extend_from_slice(data);
+            }
+            result.extend_from_slice(entry.comment);
+        }
+
+        let cd_size = result.len() - self.cd_offset;
+        let mut eocd = self.eocd.to_vec();
+        LittleEndian::write_u32(&mut eocd[12..], cd_size as u32);
+        LittleEndian::write_u16(&mut eocd[20..], comment.len() as u16);
+        result.extend_from_slice(&eocd);
+        result.extend_from_slice(comment);
+        result
+    }
+
+    /// Removes previously hidden data so that it could be replaced
+    fn strip(&mut self) {
+        for entry in &mut self.entries {
+            entry.extra.retain(|&(id, _)| id != EXTRA_FIELD_ID);
+        }
+        self.comment = split_comment(self.comment).0;
+    }
+}
+
+/// Splits the archive comment into the comment of its own and the framed payload appended after it, if any
+fn split_comment(comment: &[u8]) -> (&[u8], Option<&[u8]>) {
+    let hidden = (0..comment.len().saturating_sub(COMMENT_MAGIC.len() + 4) + 1)
+        .filter(|&i| comment[i..].starts_with(COMMENT_MAGIC))
+        .find(|&i| BigEndian::read_u32(&comment[i + COMMENT_MAGIC.len()..]) as usize == comment.len() - i - COMMENT_MAGIC.len() - 4);
+    match hidden {
+        Some(i) => (&comment[..i], Some(&comment[i + COMMENT_MAGIC.len()..])),
+        None => (comment, None),
+    }
+}
+
+/// Hides a slice of bytes along with its length in the structure of a ZIP archive.
+///
+/// This also works with every format that is a ZIP archive underneath, such as OOXML
+/// (docx, xlsx, pptx), ODF (odt, ods), jar or apk files.
+/// Compressed entries are not touched at all, so the archive keeps working as before.
+///
+/// Any data hidden in the archive before is replaced, while an archive comment that was there
+/// before is kept in front of the payload when it goes to the [comment](enum.ZipSlot.html#variant.Comment).
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when `archive` is not
+/// a valid ZIP archive (or is a ZIP64 one), or when the chosen slot has not enough room for the payload.
+///
+/// # Examples
+///
+/// ```
+/// # use steganographer_core::zip::{hide_in_zip, reveal_from_zip, ZipSlot};
+/// // an empty archive is only the end of central directory record
+/// let archive = [0x50, 0x4b, 0x05, 0x06, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+/// let cloaked = hide_in_zip(&archive, b"secret", ZipSlot::Comment).unwrap();
+///
+/// assert_eq!(reveal_from_zip(&cloaked).unwrap(), b"secret");
+///
+/// // the comment an archive already has stays in front of the payload
+/// let mut commented = archive.to_vec();
+/// commented[20] = 5;
+/// commented.extend_from_slice(b"hello");
+/// let cloaked = hide_in_zip(&commented, b"secret", ZipSlot::Comment).unwrap();
+///
+/// assert_eq!(&cloaked[22..27], b"hello");
+/// assert_eq!(reveal_from_zip(&cloaked).unwrap(), b"secret");
+///
+/// // and hiding again replaces only the payload
+/// let again = hide_in_zip(&cloaked, b"other", ZipSlot::Comment).unwrap();
+/// assert_eq!(&again[22..27], b"hello");
+/// assert_eq!(reveal_from_zip(&again).unwrap(), b"other");
+/// ```
+///
+pub fn hide_in_zip(archive: &[u8], payload: &[u8], slot: ZipSlot) -> Result<Vec<u8>, Error> {
+    let mut parsed = Archive::parse(archive)?;
+    parsed.strip();
+
+    let mut framed = Vec::with_capacity(4 + payload.len());
+    framed.write_u32::<BigEndian>(u32::try_from(payload.len()).map_err(|_| no_room(slot))?)?;
+    framed.extend_from_slice(payload);
+
+    match slot {
+        ZipSlot::Comment => {
+            let comment = [parsed.comment, &COMMENT_MAGIC[..], &framed].concat();
+            if comment.len() > 0xFFFF {
+                return Err(no_room(slot));
+            }
+            Ok(parsed.write(&comment))
+        },
+        ZipSlot::ExtraFields => {
+            let mut remaining = &framed[..];
+            for entry in &mut parsed.entries {
+                let mut used: usize = entry.extra.iter().map(|(_, data)| 4 + data.len()).sum();
+                while !remaining.is_empty() && used + 4 < 0xFFFF {
+                    let (chunk, rest) = remaining.split_at(remaining.len().min(0xFFFF - used - 4));
+                    entry.extra.push((EXTRA_FIELD_ID, chunk));
+                    used += 4 + chunk.len();
+                    remaining = rest;
+                }
+            }
+            if !remaining.is_empty() {
+                return Err(no_room(slot));
+            }
+            Ok(parsed.write(parsed.comment))
+        },
+    }
+}
+
+fn no_room(slot: ZipSlot) -> Error {
+    Error::UnsupportedCarrier(format!("not enough room in the {:?} of the ZIP archive for the payload", slot))
+}
+
+/// Reveals a slice of bytes previously hidden by the [`hide_in_zip`](fn.hide_in_zip.html) function.
+///
+/// Both of the [slots](enum.ZipSlot.html) are checked, extra fields first.
+///
+/// # Errors
+/// [`UnsupportedCarrier`](../enum.Error.html#variant.UnsupportedCarrier) when `archive` is not
+/// a valid ZIP archive, and an `UnexpectedEof` IO error when there is no hidden data in it.
+pub fn reveal_from_zip(archive: &[u8]) -> Result<Vec<u8>, Error> {
+    let parsed = Archive::parse(archive)?;
+
+    let framed = parsed.entries.iter()
+        .flat_map(|entry| entry.extra.iter())
+        .filter(|&&(id, _)| id == EXTRA_FIELD_ID)
+        .flat_map(|&(_, data)| data.iter().copied())
+        .collect::<Vec<_>>();
+
+    let mut reader = if !framed.is_empty() {
+        Cursor::new(&framed[..])
+    } else {
+        Cursor::new(split_comment(parsed.comment).1.unwrap_or_default())
+    };
+    let size = u64::from(reader.read_u32::<BigEndian>()?);
+    // the size is not trusted with the allocation until there is that much to read
+    let mut result = Vec::new();
+    if reader.take(size).read_to_end(&mut result)? as u64 != size {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(result)
+}